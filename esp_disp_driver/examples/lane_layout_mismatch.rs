@@ -0,0 +1,39 @@
+//! Must NOT build: an RGB `PixelWriterSipo` on a 3-lane bank.
+//!
+//! Three data lanes plus the two address lanes of `DefAddrShifter` need a
+//! 5-lane bank, so `PixelWriterSipo::LANE_LAYOUT` rejects this layout at
+//! compile time. CI builds this example with `--features
+//! compile-fail-checks` and fails if the build succeeds or if the error is
+//! not the `lane_layout_error` message.
+
+#![no_std]
+#![no_main]
+
+use esp_disp_driver::display::backend::sipo::{PixelWriterSipo, VgaHwResources};
+use panic_rtt_target as _;
+
+esp_bootloader_esp_idf::esp_app_desc!();
+
+/// RGB data lanes on a bank sized for the data lanes only.
+type RgbOnThreeLanes<'a> = PixelWriterSipo<'a, 3, 3>;
+
+#[esp_hal::main]
+fn main() -> ! {
+    let peripherals = esp_hal::init(esp_hal::Config::default());
+
+    let vga_res = VgaHwResources {
+        rclk : peripherals.GPIO35.into(),
+        srclk : peripherals.GPIO21.into(),
+        srclr_al : peripherals.GPIO47.into(),
+        data_ser : [
+            peripherals.GPIO48.into(),
+            peripherals.GPIO4.into(),
+            peripherals.GPIO5.into(),
+        ],
+        i_addr_ser : peripherals.GPIO45.into(),
+        j_addr_ser : peripherals.GPIO0.into(),
+    };
+    let _writer = RgbOnThreeLanes::from_resources(vga_res);
+
+    loop {}
+}
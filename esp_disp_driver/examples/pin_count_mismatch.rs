@@ -0,0 +1,23 @@
+//! Must NOT build: three pins listed for a four-pin bus.
+//!
+//! `anypins_from_peri_n!` checks the listed pins against `N` at compile
+//! time. CI builds this example with `--features compile-fail-checks` and
+//! fails if the build succeeds or if the error is not the macro's
+//! "number of pins listed does not match N" message.
+
+#![no_std]
+#![no_main]
+
+use esp_disp_driver::anypins_from_peri_n;
+use panic_rtt_target as _;
+
+esp_bootloader_esp_idf::esp_app_desc!();
+
+#[esp_hal::main]
+fn main() -> ! {
+    let peripherals = esp_hal::init(esp_hal::Config::default());
+
+    let _data = anypins_from_peri_n!(peripherals; 4; 4, 5, 6);
+
+    loop {}
+}
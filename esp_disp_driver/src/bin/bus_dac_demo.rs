@@ -9,7 +9,7 @@
 use defmt::{info, println};
 use embassy_executor::Spawner;
 use esp_hal::{
-    gpio::{Level, Output, OutputConfig, InputConfig, DriveMode, AnyPin},
+    gpio::{Level, Output, OutputConfig, InputConfig, DriveMode},
     interrupt::software::SoftwareInterruptControl,
     system::{Cpu, Stack},
     timer::timg::TimerGroup,
@@ -18,14 +18,16 @@ use esp_hal::{
 };
 use esp_rtos::embassy::Executor;
 use panic_rtt_target as _;
-use esp_disp_driver::{anypins_from_peri, sipo};
+use esp_disp_driver::{sipo, vga_hw_resources};
 use esp_disp_driver::display::drawer;
+use esp_disp_driver::display::patterns::animated_checkerboard;
+use esp_disp_driver::display::backend::utils::ScanSync;
 use esp_disp_driver::display::backend::bus_dac::*;
-use embassy_time::{Duration, Timer};
 use static_cell::StaticCell;
 extern crate alloc;
 static FB_INIT : FrameBuf = [[0; FB_WIDTH]; FB_HEIGHT];
 static FRAMEBUF_CELL: StaticCell<DoubleFb> = StaticCell::new();
+static SCAN_SYNC: ScanSync = ScanSync::new();
 
 
 fn init_double_fb() -> &'static DoubleFb {
@@ -33,55 +35,24 @@ fn init_double_fb() -> &'static DoubleFb {
 }
 
 
-pub async fn checkerboard_fade_task(fb: &'static DoubleFb) {
-    const MAX_LUM4: i8 = 15;   // 4-bit peak brightness (0..=15)
+pub async fn checkerboard_fade_task(fb: &'static DoubleFb) -> ! {
+    const MAX_LUM4: u32 = 15;   // 4-bit peak brightness (0..=15)
     const CELL_SIZE: usize = 20; // checkerboard cell size in pixels
-    
-    // Current brightness for the "black" squares in 0..=15.
-    let mut lum4: i8 = 0;
-    // Direction of change: +1 (fade in) or -1 (fade out).
-    let mut dir: i8 = 1;
-    let mut offset : usize = 0;
-    
-    loop {
-        // Clamp to the valid 4-bit range.
-        let lum_black4 = lum4.clamp(0, MAX_LUM4) as u8;
+    // Refresh frames per brightness step: ~5 steps/s at 60 Hz.
+    const FRAMES_PER_STEP: u32 = 12;
+    // Pixels the board scrolls per frame; 0 keeps it still.
+    const SCROLL_SPEED: usize = 0;
+
+    run_vsynced_animation(fb, &SCAN_SYNC, |frame: &mut FrameBuf, n| {
+        // Triangle wave 0..=15..=0: fade in, then fade out.
+        let step = (n / FRAMES_PER_STEP) % (2 * MAX_LUM4);
+        let lum_black4 = step.min(2 * MAX_LUM4 - step) as u8;
         let lum_white4 = (MAX_LUM4 as u8).saturating_sub(lum_black4);
-        
-        // 1) Draw into inactive framebuffer.
-        fb.with_inactive(|frame: &mut FrameBuf| {
-            for (y, row) in frame.iter_mut().enumerate() {
-                for (x, px) in row.iter_mut().enumerate() {
-                    // Checkerboard pattern: (x/cell + y/cell) even/odd.
-                    let is_black_square =
-                    (((x + offset) / CELL_SIZE) + ((y + offset) / CELL_SIZE)) & 1 == 0;
-                    
-                    *px = if is_black_square {
-                        lum_black4  // store directly in low 4 bits
-                    } else {
-                        lum_white4
-                    };
-                }
-            }
-        });
-        
-        // 2) Present the newly drawn frame.
-        fb.swap();
-        
-        // 3) Control animation speed (adjust to taste).
-        Timer::after_millis(200).await;
-        
-        // 4) Update brightness for the next frame.
-        lum4 += dir;
-        // offset = (offset + 1) % CELL_SIZE;
-        if lum4 >= MAX_LUM4 {
-            lum4 = MAX_LUM4;
-            dir = -1; // start fading out
-        } else if lum4 <= 0 {
-            lum4 = 0;
-            dir = 1; // start fading in
-        }
-    }
+
+        // The framebuffer holds 8-bit brightness; stretch the 4-bit steps.
+        let offset = (n as usize).wrapping_mul(SCROLL_SPEED);
+        animated_checkerboard(frame, CELL_SIZE, offset, lum_black4 * 17, lum_white4 * 17);
+    }).await
 }
 
 
@@ -107,21 +78,19 @@ async fn main(spawner: Spawner) -> ! {
     // TODO: Spawn some tasks
     let _ = spawner;
 
-    let haddr_pins : [AnyPin; 8] = anypins_from_peri!(peripherals; 21, 47, 48, 45, 0, 35, 36, 37);
-    let vaddr_pins : [AnyPin; 8] = anypins_from_peri!(peripherals; 14, 13, 12, 11, 10, 9, 46, 3);
-    let data_pins  : [AnyPin; 4] = anypins_from_peri!(peripherals; 4, 5, 6, 7);
+    let res = vga_hw_resources!(peripherals;
+        haddr: [21, 47, 48, 45, 0, 35, 36, 37],
+        vaddr: [14, 13, 12, 11, 10, 9, 46, 3],
+        data: [4, 5, 6, 7],
+    );
 
     let pixel_writer = BwPixelWriter8h8v1ch4::with_hw_resources(
-        VgaHwResources{
-            haddr_pins,
-            vaddr_pins,
-            data_pins,
-        },
+        res,
         fb,
         None,
         Some(OutputConfig::default().with_drive_mode(DriveMode::OpenDrain)),
         Some(Level::Low),
-    );
+    ).with_scan_sync(&SCAN_SYNC);
 
 
     static APP_CORE_STACK: StaticCell<Stack<8192>> = StaticCell::new();
@@ -147,6 +116,5 @@ async fn main(spawner: Spawner) -> ! {
     );
 
 
-    checkerboard_fade_task(fb).await;
-    loop{}
+    checkerboard_fade_task(fb).await
 }
\ No newline at end of file
@@ -45,7 +45,7 @@ async fn main(spawner: Spawner) -> ! {
         rclk : peripherals.GPIO35.into(),
         srclk : peripherals.GPIO21.into(),
         srclr_al : peripherals.GPIO47.into(),
-        data_ser : peripherals.GPIO48.into(),
+        data_ser : [peripherals.GPIO48.into()],
         i_addr_ser : peripherals.GPIO45.into(),
         j_addr_ser : peripherals.GPIO0.into(),
     };
@@ -54,13 +54,13 @@ async fn main(spawner: Spawner) -> ! {
     //     rclk : peripherals.GPIO20.into(),
     //     srclk : peripherals.GPIO21.into(),
     //     srclr_al : peripherals.GPIO47.into(),
-    //     data_ser : peripherals.GPIO48.into(),
+    //     data_ser : [peripherals.GPIO48.into()],
     //     i_addr_ser : peripherals.GPIO45.into(),
     //     j_addr_ser : peripherals.GPIO0.into(),
     // };
 
-    let mut pixel_writer = BwPixelWriter8h8v1ch8::from_resources(vga_res);
-    let mut drawer = drawer::Drawer::new(&mut pixel_writer);
+    let mut pixel_writer: Option<BwPixelWriter8h8v1ch8> = None;
+    let mut drawer = drawer::Drawer::from_sipo(&mut pixel_writer, vga_res).unwrap();
 
     let mut cur_brightness = 0;
 
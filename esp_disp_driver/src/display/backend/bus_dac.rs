@@ -1,45 +1,724 @@
-use crate::display::pix_writer::PixelWriter;
-use crate::display::backend::utils::DoubleBuffer;
+use crate::display::drawer::Drawer;
+use crate::display::pix_writer::{Capabilities, NativePixelWriter, PixelWriter};
+use crate::display::backend::utils::{apply_brightness_floor, BrightnessPolarity, BufferSel, DoubleBuffer, FrameStore, OutOfRangePolicy, PANEL_RESOLUTION, PresentStats, Resolution, ScanControl, ScanOrientation, ScanSync, ScanWindow, SourceLayout, VsyncEvent, VsyncTracker};
 use crate::par_data_rw::*;
-use esp_hal::{gpio::{AnyPin, InputConfig, OutputConfig, Level}, peripherals};
+use crate::sipo::ClearLine;
+use crate::display::backend::pixel_clock::PixelClock;
+use crate::error::{Error, Result};
+use crate::utils::{debug_assert_pins_disjoint, no_feed, FeedFn};
+use esp_hal::xtensa_lx::timer::get_cycle_count;
+use esp_hal::{gpio::{AnyPin, Input, InputConfig, OutputConfig, Level, Pin}, peripherals};
 use defmt::info;
-pub const FB_WIDTH: usize = 201;
-pub const FB_HEIGHT: usize = 151;
-pub type FrameBuf = [[u8; FB_WIDTH]; FB_HEIGHT];
-pub type DoubleFb = DoubleBuffer<FrameBuf>;
+use embassy_time::Timer;
+use core::task::Poll;
+pub const FB_WIDTH: usize = PANEL_RESOLUTION.width as usize;
+pub const FB_HEIGHT: usize = PANEL_RESOLUTION.height as usize;
+/// Full-resolution framebuffer, each row `STRIDE` bytes long.
+///
+/// Only the first `FB_WIDTH` bytes of a row are pixels; a larger `STRIDE`
+/// (see `utils::row_stride`) pads rows so each starts on an aligned address,
+/// as DMA output paths want. Padding costs `(STRIDE - FB_WIDTH) * FB_HEIGHT`
+/// bytes per frame, twice that for a `DoubleFb`: 453 bytes per frame for
+/// word alignment (204), 3473 for 32-byte cache lines (224). The default
+/// stride equals the width, without padding.
+pub type FrameBuf<const STRIDE: usize = FB_WIDTH> = [[u8; STRIDE]; FB_HEIGHT];
+pub type DoubleFb<const STRIDE: usize = FB_WIDTH> = DoubleBuffer<FrameBuf<STRIDE>>;
 
-pub struct BwPixelWriter8h8v1ch4<'a> {  
+/// Logical size of the pixel-doubled framebuffer (see `FrameSource::Doubled`).
+pub const HALF_FB_WIDTH: usize = 100;
+pub const HALF_FB_HEIGHT: usize = 75;
+pub type HalfFrameBuf = [[u8; HALF_FB_WIDTH]; HALF_FB_HEIGHT];
+pub type HalfDoubleFb = DoubleBuffer<HalfFrameBuf>;
+
+/// Framebuffer the scan loops read and `PixelWriter` draws into.
+pub enum FrameSource<S: 'static = DoubleFb> {
+    /// One framebuffer pixel per physical pixel, from any `FrameStore` of
+    /// `FrameBuf`s.
+    Full(&'static S),
+    /// Half resolution, scanned out as 2x2 blocks.
+    ///
+    /// Logical pixel `(i, j)` covers physical rows `2i..=2i+1` and columns
+    /// `2j..=2j+1`. The odd last physical row (150) and column (200) repeat
+    /// logical row 74 / column 99, so the bottom and right edge blocks are
+    /// three pixels tall / wide. Drawing uses logical coordinates; the scan
+    /// window stays in physical ones.
+    Doubled(&'static HalfDoubleFb),
+}
+
+impl<S> Clone for FrameSource<S> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<S> Copy for FrameSource<S> {}
+
+impl<const STRIDE: usize, S: FrameStore<Frame = FrameBuf<STRIDE>>> FrameSource<S> {
+    /// log2 of the physical pixels per logical pixel along each axis.
+    #[inline(always)]
+    fn shift(&self) -> u32 {
+        match self {
+            FrameSource::Full(_) => 0,
+            FrameSource::Doubled(_) => 1,
+        }
+    }
+
+    /// Run `f` with the active buffer's row for physical line `v`, or `None`
+    /// past the bottom of the screen.
+    #[inline(always)]
+    fn with_active_row<R>(&self, v: usize, f: impl FnOnce(Option<&[u8]>) -> R) -> R {
+        match self {
+            FrameSource::Full(fb) => fb.with_active(|frame| f(frame.get(v).map(|r| &r[..]))),
+            FrameSource::Doubled(fb) => fb.with_active(|frame| {
+                let row = (v < FB_HEIGHT).then(|| &frame[(v >> 1).min(HALF_FB_HEIGHT - 1)][..]);
+                f(row)
+            }),
+        }
+    }
+
+    fn mark_consumed(&self) {
+        match self {
+            FrameSource::Full(fb) => fb.mark_consumed(),
+            FrameSource::Doubled(fb) => fb.mark_consumed(),
+        }
+    }
+
+    fn mark_scan_started(&self) {
+        match self {
+            FrameSource::Full(fb) => fb.mark_scan_started(),
+            FrameSource::Doubled(fb) => fb.mark_scan_started(),
+        }
+    }
+}
+
+/// Address `addr` moved by `offset`. Results below 0 wrap to huge values,
+/// which every caller's bounds check treats as blanking.
+#[inline(always)]
+fn offset_addr(addr: usize, offset: i16) -> usize {
+    addr.wrapping_add_signed(offset as isize)
+}
+
+/// Logical source column for physical column `h` of `row`.
+#[inline(always)]
+fn src_col(h: usize, shift: u32, row: &[u8]) -> u8 {
+    row[(h >> shift).min(row.len() - 1)]
+}
+
+/// The scan loops' pixel mapping, split out of the loops so the `sim`
+/// captures run the same code against scripted buses.
+#[derive(Clone, Copy)]
+struct ScanMap {
+    shift: u32,
+    lut: [u8; 256],
+    window: ScanWindow,
+    blank: u8,
+    /// `(row, column)`; see `with_addr_offset`.
+    offset: (i16, i16),
+    /// Which bus is the line address; see `with_orientation`.
+    orientation: ScanOrientation,
+}
+
+impl ScanMap {
+    /// Line address for the raw line-bus value `raw`, `None` past the last
+    /// line (rows in `RowMajor`, columns in `ColumnMajor`).
+    #[inline(always)]
+    fn line(&self, raw: usize) -> Option<usize> {
+        let (lines, _) = self.orientation.to_frame(FB_HEIGHT, FB_WIDTH);
+        let (off, _) = self.orientation.to_frame(self.offset.0, self.offset.1);
+        Some(offset_addr(raw, off)).filter(|&line| line < lines)
+    }
+
+    /// Sample address for the raw sample-bus value `raw`, `None` in
+    /// blanking; see `line`.
+    #[inline(always)]
+    fn sample(&self, raw: usize) -> Option<usize> {
+        let (_, samples) = self.orientation.to_frame(FB_HEIGHT, FB_WIDTH);
+        let (_, off) = self.orientation.to_frame(self.offset.0, self.offset.1);
+        Some(offset_addr(raw, off)).filter(|&sample| sample < samples)
+    }
+
+    /// Output code of pixel `(i, j)`, whose framebuffer row is `row`.
+    #[inline(always)]
+    fn code(&self, row: &[u8], i: usize, j: usize) -> u8 {
+        self.lut[self.window.pick(i, j, src_col(j, self.shift, row), self.blank) as usize]
+    }
+
+    /// Output code of pixel `(i, j)`, looking its row up in `fb`.
+    #[inline(always)]
+    fn code_at<const STRIDE: usize, S: FrameStore<Frame = FrameBuf<STRIDE>>>(&self, fb: FrameSource<S>, i: usize, j: usize) -> u8 {
+        fb.with_active_row(i, |row| row.map_or(self.lut[self.blank as usize], |row| self.code(row, i, j)))
+    }
+
+    /// Track vertical blanking for the raw line-bus value `raw_line` and
+    /// return its line address, or `None` in blanking.
+    ///
+    /// The first blank sample after a frame feeds `feed`, enters blanking on
+    /// `sync` and marks the frame consumed; the first line after it leaves
+    /// blanking and marks the next frame started.
+    #[inline(always)]
+    fn enter_line<const STRIDE: usize, S: FrameStore<Frame = FrameBuf<STRIDE>>>(
+        &self,
+        fb: FrameSource<S>,
+        sync: Option<&ScanSync>,
+        feed: &mut impl FnMut(),
+        in_vblank: &mut bool,
+        raw_line: usize,
+    ) -> Option<usize> {
+        let Some(line) = self.line(raw_line) else {
+            if !*in_vblank {
+                *in_vblank = true;
+                feed();
+                if let Some(sync) = sync {
+                    sync.enter_vblank();
+                }
+                fb.mark_consumed();
+            }
+            return None;
+        };
+        if *in_vblank {
+            *in_vblank = false;
+            if let Some(sync) = sync {
+                sync.leave_vblank();
+            }
+            fb.mark_scan_started();
+        }
+        Some(line)
+    }
+
+    /// Handle one line-bus sample `raw_line`: track vertical blanking, or
+    /// drive the line's pixels from `fb` until `line_bus` leaves the line.
+    /// Returns whether a line was driven (`sample_bus` read at least once).
+    ///
+    /// `RowMajor` looks the row up once per line; `ColumnMajor` crosses a
+    /// row per pixel and looks each one up.
+    #[allow(clippy::too_many_arguments)]
+    #[inline(always)]
+    fn scan_line<const STRIDE: usize, S: FrameStore<Frame = FrameBuf<STRIDE>>>(
+        &self,
+        fb: FrameSource<S>,
+        sync: Option<&ScanSync>,
+        feed: &mut impl FnMut(),
+        in_vblank: &mut bool,
+        raw_line: usize,
+        line_bus: &impl AddrBus,
+        sample_bus: &impl AddrBus,
+        out: &mut impl DataBus,
+    ) -> bool {
+        let Some(line) = self.enter_line(fb, sync, feed, in_vblank, raw_line) else { return false };
+        match self.orientation {
+            ScanOrientation::RowMajor => fb.with_active_row(line, |row| {
+                let Some(row) = row else { return false };
+                self.drive_line(line, line_bus, sample_bus, out, |h| self.code(row, line, h));
+                true
+            }),
+            ScanOrientation::ColumnMajor => {
+                self.drive_line(line, line_bus, sample_bus, out, |v| self.code_at(fb, v, line));
+                true
+            }
+        }
+    }
+
+    /// Write `code(sample)` for every in-range sample until `line_bus`
+    /// leaves `line`.
+    #[inline(always)]
+    fn drive_line(
+        &self,
+        line: usize,
+        line_bus: &impl AddrBus,
+        sample_bus: &impl AddrBus,
+        out: &mut impl DataBus,
+        code: impl Fn(usize) -> u8,
+    ) {
+        loop {
+            let sample = self.sample(sample_bus.read_u8() as usize);
+            // Sample the line bus alongside so every pixel uses the same
+            // (h, v) pair the per-pixel version would have seen.
+            if self.line(line_bus.read_u8() as usize) != Some(line) {
+                return;
+            }
+            if let Some(sample) = sample {
+                out.write_u8(code(sample));
+            }
+        }
+    }
+
+    /// One `scan_step` sample: read the line bus, then, outside blanking,
+    /// the sample bus, and write that pixel.
+    #[allow(clippy::too_many_arguments)]
+    #[inline(always)]
+    fn step_sample<const STRIDE: usize, S: FrameStore<Frame = FrameBuf<STRIDE>>>(
+        &self,
+        fb: FrameSource<S>,
+        sync: Option<&ScanSync>,
+        feed: &mut impl FnMut(),
+        in_vblank: &mut bool,
+        line_bus: &impl AddrBus,
+        sample_bus: &impl AddrBus,
+        out: &mut impl DataBus,
+    ) {
+        let raw_line = line_bus.read_u8() as usize;
+        let Some(line) = self.enter_line(fb, sync, feed, in_vblank, raw_line) else { return };
+        let Some(sample) = self.sample(sample_bus.read_u8() as usize) else { return };
+        let (i, j) = self.orientation.to_frame(line, sample);
+        out.write_u8(self.code_at(fb, i, j));
+    }
+
+    /// Write one `scan_loop_generated` frame of `v_total` lines of
+    /// `h_total` pixels to `out`, row-major and without offset; pixels
+    /// outside the framebuffer are 0. Lines from `FB_HEIGHT` on are
+    /// reported as blanking to `sync` and `fb`. Returns false, leaving the
+    /// frame, as soon as `resync(v)` does before line `v`.
+    #[allow(clippy::too_many_arguments)]
+    #[inline(always)]
+    fn generated_frame<const STRIDE: usize, S: FrameStore<Frame = FrameBuf<STRIDE>>>(
+        &self,
+        fb: FrameSource<S>,
+        sync: Option<&ScanSync>,
+        h_total: usize,
+        v_total: usize,
+        out: &mut impl DataBus,
+        mut resync: impl FnMut(usize) -> bool,
+    ) -> bool {
+        for v in 0..v_total {
+            if resync(v) {
+                return false;
+            }
+            if v == 0 {
+                if let Some(sync) = sync {
+                    sync.leave_vblank();
+                }
+                fb.mark_scan_started();
+            } else if v == FB_HEIGHT {
+                if let Some(sync) = sync {
+                    sync.enter_vblank();
+                }
+                fb.mark_consumed();
+            }
+            fb.with_active_row(v, |row| {
+                for h in 0..h_total {
+                    out.write_u8(match row {
+                        Some(row) if h < FB_WIDTH => self.code(row, v, h),
+                        _ => 0,
+                    });
+                }
+            });
+        }
+        true
+    }
+
+    /// `scan_line` for `scan_loop_pipelined`: the line ends when the
+    /// sample address wraps, without reading `line_bus` again.
+    #[allow(clippy::too_many_arguments)]
+    #[inline(always)]
+    fn scan_line_pipelined<const STRIDE: usize, S: FrameStore<Frame = FrameBuf<STRIDE>>>(
+        &self,
+        fb: FrameSource<S>,
+        sync: Option<&ScanSync>,
+        feed: &mut impl FnMut(),
+        in_vblank: &mut bool,
+        raw_line: usize,
+        sample_bus: &impl AddrBus,
+        out: &mut impl DataBus,
+    ) -> bool {
+        let Some(line) = self.enter_line(fb, sync, feed, in_vblank, raw_line) else { return false };
+        match self.orientation {
+            ScanOrientation::RowMajor => fb.with_active_row(line, |row| {
+                let Some(row) = row else { return false };
+                self.drive_line_pipelined(sample_bus, out, |h| self.sample(h).map(|h| self.code(row, line, h)));
+                true
+            }),
+            ScanOrientation::ColumnMajor => {
+                self.drive_line_pipelined(sample_bus, out, |v| self.sample(v).map(|v| self.code_at(fb, v, line)));
+                true
+            }
+        }
+    }
+
+    /// `scan_loop_pipelined`'s inner loop: drive one line from the sample
+    /// bus alone, until its address wraps. `code(raw)` maps a raw sample
+    /// to its output code, `None` in blanking.
+    #[inline(always)]
+    fn drive_line_pipelined(&self, sample_bus: &impl AddrBus, out: &mut impl DataBus, code: impl Fn(usize) -> Option<u8>) {
+        let mut last = sample_bus.read_u8() as usize;
+        if let Some(c) = code(last) {
+            out.write_u8(c);
+        }
+        let mut next = code(last + 1);
+        loop {
+            let raw = sample_bus.read_u8() as usize;
+            if raw == last {
+                continue;
+            }
+            if raw < last {
+                // Wrapped: the line is over, go re-read the line bus.
+                break;
+            }
+            let c = if raw == last + 1 { next } else { code(raw) };
+            if let Some(c) = c {
+                out.write_u8(c);
+            }
+            last = raw;
+            next = code(raw + 1);
+        }
+    }
+}
+
+fn fill_frame<const W: usize, const H: usize>(
+    fb: &impl FrameStore<Frame = [[u8; W]; H]>,
+    ((i0, i1), (j0, j1)): ((u8, u8), (u8, u8)),
+    color: u8,
+) {
+    fb.with_inactive(|frame| {
+        for row in &mut frame[i0 as usize..=i1 as usize] {
+            row[j0 as usize..=j1 as usize].fill(color);
+        }
+    });
+}
+
+/// Bounding box of the pixels in the first `width` columns that differ
+/// between the drawn and the shown buffer.
+fn diff_bounds<const W: usize, const H: usize>(
+    fb: &impl FrameStore<Frame = [[u8; W]; H]>,
+    width: usize,
+) -> Option<((u8, u8), (u8, u8))> {
+    fb.with_buffer(BufferSel::Active, |shown| {
+        fb.with_buffer(BufferSel::Inactive, |drawn| {
+            let mut bounds: Option<((u8, u8), (u8, u8))> = None;
+            for (i, (a, b)) in shown.iter().zip(drawn.iter()).enumerate() {
+                let (a, b) = (&a[..width], &b[..width]);
+                let Some(j0) = a.iter().zip(b).position(|(x, y)| x != y) else { continue };
+                let j1 = a.iter().zip(b).rposition(|(x, y)| x != y).unwrap_or(j0);
+                let (i, j0, j1) = (i as u8, j0 as u8, j1 as u8);
+                grow_bounds(&mut bounds, ((i, i), (j0, j1)));
+            }
+            bounds
+        })
+    })
+}
+
+/// Inclusive `((i0, i1), (j0, j1))` box, the layout of `addr_range()`.
+type Bounds = ((u8, u8), (u8, u8));
+
+/// Grow `bounds` to cover `range`.
+#[inline(always)]
+fn grow_bounds(bounds: &mut Option<Bounds>, range: Bounds) {
+    let ((i0, i1), (j0, j1)) = range;
+    *bounds = Some(match *bounds {
+        None => range,
+        Some(((r0, r1), (c0, c1))) => ((r0.min(i0), r1.max(i1)), (c0.min(j0), c1.max(j1))),
+    });
+}
+
+/// Pixel `(i, j)` of the selected buffer; `None` past `width` or the last row,
+/// so row padding never reads back as a pixel.
+fn read_frame<const W: usize, const H: usize>(
+    fb: &impl FrameStore<Frame = [[u8; W]; H]>,
+    sel: BufferSel,
+    width: usize,
+    i: u8,
+    j: u8,
+) -> Option<u8> {
+    if j as usize >= width {
+        return None;
+    }
+    fb.with_buffer(sel, |frame| frame.get(i as usize)?.get(j as usize).copied())
+}
+
+/// Scale an 8-bit brightness to a `bits`-wide DAC code, rounding to nearest:
+/// 0 stays 0 and 255 becomes the all-ones code. `bits` of 8 or more pass the
+/// value through.
+#[inline]
+pub const fn scale_to_dac(value: u8, bits: usize) -> u8 {
+    if bits >= 8 {
+        return value;
+    }
+    let max = (1u32 << bits) - 1;
+    ((value as u32 * max + 127) / 255) as u8
+}
+
+/// Hold the data bus at `code` until `ctl` is resumed, feeding the watchdog.
+async fn park<const DATA_BITS: usize>(ctl: &ScanControl, data_writer: &mut ParDataWriter<'_, DATA_BITS>, code: u8, feed: &mut impl FnMut()) {
+    data_writer.write_u8(code);
+    ctl.set_parked(true);
+    while ctl.is_paused() {
+        feed();
+        Timer::after_micros(100).await;
+    }
+    ctl.set_parked(false);
+}
+
+/// Lines after the expected frame start on which a VSYNC edge still
+/// counts as in sync, absorbing the latency of polling once per line.
+pub const VSYNC_SLACK_LINES: usize = 1;
+
+/// Feed `tracker` a VSYNC level sampled on scan line `line`, allowing
+/// `VSYNC_SLACK_LINES` of polling latency after the frame start.
+#[inline(always)]
+fn sample_vsync(tracker: &mut VsyncTracker, high: bool, line: usize) -> VsyncEvent {
+    tracker.sample(high, line <= VSYNC_SLACK_LINES)
+}
+
+/// External VSYNC input polled by the scan loops; see `with_vsync_input`.
+pub struct VsyncInput<'a> {
+    pin: Input<'a>,
+    tracker: VsyncTracker,
+}
+
+impl<'a> VsyncInput<'a> {
+    /// `active_low` for the usual negative VSYNC pulse.
+    pub fn new(pin: AnyPin<'a>, active_low: bool) -> Self {
+        Self { pin: Input::new(pin, InputConfig::default()), tracker: VsyncTracker::new(active_low) }
+    }
+
+    /// Sample the pin on scan line `line`, counted from the frame start
+    /// the loop expects.
+    #[inline(always)]
+    fn poll(&mut self, line: usize) -> VsyncEvent {
+        sample_vsync(&mut self.tracker, self.pin.is_high(), line)
+    }
+
+    /// Edge and resync counts so far.
+    pub fn tracker(&self) -> &VsyncTracker {
+        &self.tracker
+    }
+}
+
+/// Deadlines of successive pixel clock periods in CPU cycles, for
+/// `scan_loop_generated`.
+///
+/// `cpu_hz / freq_hz` is rarely whole (9.6 cycles at 240 MHz / 25 MHz), so
+/// periods are `whole` or `whole + 1` cycles long, with the remainder
+/// carried over in `acc` as in Bresenham's line algorithm: the deadlines
+/// never drift from the clock, however long the scan runs.
+struct PixelPacer {
+    whole: u32,
+    rem: u32,
+    freq_hz: u32,
+    /// Remainder carried so far, `0..freq_hz`.
+    acc: u32,
+    /// Cycle count the next pixel is due at.
+    next: u32,
+    #[cfg(debug_assertions)]
+    timing: PixelBudget,
+}
+
+impl PixelPacer {
+    fn new(cpu_hz: u32, freq_hz: u32) -> Self {
+        let whole = cpu_hz / freq_hz;
+        Self {
+            whole,
+            rem: cpu_hz % freq_hz,
+            freq_hz,
+            acc: 0,
+            next: get_cycle_count(),
+            #[cfg(debug_assertions)]
+            timing: PixelBudget::new(whole.max(1)),
+        }
+    }
+
+    /// Make the next pixel due now, after the scan stood still.
+    fn restart(&mut self) {
+        self.next = get_cycle_count();
+        #[cfg(debug_assertions)]
+        {
+            self.timing.last = self.next;
+        }
+    }
+
+    /// Length of the next period in cycles.
+    #[inline(always)]
+    fn next_period(&mut self) -> u32 {
+        self.acc += self.rem;
+        if self.acc >= self.freq_hz {
+            self.acc -= self.freq_hz;
+            self.whole + 1
+        } else {
+            self.whole
+        }
+    }
+}
+
+/// Data bus of `scan_loop_generated`: holds every write until its pixel
+/// period starts.
+struct PacedBus<'p, W> {
+    out: &'p mut W,
+    pacer: &'p mut PixelPacer,
+    /// Where the worst pixel time is published.
+    #[cfg(debug_assertions)]
+    sync: Option<&'static ScanSync>,
+}
+
+impl<W: DataBus> DataBus for PacedBus<'_, W> {
+    #[inline(always)]
+    fn write_u8(&mut self, value: u8) {
+        #[cfg(debug_assertions)]
+        let ready = get_cycle_count();
+        while (get_cycle_count().wrapping_sub(self.pacer.next) as i32) < 0 {}
+        #[cfg(debug_assertions)]
+        let write_start = get_cycle_count();
+        self.out.write_u8(value);
+        #[cfg(debug_assertions)]
+        self.pacer.timing.record(ready, write_start, self.sync);
+        self.pacer.next = self.pacer.next.wrapping_add(self.pacer.next_period().max(1));
+    }
+}
+
+/// Debug-build check that every generated-scan pixel fits its clock period.
+///
+/// A pixel's cost is the time spent computing and writing it, excluding the
+/// wait for its slot; past `budget` the output lags the counters and the
+/// picture shears.
+#[cfg(debug_assertions)]
+struct PixelBudget {
+    budget: u32,
+    worst: u32,
+    /// End of the previous pixel's write.
+    last: u32,
+}
+
+#[cfg(debug_assertions)]
+impl PixelBudget {
+    fn new(budget: u32) -> Self {
+        Self { budget, worst: 0, last: get_cycle_count() }
+    }
+
+    /// Account one pixel: ready for output at `ready`, written during
+    /// `write_start..now`. Warns the first time the budget is exceeded.
+    #[inline(always)]
+    fn record(&mut self, ready: u32, write_start: u32, sync: Option<&ScanSync>) {
+        let now = get_cycle_count();
+        let cost = ready.wrapping_sub(self.last) + now.wrapping_sub(write_start);
+        self.last = now;
+        if cost <= self.worst {
+            return;
+        }
+        if self.worst <= self.budget && cost > self.budget {
+            defmt::warn!("scan: pixel took {} cycles, budget is {}", cost, self.budget);
+        }
+        self.worst = cost;
+        if let Some(sync) = sync {
+            sync.record_pixel_cycles(cost);
+        }
+    }
+}
+
+/// `STRIDE` is the framebuffer row stride; see `FrameBuf`.
+///
+/// `S` is the storage behind full-resolution frames: a `DoubleFb` by
+/// default, or any other `FrameStore` of `FrameBuf<STRIDE>`.
+///
+/// `DATA_BITS` is the width of the resistor DAC on the data bus, 1 to 8
+/// (4 by default). Framebuffer values are 8-bit brightness whatever the
+/// width; the scan loops scale them to `DATA_BITS`-bit codes with
+/// `scale_to_dac` as part of the output table, so the per-pixel cost does
+/// not change.
+pub struct BwPixelWriter8h8v1ch4<'a, const STRIDE: usize = FB_WIDTH, const DATA_BITS: usize = 4, S: 'static = DoubleFb<STRIDE>, F = FeedFn> {
     pub haddr_reader : ParDataReader<'a, 8>,
     pub vaddr_reader : ParDataReader<'a, 8>,
     // unfortunately, the s3 dosn't have a DAC 
-    pub data_writer  : ParDataWriter<'a, 4>,
-    pub dbf : &'static DoubleFb,
+    pub data_writer  : ParDataWriter<'a, DATA_BITS>,
+    /// Framebuffer scanned out and drawn into.
+    pub source : FrameSource<S>,
+    pub polarity : BrightnessPolarity,
+    /// Brightness that logical 0 maps to; see `with_brightness_floor`.
+    pub brightness_floor : u8,
+    /// Optional line that resets the external H/V counters to (0, 0).
+    pub counter_reset : Option<ClearLine<'a>>,
+    /// External VSYNC the scan loops check their position against; see
+    /// `with_vsync_input`.
+    pub vsync_input : Option<VsyncInput<'a>>,
+    /// Buffer that `read_pixel` samples; defaults to the one being drawn.
+    pub read_from : BufferSel,
+    /// Vertical blanking status published by the scan loops, if attached.
+    pub scan_sync : Option<&'static ScanSync>,
+    /// Pause switch checked by the scan loops, if attached.
+    pub scan_control : Option<&'static ScanControl>,
+    /// Region the scan loops drive from the framebuffer; full frame by default.
+    pub scan_window : ScanWindow,
+    /// Logical color output outside `scan_window` (polarity still applies).
+    pub blank_color : u8,
+    /// Called once per scanned frame; see `with_watchdog_feed`.
+    pub feed : F,
+    /// Which address bus selects the framebuffer row; row-major by default.
+    pub orientation : ScanOrientation,
+    /// Swap timing of the present calls; see `present_stats`.
+    pub present_stats : PresentStats,
+    /// Microsecond timestamps for `present_stats`; see `with_present_clock`.
+    pub present_clock : Option<fn() -> u64>,
+    /// Drive only every Nth frame in generated-scan mode; see `with_scan_divider`.
+    pub scan_divider : u16,
+    /// `(row, column)` added to the scanned addresses before they index the
+    /// framebuffer; see `with_addr_offset`.
+    pub addr_offset : (i16, i16),
+    /// Handling of writes outside `addr_range()`; see `with_out_of_range`.
+    pub out_of_range : OutOfRangePolicy,
+    /// Vertical blanking state carried between `scan_step` calls.
+    step_in_vblank : bool,
+    /// Union of the areas drawn since the last present, when tracked; see
+    /// `with_dirty_tracking`.
+    dirty : Option<Option<Bounds>>,
 }
 
+/// Pins of the H / V address buses and the data bus, lowest bit first.
+///
+/// Build one with `vga_hw_resources!`, which takes each pin out of
+/// `Peripherals` by number.
 pub struct VgaHwResources<'a, const HADDR_CNT : usize, const VADDR_CNT : usize, const DATA_CNT : usize> { 
     pub haddr_pins : [AnyPin<'a>; HADDR_CNT],
     pub vaddr_pins : [AnyPin<'a>; VADDR_CNT],
     pub data_pins  : [AnyPin<'a>; DATA_CNT],
 }
 
-impl <'a> BwPixelWriter8h8v1ch4<'a> {
+impl <'a, const STRIDE: usize, const DATA_BITS: usize, S> BwPixelWriter8h8v1ch4<'a, STRIDE, DATA_BITS, S>
+where
+    S: FrameStore<Frame = FrameBuf<STRIDE>>,
+{
     pub fn new(
         haddr_reader : ParDataReader<'a, 8>,
         vaddr_reader : ParDataReader<'a, 8>,
-        data_writer  : ParDataWriter<'a, 4>,
-        dbf : &'static DoubleFb,
+        data_writer  : ParDataWriter<'a, DATA_BITS>,
+        dbf : &'static S,
+    ) -> Self {
+        Self::from_source(haddr_reader, vaddr_reader, data_writer, FrameSource::Full(dbf))
+    }
+
+    fn from_source(
+        haddr_reader : ParDataReader<'a, 8>,
+        vaddr_reader : ParDataReader<'a, 8>,
+        data_writer  : ParDataWriter<'a, DATA_BITS>,
+        source : FrameSource<S>,
     ) -> Self {
+        const { assert!(STRIDE >= FB_WIDTH, "row stride must cover FB_WIDTH") };
+        const { assert!(DATA_BITS >= 1 && DATA_BITS <= 8, "DATA_BITS must be 1..=8") };
         BwPixelWriter8h8v1ch4{
             haddr_reader,
             vaddr_reader,
             data_writer,
-            dbf,
+            source,
+            polarity : BrightnessPolarity::Normal,
+            brightness_floor : 0,
+            counter_reset : None,
+            vsync_input : None,
+            read_from : BufferSel::Inactive,
+            scan_sync : None,
+            scan_control : None,
+            scan_window : ScanWindow::new(0, 0, FB_HEIGHT as u8 - 1, FB_WIDTH as u8 - 1),
+            blank_color : 0,
+            feed : no_feed,
+            orientation : ScanOrientation::RowMajor,
+            present_stats : PresentStats::new(),
+            present_clock : None,
+            scan_divider : 1,
+            addr_offset : (0, 0),
+            out_of_range : OutOfRangePolicy::Skip,
+            step_in_vblank : false,
+            dirty : None,
         }
     }
 
     pub fn with_hw_resources(
-        res : VgaHwResources<'a, 8, 8, 4>,
-        dbf : &'static DoubleFb,
+        res : VgaHwResources<'a, 8, 8, DATA_BITS>,
+        dbf : &'static S,
         iconf : Option<InputConfig>,
         oconf : Option<OutputConfig>,
         init_level : Option<Level>,
@@ -47,50 +726,1337 @@ impl <'a> BwPixelWriter8h8v1ch4<'a> {
         let iconf = iconf.unwrap_or(InputConfig::default());
         let oconf = oconf.unwrap_or(OutputConfig::default());
         let init_level = init_level.unwrap_or(Level::Low);
+        debug_assert_pins_disjoint(&[
+            ("haddr", &res.haddr_pins.each_ref().map(|p| p.number())),
+            ("vaddr", &res.vaddr_pins.each_ref().map(|p| p.number())),
+            ("data", &res.data_pins.each_ref().map(|p| p.number())),
+        ]);
         let haddr_reader = ParDataReader::from_pins(res.haddr_pins, iconf);
         let vaddr_reader = ParDataReader::from_pins(res.vaddr_pins, iconf);
         let data_writer  = ParDataWriter::from_pins(res.data_pins, oconf, init_level);
         Self::new(haddr_reader, vaddr_reader, data_writer, dbf)
     }
+}
+
+impl <'a, const STRIDE: usize, const DATA_BITS: usize, S, F: FnMut()> BwPixelWriter8h8v1ch4<'a, STRIDE, DATA_BITS, S, F>
+where
+    S: FrameStore<Frame = FrameBuf<STRIDE>>,
+{
+
+    /// Set how DAC codes map to brightness on this panel.
+    pub fn with_polarity(mut self, polarity : BrightnessPolarity) -> Self {
+        self.polarity = polarity;
+        self
+    }
+
+    /// Map logical 0 to DAC code `floor` and compress the rest of the range
+    /// above it.
+    ///
+    /// For panels that still glow faintly at code 0; see
+    /// `apply_brightness_floor`. `floor` is a `DATA_BITS`-bit code, e.g.
+    /// `0..=15` on the 4-bit bus, as the glow threshold is measured in DAC
+    /// steps. Defaults to 0 (no change).
+    pub fn with_brightness_floor(mut self, floor : u8) -> Self {
+        self.brightness_floor = floor;
+        self
+    }
+
+    /// Hardware code for every logical color: scaling to `DATA_BITS`, then
+    /// the brightness floor and polarity on the scaled code.
+    ///
+    /// Built once per scan loop so the per-pixel path stays a table lookup.
+    fn output_lut(&self) -> [u8; 256] {
+        let max = scale_to_dac(u8::MAX, DATA_BITS);
+        let (polarity, floor) = (self.polarity, self.brightness_floor);
+        core::array::from_fn(|c| polarity.apply(apply_brightness_floor(scale_to_dac(c as u8, DATA_BITS), floor, max), max))
+    }
+
+    /// Ignore the H / V address bits outside `h_mask` / `v_mask`, for counters
+    /// narrower than their 8-pin readers; see `ParDataReader::with_addr_mask`.
+    pub fn with_addr_masks(mut self, h_mask : u8, v_mask : u8) -> Self {
+        self.haddr_reader = self.haddr_reader.with_addr_mask(h_mask as u32);
+        self.vaddr_reader = self.vaddr_reader.with_addr_mask(v_mask as u32);
+        self
+    }
+
+    /// Read only the low `h_bits` / `v_bits` address lines, e.g. 7 for a
+    /// 128-line counter on the 8-pin readers; see `with_addr_masks`.
+    pub fn with_addr_used_bits(mut self, h_bits : u32, v_bits : u32) -> Self {
+        self.haddr_reader = self.haddr_reader.with_used_bits(h_bits);
+        self.vaddr_reader = self.vaddr_reader.with_used_bits(v_bits);
+        self
+    }
+
+    /// Let `control` pause and resume the scan loops; see `ScanControl`.
+    ///
+    /// Parking latency: `scan_loop` checks at every row change and during
+    /// vertical blanking (one row, ~32 us at 640x480, as long as the counters
+    /// run), `scan_loop_proc` at every row change and `scan_loop_generated`
+    /// at the start of every frame. While parked the bus holds
+    /// `blank_color`.
+    pub fn with_scan_control(mut self, control : &'static ScanControl) -> Self {
+        self.scan_control = Some(control);
+        self
+    }
+
+    /// Select which buffer `read_pixel` (and so `Drawer` queries) reads from.
+    pub fn with_read_from(mut self, sel : BufferSel) -> Self {
+        self.read_from = sel;
+        self
+    }
+
+    /// Publish vertical blanking to `sync`, for `run_vsynced_animation`.
+    pub fn with_scan_sync(mut self, sync : &'static ScanSync) -> Self {
+        self.scan_sync = Some(sync);
+        self
+    }
+
+    /// Only drive framebuffer rows `i0..=i1` and columns `j0..=j1`.
+    ///
+    /// Both bounds are inclusive, so the default full-frame window is
+    /// `(0, 0, FB_HEIGHT - 1, FB_WIDTH - 1)`. Corners may be given in any
+    /// order and are clamped to the framebuffer; everything outside the
+    /// window is output as `blank_color`. The scan loops copy the window
+    /// when they start, so set it before entering one.
+    pub fn set_scan_window(&mut self, i0 : u8, j0 : u8, i1 : u8, j1 : u8) {
+        let (i_max, j_max) = (FB_HEIGHT as u8 - 1, FB_WIDTH as u8 - 1);
+        self.scan_window = ScanWindow::new(i0.min(i_max), j0.min(j_max), i1.min(i_max), j1.min(j_max));
+    }
+
+    /// Set the logical color output outside the scan window.
+    pub fn with_blank_color(mut self, color : u8) -> Self {
+        self.blank_color = color;
+        self
+    }
+
+    /// Set which address bus changes once per scanned line.
+    ///
+    /// Use `ScanOrientation::ColumnMajor` for counters chained with V
+    /// stepping fastest, which sweep the panel column by column: H is then
+    /// the line address (blank from `FB_WIDTH` on) and V the sample address
+    /// (blank from `FB_HEIGHT` on). The picture is the same either way;
+    /// column-major costs a frame lookup per pixel instead of per line.
+    /// Applies to the address-driven loops; `scan_loop_generated` drives
+    /// the counters itself and ignores it.
+    pub fn with_orientation(mut self, orientation : ScanOrientation) -> Self {
+        self.orientation = orientation;
+        self
+    }
+
+    /// Shift the picture for counters that do not read 0 at the first
+    /// visible pixel.
+    ///
+    /// `offset` is `(rows, columns)` in framebuffer terms, whatever the
+    /// `orientation`, and is added to the V / H address before it indexes
+    /// the framebuffer: counters that read 40 at the first visible column
+    /// because they also count the back porch need a column offset of -40.
+    /// Addresses that land outside the framebuffer count as blanking.
+    /// Applies to `scan_loop`, `scan_loop_pipelined` and `scan_step`;
+    /// `scan_loop_proc` passes raw addresses and `scan_loop_generated`
+    /// reads none. Defaults to `(0, 0)`.
+    pub fn with_addr_offset(mut self, offset : (i16, i16)) -> Self {
+        self.addr_offset = offset;
+        self
+    }
+
+    /// Framebuffer `(row, column)` the address-driven scan loops show at
+    /// the counter address `(h_addr, v_addr)`, after `addr_offset`; `None`
+    /// in blanking. In doubled mode these are physical pixels, each logical
+    /// pixel covering 2x2 of them.
+    pub fn frame_position(&self, h_addr : u8, v_addr : u8) -> Option<(usize, usize)> {
+        let (v_off, h_off) = self.addr_offset;
+        let (v, h) = (offset_addr(v_addr as usize, v_off), offset_addr(h_addr as usize, h_off));
+        (v < FB_HEIGHT && h < FB_WIDTH).then_some((v, h))
+    }
+
+    /// Refresh the panel only on every `divider`-th frame of `scan_loop_generated`.
+    ///
+    /// For mostly static content on panels without persistence of their own:
+    /// the skipped frames output `blank_color` while the scan task sleeps,
+    /// saving CPU time and power. The panel is lit for 1 of every `divider`
+    /// frames, so brightness drops by that factor and flicker sets in once
+    /// the effective rate (refresh / `divider`) falls below ~50 Hz; 2 is
+    /// usually the limit at 60 Hz. The other scan loops follow external
+    /// counters and ignore it. Defaults to 1 (every frame); 0 is treated as 1.
+    pub fn with_scan_divider(mut self, divider : u16) -> Self {
+        self.scan_divider = divider.max(1);
+        self
+    }
+
+    /// Call `feed` once per frame from the (never returning) scan loops.
+    ///
+    /// `scan_loop` calls it on entering vertical blanking, `scan_loop_generated`
+    /// at the start of every frame and `scan_loop_proc` whenever the V address
+    /// wraps around, i.e. at the refresh rate (~60 Hz). If the external
+    /// counters stop, so do the calls, letting the watchdog catch the stall.
+    /// No-op by default.
+    ///
+    /// `feed` can be any closure, so it can own or borrow the watchdog:
+    /// `.with_watchdog_feed(move || wdt.feed())`.
+    pub fn with_watchdog_feed<G: FnMut()>(self, feed : G) -> BwPixelWriter8h8v1ch4<'a, STRIDE, DATA_BITS, S, G> {
+        BwPixelWriter8h8v1ch4 {
+            haddr_reader : self.haddr_reader,
+            vaddr_reader : self.vaddr_reader,
+            data_writer : self.data_writer,
+            source : self.source,
+            polarity : self.polarity,
+            brightness_floor : self.brightness_floor,
+            counter_reset : self.counter_reset,
+            vsync_input : self.vsync_input,
+            read_from : self.read_from,
+            scan_sync : self.scan_sync,
+            scan_control : self.scan_control,
+            scan_window : self.scan_window,
+            blank_color : self.blank_color,
+            feed,
+            orientation : self.orientation,
+            present_stats : self.present_stats,
+            present_clock : self.present_clock,
+            scan_divider : self.scan_divider,
+            addr_offset : self.addr_offset,
+            out_of_range : self.out_of_range,
+            step_in_vblank : self.step_in_vblank,
+            dirty : self.dirty,
+        }
+    }
+
+    /// Choose what `write_pixel` does outside `addr_range()`; see
+    /// `OutOfRangePolicy`. Skipped by default, where indexing the frame
+    /// would otherwise panic.
+    pub fn with_out_of_range(mut self, policy : OutOfRangePolicy) -> Self {
+        self.out_of_range = policy;
+        self
+    }
+
+    /// Attach a GPIO that resets the external counters when pulsed.
+    ///
+    /// `active_low` selects the pulse polarity, as for `ClearLine`.
+    pub fn with_counter_reset(mut self, pin : AnyPin<'a>, active_low : bool) -> Self {
+        self.counter_reset = Some(ClearLine::from_pin(pin, active_low));
+        self
+    }
+
+    /// Watch an external VSYNC on `pin` and resync the scan to it.
+    ///
+    /// Each leading edge marks a frame start, i.e. the external V counter
+    /// wrapping to 0. `scan_loop_generated` restarts
+    /// its frame (pulsing the counter reset line) when an edge arrives more
+    /// than `VSYNC_SLACK_LINES` after the line it expected; `scan_loop`
+    /// checks the external V counter instead and pulses the reset line when
+    /// it is not at the frame start. A missed pulse is not an error: the
+    /// loop keeps its own timing. Without this, as before, neither loop
+    /// looks at VSYNC. `vsync_resyncs()` counts the mid-frame edges; in
+    /// `scan_loop` without a counter reset line they are only counted.
+    pub fn with_vsync_input(mut self, pin : AnyPin<'a>, active_low : bool) -> Self {
+        self.vsync_input = Some(VsyncInput::new(pin, active_low));
+        self
+    }
+
+    /// VSYNC edges that arrived mid-frame; 0 without a VSYNC input. Each
+    /// one restarts `scan_loop_generated`'s frame, or pulses the counter
+    /// reset line in `scan_loop`, which cannot correct anything without it.
+    pub fn vsync_resyncs(&self) -> u32 {
+        self.vsync_input.as_ref().map_or(0, |vsync| vsync.tracker().resyncs())
+    }
+
+    /// Put the output in a defined state before the first scan: every
+    /// frame buffer black, the data bus at the black code and the address
+    /// counters reset (if a reset line is configured).
+    ///
+    /// Not done by the constructors, so boards with their own power-on
+    /// sequence keep control; opt in with `initialized`. Fills the buffers
+    /// in place (`FrameStore::fill_all`) without presenting, so
+    /// `on_present` hooks are not called. Must not run while the scan loop
+    /// is running.
+    pub fn initialize(&mut self) {
+        match self.source {
+            FrameSource::Full(fb) => fb.fill_all(0),
+            FrameSource::Doubled(fb) => fb.fill_all(0),
+        }
+        self.clear_dirty();
+        self.data_writer.write_u8(self.output_lut()[0]);
+        self.reset_counters();
+    }
+
+    /// Builder form of `initialize`.
+    pub fn initialized(mut self) -> Self {
+        self.initialize();
+        self
+    }
+
+    /// Turn the screen off without tearing the driver down, e.g. for
+    /// standby: pauses the scan through the attached `ScanControl`, so the
+    /// scan loop parks holding `blank_color` on the bus.
+    ///
+    /// Drawing keeps landing in the framebuffer unseen, and `unblank`
+    /// resumes the scan with whatever it holds by then. Returns
+    /// `Error::NotConfigured` without `with_scan_control`.
+    pub fn blank(&mut self) -> Result<()> {
+        self.scan_control.ok_or(Error::NotConfigured)?.pause();
+        Ok(())
+    }
+
+    /// Resume scanning after `blank`; see `ScanControl::resume`.
+    pub fn unblank(&mut self) -> Result<()> {
+        self.scan_control.ok_or(Error::NotConfigured)?.resume();
+        Ok(())
+    }
+
+    /// Whether the scan is paused, by `blank` or directly through the
+    /// `ScanControl`.
+    pub fn is_blanked(&self) -> bool {
+        self.scan_control.is_some_and(|ctl| ctl.is_paused())
+    }
+
+    /// Pulse the counter reset line, if configured, to resync to (0, 0).
+    #[inline]
+    pub fn reset_counters(&mut self) {
+        if let Some(reset) = &mut self.counter_reset {
+            reset.pulse();
+        }
+    }
+
+    /// Timestamp presents with `now_us`, e.g. `|| Instant::now().as_micros()`,
+    /// so `present_stats()` reports the intervals between them.
+    pub fn with_present_clock(mut self, now_us : fn() -> u64) -> Self {
+        self.present_clock = Some(now_us);
+        self
+    }
+
+    /// Swap counts and timing of `present_frame` / `present_frame_from` /
+    /// `present_doubled` so far.
+    ///
+    /// Dropped frames are detected through the frame counter of the
+    /// attached `ScanSync` (see `with_scan_sync`): a present with no
+    /// vertical blanking since the previous one replaced a frame that was
+    /// never scanned out in full. Clear with `present_stats.reset()`.
+    pub fn present_stats(&self) -> PresentStats {
+        self.present_stats
+    }
+
+    fn record_present(&mut self) {
+        self.clear_dirty();
+        let frame = self.scan_sync.map(ScanSync::frame);
+        let now = self.present_clock.map(|now| now());
+        self.present_stats.record_swap(frame, now);
+    }
 
+    /// Swap in the full-resolution frame; a no-op in doubled mode.
     pub fn present_frame(&mut self) {
-        self.dbf.swap();
+        if let FrameSource::Full(fb) = self.source {
+            fb.swap();
+            self.record_present();
+        }
+    }
+
+    /// Copy an externally owned `frame` into the inactive buffer and swap.
+    ///
+    /// The frame's dimensions are fixed by its type; the only mismatch left
+    /// is doubled mode, whose buffer is half size, which returns
+    /// `Error::OutOfRange` without touching anything. The scan loops pick
+    /// the frame up from the next row; see `FrameStore::present_from`.
+    pub fn present_frame_from(&mut self, frame : &FrameBuf<STRIDE>) -> Result<()> {
+        let FrameSource::Full(fb) = self.source else {
+            return Err(Error::OutOfRange);
+        };
+        fb.present_from(frame);
+        self.record_present();
+        Ok(())
+    }
+
+    /// Copy a packed `FB_HEIGHT` x `FB_WIDTH` frame stored in `layout`
+    /// order into the inactive buffer and swap.
+    ///
+    /// For producers whose buffer is column-major (e.g. a transposed
+    /// renderer) or simply a flat slice: the copy reorders it, so they need
+    /// not match the framebuffer's layout. `SourceLayout::RowMajor` matches
+    /// `present_frame_from`. Returns `Error::BufferTooSmall` if `src` holds
+    /// fewer than `FB_WIDTH * FB_HEIGHT` pixels and `Error::OutOfRange` in
+    /// doubled mode, both without touching anything.
+    pub fn present_slice_from(&mut self, src : &[u8], layout : SourceLayout) -> Result<()> {
+        let FrameSource::Full(fb) = self.source else {
+            return Err(Error::OutOfRange);
+        };
+        if src.len() < FB_WIDTH * FB_HEIGHT {
+            return Err(Error::BufferTooSmall);
+        }
+        fb.with_inactive(|frame| {
+            for (i, row) in frame.iter_mut().enumerate() {
+                for (j, px) in row[..FB_WIDTH].iter_mut().enumerate() {
+                    *px = src[layout.index(i, j, FB_HEIGHT, FB_WIDTH)];
+                }
+            }
+        });
+        fb.swap();
+        self.record_present();
+        Ok(())
+    }
+
+    /// Swap in the half-resolution frame; a no-op in full-resolution mode.
+    ///
+    /// The scan loops pick it up at the next row and draw every logical
+    /// pixel as a 2x2 block.
+    pub fn present_doubled(&mut self) {
+        if let FrameSource::Doubled(fb) = self.source {
+            fb.swap();
+            self.record_present();
+        }
+    }
+
+    /// Run `f` on the full-resolution frame presented last, i.e. the one on
+    /// screen; `None` in doubled mode.
+    ///
+    /// For producers that diff their next frame against what is shown,
+    /// e.g. to send only the changes on to a slower mirror. With a double
+    /// buffer this costs no memory: the presented frame is the active
+    /// buffer, which stays untouched until the next present. A
+    /// `FrameStore` that draws into the shown frame would need a copy of
+    /// its own (a `FrameBuf` per snapshot) or a checksum instead.
+    pub fn with_last_presented<R>(&self, f : impl FnOnce(&FrameBuf<STRIDE>) -> R) -> Option<R> {
+        match self.source {
+            FrameSource::Full(fb) => Some(fb.with_buffer(BufferSel::Active, f)),
+            FrameSource::Doubled(_) => None,
+        }
+    }
+
+    /// Track the bounding box of everything drawn between presents, for
+    /// `present_dirty`.
+    ///
+    /// Costs a few compares per `write_pixel` / `fill_rect`, the writer's
+    /// only drawing paths. Every present clears the area, whichever method
+    /// it goes through (`present_frame_from` and `present_slice_from`
+    /// replace the whole frame and present it at once), and so does
+    /// `initialize`. Unlike `changed_since_present` it counts writes, not
+    /// differences: a pixel rewritten with its old color is still dirty,
+    /// and nothing drawn behind the writer's back (e.g. through
+    /// `with_inactive`) is.
+    pub fn with_dirty_tracking(mut self, enabled : bool) -> Self {
+        self.dirty = enabled.then_some(None);
+        self
+    }
+
+    /// Add `range` to the area `present_dirty` reports, if tracked.
+    #[inline(always)]
+    fn mark_dirty(&mut self, range : Bounds) {
+        if let Some(dirty) = &mut self.dirty {
+            grow_bounds(dirty, range);
+        }
+    }
+
+    /// Forget the tracked area, as after a present.
+    fn clear_dirty(&mut self) {
+        if let Some(dirty) = &mut self.dirty {
+            *dirty = None;
+        }
     }
 
+    /// Present, then hand `f` the area `((i0, i1), (j0, j1))` drawn since
+    /// the previous present, e.g. to start a DMA or partial update of only
+    /// that region on a mirror.
+    ///
+    /// Without dirty tracking `f` gets the full `addr_range()`. With it
+    /// and nothing drawn, the frame is still presented but `f` is not
+    /// called. Coordinates are logical in doubled mode.
+    pub fn present_dirty(&mut self, f : impl FnOnce((u8, u8), (u8, u8))) {
+        let region = match self.dirty.as_mut() {
+            Some(dirty) => dirty.take(),
+            None => Some(self.resolution().addr_range()),
+        };
+        PixelWriter::present(self);
+        if let Some((rows, cols)) = region {
+            f(rows, cols);
+        }
+    }
+
+    /// Bounding box `((i0, i1), (j0, j1))` of the pixels where the frame
+    /// being drawn differs from the presented one, in drawing coordinates
+    /// (logical in doubled mode); `None` if they are identical.
+    ///
+    /// The minimal rectangle to update after the next present. After a
+    /// swap the drawn buffer still holds the frame from two presents ago,
+    /// so its stale pixels count as changes until redrawn. Compares
+    /// both buffers in full (~30 KB at full resolution), so call it once
+    /// per frame, not per pixel. Must not overlap drawing.
+    pub fn changed_since_present(&self) -> Option<((u8, u8), (u8, u8))> {
+        match self.source {
+            FrameSource::Full(fb) => diff_bounds(fb, FB_WIDTH),
+            FrameSource::Doubled(fb) => diff_bounds(fb, HALF_FB_WIDTH),
+        }
+    }
+
+    /// Stream pixels from the active buffer to the data bus forever.
+    ///
+    /// The active buffer is acquired once per row: while the V address stays
+    /// on the same line, H samples are served from the cached row slice. A
+    /// `swap()` therefore takes effect at the next row boundary.
+    ///
+    /// Any V address past the framebuffer counts as vertical blanking: it is
+    /// reported through the attached `ScanSync`, entering it marks the
+    /// frame as consumed (`FrameStore::mark_consumed`) and leaving it marks
+    /// the next one as started (`FrameStore::mark_scan_started`). In doubled mode
+    /// both addresses are halved to index the logical framebuffer.
+    ///
+    /// With `with_vsync_input`, a VSYNC edge seen while V is past its first
+    /// lines pulses the counter reset line, if configured.
+    ///
+    /// See `scan_loop_pipelined` for a lower-latency variant.
     pub async fn scan_loop(&mut self) {
+        let fb = self.source;
+        let map = self.scan_map();
+        let sync = self.scan_sync;
+        let control = self.scan_control;
+        // The line bus changes once per scanned line, the sample bus every pixel.
+        let (line_reader, sample_reader) = match self.orientation {
+            ScanOrientation::RowMajor => (&self.vaddr_reader, &self.haddr_reader),
+            ScanOrientation::ColumnMajor => (&self.haddr_reader, &self.vaddr_reader),
+        };
+        let mut in_vblank = false;
+        loop {
+            if let Some(ctl) = control && ctl.is_paused() {
+                park(ctl, &mut self.data_writer, map.lut[map.blank as usize], &mut self.feed).await;
+            }
+            let raw_line = line_reader.read_u8() as usize;
+            if let Some(vsync) = &mut self.vsync_input
+                && vsync.poll(raw_line) == VsyncEvent::Resync
+                && let Some(reset) = &mut self.counter_reset
+            {
+                reset.pulse();
+                continue;
+            }
+            map.scan_line(fb, sync, &mut self.feed, &mut in_vblank, raw_line, line_reader, sample_reader, &mut self.data_writer);
+        }
+    }
+
+
+    /// Per-frame constants of `scan_loop`'s pixel mapping.
+    fn scan_map(&self) -> ScanMap {
+        ScanMap {
+            shift: self.source.shift(),
+            lut: self.output_lut(),
+            window: self.scan_window,
+            blank: self.blank_color,
+            offset: self.addr_offset,
+            orientation: self.orientation,
+        }
+    }
+
+    /// Pipelined `scan_loop`: writes each pixel's color as soon as its H
+    /// address is read, with the color prepared during the previous pixel.
+    ///
+    /// `scan_loop` reads H, then V, then looks the color up before writing,
+    /// so the color lands one V read plus one lookup after the address
+    /// changed, which shows as the picture lagging right of the counters
+    /// by up to a pixel. Here every pixel does:
+    ///
+    /// 1. read H;
+    /// 2. write the color prefetched for `H` (if H advanced by one, the
+    ///    usual case) or look it up on the spot (after a skipped sample);
+    /// 3. prefetch the color of `H + 1` while the beam crosses the pixel.
+    ///
+    /// V is read once per row: a row ends when H wraps around, then the loop
+    /// re-reads V to pick the next row. The critical path shrinks from two
+    /// bus reads and a lookup to one read, a compare and the write; check
+    /// the gain with a scope between the H LSB and a data pin on both loops,
+    /// or its software share with `measure_scan_latency` (`bench` feature).
+    ///
+    /// Assumes H counts up by one per pixel and wraps once per line, as
+    /// counter-driven scans do (V in `ColumnMajor`, where the same applies
+    /// per column). Feeding a glitchy or non-monotonic counter costs extra
+    /// row breaks; use `scan_loop`, the non-pipelined fallback, there.
+    /// Vertical blanking, `ScanSync`, the scan window, orientation and
+    /// pausing behave as in `scan_loop`.
+    pub async fn scan_loop_pipelined(&mut self) {
+        let fb = self.source;
+        let map = self.scan_map();
+        let sync = self.scan_sync;
+        let control = self.scan_control;
+        let (line_reader, sample_reader) = match self.orientation {
+            ScanOrientation::RowMajor => (&self.vaddr_reader, &self.haddr_reader),
+            ScanOrientation::ColumnMajor => (&self.haddr_reader, &self.vaddr_reader),
+        };
+        let (data_writer, feed) = (&mut self.data_writer, &mut self.feed);
+        let mut in_vblank = false;
+        loop {
+            if let Some(ctl) = control && ctl.is_paused() {
+                park(ctl, data_writer, map.lut[map.blank as usize], feed).await;
+            }
+            let raw_line = line_reader.read_u8() as usize;
+            map.scan_line_pipelined(fb, sync, feed, &mut in_vblank, raw_line, sample_reader, data_writer);
+        }
+    }
+
+    /// Cooperative `scan_loop`: serve `samples` address samples, then return
+    /// so other tasks on the same core get to run.
+    ///
+    /// Each sample reads V and H, looks the pixel up and writes it, with
+    /// vertical blanking, `ScanSync`, the scan window and pausing handled as
+    /// in `scan_loop` (blanking state carries over between calls). There is
+    /// no row caching, so a sample costs a little more than in `scan_loop`,
+    /// and the output table is rebuilt on every call (256 lookups), so keep
+    /// `samples` in the hundreds or more. See `bw8h8v1ch4_coop_scan_task`
+    /// for the ceiling this puts on the picture, and `capture_scan_step`
+    /// (`sim` feature) to run it against scripted counters.
+    pub async fn scan_step(&mut self, samples : usize) {
+        let fb = self.source;
+        let map = self.scan_map();
+        let sync = self.scan_sync;
+        if let Some(ctl) = self.scan_control && ctl.is_paused() {
+            park(ctl, &mut self.data_writer, map.lut[map.blank as usize], &mut self.feed).await;
+        }
+        let (line_reader, sample_reader) = match self.orientation {
+            ScanOrientation::RowMajor => (&self.vaddr_reader, &self.haddr_reader),
+            ScanOrientation::ColumnMajor => (&self.haddr_reader, &self.vaddr_reader),
+        };
+        for _ in 0..samples {
+            map.step_sample(fb, sync, &mut self.feed, &mut self.step_in_vblank, line_reader, sample_reader, &mut self.data_writer);
+        }
+    }
+
+    /// Stream procedurally generated pixels forever, without a framebuffer.
+    ///
+    /// For every address sample, `f(h, v)` computes the color to output
+    /// (polarity is applied as usual); `h` / `v` are the raw column / row
+    /// addresses, and pausing and `feed` follow the line address picked by
+    /// `orientation`. The whole address range is passed to
+    /// `f`, including blanking, and the framebuffer, the scan window and `ScanSync`
+    /// are not used. `f` runs once per sample inside the scan loop, so it
+    /// must finish well within one pixel period of the external counters or
+    /// the output falls behind the beam; keep it to a few integer ops or a
+    /// table lookup.
+    pub async fn scan_loop_proc(&mut self, mut f: impl FnMut(u8, u8) -> u8) {
+        let lut = self.output_lut();
+        let orientation = self.orientation;
+        let control = self.scan_control;
+        let blank = lut[self.blank_color as usize];
+        let mut last_line = 0;
         loop {
-            let h = self.haddr_reader.read_u8() as usize;
-            let v = self.vaddr_reader.read_u8() as usize;
-            if h < FB_WIDTH && v < FB_HEIGHT {
-                let fb = self.dbf;
-                fb.with_active(|frame| {
-                    let color = frame[v][h]; 
-                    self.data_writer.write_u8(color);
-                });
-            } 
+            let (h, v) = (self.haddr_reader.read_u8(), self.vaddr_reader.read_u8());
+            let (line, _) = orientation.to_frame(v, h);
+            if line != last_line && let Some(ctl) = control && ctl.is_paused() {
+                park(ctl, &mut self.data_writer, blank, &mut self.feed).await;
+            }
+            if line < last_line {
+                (self.feed)();
+            }
+            last_line = line;
+            self.data_writer.write_u8(lut[f(h, v) as usize]);
+        }
+    }
+
+    /// Stream frames in lockstep with `clock` instead of reading addresses.
+    ///
+    /// Generated-scan mode: `clock` drives the external H/V counters, so the
+    /// address readers are not consulted. Each line is `h_total` clocks and
+    /// each frame `v_total` lines (visible area plus blanking); pixels
+    /// outside the framebuffer are output as 0. Data writes are paced with
+    /// the cycle counter at `cpu_hz / clock.freq_hz()` cycles per pixel,
+    /// fraction included (see `PixelPacer`), so the clock must be slow
+    /// enough for one `write_u8` per period.
+    ///
+    /// If a counter reset line is configured, it is pulsed at the start of
+    /// every frame so the counters cannot drift away from the data stream.
+    /// Lines from `FB_HEIGHT` to `v_total` are reported as vertical blanking
+    /// through the attached `ScanSync`; the first of them marks the frame as
+    /// consumed.
+    ///
+    /// With `with_vsync_input`, a VSYNC edge arriving mid-frame abandons the
+    /// frame and starts the next one at once.
+    ///
+    /// With `with_scan_divider(n)` only every `n`-th frame is driven; the
+    /// others output `blank_color` and count as vertical blanking throughout,
+    /// signalled once as the first of them starts.
+    ///
+    /// Debug builds also time every pixel against the whole cycles of a
+    /// period, warn once when one runs over and publish the worst case
+    /// through `ScanSync::worst_pixel_cycles`. `capture_generated` (`sim`
+    /// feature) records the codes of one frame.
+    pub async fn scan_loop_generated(
+        &mut self,
+        clock: &PixelClock<'_>,
+        cpu_hz: u32,
+        h_total: usize,
+        v_total: usize,
+    ) {
+        let fb = self.source;
+        let map = self.scan_map();
+        let mut pacer = PixelPacer::new(cpu_hz, clock.freq_hz());
+        let sync = self.scan_sync;
+        let control = self.scan_control;
+        let divider = self.scan_divider.max(1);
+        // From the exact clock rate: the whole cycles per pixel run short.
+        let freq_hz = clock.freq_hz() as u64;
+        let frame_us = ((h_total * v_total) as u64 * 1_000_000 + freq_hz / 2) / freq_hz;
+        let mut skipped: u16 = 0;
+        loop {
+            let mut idle = false;
+            if let Some(ctl) = control && ctl.is_paused() {
+                park(ctl, &mut self.data_writer, map.lut[map.blank as usize], &mut self.feed).await;
+                idle = true;
+            }
+            (self.feed)();
+            if skipped + 1 < divider {
+                // Decimated frame: blank output, sleep through it.
+                if skipped == 0 {
+                    self.data_writer.write_u8(map.lut[map.blank as usize]);
+                    if let Some(sync) = sync {
+                        sync.enter_vblank();
+                    }
+                }
+                skipped += 1;
+                Timer::after_micros(frame_us).await;
+                idle = true;
+            } else {
+                skipped = 0;
+            }
+            if idle {
+                pacer.restart();
+            }
+            if skipped != 0 {
+                continue;
+            }
+            self.reset_counters();
+            let vsync_input = &mut self.vsync_input;
+            let mut out = PacedBus {
+                out: &mut self.data_writer,
+                pacer: &mut pacer,
+                #[cfg(debug_assertions)]
+                sync,
+            };
+            let resync = |v| vsync_input.as_mut().is_some_and(|vsync| vsync.poll(v) == VsyncEvent::Resync);
+            if !map.generated_frame(fb, sync, h_total, v_total, &mut out, resync) {
+                // The external frame started mid-ours: start over.
+                pacer.restart();
+            }
+        }
+    }
+}
+
+/// Scripted H / V counter values for `capture_scan` and its variants.
+///
+/// Each sample is one `(v, h)` pair of raw bus values, as the counters
+/// would show them for one pixel. Every read of the sample bus (H, or V
+/// in `ColumnMajor`) moves to the next sample, the first one reading
+/// sample 0; the line bus returns the sample the last sample-bus read saw.
+/// Past the end the line bus reads `0xFF` and the sample bus alternates
+/// between 0 and `0xFF`, so a line still in progress wraps and ends.
+/// `capture_scan_step` swaps the roles: its line bus steps.
+#[cfg(feature = "sim")]
+pub struct ScanScript<'s> {
+    samples: &'s [(u8, u8)],
+    cur: core::cell::Cell<usize>,
+    /// Whether the stepping bus has not read `cur` yet.
+    fresh: core::cell::Cell<bool>,
+}
+
+#[cfg(feature = "sim")]
+impl<'s> ScanScript<'s> {
+    pub fn new(samples: &'s [(u8, u8)]) -> Self {
+        Self { samples, cur: core::cell::Cell::new(0), fresh: core::cell::Cell::new(true) }
+    }
+
+    /// The current `(v, h)` sample, `(0xFF, 0xFF)` past the end.
+    fn sample(&self) -> (u8, u8) {
+        self.samples.get(self.cur.get()).copied().unwrap_or((0xFF, 0xFF))
+    }
+
+    fn done(&self) -> bool {
+        self.cur.get() >= self.samples.len()
+    }
+
+    /// Skip to the next sample, e.g. while the scan waits out blanking.
+    fn advance(&self) {
+        self.cur.set(self.cur.get() + 1);
+        self.fresh.set(true);
+    }
+
+    /// Move to the sample a stepping-bus read sees.
+    fn step(&self) {
+        if !self.fresh.replace(false) {
+            self.cur.set(self.cur.get() + 1);
+        }
+    }
+}
+
+/// One bus of a `ScanScript`: V if `vertical`, else H; `stepping` for the
+/// bus that steps through the samples.
+#[cfg(feature = "sim")]
+struct ScriptBus<'r, 's> {
+    script: &'r ScanScript<'s>,
+    vertical: bool,
+    stepping: bool,
+}
+
+#[cfg(feature = "sim")]
+impl AddrBus for ScriptBus<'_, '_> {
+    fn read_u8(&self) -> u8 {
+        if self.stepping {
+            self.script.step();
+            if self.script.done() {
+                return if self.script.cur.get().is_multiple_of(2) { 0 } else { 0xFF };
+            }
+        }
+        let (v, h) = self.script.sample();
+        if self.vertical { v } else { h }
+    }
+}
+
+/// Codes written during a script capture, tagged with the sample they were
+/// written at; writes past the end of the script are dropped.
+#[cfg(feature = "sim")]
+struct ScanRecorder<'r, 's> {
+    script: &'r ScanScript<'s>,
+    out: alloc::vec::Vec<(u8, u8, u8)>,
+}
+
+/// Codes written during `capture_generated`.
+#[cfg(feature = "sim")]
+struct CodeRecorder(alloc::vec::Vec<u8>);
+
+#[cfg(feature = "sim")]
+impl DataBus for CodeRecorder {
+    fn write_u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+}
+
+#[cfg(feature = "sim")]
+impl DataBus for ScanRecorder<'_, '_> {
+    fn write_u8(&mut self, value: u8) {
+        if self.script.done() {
+            return;
+        }
+        let (v, h) = self.script.sample();
+        self.out.push((v, h, value));
+    }
+}
+
+#[cfg(feature = "sim")]
+impl<const STRIDE: usize, const DATA_BITS: usize, S, F: FnMut()> BwPixelWriter8h8v1ch4<'_, STRIDE, DATA_BITS, S, F>
+where
+    S: FrameStore<Frame = FrameBuf<STRIDE>>,
+{
+    /// Run `scan_loop`'s pixel mapping over scripted counter values and
+    /// return every code it would put on the data bus, as `(v, h, code)`
+    /// with the raw addresses of the sample it was written at.
+    ///
+    /// Same code path as `scan_loop`: orientation, address offset, scan
+    /// window, blank color and the output table (polarity, brightness
+    /// floor, DAC scaling) all apply, and blanking is reported to the
+    /// attached `ScanSync` and frame store. The data bus, VSYNC input,
+    /// watchdog feed and scan control are not touched. As on hardware, the
+    /// sample where the line address changes ends the line without being
+    /// written. Stops after the last sample; the script must not end
+    /// inside a line whose address is `0xFF`.
+    pub fn capture_scan(&self, script: &ScanScript<'_>) -> alloc::vec::Vec<(u8, u8, u8)> {
+        let map = self.scan_map();
+        // The line bus changes once per scanned line, as in `scan_loop`.
+        let line_is_v = self.orientation == ScanOrientation::RowMajor;
+        let line_bus = ScriptBus { script, vertical: line_is_v, stepping: false };
+        let sample_bus = ScriptBus { script, vertical: !line_is_v, stepping: true };
+        let mut out = ScanRecorder { script, out: alloc::vec::Vec::new() };
+        let mut in_vblank = false;
+        while !script.done() {
+            let raw_line = line_bus.read_u8() as usize;
+            if !map.scan_line(self.source, self.scan_sync, &mut no_feed, &mut in_vblank, raw_line, &line_bus, &sample_bus, &mut out) {
+                script.advance();
+            }
+        }
+        out.out
+    }
+
+    /// `capture_scan` through `scan_loop_pipelined`'s code path.
+    ///
+    /// Each sample-bus read is a fresh counter value here, while on
+    /// hardware the loop reads every pixel several times: the sample
+    /// that wraps the sample address ends the line unwritten, so script it
+    /// twice to have it drawn, as the counters would hold it.
+    pub fn capture_scan_pipelined(&self, script: &ScanScript<'_>) -> alloc::vec::Vec<(u8, u8, u8)> {
+        let map = self.scan_map();
+        let line_is_v = self.orientation == ScanOrientation::RowMajor;
+        let line_bus = ScriptBus { script, vertical: line_is_v, stepping: false };
+        let sample_bus = ScriptBus { script, vertical: !line_is_v, stepping: true };
+        let mut out = ScanRecorder { script, out: alloc::vec::Vec::new() };
+        let mut in_vblank = false;
+        while !script.done() {
+            let raw_line = line_bus.read_u8() as usize;
+            if !map.scan_line_pipelined(self.source, self.scan_sync, &mut no_feed, &mut in_vblank, raw_line, &sample_bus, &mut out) {
+                script.advance();
+            }
+        }
+        out.out
+    }
+
+    /// `capture_scan` through `scan_step`'s code path, one step per
+    /// sample.
+    ///
+    /// Each step reads the line bus first, so here the line bus moves to
+    /// the next sample and the sample bus reads the same one. Every
+    /// sample outside blanking is written. Blanking state starts afresh
+    /// instead of carrying over from `scan_step` calls.
+    pub fn capture_scan_step(&self, script: &ScanScript<'_>) -> alloc::vec::Vec<(u8, u8, u8)> {
+        let map = self.scan_map();
+        let line_is_v = self.orientation == ScanOrientation::RowMajor;
+        let line_bus = ScriptBus { script, vertical: line_is_v, stepping: true };
+        let sample_bus = ScriptBus { script, vertical: !line_is_v, stepping: false };
+        let mut out = ScanRecorder { script, out: alloc::vec::Vec::new() };
+        let mut in_vblank = false;
+        for _ in 0..script.samples.len() {
+            map.step_sample(self.source, self.scan_sync, &mut no_feed, &mut in_vblank, &line_bus, &sample_bus, &mut out);
+        }
+        out.out
+    }
+
+    /// Every code one `scan_loop_generated` frame of `v_total` lines of
+    /// `h_total` clocks writes, in order, without the pacing.
+    ///
+    /// Blanking is reported as in the loop; the counter reset, VSYNC
+    /// input, divider and watchdog feed are not used.
+    pub fn capture_generated(&self, h_total: usize, v_total: usize) -> alloc::vec::Vec<u8> {
+        let mut out = CodeRecorder(alloc::vec::Vec::with_capacity(h_total * v_total));
+        self.scan_map().generated_frame(self.source, self.scan_sync, h_total, v_total, &mut out, |_| false);
+        out.0
+    }
+
+    /// `capture_generated` for `frames` frames with a scripted VSYNC input:
+    /// `vsync(t)` is the pin level on line `t` since the start, counted in
+    /// lines written. As in `scan_loop_generated`, an edge more than
+    /// `VSYNC_SLACK_LINES` after the expected frame start ends the frame
+    /// early, and the next one starts on that same line.
+    /// Returns the codes written and the tracker's counts.
+    pub fn capture_generated_synced(
+        &self,
+        h_total: usize,
+        v_total: usize,
+        frames: usize,
+        active_low: bool,
+        mut vsync: impl FnMut(usize) -> bool,
+    ) -> (alloc::vec::Vec<u8>, VsyncTracker) {
+        let map = self.scan_map();
+        let mut tracker = VsyncTracker::new(active_low);
+        let mut out = CodeRecorder(alloc::vec::Vec::with_capacity(frames * h_total * v_total));
+        for _ in 0..frames {
+            let start = out.0.len() / h_total;
+            let resync = |v| sample_vsync(&mut tracker, vsync(start + v), v) == VsyncEvent::Resync;
+            map.generated_frame(self.source, self.scan_sync, h_total, v_total, &mut out, resync);
+        }
+        (out.0, tracker)
+    }
+}
+
+/// Address-to-color latency of the scan loops, from
+/// `BwPixelWriter8h8v1ch4::measure_scan_latency` (`bench` feature).
+#[cfg(feature = "bench")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct ScanLatency {
+    /// `scan_loop`, in CPU cycles per pixel on average.
+    pub plain: u32,
+    /// `scan_loop_pipelined`, in CPU cycles per pixel on average.
+    pub pipelined: u32,
+}
+
+/// Sample bus counting through one line, one address per read, and a
+/// data bus timing each write from the read before it.
+#[cfg(feature = "bench")]
+#[derive(Default)]
+struct LatencyProbe {
+    next: core::cell::Cell<u16>,
+    read_at: core::cell::Cell<u32>,
+    cycles: core::cell::Cell<u32>,
+    writes: core::cell::Cell<u32>,
+}
+
+/// One view of a `LatencyProbe`; the `line` bus holds line 0 until the
+/// sample count passes 255.
+#[cfg(feature = "bench")]
+struct ProbeBus<'p> {
+    probe: &'p LatencyProbe,
+    line: bool,
+}
+
+#[cfg(feature = "bench")]
+impl AddrBus for ProbeBus<'_> {
+    fn read_u8(&self) -> u8 {
+        let next = self.probe.next.get();
+        if self.line {
+            return if next > 0xFF { 0xFF } else { 0 };
         }
+        self.probe.next.set(next + 1);
+        self.probe.read_at.set(get_cycle_count());
+        next as u8
+    }
+}
+
+#[cfg(feature = "bench")]
+impl DataBus for ProbeBus<'_> {
+    fn write_u8(&mut self, _value: u8) {
+        let probe = self.probe;
+        probe.cycles.set(probe.cycles.get() + get_cycle_count().wrapping_sub(probe.read_at.get()));
+        probe.writes.set(probe.writes.get() + 1);
     }
 }
 
-impl PixelWriter<u8, u8> for BwPixelWriter8h8v1ch4<'_> {
+#[cfg(feature = "bench")]
+impl<const STRIDE: usize, const DATA_BITS: usize, S, F: FnMut()> BwPixelWriter8h8v1ch4<'_, STRIDE, DATA_BITS, S, F>
+where
+    S: FrameStore<Frame = FrameBuf<STRIDE>>,
+{
+    /// Time the path from reading a pixel's address to writing its color
+    /// in `scan_loop` and `scan_loop_pipelined`, over row 0 of the active
+    /// frame.
+    ///
+    /// The address buses are simulated and cost nothing themselves, so
+    /// this is the software share of the latency: `scan_loop`'s second bus
+    /// read and lookup against the pipelined compare. Run with the rest of
+    /// the system quiet, as for `display::bench`, and log the result.
+    pub fn measure_scan_latency(&self) -> ScanLatency {
+        let map = ScanMap { orientation: ScanOrientation::RowMajor, offset: (0, 0), ..self.scan_map() };
+        let run = |pipelined: bool| {
+            let probe = LatencyProbe::default();
+            let (line_bus, sample_bus) = (ProbeBus { probe: &probe, line: true }, ProbeBus { probe: &probe, line: false });
+            let mut out = ProbeBus { probe: &probe, line: false };
+            self.source.with_active_row(0, |row| {
+                let Some(row) = row else { return };
+                if pipelined {
+                    map.drive_line_pipelined(&sample_bus, &mut out, |h| map.sample(h).map(|h| map.code(row, 0, h)));
+                } else {
+                    map.drive_line(0, &line_bus, &sample_bus, &mut out, |h| map.code(row, 0, h));
+                }
+            });
+            probe.cycles.get() / probe.writes.get().max(1)
+        };
+        ScanLatency { plain: run(false), pipelined: run(true) }
+    }
+}
+
+impl<const STRIDE: usize, const DATA_BITS: usize, S, F: FnMut()> BwPixelWriter8h8v1ch4<'_, STRIDE, DATA_BITS, S, F> {
+    /// Logical drawing resolution: `PANEL_RESOLUTION`, or half of it
+    /// (rounded down) in doubled mode.
+    pub fn resolution(&self) -> Resolution {
+        match self.source {
+            FrameSource::Full(_) => PANEL_RESOLUTION,
+            FrameSource::Doubled(_) => Resolution::new(HALF_FB_WIDTH as u16, HALF_FB_HEIGHT as u16),
+        }
+    }
+}
+
+impl<'d, 'a, const STRIDE: usize, const DATA_BITS: usize, S> Drawer<'d, u8, u8, BwPixelWriter8h8v1ch4<'a, STRIDE, DATA_BITS, S>>
+where
+    S: FrameStore<Frame = FrameBuf<STRIDE>>,
+{
+    /// Build the writer from `res` and `dbf` with the default pin
+    /// configuration (see `BwPixelWriter8h8v1ch4::with_hw_resources`) and
+    /// a drawer on it in one step.
+    ///
+    /// As with `Drawer::from_sipo`, the writer lives in `slot`, which must
+    /// outlive the drawer and keeps the writer afterwards. The scan loops
+    /// need the writer itself, so for a scan task on another core build
+    /// the writer directly and draw into `dbf` instead.
+    pub fn from_bus_dac(
+        slot : &'d mut Option<BwPixelWriter8h8v1ch4<'a, STRIDE, DATA_BITS, S>>,
+        res : VgaHwResources<'a, 8, 8, DATA_BITS>,
+        dbf : &'static S,
+    ) -> Self {
+        let writer = BwPixelWriter8h8v1ch4::with_hw_resources(res, dbf, None, None, None);
+        Drawer::new(slot.insert(writer))
+    }
+}
+
+impl <'a, const DATA_BITS: usize> BwPixelWriter8h8v1ch4<'a, FB_WIDTH, DATA_BITS> {
+    /// Like `new`, but scan out a half-resolution `HalfDoubleFb` as 2x2 blocks.
+    ///
+    /// Drawing cost and framebuffer RAM drop to a quarter of full
+    /// resolution; see `FrameSource::Doubled` for the coordinate mapping.
+    pub fn new_doubled(
+        haddr_reader : ParDataReader<'a, 8>,
+        vaddr_reader : ParDataReader<'a, 8>,
+        data_writer  : ParDataWriter<'a, DATA_BITS>,
+        half : &'static HalfDoubleFb,
+    ) -> Self {
+        Self::from_source(haddr_reader, vaddr_reader, data_writer, FrameSource::Doubled(half))
+    }
+}
+
+impl<const STRIDE: usize, const DATA_BITS: usize, S, F: FnMut()> NativePixelWriter for BwPixelWriter8h8v1ch4<'_, STRIDE, DATA_BITS, S, F>
+where
+    S: FrameStore<Frame = FrameBuf<STRIDE>>,
+{
+    type Addr = u8;
+    type Color = u8;
+}
+
+impl<const STRIDE: usize, const DATA_BITS: usize, S, F: FnMut()> PixelWriter<u8, u8> for BwPixelWriter8h8v1ch4<'_, STRIDE, DATA_BITS, S, F>
+where
+    S: FrameStore<Frame = FrameBuf<STRIDE>>,
+{
     fn write_pixel(&mut self, i: u8, j: u8, color: u8) {
-        self.dbf.with_inactive(|frame| {
-            frame[i as usize][j as usize] = color;
-        });
+        let Some((i, j)) = self.out_of_range.resolve(i, j, self.addr_range()) else { return };
+        self.mark_dirty(((i, i), (j, j)));
+        let (i, j) = (i as usize, j as usize);
+        match self.source {
+            FrameSource::Full(fb) => fb.with_inactive(|frame| frame[i][j] = color),
+            FrameSource::Doubled(fb) => fb.with_inactive(|frame| frame[i][j] = color),
+        }
     }
 
+    /// Logical size: 100x75 in doubled mode.
     #[inline(always)]
     fn addr_range(&self) -> ((u8, u8), (u8, u8)) {
-        ((0, FB_HEIGHT as u8 - 1), (0, FB_WIDTH as u8 - 1))
+        self.resolution().addr_range()
     }
 
     #[inline(always)]
     fn color_range(&self) -> (u8, u8) {
         (0, 255)
     }
+
+    /// One `with_inactive` call and a slice fill per row.
+    fn fill_rect(&mut self, range: ((u8, u8), (u8, u8)), color: u8) {
+        self.mark_dirty(range);
+        match self.source {
+            FrameSource::Full(fb) => fill_frame(fb, range, color),
+            FrameSource::Doubled(fb) => fill_frame(fb, range, color),
+        }
+    }
+
+    fn read_pixel(&self, i: u8, j: u8) -> Option<u8> {
+        match self.source {
+            FrameSource::Full(fb) => read_frame(fb, self.read_from, FB_WIDTH, i, j),
+            FrameSource::Doubled(fb) => read_frame(fb, self.read_from, HALF_FB_WIDTH, i, j),
+        }
+    }
+
+    fn present(&mut self) {
+        match self.source {
+            FrameSource::Full(_) => self.present_frame(),
+            FrameSource::Doubled(_) => self.present_doubled(),
+        }
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            read_pixel : true,
+            presentation : true,
+            fast_fill : true,
+            ..Capabilities::write_only::<u8, u8>()
+        }
+    }
+}
+
+/// Bytes per `PackedFrameBuf` row: two pixels per byte.
+#[cfg(feature = "packed-fb")]
+pub const PACKED_FB_STRIDE: usize = FB_WIDTH.div_ceil(2);
+
+/// Full-resolution frame at 4 bits per pixel, half the RAM of a `FrameBuf`.
+///
+/// The default 4-bit DAC only resolves 16 levels, so nothing is lost. Column `2k` is the
+/// low nibble of byte `k` in its row and column `2k + 1` the high one; the
+/// last byte's high nibble is padding. The scan loops read whole bytes, so
+/// a finished frame goes out through `unpack_into` and `present_frame_from`.
+#[cfg(feature = "packed-fb")]
+#[derive(Clone)]
+pub struct PackedFrameBuf {
+    pub rows: [[u8; PACKED_FB_STRIDE]; FB_HEIGHT],
 }
 
+#[cfg(feature = "packed-fb")]
+impl PackedFrameBuf {
+    pub const fn new() -> Self {
+        Self { rows: [[0; PACKED_FB_STRIDE]; FB_HEIGHT] }
+    }
+
+    #[inline(always)]
+    pub fn pixel(&self, i: usize, j: usize) -> u8 {
+        (self.rows[i][j >> 1] >> ((j & 1) * 4)) & 0xF
+    }
+
+    /// Only the low nibble of `color` is stored.
+    #[inline(always)]
+    pub fn set_pixel(&mut self, i: usize, j: usize, color: u8) {
+        let shift = (j & 1) * 4;
+        let byte = &mut self.rows[i][j >> 1];
+        *byte = (*byte & !(0xF << shift)) | ((color & 0xF) << shift);
+    }
+
+    /// Expand into a byte-per-pixel frame, stretching each level to 8-bit
+    /// brightness (`level * 17`) so a 4-bit DAC outputs it unchanged.
+    pub fn unpack_into<const STRIDE: usize>(&self, frame: &mut FrameBuf<STRIDE>) {
+        for (src, dst) in self.rows.iter().zip(frame.iter_mut()) {
+            for (j, px) in dst[..FB_WIDTH].iter_mut().enumerate() {
+                *px = ((src[j >> 1] >> ((j & 1) * 4)) & 0xF) * 17;
+            }
+        }
+    }
+
+    /// Copy a 4-bit-per-pixel `width` x `height` sprite with its top-left
+    /// corner at `(i, j)`, clipped to `addr_range()`.
+    ///
+    /// `src` uses the same nibble order, each row padded to a whole byte
+    /// (`width.div_ceil(2)` bytes). Pixel pairs are moved a byte at a time:
+    /// copied as they are when source and destination columns have the same
+    /// parity, re-paired from two source bytes when they don't. Returns
+    /// `Error::BufferTooSmall`, without drawing, if `src` is shorter than
+    /// `height` rows.
+    pub fn blit_4bpp(&mut self, i: i16, j: i16, width: usize, height: usize, src: &[u8]) -> Result<()> {
+        let src_stride = width.div_ceil(2);
+        if src.len() < height * src_stride {
+            return Err(Error::BufferTooSmall);
+        }
+        let ((_, i_max), (_, j_max)) = self.addr_range();
+        let (i, j) = (i as i32, j as i32);
+        let r_lo = (-i).max(0);
+        let r_hi = (i_max as i32 + 1 - i).min(height as i32);
+        let c_lo = (-j).max(0) as usize;
+        let c_hi = (j_max as i32 + 1 - j).min(width as i32);
+        if r_lo >= r_hi || c_lo as i32 >= c_hi {
+            return Ok(());
+        }
+        let c_hi = c_hi as usize;
+        let nibble = |row: &[u8], c: usize| (row[c >> 1] >> ((c & 1) * 4)) & 0xF;
+        for r in r_lo as usize..r_hi as usize {
+            let src_row = &src[r * src_stride..][..src_stride];
+            let di = (i + r as i32) as usize;
+            // Destination column of source column `c`.
+            let dst = |c: usize| (j + c as i32) as usize;
+            let mut c = c_lo;
+            if dst(c) & 1 == 1 {
+                self.set_pixel(di, dst(c), nibble(src_row, c));
+                c += 1;
+            }
+            while c + 1 < c_hi {
+                let pair = if c & 1 == 0 {
+                    src_row[c >> 1]
+                } else {
+                    (src_row[c >> 1] >> 4) | (src_row[(c >> 1) + 1] << 4)
+                };
+                self.rows[di][dst(c) >> 1] = pair;
+                c += 2;
+            }
+            if c < c_hi {
+                self.set_pixel(di, dst(c), nibble(src_row, c));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "packed-fb")]
+impl Default for PackedFrameBuf {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "packed-fb")]
+impl NativePixelWriter for PackedFrameBuf {
+    type Addr = u8;
+    type Color = u8;
+}
+
+#[cfg(feature = "packed-fb")]
+impl PixelWriter<u8, u8> for PackedFrameBuf {
+    fn write_pixel(&mut self, i: u8, j: u8, color: u8) {
+        self.set_pixel(i as usize, j as usize, color);
+    }
+
+    fn addr_range(&self) -> ((u8, u8), (u8, u8)) {
+        PANEL_RESOLUTION.addr_range()
+    }
+
+    /// 4-bit DAC codes.
+    fn color_range(&self) -> (u8, u8) {
+        (0, 15)
+    }
+
+    fn read_pixel(&self, i: u8, j: u8) -> Option<u8> {
+        Some(self.pixel(i as usize, j as usize))
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities { read_pixel : true, ..Capabilities::write_only::<u8, u8>() }
+    }
+}
+
+/// Scan task for the default, unpadded stride; embassy tasks cannot be
+/// generic, so padded writers need their own task around `scan_loop`.
 #[embassy_executor::task]
 pub async fn bw8h8v1ch4_scan_task(mut writer: BwPixelWriter8h8v1ch4<'static>) {
     writer.scan_loop().await;
-}
\ No newline at end of file
+}
+
+/// Single-core alternative to `bw8h8v1ch4_scan_task`: serve `samples`
+/// address samples with `scan_step`, sleep `tick_us`, repeat.
+///
+/// Spawn it on the main executor next to the drawing tasks when no second
+/// core is free for a dedicated scan loop. The price is the picture: the
+/// external counters keep scanning while other tasks (and this task's
+/// sleep) run, and the data bus just holds the last pixel written, so
+/// only the share of time spent inside `scan_step` is drawn correctly.
+/// At best, with `tick_us` 0 and nothing else ready to run, that is the
+/// `scan_loop` picture minus a gap per yield; with a drawing task taking
+/// half the core, half of every frame smears horizontally. Expect it to be
+/// usable for static or slowly changing content and debugging, not video.
+/// Smaller `samples` and `tick_us` spread the gaps finer at the cost of
+/// more executor overhead. A `tick_us` of 0 only yields to the tasks that
+/// are ready, without sleeping.
+#[embassy_executor::task]
+pub async fn bw8h8v1ch4_coop_scan_task(mut writer: BwPixelWriter8h8v1ch4<'static>, samples: usize, tick_us: u64) {
+    loop {
+        writer.scan_step(samples).await;
+        if tick_us == 0 {
+            yield_now().await;
+        } else {
+            Timer::after_micros(tick_us).await;
+        }
+    }
+}
+
+/// Let the executor run every other ready task once before resuming.
+async fn yield_now() {
+    let mut yielded = false;
+    core::future::poll_fn(|cx| {
+        if yielded {
+            return Poll::Ready(());
+        }
+        yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    })
+    .await
+}
+
+/// Draw frames into `fb` and swap them in only during vertical blanking.
+///
+/// `draw_fn(frame, n)` renders the `n`-th animation frame into the inactive
+/// buffer. Once it returns, the next `swap()` is held back until `sync`
+/// reports a blanking interval that started after the previous swap, so the
+/// scan never switches buffers mid-picture (no tearing) and at most one
+/// frame is presented per scanned frame. The animation is therefore paced
+/// by the display's refresh rate; derive motion from `n` rather than from
+/// a timer.
+///
+/// Core coordination: the scan loop (with `with_scan_sync(sync)`) runs on
+/// core 1 and is the only writer of `sync`; this function runs on core 0
+/// and is the only caller of `with_inactive()` / `swap()`. The scan loop
+/// reads no framebuffer row during blanking, so the swap lands before the
+/// first visible row of the next frame. `sync` is polled every 100 us,
+/// well inside the ~1.4 ms blanking interval of 640x480@60.
+///
+/// This is a plain `async fn` because embassy tasks cannot be generic;
+/// wrap it in an application task that fixes `draw_fn`.
+pub async fn run_vsynced_animation<const STRIDE: usize>(
+    fb : &'static impl FrameStore<Frame = FrameBuf<STRIDE>>,
+    sync : &'static ScanSync,
+    mut draw_fn : impl FnMut(&mut FrameBuf<STRIDE>, u32),
+) -> ! {
+    let mut shown = sync.frame();
+    let mut n: u32 = 0;
+    loop {
+        fb.with_inactive(|frame| draw_fn(frame, n));
+        while !(sync.in_vblank() && sync.frame() != shown) {
+            Timer::after_micros(100).await;
+        }
+        fb.swap();
+        shown = sync.frame();
+        n = n.wrapping_add(1);
+    }
+}
@@ -1,3 +1,4 @@
 pub mod sipo;
 pub mod bus_dac;
-pub mod utils;
\ No newline at end of file
+pub mod utils;
+pub mod pixel_clock;
\ No newline at end of file
@@ -0,0 +1,87 @@
+//! Hardware pixel clock on a GPIO, driven by MCPWM0 timer 0 / operator 0.
+//!
+//! Used by the generated-scan mode (`scan_loop_generated`) to clock the
+//! external address counters and sample-and-hold DACs once per pixel, with
+//! a 50% duty square wave that keeps running without CPU involvement.
+//!
+//! Achievable range on the S3 (MCPWM source clock 160 MHz):
+//! - maximum: 80 MHz in theory (period of 2 ticks), but the GPIO matrix
+//!   limits clean edges to roughly 40 MHz; in practice the CPU-paced data
+//!   output of `scan_loop_generated` is the real bottleneck (~1 MHz).
+//! - minimum: ~9.5 Hz (timer prescaler 256, period 65536).
+//!
+//! The requested frequency is rounded to the nearest achievable divider;
+//! query `freq_hz()` for the exact output rate.
+
+use esp_hal::gpio::interconnect::PeripheralOutput;
+use esp_hal::mcpwm::operator::{PwmPin, PwmPinConfig};
+use esp_hal::mcpwm::timer::{PwmWorkingMode, Timer};
+use esp_hal::mcpwm::{McPwm, PeripheralClockConfig};
+use esp_hal::peripherals::MCPWM0;
+use crate::error::{Error, Result};
+
+/// MCPWM source clock on the S3 (CRYPTO_PWM_CLOCK).
+const MCPWM_SRC_HZ: u32 = 160_000_000;
+
+/// Free-running pixel clock output.
+///
+/// Dropping it releases the MCPWM timer and pin; `stop()` halts the output
+/// while keeping them.
+pub struct PixelClock<'d> {
+    timer: Timer<0, MCPWM0<'d>>,
+    _pin: PwmPin<'d, MCPWM0<'d>, 0, true>,
+    freq_hz: u32,
+}
+
+impl<'d> PixelClock<'d> {
+    /// Start a square wave of (approximately) `freq_hz` on `pin`.
+    ///
+    /// Returns `Error::OutOfRange` if `freq_hz` is 0 or outside the range
+    /// documented at module level.
+    pub fn start(
+        mcpwm: MCPWM0<'d>,
+        pin: impl PeripheralOutput<'d>,
+        freq_hz: u32,
+    ) -> Result<Self> {
+        if freq_hz == 0 || freq_hz > MCPWM_SRC_HZ / 2 {
+            return Err(Error::OutOfRange);
+        }
+        // Total divider = (prescaler + 1) * (period + 1); use the smallest
+        // prescaler that keeps the period within the 16-bit counter.
+        let div = (MCPWM_SRC_HZ + freq_hz / 2) / freq_hz;
+        let prescaler = div.div_ceil(1 << 16).max(1) - 1;
+        if prescaler > u8::MAX as u32 {
+            return Err(Error::OutOfRange);
+        }
+        let period = (div / (prescaler + 1)).max(2) - 1;
+
+        let clock_cfg = PeripheralClockConfig::with_prescaler(0);
+        let mut pwm = McPwm::new(mcpwm, clock_cfg);
+        pwm.operator0.set_timer(&pwm.timer0);
+        let mut pin = pwm.operator0.with_pin_a(pin, PwmPinConfig::UP_ACTIVE_HIGH);
+        let timer_cfg = clock_cfg.timer_clock_with_prescaler(
+            period as u16,
+            PwmWorkingMode::Increase,
+            prescaler as u8,
+        );
+        let mut timer = pwm.timer0;
+        timer.start(timer_cfg);
+        pin.set_timestamp(period.div_ceil(2) as u16);
+
+        Ok(Self {
+            timer,
+            _pin: pin,
+            freq_hz: timer_cfg.frequency().as_hz(),
+        })
+    }
+
+    /// Actual output frequency in Hz after rounding to the hardware divider.
+    pub fn freq_hz(&self) -> u32 {
+        self.freq_hz
+    }
+
+    /// Stop the clock output.
+    pub fn stop(&mut self) {
+        self.timer.stop();
+    }
+}
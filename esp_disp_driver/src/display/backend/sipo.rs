@@ -1,62 +1,815 @@
 use crate::sipo::*;
-use crate::display::pix_writer::PixelWriter;
-use esp_hal::{gpio::AnyPin, peripherals}; 
+use crate::display::drawer::Drawer;
+use crate::display::pix_writer::{Capabilities, NativePixelWriter, PixelWriter};
+use crate::display::backend::utils::{apply_brightness_floor, BrightnessPolarity, OutOfRangePolicy, Resolution, PANEL_RESOLUTION};
+use crate::error::{Error, Result};
+use crate::utils::{debug_assert_pins_disjoint, delay_cycles, PrimInt};
+use num_traits::NumCast;
+use esp_hal::gpio::{AnyPin, Pin};
 
-pub struct BwPixelWriter8h8v1ch8<'a> {
-    // 8 bit for H address, 8 bit for V address
-    // 1ch8: 1 channel, 8 bit color depth (BW)
-    pub p_sipo_bank : ParallelBank<'a, 3, 1>,
+/// Packs an `(i, j)` address into one `N`-byte frame per address lane.
+///
+/// Frames use the same convention as `ParallelBank::shift_exact`: byte 0 is
+/// shifted first and bits go out MSB-first, so the last byte ends up in the
+/// register nearest the SER pin.
+pub trait AddrShifter<const ADDR_LANES: usize, const N: usize> {
+    fn frames(&self, i: u8, j: u8) -> [[u8; N]; ADDR_LANES];
 }
 
-pub struct VgaHwResources<'a>{ 
-    pub rclk : AnyPin<'a>, 
-    pub srclk : AnyPin<'a>, 
+/// Default layout: V address on one lane, H address on another, one byte each.
+///
+/// With `N > 1` the address byte is preceded by `N - 1` zero bytes, which
+/// simply fall off the end of a single-register address chain.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefAddrShifter;
+
+impl<const N: usize> AddrShifter<2, N> for DefAddrShifter {
+    #[inline(always)]
+    fn frames(&self, i: u8, j: u8) -> [[u8; N]; 2] {
+        let mut frames = [[0u8; N]; 2];
+        frames[0][N - 1] = i;
+        frames[1][N - 1] = j;
+        frames
+    }
+}
+
+/// Bit order of the 16-bit word produced by `InterleavedAddrShifter`.
+///
+/// `h` is the H address (`j`), `v` the V address (`i`); the word is listed
+/// from the first bit shifted out (ends up furthest along the chain) to the
+/// last.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InterleaveLayout {
+    /// `h7 v7 h6 v6 ... h0 v0`
+    #[default]
+    HvBitwise,
+    /// `v7 h7 v6 h6 ... v0 h0`
+    VhBitwise,
+    /// `h7 ... h0 v7 ... v0`: H register(s) after the V register in the chain.
+    HThenV,
+    /// `v7 ... v0 h7 ... h0`: V register(s) after the H register in the chain.
+    VThenH,
+}
+
+/// Shifts both address bytes down a single serial lane (two chained registers).
+///
+/// Saves one data pin compared to `DefAddrShifter` at the cost of a 16-bit
+/// shift per pixel; see `InterleaveLayout` for the bit order.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct InterleavedAddrShifter {
+    pub layout: InterleaveLayout,
+}
+
+impl InterleavedAddrShifter {
+    pub fn new(layout: InterleaveLayout) -> Self {
+        Self { layout }
+    }
+}
+
+/// Spread the 8 bits of `x` onto the even bits of a `u16` (bit k -> bit 2k).
+#[inline(always)]
+fn spread_bits(x: u8) -> u16 {
+    let mut v = x as u16;
+    v = (v | (v << 4)) & 0x0F0F;
+    v = (v | (v << 2)) & 0x3333;
+    v = (v | (v << 1)) & 0x5555;
+    v
+}
+
+impl AddrShifter<1, 2> for InterleavedAddrShifter {
+    #[inline(always)]
+    fn frames(&self, i: u8, j: u8) -> [[u8; 2]; 1] {
+        let (h, v) = (j, i);
+        let word: u16 = match self.layout {
+            InterleaveLayout::HvBitwise => (spread_bits(h) << 1) | spread_bits(v),
+            InterleaveLayout::VhBitwise => (spread_bits(v) << 1) | spread_bits(h),
+            InterleaveLayout::HThenV => ((h as u16) << 8) | v as u16,
+            InterleaveLayout::VThenH => ((v as u16) << 8) | h as u16,
+        };
+        [word.to_be_bytes()]
+    }
+}
+
+/// Order in which `fill_rect` visits the pixels of a rectangle.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Traversal {
+    /// Row by row, left to right.
+    #[default]
+    RowMajor,
+    /// Rows and columns in reflected Gray-code order of their addresses,
+    /// the columns running backwards on every other row: consecutive
+    /// pixels differ in a single latched address line, so the address
+    /// outputs toggle about half as often as row-major and the DAC sees
+    /// fewer glitches while they settle. Exact for ranges aligned to a
+    /// power of two (a full 8x8 block takes 63 toggles instead of 120);
+    /// other ranges walk the enclosing aligned block and skip outside
+    /// addresses, which costs an occasional multi-bit jump.
+    Gray,
+}
+
+impl Traversal {
+    /// Every address of the inclusive `((i0, i1), (j0, j1))` range, once,
+    /// in this order.
+    pub fn iter(self, ((i0, i1), (j0, j1)): ((u8, u8), (u8, u8))) -> TraversalIter {
+        let gray = self == Traversal::Gray;
+        TraversalIter {
+            rows: Axis::new(i0, i1, gray),
+            cols: Axis::new(j0, j1, gray),
+            snake: gray,
+            reverse: false,
+            r: 0,
+            c: 0,
+        }
+    }
+}
+
+/// One address axis of a `TraversalIter`.
+#[derive(Clone, Copy, Debug)]
+struct Axis {
+    lo: u8,
+    hi: u8,
+    /// First address of the walk; the aligned block start for Gray order.
+    base: u16,
+    /// Positions in the walk, including skipped ones.
+    len: u32,
+    gray: bool,
+}
+
+impl Axis {
+    fn new(lo: u8, hi: u8, gray: bool) -> Self {
+        if lo > hi {
+            return Self { lo, hi, base: 0, len: 0, gray };
+        }
+        if !gray {
+            return Self { lo, hi, base: lo as u16, len: (hi - lo) as u32 + 1, gray };
+        }
+        // Smallest aligned power-of-two block holding both ends.
+        let bits = 8 - (lo ^ hi).leading_zeros();
+        let base = ((lo as u16) >> bits) << bits;
+        Self { lo, hi, base, len: 1 << bits, gray }
+    }
+
+    /// Address at walk position `k`, or `None` if it falls outside `lo..=hi`.
+    fn get(&self, k: u32) -> Option<u8> {
+        let step = if self.gray { k ^ (k >> 1) } else { k };
+        let addr = self.base + step as u16;
+        (self.lo as u16..=self.hi as u16).contains(&addr).then_some(addr as u8)
+    }
+}
+
+/// Iterator returned by `Traversal::iter`.
+#[derive(Clone, Copy, Debug)]
+pub struct TraversalIter {
+    rows: Axis,
+    cols: Axis,
+    /// Flip the column direction after every row.
+    snake: bool,
+    reverse: bool,
+    r: u32,
+    c: u32,
+}
+
+impl Iterator for TraversalIter {
+    type Item = (u8, u8);
+
+    fn next(&mut self) -> Option<(u8, u8)> {
+        loop {
+            if self.r >= self.rows.len {
+                return None;
+            }
+            let Some(i) = self.rows.get(self.r) else {
+                self.r += 1;
+                continue;
+            };
+            if self.c >= self.cols.len {
+                self.r += 1;
+                self.c = 0;
+                self.reverse ^= self.snake;
+                continue;
+            }
+            let k = if self.reverse { self.cols.len - 1 - self.c } else { self.c };
+            self.c += 1;
+            if let Some(j) = self.cols.get(k) {
+                return Some((i, j));
+            }
+        }
+    }
+}
+
+/// Where each byte of a pixel frame is shifted out, for boards whose
+/// registers are not wired in the default order.
+///
+/// A frame is built in logical order, the color channels followed by the
+/// address frames of the `AddrShifter` (`[[color], [i], [j]]` for
+/// `BwPixelWriter8h8v1ch8`); logical byte `b` of lane `l` then goes to
+/// physical `slot(l, b)`. `from_lane_order` covers registers chained in a
+/// different order, `with_byte` moves single bytes, e.g. to interleave
+/// address bytes across lanes. The default maps everything to itself.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LaneMap<const LANES: usize, const N: usize> {
+    slots: [[(u8, u8); N]; LANES],
+}
+
+impl<const LANES: usize, const N: usize> LaneMap<LANES, N> {
+    pub fn identity() -> Self {
+        Self { slots: core::array::from_fn(|l| core::array::from_fn(|b| (l as u8, b as u8))) }
+    }
+
+    /// Move whole lanes: logical lane `l` goes to physical lane `order[l]`,
+    /// bytes in place. `[2, 0, 1]` puts the color of a 1ch8 writer on the
+    /// third register chain and `i`, `j` on the first two.
+    pub fn from_lane_order(order: [u8; LANES]) -> Self {
+        Self { slots: core::array::from_fn(|l| core::array::from_fn(|b| (order[l], b as u8))) }
+    }
+
+    /// Send logical byte `byte` of `lane` to physical `(lane, byte)` `to`.
+    pub fn with_byte(mut self, lane: usize, byte: usize, to: (u8, u8)) -> Self {
+        self.slots[lane][byte] = to;
+        self
+    }
+
+    /// Physical `(lane, byte)` of logical byte `byte` of `lane`.
+    pub fn slot(&self, lane: usize, byte: usize) -> (u8, u8) {
+        self.slots[lane][byte]
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == Self::identity()
+    }
+
+    /// Whether every physical byte is in range and fed by exactly one
+    /// logical byte, so no field is lost or overwritten.
+    pub fn is_permutation(&self) -> bool {
+        let mut seen = [[false; N]; LANES];
+        for &(l, b) in self.slots.iter().flatten() {
+            let Some(hit) = seen.get_mut(l as usize).and_then(|lane| lane.get_mut(b as usize)) else {
+                return false;
+            };
+            if core::mem::replace(hit, true) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Rearrange a logical `frame` into bank order.
+    pub fn apply(&self, frame: [[u8; N]; LANES]) -> [[u8; N]; LANES] {
+        let mut out = [[0u8; N]; LANES];
+        for (slots, bytes) in self.slots.iter().zip(frame.iter()) {
+            for (&(l, b), &byte) in slots.iter().zip(bytes.iter()) {
+                out[l as usize][b as usize] = byte;
+            }
+        }
+        out
+    }
+}
+
+impl<const LANES: usize, const N: usize> Default for LaneMap<LANES, N> {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+/// Why a bank of `lanes` lanes, `n` bytes each, cannot carry `data_lanes`
+/// color channels followed by `addr_lanes` address lanes, or `None` if it
+/// can. The check behind `PixelWriterSipo::LANE_LAYOUT`.
+pub const fn lane_layout_error(data_lanes: usize, addr_lanes: usize, lanes: usize, n: usize) -> Option<&'static str> {
+    if data_lanes < 1 {
+        Some("PixelWriterSipo needs at least one data lane")
+    } else if n < 1 {
+        Some("PixelWriterSipo needs at least one byte per lane (N >= 1)")
+    } else if lanes != data_lanes + addr_lanes {
+        Some("LANES must be DATA_LANES + ADDR_LANES: data lanes first, then one lane per AddrShifter frame")
+    } else {
+        None
+    }
+}
+
+/// SIPO-driven pixel writer with `DATA_LANES` color channels.
+///
+/// 8 bit for H address, 8 bit for V address, one `C` color per data channel.
+/// All lanes share one control group and are laid out in the bank as:
+/// - `lanes[0..DATA_LANES]` → color channels (e.g. R, G, B),
+/// - `lanes[DATA_LANES..]`  → address lanes, as packed by the `AddrShifter`
+///   (V then H for `DefAddrShifter`).
+///
+/// Boards chained in another order describe it with `with_lane_map`.
+///
+/// Every lane shifts `N` bytes per pixel. A color is written big-endian into
+/// the last `N` bytes of its frame: the least significant byte goes last and
+/// lands in the register nearest SER, more significant bytes sit further
+/// along the chain. So with `N = 2` and `C = u16`, bits 15..8 end up in the
+/// second register and bits 7..0 in the first; a 12-bit panel uses the low 12
+/// bits. Bits of `C` beyond `8 * N` are dropped.
+///
+/// `LANES` must equal `DATA_LANES + ADDR_LANES`; it is a separate parameter
+/// only because stable Rust cannot derive it.
+///
+/// The bank is a GPIO `ParallelBank` by default; any `ShiftBank`, such as
+/// an `rmt_bank::RmtBank`, works through `new`.
+///
+/// Pixels show up as soon as they are written, so large redraws tear; wrap
+/// the writer in `display::buffered::BufferedWriter` to draw off-screen and
+/// flush only the changes on `present()`.
+pub struct PixelWriterSipo<
+    'a,
+    const DATA_LANES: usize,
+    const LANES: usize,
+    const ADDR_LANES: usize = 2,
+    const N: usize = 1,
+    S = DefAddrShifter,
+    C = u8,
+    B = ParallelBank<'a, LANES, N>,
+> {
+    pub p_sipo_bank : B,
+    pub addr_shifter : S,
+    pub polarity : BrightnessPolarity,
+    /// Brightness that logical 0 maps to; see `with_brightness_floor`.
+    pub brightness_floor : u8,
+    /// Latch once per batch instead of once per pixel (see `with_batched_latch`).
+    pub batch_latch : bool,
+    /// CPU cycles to wait after every pixel (see `with_inter_pixel_delay_cycles`).
+    pub inter_pixel_delay_cycles : u32,
+    /// Panel size behind the address lanes (see `with_resolution`).
+    pub resolution : Resolution,
+    /// Frame byte routing; `None` for the default order (see `with_lane_map`).
+    lane_map : Option<LaneMap<LANES, N>>,
+    /// Pixel order of `fill_rect` (see `with_traversal`).
+    pub traversal : Traversal,
+    /// Handling of writes outside `resolution` (see `with_out_of_range`).
+    pub out_of_range : OutOfRangePolicy,
+    /// Set by `blank`: the frame that was on the outputs, if any.
+    blanked : Option<Option<[[u8; N]; LANES]>>,
+    in_batch : bool,
+    /// Packed frames of the current address; `None` until one is set.
+    cur_addr : Option<[[u8; N]; ADDR_LANES]>,
+    _color : core::marker::PhantomData<(&'a (), C)>,
+}
+
+/// 1ch8: 1 channel, 8 bit color depth (BW)
+pub type BwPixelWriter8h8v1ch8<'a> = PixelWriterSipo<'a, 1, 3>;
+
+/// BW writer with both address bytes interleaved on a single lane.
+pub type BwPixelWriter8h8v1ch8Interleaved<'a> =
+    PixelWriterSipo<'a, 1, 2, 1, 2, InterleavedAddrShifter>;
+
+/// 1ch16: 1 channel, up to 16 bit gray on a two-register data chain.
+pub type BwPixelWriter8h8v1ch16<'a> = PixelWriterSipo<'a, 1, 3, 2, 2, DefAddrShifter, u16>;
+
+pub struct VgaHwResources<'a, const DATA_LANES: usize = 1>{
+    pub rclk : AnyPin<'a>,
+    pub srclk : AnyPin<'a>,
     pub srclr_al : AnyPin<'a>,
-    pub data_ser : AnyPin<'a>,
+    pub data_ser : [AnyPin<'a>; DATA_LANES],
     pub i_addr_ser : AnyPin<'a>,
     pub j_addr_ser : AnyPin<'a>,
 
 }
 
-impl BwPixelWriter8h8v1ch8<'_> {
-    pub fn from_resources<'a>(res : VgaHwResources<'a>) -> BwPixelWriter8h8v1ch8<'a>{
+impl<'a, const DATA_LANES: usize, const LANES: usize, const N: usize, C: PrimInt>
+    PixelWriterSipo<'a, DATA_LANES, LANES, 2, N, DefAddrShifter, C>
+{
+    pub fn from_resources(res : VgaHwResources<'a, DATA_LANES>) -> Result<Self> {
+        debug_assert_pins_disjoint(&[
+            ("rclk", &[res.rclk.number()]),
+            ("srclk", &[res.srclk.number()]),
+            ("srclr", &[res.srclr_al.number()]),
+            ("data_ser", &res.data_ser.each_ref().map(|p| p.number())),
+            ("i_addr_ser", &[res.i_addr_ser.number()]),
+            ("j_addr_ser", &[res.j_addr_ser.number()]),
+        ]);
         let control_pin_cfg = ControlPinCfg {
-            srclk : res.srclk,
-            rclk : Some(res.rclk),    
+            rclk : Some(res.rclk),
             srclr : Some(res.srclr_al),
-            clr_active_low : true,
+            ..ControlPinCfg::new(res.srclk)
         };
         let control_group = ControlGroup::from_cfg(control_pin_cfg);
-        let data_lane = SerLane::from_pin(res.data_ser);
-        let i_addr_lane = SerLane::from_pin(res.i_addr_ser);
-        let j_addr_lane = SerLane::from_pin(res.j_addr_ser);
-        let p_sipo_bank = ParallelBank::new(
-            [data_lane, i_addr_lane, j_addr_lane],
-            control_group,
-        );
-        BwPixelWriter8h8v1ch8{
-            p_sipo_bank
+        let mut data_pins = res.data_ser.into_iter();
+        let mut i_addr_pin = Some(res.i_addr_ser);
+        let mut j_addr_pin = Some(res.j_addr_ser);
+        let lanes: [SerLane<'a>; LANES] = core::array::from_fn(|idx| {
+            let pin = if idx < DATA_LANES {
+                data_pins.next()
+            } else if idx == DATA_LANES {
+                i_addr_pin.take()
+            } else {
+                j_addr_pin.take()
+            };
+            SerLane::from_pin(pin.unwrap())
+        });
+        let p_sipo_bank = ParallelBank::new(lanes, control_group);
+        Self::new(p_sipo_bank, DefAddrShifter)
+    }
+}
+
+impl<'d, 'a, const DATA_LANES: usize, const LANES: usize, const N: usize, C: PrimInt>
+    Drawer<'d, u8, C, PixelWriterSipo<'a, DATA_LANES, LANES, 2, N, DefAddrShifter, C>>
+{
+    /// Build the writer from `res` (see `PixelWriterSipo::from_resources`)
+    /// and a drawer on it in one step.
+    ///
+    /// The drawer borrows the writer, so it is stored in `slot`, which must
+    /// outlive the drawer; any writer already there is dropped. Once the
+    /// drawer is gone, the writer stays in `slot` for direct use. Name the
+    /// writer type on the slot to pick the layout:
+    ///
+    /// ```ignore
+    /// let mut slot: Option<BwPixelWriter8h8v1ch8> = None;
+    /// let mut drawer = Drawer::from_sipo(&mut slot, res)?;
+    /// ```
+    pub fn from_sipo(
+        slot : &'d mut Option<PixelWriterSipo<'a, DATA_LANES, LANES, 2, N, DefAddrShifter, C>>,
+        res : VgaHwResources<'a, DATA_LANES>,
+    ) -> Result<Self> {
+        let writer = PixelWriterSipo::from_resources(res)?;
+        Ok(Drawer::new(slot.insert(writer)))
+    }
+}
+
+impl<'a, const DATA_LANES: usize, const LANES: usize, const ADDR_LANES: usize, const N: usize, S, C, B>
+    PixelWriterSipo<'a, DATA_LANES, LANES, ADDR_LANES, N, S, C, B>
+where
+    S: AddrShifter<ADDR_LANES, N>,
+    C: PrimInt,
+    B: ShiftBank<LANES, N>,
+{
+    /// Compile-time check of the lane layout: at least one data lane and
+    /// one byte per lane, and a bank of exactly `DATA_LANES` data lanes
+    /// followed by the `ADDR_LANES` the `AddrShifter` fills. Referenced by
+    /// every path that builds a bank frame, so a mismatch fails to build
+    /// instead of shifting colors into address registers, with the
+    /// message from `lane_layout_error`. CI keeps this honest by building
+    /// `examples/lane_layout_mismatch.rs`, which must fail.
+    pub const LANE_LAYOUT: () = if let Some(msg) = lane_layout_error(DATA_LANES, ADDR_LANES, LANES, N) {
+        panic!("{}", msg)
+    };
+
+    /// Build a writer from an already wired bank and an address shifter.
+    ///
+    /// The bank's lanes must be ordered data lanes first, then address lanes.
+    /// Returns `Error::NotConfigured` if the bank cannot latch (no RCLK
+    /// line and no `LatchSource::SharedWithShift`), which every pixel write
+    /// relies on.
+    ///
+    /// The lane counts are checked at compile time (see `LANE_LAYOUT`), so
+    /// a bank that does not match the data and address lanes, say RGB plus
+    /// two address lanes on a 3-lane bank instead of a 5-lane one, fails to
+    /// build.
+    pub fn new(p_sipo_bank : B, addr_shifter : S) -> Result<Self> {
+        let () = Self::LANE_LAYOUT;
+        if !p_sipo_bank.can_latch() {
+            return Err(Error::NotConfigured);
+        }
+        Ok(PixelWriterSipo{
+            p_sipo_bank,
+            addr_shifter,
+            polarity : BrightnessPolarity::Normal,
+            brightness_floor : 0,
+            batch_latch : false,
+            inter_pixel_delay_cycles : 0,
+            resolution : PANEL_RESOLUTION,
+            lane_map : None,
+            traversal : Traversal::RowMajor,
+            out_of_range : OutOfRangePolicy::Skip,
+            blanked : None,
+            in_batch : false,
+            cur_addr : None,
+            _color : core::marker::PhantomData,
+        })
+    }
+
+    /// Largest color the data chain can hold: all ones in `min(8 * N, bits of C)` bits.
+    #[inline(always)]
+    fn color_max() -> C {
+        let bits = (8 * N).min(8 * core::mem::size_of::<C>());
+        if bits >= 8 * core::mem::size_of::<C>() {
+            C::max_value()
+        } else {
+            (C::one() << bits) - C::one()
+        }
+    }
+
+    /// Put the panel in a defined state: clear the shift registers, then
+    /// latch black at address (0, 0) through the usual color mapping.
+    ///
+    /// Not done by the constructors, so boards with their own power-on
+    /// sequence keep control; opt in with `initialized`. Leaves any open
+    /// batch alone but latches regardless.
+    pub fn initialize(&mut self) -> Result<()> {
+        self.p_sipo_bank.initialize()?;
+        let in_batch = core::mem::replace(&mut self.in_batch, false);
+        self.set_address(0, 0);
+        let res = self.write_channels_at_current([C::zero(); DATA_LANES]);
+        self.in_batch = in_batch;
+        res
+    }
+
+    /// Builder form of `initialize`, e.g.
+    /// `PixelWriterSipo::from_resources(res)?.initialized()?`.
+    pub fn initialized(mut self) -> Result<Self> {
+        self.initialize()?;
+        Ok(self)
+    }
+
+    /// Switch all outputs off without tearing the writer down: clear the
+    /// registers through SRCLR and latch the cleared state.
+    ///
+    /// While blanked, pixel writes are dropped, since this backend has no
+    /// framebuffer to keep them in and shifting one would switch the
+    /// outputs back on. `unblank` re-latches the frame that was showing
+    /// before; to also replay what was drawn meanwhile, draw through a
+    /// `BufferedWriter` and `present()` after unblanking. Returns
+    /// `Error::NotConfigured` without an SRCLR line. A no-op if already
+    /// blanked.
+    pub fn blank(&mut self) -> Result<()> {
+        if self.blanked.is_some() {
+            return Ok(());
+        }
+        let shown = self.p_sipo_bank.last_frame().copied();
+        self.p_sipo_bank.clear_all()?;
+        self.p_sipo_bank.latch()?;
+        self.blanked = Some(shown);
+        Ok(())
+    }
+
+    /// Leave `blank` mode, shifting and latching the frame that was on the
+    /// outputs when it was entered. A no-op if not blanked.
+    pub fn unblank(&mut self) -> Result<()> {
+        match self.blanked.take() {
+            Some(Some(frame)) => self.p_sipo_bank.write_exact(frame),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn is_blanked(&self) -> bool {
+        self.blanked.is_some()
+    }
+
+    /// Set how DAC codes map to brightness on this panel.
+    pub fn with_polarity(mut self, polarity : BrightnessPolarity) -> Self {
+        self.polarity = polarity;
+        self
+    }
+
+    /// Map logical 0 to `floor` and compress the rest of the range above it.
+    ///
+    /// For panels that still glow faintly at code 0; see
+    /// `apply_brightness_floor`. Defaults to 0 (no change).
+    pub fn with_brightness_floor(mut self, floor : u8) -> Self {
+        self.brightness_floor = floor;
+        self
+    }
+
+    /// Honor `begin_batch()`/`end_batch()` by latching only once per batch.
+    ///
+    /// Off by default: with the plain address+data wiring every pixel must be
+    /// latched to reach the RAM, so only enable this for boards that consume
+    /// pixels straight from the shift stage.
+    pub fn with_batched_latch(mut self, enabled : bool) -> Self {
+        self.batch_latch = enabled;
+        self
+    }
+
+    /// Busy-wait `cycles` CPU cycles after every pixel, so slow register
+    /// chains or a slow panel RAM settle before the next address goes out.
+    ///
+    /// Defaults to 0 (back to back). The cost is per pixel, shifted or
+    /// latched alike: a full-frame fill of the 201x151 panel writes 30351
+    /// pixels, so each cycle adds about 30k cycles, i.e. 100 cycles add
+    /// roughly 12.6 ms per fill at 240 MHz. Use `ns_to_cycles` to convert
+    /// a settle time from a datasheet.
+    pub fn with_inter_pixel_delay_cycles(mut self, cycles : u32) -> Self {
+        self.inter_pixel_delay_cycles = cycles;
+        self
+    }
+
+    /// Set the panel size `addr_range()` reports. Defaults to
+    /// `PANEL_RESOLUTION`, the same panel `bus_dac` drives; each dimension
+    /// is capped at 256 by the 8-bit address bytes.
+    pub fn with_resolution(mut self, resolution : Resolution) -> Self {
+        self.resolution = resolution;
+        self
+    }
+
+    pub fn resolution(&self) -> Resolution {
+        self.resolution
+    }
+
+    /// Route frame bytes to the bank through `map`, for boards that chain
+    /// the data and address registers in another order than the default
+    /// data-then-address layout.
+    ///
+    /// Returns `Error::OutOfRange` unless `map` is a permutation (see
+    /// `LaneMap::is_permutation`). The identity map costs nothing per
+    /// pixel; any other costs one `LANES * N` byte shuffle.
+    pub fn with_lane_map(mut self, map : LaneMap<LANES, N>) -> Result<Self> {
+        if !map.is_permutation() {
+            return Err(Error::OutOfRange);
         }
+        self.lane_map = (!map.is_identity()).then_some(map);
+        Ok(self)
+    }
+
+    pub fn lane_map(&self) -> LaneMap<LANES, N> {
+        self.lane_map.unwrap_or_default()
+    }
+
+    /// Visit `fill_rect` pixels in `traversal` order, e.g. `Traversal::Gray`
+    /// to change one address line per pixel on boards where address
+    /// transitions glitch the output. Defaults to row-major.
+    pub fn with_traversal(mut self, traversal : Traversal) -> Self {
+        self.traversal = traversal;
+        self
+    }
+
+    /// Choose what writes outside `resolution` do; see `OutOfRangePolicy`.
+    /// Skipped by default, where they would otherwise shift an address the
+    /// panel does not decode.
+    pub fn with_out_of_range(mut self, policy : OutOfRangePolicy) -> Self {
+        self.out_of_range = policy;
+        self
+    }
+
+    /// Largest color that fits both the data chain and the clocked bits.
+    #[inline(always)]
+    fn code_max(&self) -> C {
+        let max = Self::color_max();
+        let bits = self.p_sipo_bank.used_bits();
+        if bits >= 8 * core::mem::size_of::<C>() {
+            max
+        } else {
+            max.min((C::one() << bits) - C::one())
+        }
+    }
+
+    /// Write one pixel with an individual value per color channel.
+    pub fn write_pixel_channels(&mut self, i: u8, j: u8, colors: [C; DATA_LANES]) {
+        let Some((i, j)) = self.out_of_range.resolve(i, j, self.resolution.addr_range()) else { return };
+        self.set_address(i, j);
+        // Just set above.
+        let _ = self.write_channels_at_current(colors);
+    }
+
+    /// Shift out a whole off-screen frame: `fb[i][j]` goes to `(i, j)`, one
+    /// address and color per pixel in row-major address order.
+    ///
+    /// The immediate-mode counterpart of presenting a framebuffer: render
+    /// into RAM first (a `SimPixelWriter`, a plain array), then upload in
+    /// one call. Each pixel is latched as it goes, or once at the end with
+    /// `with_batched_latch`. Cells past `resolution()` are skipped. To
+    /// send only what changed since the last upload, wrap the writer in a
+    /// `DiffWriter` and use its `write_frame`. Returns the pixels written.
+    pub fn write_frame<const W: usize, const H: usize>(&mut self, fb: &[[C; W]; H]) -> usize {
+        let ((_, i_max), (_, j_max)) = self.resolution.addr_range::<u8>();
+        let (rows, cols) = (H.min(i_max as usize + 1), W.min(j_max as usize + 1));
+        self.begin_batch();
+        for (i, row) in fb.iter().enumerate().take(rows) {
+            for (j, &color) in row.iter().enumerate().take(cols) {
+                self.write_pixel_channels(i as u8, j as u8, [color; DATA_LANES]);
+            }
+        }
+        self.end_batch();
+        rows * cols
+    }
+
+    /// Make `(i, j)` the current address for `write_color_at_current`.
+    ///
+    /// Only packs the address; nothing is shifted until the next write. The
+    /// current address is also updated by every `write_pixel` /
+    /// `write_pixel_channels`, so the stateful contract is simply: writes
+    /// "at current" go to the address of the last `set_address` or pixel
+    /// write.
+    pub fn set_address(&mut self, i: u8, j: u8) {
+        self.cur_addr = Some(self.addr_shifter.frames(i, j));
+    }
+
+    /// Write `color` to every channel at the current address.
+    ///
+    /// For hotspot updates such as a blinking cursor, where the caller has
+    /// no address at hand. This saves only the address packing: the
+    /// address lanes share SRCLK with the data lanes, so every update still
+    /// shifts the whole frame, address included, and costs the same bus
+    /// time as `write_pixel`. Returns `Error::NotConfigured` if no address
+    /// was set yet.
+    pub fn write_color_at_current(&mut self, color: C) -> Result<()> {
+        self.write_channels_at_current([color; DATA_LANES])
+    }
+
+    /// Per-channel version of `write_color_at_current`.
+    pub fn write_channels_at_current(&mut self, colors: [C; DATA_LANES]) -> Result<()> {
+        let () = Self::LANE_LAYOUT;
+        let addr = self.cur_addr.ok_or(Error::NotConfigured)?;
+        if self.blanked.is_some() {
+            return Ok(());
+        }
+        let max = self.code_max();
+        let floor = <C as NumCast>::from(self.brightness_floor).unwrap_or(max);
+        let frame: [[u8; N]; LANES] = core::array::from_fn(|idx| {
+            if idx < DATA_LANES {
+                let code = self.polarity.apply(apply_brightness_floor(colors[idx].min(max), floor, max), max).to_u64().unwrap_or(0);
+                let mut bytes = [0u8; N];
+                for (k, byte) in bytes.iter_mut().rev().enumerate().take(8) {
+                    *byte = (code >> (8 * k)) as u8;
+                }
+                bytes
+            } else {
+                addr[idx - DATA_LANES]
+            }
+        });
+        let frame = match &self.lane_map {
+            Some(map) => map.apply(frame),
+            None => frame,
+        };
+        if self.in_batch {
+            // Cannot fail on `ParallelBank`; an RMT error drops the pixel.
+            let _ = self.p_sipo_bank.shift_exact(frame);
+        } else {
+            // The latch cannot fail: `new()` checked that the line exists.
+            let _ = self.p_sipo_bank.write_exact(frame);
+        }
+        delay_cycles(self.inter_pixel_delay_cycles);
+        Ok(())
     }
 }
 
-impl<'a> PixelWriter<u8, u8> for BwPixelWriter8h8v1ch8<'a> {
-    fn write_pixel(&mut self, i: u8, j: u8, color: u8) {
-        let frame = [
-            [color], // BW channel
-            [i],    // V address
-            [j],    // H address
-        ];
-        self.p_sipo_bank.write_exact(frame);
+impl<'a, const DATA_LANES: usize, const LANES: usize, const ADDR_LANES: usize, const N: usize, S, C>
+    PixelWriterSipo<'a, DATA_LANES, LANES, ADDR_LANES, N, S, C, ParallelBank<'a, LANES, N>>
+where
+    S: AddrShifter<ADDR_LANES, N>,
+    C: PrimInt,
+{
+    /// Clock only the low `bits` bits of every frame; see
+    /// `ParallelBank::with_used_bits`.
+    ///
+    /// For narrow panels, e.g. 128 lines on 7 address bits with
+    /// `DefAddrShifter`: 7 ticks per pixel instead of 8. The data lanes
+    /// share SRCLK and are cut too, so colors clamp to `bits` bits as well
+    /// (see `color_range`). With `InterleavedAddrShifter` the cut drops the
+    /// first bits of the interleaved word, i.e. the top address bits.
+    /// Returns `Error::OutOfRange` unless `bits` is in `1..=8 * N`.
+    pub fn with_used_bits(mut self, bits : usize) -> Result<Self> {
+        self.p_sipo_bank = self.p_sipo_bank.with_used_bits(bits)?;
+        Ok(self)
+    }
+}
+
+impl<'a, const DATA_LANES: usize, const LANES: usize, const ADDR_LANES: usize, const N: usize, S, C, B>
+    NativePixelWriter for PixelWriterSipo<'a, DATA_LANES, LANES, ADDR_LANES, N, S, C, B>
+where
+    S: AddrShifter<ADDR_LANES, N>,
+    C: PrimInt,
+    B: ShiftBank<LANES, N>,
+{
+    type Addr = u8;
+    type Color = C;
+}
+
+/// Writes the same value to every color channel (gray on multi-channel panels).
+impl<'a, const DATA_LANES: usize, const LANES: usize, const ADDR_LANES: usize, const N: usize, S, C, B>
+    PixelWriter<u8, C> for PixelWriterSipo<'a, DATA_LANES, LANES, ADDR_LANES, N, S, C, B>
+where
+    S: AddrShifter<ADDR_LANES, N>,
+    C: PrimInt,
+    B: ShiftBank<LANES, N>,
+{
+    fn write_pixel(&mut self, i: u8, j: u8, color: C) {
+        self.write_pixel_channels(i, j, [color; DATA_LANES]);
     }
 
     #[inline(always)]
     fn addr_range(&self) -> ((u8, u8), (u8, u8)) {
-        ((0, 150), (0, 200))
+        self.resolution.addr_range()
     }
 
     #[inline(always)]
-    fn color_range(&self) -> (u8, u8) {
-        (0, 255)
+    fn color_range(&self) -> (C, C) {
+        (C::zero(), self.code_max())
+    }
+
+    fn begin_batch(&mut self) {
+        self.in_batch = self.batch_latch;
+    }
+
+    fn fill_rect(&mut self, range: ((u8, u8), (u8, u8)), color: C) {
+        self.begin_batch();
+        for (i, j) in self.traversal.iter(range) {
+            self.write_pixel(i, j, color);
+        }
+        self.end_batch();
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            batching : self.batch_latch,
+            channels : DATA_LANES as u8,
+            ..Capabilities::write_only::<u8, C>()
+        }
     }
-}
\ No newline at end of file
+
+    fn end_batch(&mut self) {
+        if self.in_batch {
+            self.in_batch = false;
+            let _ = self.p_sipo_bank.latch();
+        }
+    }
+}
@@ -1,5 +1,11 @@
 use core::cell::UnsafeCell;
-use core::sync::atomic::{AtomicU8, Ordering};
+use crate::display::pix_writer::{Capabilities, NativePixelWriter, PixelWriter};
+use crate::utils::{adler32, no_feed, PrimInt};
+use num_traits::NumCast;
+use core::sync::atomic::{AtomicBool, AtomicU32, AtomicU8, Ordering};
+use embassy_time::{Duration, Instant, Timer};
+#[cfg(feature = "async-swap")]
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, signal::Signal};
 
 /// Generic double-buffered storage.
 ///
@@ -9,6 +15,17 @@ use core::sync::atomic::{AtomicU8, Ordering};
 pub struct DoubleBuffer<T> {
     bufs: [UnsafeCell<T>; 2],
     active_idx: AtomicU8, // 0 or 1
+    /// Raised by the consumer once a swapped-in buffer has been scanned out.
+    #[cfg(feature = "async-swap")]
+    consumed: Signal<CriticalSectionRawMutex, ()>,
+    /// Called after every successful swap; see `with_on_present`.
+    on_present: Option<fn()>,
+    /// Set while an `InactiveGuard` holds the inactive buffer.
+    inactive_locked: AtomicBool,
+    /// Number of swaps so far.
+    published: AtomicU32,
+    /// `published` as seen by the consumer when it last started a frame.
+    scan_started: AtomicU32,
 }
 
 // We promise that if T is Send/Sync, then DoubleBuffer<T> can be
@@ -18,20 +35,78 @@ unsafe impl<T: Send + Sync> Sync for DoubleBuffer<T> {}
 
 impl<T: Clone> DoubleBuffer<T> {
     /// Create a new double-buffer, initialising both buffers with `init`.
+    ///
+    /// Clones `init` once; see `new_with` to skip the copy.
     pub fn new(init: T) -> Self {
+        Self::new_with(init.clone(), init)
+    }
+
+    /// Copy `frame` into the inactive buffer and `swap()` it in.
+    ///
+    /// For producers that render into a buffer of their own (a decoder,
+    /// an external pipeline) instead of through `with_inactive`. Costs one
+    /// full-frame copy.
+    pub fn present_from(&self, frame: &T) {
+        self.with_inactive(|buf| buf.clone_from(frame));
+        self.swap();
+    }
+}
+
+impl<T: Default> DoubleBuffer<T> {
+    /// Both buffers `T::default()`, each built in place, without `Clone`.
+    ///
+    /// Note that `core` only implements `Default` for arrays of up to 32
+    /// elements, so a `FrameBuf` needs `new_with` instead.
+    pub fn new_default() -> Self {
+        Self::new_with(T::default(), T::default())
+    }
+}
+
+impl<T> DoubleBuffer<T> {
+    /// Create a new double-buffer from two separately built buffers:
+    /// `init_a` starts active (scanned out), `init_b` inactive (drawn).
+    ///
+    /// Needs no `Clone`, so a large frame is not copied at startup, and
+    /// being `const` it can initialise a `static` directly, e.g.
+    /// `DoubleBuffer::new_with([[0; FB_WIDTH]; FB_HEIGHT], [[0; FB_WIDTH]; FB_HEIGHT])`,
+    /// which lands in `.bss` instead of being built on the stack.
+    pub const fn new_with(init_a: T, init_b: T) -> Self {
         Self {
-            bufs: [UnsafeCell::new(init.clone()), UnsafeCell::new(init)],
+            bufs: [UnsafeCell::new(init_a), UnsafeCell::new(init_b)],
             active_idx: AtomicU8::new(0),
+            #[cfg(feature = "async-swap")]
+            consumed: Signal::new(),
+            on_present: None,
+            inactive_locked: AtomicBool::new(false),
+            published: AtomicU32::new(0),
+            scan_started: AtomicU32::new(0),
         }
     }
-}
 
-impl<T> DoubleBuffer<T> {
+    /// Call `f` right after every `swap()` / successful `try_swap`, e.g.
+    /// to toggle a sync GPIO, blink a frame LED or log.
+    ///
+    /// `f` runs in the producer's context (whichever task or core swapped),
+    /// not the scan loop's, so keep it short. Set it before sharing the
+    /// buffer; without a hook a swap costs one extra branch.
+    pub fn with_on_present(mut self, f: fn()) -> Self {
+        self.on_present = Some(f);
+        self
+    }
+
     #[inline]
     fn active_index(&self) -> usize {
         self.active_idx.load(Ordering::Acquire) as usize
     }
 
+    /// Index (0 or 1) of the buffer being scanned out.
+    ///
+    /// A producer reads it before drawing a frame and hands it to
+    /// `try_swap`, which only swaps if no one else did in between.
+    pub fn front_index(&self) -> usize {
+        self.active_index()
+    }
+
     #[inline]
     fn inactive_index(&self) -> usize {
         1 ^ self.active_index()
@@ -52,19 +127,1264 @@ impl<T> DoubleBuffer<T> {
     ///
     /// This is intended for the producer (drawing task). You are expected
     /// to call `swap()` once a full frame is ready.
+    ///
+    /// Holds the buffer's lock for the duration of `f`, spinning first while
+    /// an `InactiveGuard` has it; see `lock_inactive`.
     pub fn with_inactive<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        f(&mut self.lock_inactive_spin())
+    }
+
+    /// Address of the active buffer. Valid for as long as `self` is, but
+    /// which buffer it points at changes with every swap.
+    pub fn active_ptr(&self) -> *const T {
+        self.bufs[self.active_index()].get()
+    }
+
+    /// Address of the inactive buffer; see `active_ptr`.
+    pub fn inactive_ptr(&self) -> *const T {
+        self.bufs[self.inactive_index()].get()
+    }
+
+    /// `(address, size in bytes)` of the active buffer, for cache
+    /// maintenance calls such as the ROM's `Cache_Invalidate_Addr`.
+    ///
+    /// Only matters for buffers in cached external memory (PSRAM) shared
+    /// between cores. The producer must write its frame back from its
+    /// cache before `swap()` publishes it, i.e. on `inactive_cache_range()`,
+    /// which is the active range right after the swap. The scanning core
+    /// must invalidate the active range after it sees the swap and before
+    /// it reads, or it may keep scanning stale cache lines. Internal SRAM
+    /// needs neither.
+    pub fn active_cache_range(&self) -> (usize, usize) {
+        (self.active_ptr() as usize, core::mem::size_of::<T>())
+    }
+
+    /// `(address, size in bytes)` of the inactive buffer; see
+    /// `active_cache_range`.
+    pub fn inactive_cache_range(&self) -> (usize, usize) {
+        (self.inactive_ptr() as usize, core::mem::size_of::<T>())
+    }
+
+    /// Borrow the inactive buffer until the returned guard is dropped.
+    ///
+    /// `with_inactive` looks up the buffer on every call, which adds up
+    /// when a `Drawer` writes pixel by pixel; the guard looks it up once and
+    /// is itself a `PixelWriter` (for `u8` frames), so a whole frame can be
+    /// drawn under one lock. Finish with `InactiveGuard::swap` to hand the
+    /// frame over, or drop the guard to keep drawing later.
+    ///
+    /// While the guard lives `try_swap` refuses, and `with_inactive` and
+    /// `swap()` spin until it is dropped, since the scan loop would
+    /// otherwise start reading a buffer that is still being written, or two
+    /// writers would share it. Calling either from the task holding the
+    /// guard, or from another task on the same core while it is parked,
+    /// therefore never returns. Returns `None` if another guard already
+    /// holds the buffer.
+    pub fn lock_inactive(&self) -> Option<InactiveGuard<'_, T>> {
+        if self.inactive_locked.swap(true, Ordering::Acquire) {
+            return None;
+        }
         let idx = self.inactive_index();
-        // Safe if only the producer calls this and we never alias &mut T.
+        // Exclusive while the flag is set: no other guard, and no swap can
+        // turn this buffer into the one the consumer reads.
         let buf = unsafe { &mut *self.bufs[idx].get() };
+        Some(InactiveGuard { owner: self, buf })
+    }
+
+    /// `lock_inactive`, spinning until the current holder lets go.
+    fn lock_inactive_spin(&self) -> InactiveGuard<'_, T> {
+        loop {
+            if let Some(guard) = self.lock_inactive() {
+                return guard;
+            }
+            core::hint::spin_loop();
+        }
+    }
+
+    /// Run `f` with an immutable reference to the buffer selected by `sel`.
+    ///
+    /// Reading the inactive buffer must not overlap a `with_inactive` call.
+    pub fn with_buffer<R>(&self, sel: BufferSel, f: impl FnOnce(&T) -> R) -> R {
+        let idx = match sel {
+            BufferSel::Active => self.active_index(),
+            BufferSel::Inactive => self.inactive_index(),
+        };
+        // Shared access only; the caller keeps it from overlapping the producer.
+        let buf = unsafe { &*self.bufs[idx].get() };
         f(buf)
     }
 
     /// Swap active and inactive buffers.
     ///
     /// Typically called by the producer after finishing drawing a frame.
+    /// This is a plain load-then-store and assumes a single producer; with
+    /// several producers use `try_swap` instead. Spins while an
+    /// `InactiveGuard` holds the inactive buffer; see `lock_inactive`.
     pub fn swap(&self) {
-        let cur = self.active_index() as u8;
-        let next = cur ^ 1;
-        self.active_idx.store(next, Ordering::Release);
+        self.lock_inactive_spin().swap();
+    }
+
+    /// Count a swap and run the `with_on_present` hook, once the buffers
+    /// have been flipped and the lock released.
+    fn after_swap(&self) {
+        self.published.fetch_add(1, Ordering::Release);
+        if let Some(f) = self.on_present {
+            f();
+        }
+    }
+
+    /// Swap active and inactive buffers unless another producer swapped first.
+    ///
+    /// `expected_front` is the `front_index()` the caller read before it
+    /// started drawing. The swap is a `compare_exchange` from it, so of two
+    /// producers that drew against the same front buffer exactly one
+    /// succeeds; the loser gets `false` and should treat its frame as
+    /// superseded (re-read `front_index()` and redraw) instead of flipping
+    /// the buffers back.
+    ///
+    /// Also returns `false`, without waiting, while an `InactiveGuard`
+    /// holds the inactive buffer.
+    pub fn try_swap(&self, expected_front: usize) -> bool {
+        let Some(guard) = self.lock_inactive() else {
+            return false;
+        };
+        let cur = expected_front as u8 & 1;
+        let swapped = self
+            .active_idx
+            .compare_exchange(cur, cur ^ 1, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok();
+        #[cfg(feature = "async-swap")]
+        if swapped {
+            self.consumed.reset();
+        }
+        drop(guard);
+        if swapped {
+            self.after_swap();
+        }
+        swapped
+    }
+
+    /// Report that the active buffer has been scanned out in full.
+    ///
+    /// Called by the consumer (scan loop) at the end of every frame; wakes a
+    /// producer blocked in `wait_consumed()`. A no-op without the
+    /// `async-swap` feature.
+    #[inline]
+    pub fn mark_consumed(&self) {
+        #[cfg(feature = "async-swap")]
+        self.consumed.signal(());
+    }
+
+    /// Report that the consumer is starting a new frame, before it reads
+    /// any of it.
+    ///
+    /// Called by the scan loop on leaving vertical blanking. Whatever was
+    /// swapped in before this call is what the frame will show.
+    #[inline]
+    pub fn mark_scan_started(&self) {
+        self.scan_started.store(self.published.load(Ordering::Acquire), Ordering::Release);
+    }
+
+    /// Whether the consumer has started a frame since the last swap, i.e.
+    /// is reading the most recently published buffer.
+    pub fn is_frame_synced(&self) -> bool {
+        self.scan_started.load(Ordering::Acquire) == self.published.load(Ordering::Acquire)
+    }
+
+    /// Spin until the consumer has started reading the buffer published by
+    /// the last swap.
+    ///
+    /// The swap itself is just an index store, so without this a fast
+    /// producer can swap again before the scan loop ever picked the frame
+    /// up, dropping it, and with more than two buffers it would go on to
+    /// draw into one still being scanned. After `swap(); sync_frame()` the
+    /// scan loop is past the start of the new frame, which bounds the
+    /// producer to one frame ahead of the display.
+    ///
+    /// Liveness: this only returns once the scan loop runs and passes a
+    /// vertical blank (it calls `mark_scan_started()`); with the scan
+    /// stopped or paused it spins forever. Returns immediately if nothing
+    /// was swapped since the last frame start.
+    pub fn sync_frame(&self) {
+        self.sync_frame_with(no_feed);
+    }
+
+    /// `sync_frame`, calling `feed` on every spin, e.g. to pet a watchdog
+    /// while a long frame finishes.
+    pub fn sync_frame_with(&self, mut feed: impl FnMut()) {
+        while !self.is_frame_synced() {
+            feed();
+            core::hint::spin_loop();
+        }
+    }
+
+    /// Wait until the consumer has finished a frame since the last swap.
+    ///
+    /// After `swap(); wait_consumed().await` the new buffer is on screen and
+    /// the old one is no longer read, so the producer can start drawing
+    /// without racing the scan or polling a frame counter.
+    ///
+    /// Cross-core cost: `mark_consumed()` takes a critical section (interrupt
+    /// disable plus the inter-core spinlock on the S3) and, if a producer is
+    /// parked, pends a software interrupt on its core to run its executor;
+    /// expect a few microseconds of latency per frame, nothing per pixel.
+    #[cfg(feature = "async-swap")]
+    pub async fn wait_consumed(&self) {
+        self.consumed.wait().await
+    }
+}
+
+/// Exclusive borrow of a `DoubleBuffer`'s inactive buffer; see
+/// `DoubleBuffer::lock_inactive`.
+///
+/// Derefs to the buffer. For `[[u8; W]; H]` frames it is also a
+/// `PixelWriter` covering the whole buffer, writing raw values with no
+/// output mapping, so `Drawer::new(&mut guard)` draws straight into it.
+pub struct InactiveGuard<'a, T> {
+    owner: &'a DoubleBuffer<T>,
+    buf: &'a mut T,
+}
+
+impl<T> InactiveGuard<'_, T> {
+    /// Swap the buffer in and release it.
+    ///
+    /// The buffers flip while the lock is still held, so no other producer
+    /// can get at the buffer in between.
+    pub fn swap(self) {
+        let owner = self.owner;
+        #[cfg(feature = "async-swap")]
+        owner.consumed.reset();
+        owner.active_idx.store(owner.active_index() as u8 ^ 1, Ordering::Release);
+        drop(self);
+        owner.after_swap();
+    }
+}
+
+impl<T> core::ops::Deref for InactiveGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.buf
+    }
+}
+
+impl<T> core::ops::DerefMut for InactiveGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.buf
+    }
+}
+
+impl<T> Drop for InactiveGuard<'_, T> {
+    fn drop(&mut self) {
+        self.owner.inactive_locked.store(false, Ordering::Release);
+    }
+}
+
+impl<const W: usize, const H: usize> NativePixelWriter for InactiveGuard<'_, [[u8; W]; H]> {
+    type Addr = u8;
+    type Color = u8;
+}
+
+impl<const W: usize, const H: usize> PixelWriter<u8, u8> for InactiveGuard<'_, [[u8; W]; H]> {
+    #[inline(always)]
+    fn write_pixel(&mut self, i: u8, j: u8, color: u8) {
+        self.buf[i as usize][j as usize] = color;
+    }
+
+    fn addr_range(&self) -> ((u8, u8), (u8, u8)) {
+        ((0, (H - 1).min(255) as u8), (0, (W - 1).min(255) as u8))
+    }
+
+    fn color_range(&self) -> (u8, u8) {
+        (0, 255)
+    }
+
+    fn read_pixel(&self, i: u8, j: u8) -> Option<u8> {
+        Some(self.buf[i as usize][j as usize])
+    }
+
+    fn fill_rect(&mut self, range: ((u8, u8), (u8, u8)), color: u8) {
+        let ((i0, i1), (j0, j1)) = range;
+        for row in &mut self.buf[i0 as usize..=i1 as usize] {
+            row[j0 as usize..=j1 as usize].fill(color);
+        }
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities { read_pixel: true, fast_fill: true, ..Capabilities::write_only::<u8, u8>() }
+    }
+}
+
+/// Frame storage a backend scans out of and draws into.
+///
+/// `DoubleBuffer` is the stock implementation, `RingBuffer` (and its
+/// `TripleBuffer` alias) the one for three or more buffers that never make
+/// the producer wait, and `SingleBuffer` the one that trades tearing for
+/// RAM. Other schemes only have to say which frame is on screen, which one
+/// is drawn into and how the drawn one is handed over; the backends do the
+/// rest. The contract is
+/// `DoubleBuffer`'s: one producer calling `with_inactive` / `swap`, one
+/// scan loop calling `with_active` / `mark_consumed`.
+pub trait FrameStore {
+    /// One frame, e.g. `[[u8; W]; H]`.
+    type Frame;
+
+    /// Run `f` on the frame being scanned out.
+    fn with_active<R>(&self, f: impl FnOnce(&Self::Frame) -> R) -> R;
+
+    /// Run `f` on the frame being drawn into.
+    fn with_inactive<R>(&self, f: impl FnOnce(&mut Self::Frame) -> R) -> R;
+
+    /// Hand the drawn frame over to the scan loop.
+    fn swap(&self);
+
+    /// Run `f` on every buffer, the one on screen included, without
+    /// handing any of them over.
+    ///
+    /// For start-up, before a scan loop runs: while one does, it would see
+    /// the frame on screen change under it.
+    fn for_each_buffer(&self, f: impl FnMut(&mut Self::Frame));
+
+    /// Fill every buffer with `color`; see `for_each_buffer`.
+    fn fill_all<P: Copy, const W: usize, const H: usize>(&self, color: P)
+    where
+        Self: FrameStore<Frame = [[P; W]; H]>,
+    {
+        self.for_each_buffer(|frame| fill_frame(frame, color));
+    }
+
+    /// Stored size of a frame, including any row padding.
+    fn resolution(&self) -> Resolution;
+
+    /// Run `f` on the frame selected by `sel`, read-only.
+    fn with_buffer<R>(&self, sel: BufferSel, f: impl FnOnce(&Self::Frame) -> R) -> R {
+        match sel {
+            BufferSel::Active => self.with_active(f),
+            BufferSel::Inactive => self.with_inactive(|frame| f(frame)),
+        }
+    }
+
+    /// Copy `frame` into the drawn frame and `swap()` it in.
+    fn present_from(&self, frame: &Self::Frame)
+    where
+        Self::Frame: Clone,
+    {
+        self.with_inactive(|buf| buf.clone_from(frame));
+        self.swap();
+    }
+
+    /// Called by the scan loop each time the frame on screen has been
+    /// scanned out in full. Does nothing by default.
+    fn mark_consumed(&self) {}
+
+    /// Called by the scan loop as it starts a frame, before reading it.
+    /// Does nothing by default.
+    fn mark_scan_started(&self) {}
+}
+
+/// Set every pixel of `frame` to `color`.
+fn fill_frame<P: Copy, const W: usize, const H: usize>(frame: &mut [[P; W]; H], color: P) {
+    frame.iter_mut().for_each(|row| row.fill(color));
+}
+
+impl<P, const W: usize, const H: usize> FrameStore for DoubleBuffer<[[P; W]; H]> {
+    type Frame = [[P; W]; H];
+
+    fn with_active<R>(&self, f: impl FnOnce(&Self::Frame) -> R) -> R {
+        DoubleBuffer::with_active(self, f)
+    }
+
+    fn with_inactive<R>(&self, f: impl FnOnce(&mut Self::Frame) -> R) -> R {
+        DoubleBuffer::with_inactive(self, f)
+    }
+
+    fn swap(&self) {
+        DoubleBuffer::swap(self)
+    }
+
+    fn for_each_buffer(&self, mut f: impl FnMut(&mut Self::Frame)) {
+        f(&mut self.lock_inactive_spin());
+        // No scan loop runs yet (see the trait), so nothing reads it.
+        f(unsafe { &mut *self.bufs[self.active_index()].get() });
+    }
+
+    fn resolution(&self) -> Resolution {
+        Resolution::new(W as u16, H as u16)
+    }
+
+    fn with_buffer<R>(&self, sel: BufferSel, f: impl FnOnce(&Self::Frame) -> R) -> R {
+        DoubleBuffer::with_buffer(self, sel, f)
+    }
+
+    fn mark_consumed(&self) {
+        DoubleBuffer::mark_consumed(self)
+    }
+
+    fn mark_scan_started(&self) {
+        DoubleBuffer::mark_scan_started(self)
+    }
+}
+
+impl<const W: usize, const H: usize> DoubleBuffer<[[u8; W]; H]> {
+    /// Count the pixels that differ between the two buffers.
+    ///
+    /// A debugging aid for tearing and ghosting, not for the hot path: it
+    /// walks both frames in full. Called by the producer between frames, a
+    /// healthy animation shows a diff bounded by how much moves per frame;
+    /// a diff stuck at zero means the frames are identical (e.g. nothing is
+    /// drawn, or the swap is not happening), and one near `W * H` on a
+    /// mostly static scene means the producer draws into a stale buffer.
+    pub fn debug_diff(&self) -> u32 {
+        self.with_buffer(BufferSel::Active, |active| {
+            self.with_buffer(BufferSel::Inactive, |inactive| {
+                let rows = active.iter().zip(inactive.iter());
+                rows.map(|(a, b)| a.iter().zip(b.iter()).filter(|(x, y)| x != y).count() as u32)
+                    .sum()
+            })
+        })
+    }
+
+    /// Adler-32 (see `utils::adler32`) of the buffer being scanned out,
+    /// row-major, including any row padding.
+    ///
+    /// For tests and watchdogs that compare what is on screen against an
+    /// expected frame, e.g. a `SimPixelWriter::checksum` golden value, to
+    /// catch corruption from concurrent access. Walks the whole frame, so
+    /// keep it off the scan loop.
+    pub fn active_checksum(&self) -> u32 {
+        self.with_active(|frame| adler32(frame.iter().flatten().copied()))
+    }
+
+    /// Alpha-blend the buffer on screen with `other` into `out`: alpha 0
+    /// gives the active frame, 255 gives `other`; see `blend_frames`.
+    ///
+    /// `out` must not be the active buffer; the inactive one, reached
+    /// through `with_inactive`, is fine.
+    pub fn blend_into(&self, other: &[[u8; W]; H], out: &mut [[u8; W]; H], alpha: u8) {
+        self.with_active(|active| blend_frames(active, other, out, alpha));
+    }
+}
+
+/// Alpha-blend `from` and `to` into `out` per pixel, for crossfades:
+/// `out = (to * alpha + from * (255 - alpha)) / 255`, rounded.
+///
+/// Integer math only, about a multiply-add per pixel. Each result lies
+/// between its two inputs, so frames already within a backend's color
+/// range stay in it (e.g. 4-bit codes stay below 16). Keep both scenes in
+/// their own buffers and step `alpha` once per frame, rendering into the
+/// inactive buffer and swapping.
+pub fn blend_frames<const W: usize, const H: usize>(
+    from: &[[u8; W]; H],
+    to: &[[u8; W]; H],
+    out: &mut [[u8; W]; H],
+    alpha: u8,
+) {
+    let (a, rest) = (alpha as u32, 255 - alpha as u32);
+    for ((out, from), to) in out.iter_mut().zip(from).zip(to) {
+        for ((px, &f), &t) in out.iter_mut().zip(from).zip(to) {
+            *px = ((t as u32 * a + f as u32 * rest + 127) / 255) as u8;
+        }
+    }
+}
+
+/// Buffer handoff between one producer and one scan loop over `N` frame
+/// buffers, identified by index `0..N`.
+///
+/// At any time one buffer is *displayed* (read by the scan loop), at most
+/// one is *pending* (finished, waiting for the next frame start) and the
+/// rest are free. The producer takes a free buffer with `acquire`, draws
+/// into it and hands it over with `publish`; the scan loop calls
+/// `frame_boundary` at each frame start, which moves the pending buffer,
+/// if any, on screen. The displayed buffer therefore only changes between
+/// frames, never mid-scan.
+///
+/// With `N >= 3` a free buffer always exists, so the producer never waits:
+/// publishing over a frame that is still pending replaces it (counted by
+/// `dropped`). With `N == 2` this is plain double buffering, and `acquire`
+/// returns `None` until the scan loop has picked up the last publish.
+///
+/// Both indices live in one atomic word, so each side sees them change
+/// together. Ordering guarantees:
+///
+/// - Everything the producer wrote to a buffer before `publish` is visible
+///   to the scan loop once `frame_boundary` returns that buffer (release /
+///   acquire on the state word).
+/// - The scan loop's reads of a buffer it stops displaying happen before
+///   the producer can `acquire` that buffer again.
+/// - A buffer returned by `acquire` is neither displayed nor pending, and
+///   cannot become either until the producer publishes it.
+///
+/// Only one producer and one scan loop may use a coordinator; the producer
+/// owns an acquired index until it publishes it.
+pub struct ScanCoordinator<const N: usize> {
+    /// Displayed index in the low byte, pending index (or `NO_BUFFER`) in
+    /// the next one.
+    state: AtomicU32,
+    dropped: AtomicU32,
+}
+
+const NO_BUFFER: u32 = 0xFF;
+
+impl<const N: usize> ScanCoordinator<N> {
+    /// Buffer 0 displayed, nothing pending.
+    pub const fn new() -> Self {
+        const { assert!(N >= 2 && N < NO_BUFFER as usize, "ScanCoordinator needs 2..255 buffers") };
+        Self { state: AtomicU32::new(NO_BUFFER << 8), dropped: AtomicU32::new(0) }
+    }
+
+    #[inline]
+    fn unpack(state: u32) -> (usize, Option<usize>) {
+        let pending = (state >> 8) & 0xFF;
+        ((state & 0xFF) as usize, (pending != NO_BUFFER).then_some(pending as usize))
+    }
+
+    /// Buffer the scan loop is reading.
+    pub fn displayed(&self) -> usize {
+        Self::unpack(self.state.load(Ordering::Acquire)).0
+    }
+
+    /// Buffer waiting for the next frame start, if any.
+    pub fn pending(&self) -> Option<usize> {
+        Self::unpack(self.state.load(Ordering::Acquire)).1
+    }
+
+    /// Producer: the lowest-numbered buffer that is neither displayed nor
+    /// pending, or `None` if there is none.
+    pub fn acquire(&self) -> Option<usize> {
+        let (displayed, pending) = Self::unpack(self.state.load(Ordering::Acquire));
+        (0..N).find(|&k| k != displayed && Some(k) != pending)
+    }
+
+    /// Producer: hand finished buffer `idx` over for the next frame start.
+    ///
+    /// A frame still pending is replaced and counted as dropped.
+    pub fn publish(&self, idx: usize) {
+        debug_assert!(idx < N, "ScanCoordinator::publish: buffer {} out of range", idx);
+        let prev = self
+            .state
+            .fetch_update(Ordering::AcqRel, Ordering::Relaxed, |state| Some((state & 0xFF) | (idx as u32) << 8))
+            .unwrap();
+        if Self::unpack(prev).1.is_some() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Scan loop: called at every frame start, before reading the frame.
+    /// Puts the pending buffer on screen, if any, and returns the buffer
+    /// to scan.
+    pub fn frame_boundary(&self) -> usize {
+        let prev = self
+            .state
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |state| match Self::unpack(state) {
+                (_, Some(pending)) => Some((NO_BUFFER << 8) | pending as u32),
+                (_, None) => None,
+            });
+        match prev {
+            Ok(state) => Self::unpack(state).1.unwrap(),
+            Err(state) => Self::unpack(state).0,
+        }
+    }
+
+    /// Published frames that were replaced before reaching the screen.
+    pub fn dropped(&self) -> u32 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl<const N: usize> Default for ScanCoordinator<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `FrameStore` over `N >= 3` buffers (triple buffering and up), with the
+/// handoff done by a `ScanCoordinator`.
+///
+/// `swap()` never waits for the scan loop: the finished frame goes on
+/// screen at the next `mark_scan_started`, replacing any frame still
+/// pending, and drawing continues in another free buffer. That buffer
+/// holds whatever it last showed, so redraw it in full. Needs a scan loop
+/// that calls `mark_scan_started`, as the `bus_dac` ones do.
+pub struct RingBuffer<T, const N: usize> {
+    bufs: [UnsafeCell<T>; N],
+    coord: ScanCoordinator<N>,
+    /// Buffer the producer draws into; never displayed or pending.
+    drawing: AtomicU8,
+}
+
+// Same contract as `DoubleBuffer`: one producer, one scan loop.
+unsafe impl<T: Send, const N: usize> Send for RingBuffer<T, N> {}
+unsafe impl<T: Send + Sync, const N: usize> Sync for RingBuffer<T, N> {}
+
+impl<T: Clone, const N: usize> RingBuffer<T, N> {
+    /// Every buffer starts as `init`; buffer 0 is displayed, 1 drawn.
+    pub fn new(init: T) -> Self {
+        const { assert!(N >= 3, "RingBuffer needs at least 3 buffers; use DoubleBuffer for 2") };
+        Self {
+            bufs: core::array::from_fn(|_| UnsafeCell::new(init.clone())),
+            coord: ScanCoordinator::new(),
+            drawing: AtomicU8::new(1),
+        }
+    }
+}
+
+impl<T, const N: usize> RingBuffer<T, N> {
+    /// The handoff state, e.g. for `dropped()`.
+    pub fn coordinator(&self) -> &ScanCoordinator<N> {
+        &self.coord
+    }
+}
+
+impl<P, const W: usize, const H: usize, const N: usize> FrameStore for RingBuffer<[[P; W]; H], N> {
+    type Frame = [[P; W]; H];
+
+    fn with_active<R>(&self, f: impl FnOnce(&Self::Frame) -> R) -> R {
+        // The displayed buffer is never handed to the producer.
+        f(unsafe { &*self.bufs[self.coord.displayed()].get() })
+    }
+
+    fn with_inactive<R>(&self, f: impl FnOnce(&mut Self::Frame) -> R) -> R {
+        let idx = self.drawing.load(Ordering::Relaxed) as usize;
+        // Only the producer calls this, and `acquire` kept the buffer free.
+        f(unsafe { &mut *self.bufs[idx].get() })
+    }
+
+    fn swap(&self) {
+        self.coord.publish(self.drawing.load(Ordering::Relaxed) as usize);
+        // At most one buffer is displayed and one pending, so N >= 3 leaves one.
+        let next = self.coord.acquire().unwrap();
+        self.drawing.store(next as u8, Ordering::Relaxed);
+    }
+
+    fn for_each_buffer(&self, mut f: impl FnMut(&mut Self::Frame)) {
+        for buf in &self.bufs {
+            // No scan loop runs yet (see the trait), so nothing reads them.
+            f(unsafe { &mut *buf.get() });
+        }
+    }
+
+    fn resolution(&self) -> Resolution {
+        Resolution::new(W as u16, H as u16)
+    }
+
+    fn mark_scan_started(&self) {
+        self.coord.frame_boundary();
+    }
+}
+
+/// `RingBuffer` of three: classic triple buffering.
+pub type TripleBuffer<T> = RingBuffer<T, 3>;
+
+/// `FrameStore` over a single buffer, drawn into while on screen.
+///
+/// Half the RAM of a `DoubleBuffer`, paid for in tearing: the scan loop
+/// shows every write as it lands, half-drawn frames included, and
+/// `swap()` has nothing to hand over. Suits static content, or drawing
+/// kept to vertical blanking (see `ScanSync`).
+pub struct SingleBuffer<T> {
+    buf: UnsafeCell<T>,
+}
+
+// One producer, one scan loop, as for `DoubleBuffer`; the scan loop reads
+// the frame being drawn, which is the tearing this store accepts.
+unsafe impl<T: Send> Send for SingleBuffer<T> {}
+unsafe impl<T: Send + Sync> Sync for SingleBuffer<T> {}
+
+impl<T> SingleBuffer<T> {
+    pub const fn new(init: T) -> Self {
+        Self { buf: UnsafeCell::new(init) }
+    }
+}
+
+impl<P, const W: usize, const H: usize> FrameStore for SingleBuffer<[[P; W]; H]> {
+    type Frame = [[P; W]; H];
+
+    fn with_active<R>(&self, f: impl FnOnce(&Self::Frame) -> R) -> R {
+        f(unsafe { &*self.buf.get() })
+    }
+
+    fn with_inactive<R>(&self, f: impl FnOnce(&mut Self::Frame) -> R) -> R {
+        f(unsafe { &mut *self.buf.get() })
+    }
+
+    /// Nothing to hand over: drawing is already on screen.
+    fn swap(&self) {}
+
+    fn for_each_buffer(&self, mut f: impl FnMut(&mut Self::Frame)) {
+        f(unsafe { &mut *self.buf.get() });
+    }
+
+    fn resolution(&self) -> Resolution {
+        Resolution::new(W as u16, H as u16)
+    }
+}
+
+/// Scan-out position shared between the scan loop and drawing tasks.
+///
+/// The scan loop (usually on core 1) calls `enter_vblank()` / `leave_vblank()`
+/// as the beam leaves and re-enters the framebuffer area; producers on the
+/// other core read `in_vblank()` and `frame()` to time their `swap()`.
+pub struct ScanSync {
+    /// Number of vertical blanking intervals entered so far (wraps).
+    frame: AtomicU32,
+    in_vblank: AtomicBool,
+    /// Worst per-pixel cost seen by `scan_loop_generated` (debug builds).
+    worst_pixel_cycles: AtomicU32,
+}
+
+impl ScanSync {
+    pub const fn new() -> Self {
+        Self {
+            frame: AtomicU32::new(0),
+            in_vblank: AtomicBool::new(false),
+            worst_pixel_cycles: AtomicU32::new(0),
+        }
+    }
+
+    /// Mark the start of vertical blanking; counts a frame on the first call.
+    #[inline]
+    pub fn enter_vblank(&self) {
+        if !self.in_vblank.swap(true, Ordering::AcqRel) {
+            self.frame.fetch_add(1, Ordering::Release);
+        }
+    }
+
+    /// Mark that the scan is back inside the visible framebuffer rows.
+    #[inline]
+    pub fn leave_vblank(&self) {
+        self.in_vblank.store(false, Ordering::Release);
+    }
+
+    /// Whether the scan is currently in the vertical blanking interval.
+    #[inline]
+    pub fn in_vblank(&self) -> bool {
+        self.in_vblank.load(Ordering::Acquire)
+    }
+
+    /// Frames scanned so far, counted at the start of each blanking interval.
+    #[inline]
+    pub fn frame(&self) -> u32 {
+        self.frame.load(Ordering::Acquire)
+    }
+
+    /// Raise the recorded worst per-pixel cost to `cycles` if it is higher.
+    #[inline]
+    pub fn record_pixel_cycles(&self, cycles: u32) {
+        self.worst_pixel_cycles.fetch_max(cycles, Ordering::Relaxed);
+    }
+
+    /// Worst CPU cycles a generated-scan pixel has taken, excluding the wait
+    /// for its slot. Compare against `cpu_hz / pixel_clock_hz`; stays 0 in
+    /// release builds, where the measurement is compiled out.
+    #[inline]
+    pub fn worst_pixel_cycles(&self) -> u32 {
+        self.worst_pixel_cycles.load(Ordering::Relaxed)
+    }
+}
+
+impl Default for ScanSync {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Pause switch for a running scan loop, shared with other tasks.
+///
+/// `pause()` asks the scan loop to park: it drives its blank color, stops
+/// reading the framebuffer and polls every 100 us (feeding the watchdog)
+/// until `resume()`. Scan loops only check the flag at row or frame
+/// boundaries, so poll `is_parked()` before reconfiguring pins or clocks.
+pub struct ScanControl {
+    paused: AtomicBool,
+    parked: AtomicBool,
+}
+
+impl ScanControl {
+    /// Starts out running.
+    pub const fn new() -> Self {
+        Self {
+            paused: AtomicBool::new(false),
+            parked: AtomicBool::new(false),
+        }
+    }
+
+    /// Ask the scan loop to park at its next check.
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::Release);
+    }
+
+    /// Let a parked scan loop continue; it resumes within ~100 us.
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::Release);
+    }
+
+    /// Whether a pause was requested; checked by the scan loops.
+    #[inline(always)]
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// Whether the scan loop has actually parked and holds the bus at blank.
+    pub fn is_parked(&self) -> bool {
+        self.parked.load(Ordering::Acquire)
+    }
+
+    /// Called by the scan loop as it parks and unparks.
+    pub fn set_parked(&self, parked: bool) {
+        self.parked.store(parked, Ordering::Release);
+    }
+}
+
+impl Default for ScanControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What a `VsyncTracker` sample found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VsyncEvent {
+    /// No new VSYNC edge.
+    None,
+    /// An edge arrived where the scan expected a frame to start.
+    InSync,
+    /// An edge arrived mid-frame: the scan has drifted and must resync.
+    Resync,
+}
+
+/// Edge detector for an external VSYNC input, checked against where the
+/// scan loop believes the frame starts.
+///
+/// The scan loop samples the pin once per line and says whether that line
+/// counts as a frame start. Only the leading (asserting) edge matters, so
+/// a pulse spanning several lines is seen once; a missed pulse is simply
+/// not seen, and the loop keeps its own timing until the next one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VsyncTracker {
+    active_low: bool,
+    asserted: bool,
+    frames: u32,
+    resyncs: u32,
+}
+
+impl VsyncTracker {
+    /// `active_low` for the usual negative VSYNC pulse.
+    pub const fn new(active_low: bool) -> Self {
+        Self { active_low, asserted: false, frames: 0, resyncs: 0 }
+    }
+
+    /// Feed one sample of the pin level, taken on a line that is (or is
+    /// not) where the scan expects the frame to start.
+    pub fn sample(&mut self, high: bool, at_frame_start: bool) -> VsyncEvent {
+        let asserted = high != self.active_low;
+        let edge = asserted && !self.asserted;
+        self.asserted = asserted;
+        if !edge {
+            return VsyncEvent::None;
+        }
+        self.frames = self.frames.wrapping_add(1);
+        if at_frame_start {
+            VsyncEvent::InSync
+        } else {
+            self.resyncs = self.resyncs.wrapping_add(1);
+            VsyncEvent::Resync
+        }
+    }
+
+    /// VSYNC edges seen so far (wraps).
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    /// Edges that arrived mid-frame and forced a resync (wraps).
+    pub fn resyncs(&self) -> u32 {
+        self.resyncs
+    }
+}
+
+/// Swap timing of a double-buffered present path, for diagnosing stutter.
+///
+/// The producer calls `record_swap` after each swap with the consumer's
+/// frame counter (`ScanSync::frame()`) and, optionally, a microsecond
+/// timestamp. A swap is *dropped* when the counter has not moved since the
+/// previous one: the scan never reached vertical blanking in between, so
+/// the previously swapped frame was shown partly (torn) or not at all.
+/// Dropped frames mean the producer outruns the scan; pace it with
+/// `wait_consumed()` or `run_vsynced_animation`. Without a counter nothing
+/// is counted as dropped, and intervals stay 0 without timestamps.
+///
+/// Plain integers updated in place: a few compares and adds per swap.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PresentStats {
+    swaps: u32,
+    dropped: u32,
+    last_frame: Option<u32>,
+    last_us: Option<u64>,
+    intervals: u32,
+    total_us: u64,
+    min_us: u32,
+    max_us: u32,
+}
+
+impl PresentStats {
+    pub const fn new() -> Self {
+        Self {
+            swaps: 0,
+            dropped: 0,
+            last_frame: None,
+            last_us: None,
+            intervals: 0,
+            total_us: 0,
+            min_us: u32::MAX,
+            max_us: 0,
+        }
+    }
+
+    /// Account for one swap at consumer frame `frame`, taken at `now_us`.
+    pub fn record_swap(&mut self, frame: Option<u32>, now_us: Option<u64>) {
+        self.swaps = self.swaps.wrapping_add(1);
+        if let (Some(frame), Some(last)) = (frame, self.last_frame)
+            && frame == last
+        {
+            self.dropped = self.dropped.wrapping_add(1);
+        }
+        if let (Some(now), Some(last)) = (now_us, self.last_us) {
+            let dt = now.saturating_sub(last).min(u32::MAX as u64) as u32;
+            self.intervals = self.intervals.saturating_add(1);
+            self.total_us = self.total_us.saturating_add(dt as u64);
+            self.min_us = self.min_us.min(dt);
+            self.max_us = self.max_us.max(dt);
+        }
+        self.last_frame = frame;
+        self.last_us = now_us;
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Swaps recorded so far.
+    pub fn swaps(&self) -> u32 {
+        self.swaps
+    }
+
+    /// Swaps that replaced a frame the scan never finished; see above.
+    pub fn dropped(&self) -> u32 {
+        self.dropped
+    }
+
+    /// Shortest time between two timestamped swaps, 0 before there are two.
+    pub fn min_interval_us(&self) -> u32 {
+        if self.intervals == 0 { 0 } else { self.min_us }
+    }
+
+    pub fn max_interval_us(&self) -> u32 {
+        self.max_us
+    }
+
+    /// Mean time between timestamped swaps, 0 before there are two.
+    pub fn avg_interval_us(&self) -> u32 {
+        if self.intervals == 0 { 0 } else { (self.total_us / self.intervals as u64) as u32 }
+    }
+}
+
+impl Default for PresentStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Alarm for a scan loop whose refresh rate falls below a minimum, e.g.
+/// because a drawing task sharing its core starves it.
+///
+/// Call `check` about once per second from any task (or spawn `run`): it
+/// compares how far `ScanSync::frame()` advanced with the time elapsed
+/// since the previous check, and a rate under `min_fps` is reported with
+/// `defmt::error!` and counted in `violations()`. Each check is one atomic
+/// load and a division, so the scan loop itself pays nothing.
+///
+/// Intentional pauses: with `with_control`, a check that finds the scan
+/// paused or parked is skipped and the next one only restarts the
+/// measurement, so `ScanControl::pause()` never raises an alarm. A pause
+/// that starts and ends between two checks is invisible and lowers that
+/// window's rate; call `reset()` after resuming to discard the window.
+pub struct RefreshWatchdog<'a> {
+    sync: &'a ScanSync,
+    control: Option<&'a ScanControl>,
+    min_fps: u32,
+    /// Frame counter and timestamp of the previous check.
+    last: Option<(u32, u64)>,
+    violations: u32,
+}
+
+impl<'a> RefreshWatchdog<'a> {
+    pub fn new(sync: &'a ScanSync, min_fps: u32) -> Self {
+        Self { sync, control: None, min_fps, last: None, violations: 0 }
+    }
+
+    /// Skip measurements while `control` has the scan loop paused.
+    pub fn with_control(mut self, control: &'a ScanControl) -> Self {
+        self.control = Some(control);
+        self
+    }
+
+    /// Measure the rate since the previous call, at `now_us` microseconds.
+    ///
+    /// Returns the measured frames per second, or `None` for the first
+    /// call, a call right after a pause or `reset()`, or no elapsed time.
+    pub fn check(&mut self, now_us: u64) -> Option<u32> {
+        let frame = self.sync.frame();
+        let last = self.last.replace((frame, now_us));
+        if self.control.is_some_and(|c| c.is_paused() || c.is_parked()) {
+            self.last = None;
+            return None;
+        }
+        let (last_frame, last_us) = last?;
+        let dt = now_us.saturating_sub(last_us);
+        if dt == 0 {
+            return None;
+        }
+        let fps = (frame.wrapping_sub(last_frame) as u64 * 1_000_000 / dt) as u32;
+        if fps < self.min_fps {
+            self.violations = self.violations.wrapping_add(1);
+            defmt::error!("refresh watchdog: {} fps over {} ms, minimum is {}", fps, dt / 1000, self.min_fps);
+        }
+        Some(fps)
+    }
+
+    /// Check once per second, forever.
+    pub async fn run(&mut self) -> ! {
+        loop {
+            Timer::after(Duration::from_secs(1)).await;
+            self.check(Instant::now().as_micros());
+        }
+    }
+
+    /// Forget the previous check, so the next one starts a new window.
+    pub fn reset(&mut self) {
+        self.last = None;
+    }
+
+    /// Checks that found the rate below the minimum.
+    pub fn violations(&self) -> u32 {
+        self.violations
+    }
+}
+
+/// Which half of a `DoubleBuffer` a read refers to.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BufferSel {
+    /// The buffer currently being scanned out.
+    Active,
+    /// The buffer currently being drawn into.
+    #[default]
+    Inactive,
+}
+
+/// Rectangle of the screen a scan loop drives: rows `i0..=i1` and columns
+/// `j0..=j1`, both bounds inclusive.
+///
+/// Addresses outside it are output as the backend's blank color, leaving
+/// that part of the screen to another video source.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScanWindow {
+    pub i0: u8,
+    pub j0: u8,
+    pub i1: u8,
+    pub j1: u8,
+}
+
+impl ScanWindow {
+    /// Window spanned by two opposite corners, given in any order.
+    pub fn new(i0: u8, j0: u8, i1: u8, j1: u8) -> Self {
+        Self {
+            i0: i0.min(i1),
+            j0: j0.min(j1),
+            i1: i0.max(i1),
+            j1: j0.max(j1),
+        }
+    }
+
+    #[inline(always)]
+    pub fn contains_row(&self, i: usize) -> bool {
+        (self.i0 as usize..=self.i1 as usize).contains(&i)
+    }
+
+    #[inline(always)]
+    pub fn contains_col(&self, j: usize) -> bool {
+        (self.j0 as usize..=self.j1 as usize).contains(&j)
+    }
+
+    /// `color` if `(i, j)` is inside the window, `blank` otherwise.
+    #[inline(always)]
+    pub fn pick(&self, i: usize, j: usize, color: u8, blank: u8) -> u8 {
+        if self.contains_row(i) && self.contains_col(j) { color } else { blank }
+    }
+}
+
+/// Addressable size of a panel, in pixels.
+///
+/// Backends report it through `resolution()` and derive `addr_range()` from
+/// it, so code sizing buffers or layouts need not hard-code `FB_WIDTH` and
+/// `FB_HEIGHT`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Resolution {
+    pub width: u16,
+    pub height: u16,
+}
+
+/// The reference 8h8v panel: 201 columns by 151 rows.
+pub const PANEL_RESOLUTION: Resolution = Resolution::new(201, 151);
+
+impl Resolution {
+    pub const fn new(width: u16, height: u16) -> Self {
+        Self { width, height }
+    }
+
+    pub const fn pixels(self) -> usize {
+        self.width as usize * self.height as usize
+    }
+
+    /// `((0, height - 1), (0, width - 1))`, as `PixelWriter::addr_range()`
+    /// reports it. Dimensions too large for `AddrT` saturate at its maximum.
+    pub fn addr_range<AddrT: PrimInt>(self) -> ((AddrT, AddrT), (AddrT, AddrT)) {
+        let last = |len: u16| <AddrT as NumCast>::from(len.saturating_sub(1)).unwrap_or(AddrT::max_value());
+        ((AddrT::zero(), last(self.height)), (AddrT::zero(), last(self.width)))
+    }
+}
+
+/// Which address bus the scan loops follow line by line.
+///
+/// V always selects the framebuffer row and H the column, so `frame[v][h]`
+/// is shown either way. The loops treat one bus as the line address,
+/// changing slowest (once per scanned line), and the other as the sample
+/// address, changing every pixel; this says which is which. Counters
+/// chained with V stepping fastest sweep the panel column by column and
+/// need `ColumnMajor`, instead of a transposed drawing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScanOrientation {
+    /// V is the line address: the panel is swept row by row.
+    #[default]
+    RowMajor,
+    /// H is the line address: the panel is swept column by column.
+    ColumnMajor,
+}
+
+impl ScanOrientation {
+    /// Framebuffer `(row, col)` for a `(line, sample)` address pair.
+    ///
+    /// The mapping is its own inverse, so it also turns `(rows, columns)`
+    /// sizes or offsets into `(line, sample)` ones.
+    #[inline(always)]
+    pub fn to_frame<T>(self, line: T, sample: T) -> (T, T) {
+        match self {
+            ScanOrientation::RowMajor => (line, sample),
+            ScanOrientation::ColumnMajor => (sample, line),
+        }
+    }
+}
+
+/// Memory order of an externally produced frame handed to a backend as a
+/// flat slice.
+///
+/// Independent of `ScanOrientation`, which describes the address counters:
+/// this only says how the producer laid out its buffer, and the copy
+/// reorders it into the backend's row-major storage.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SourceLayout {
+    /// `src[i * width + j]`: rows one after another, like the framebuffer.
+    #[default]
+    RowMajor,
+    /// `src[j * height + i]`: columns one after another, i.e. the
+    /// transpose of the framebuffer.
+    ColumnMajor,
+}
+
+impl SourceLayout {
+    /// Index of pixel `(i, j)` in a `height` x `width` source.
+    #[inline(always)]
+    pub fn index(self, i: usize, j: usize, height: usize, width: usize) -> usize {
+        match self {
+            SourceLayout::RowMajor => i * width + j,
+            SourceLayout::ColumnMajor => j * height + i,
+        }
+    }
+}
+
+/// Compress logical `0..=max` into `floor..=max`, rounding to nearest.
+///
+/// Calibrates true black on panels whose pixels still glow at low DAC codes:
+/// logical 0 lands on `floor` and `max` stays `max`. Applied before
+/// `BrightnessPolarity`, so `floor` is a brightness level, not a raw code.
+/// A zero floor (or `floor >= max`) leaves `color` unchanged / pinned at `max`.
+#[inline]
+pub fn apply_brightness_floor<C: PrimInt>(color: C, floor: C, max: C) -> C {
+    if floor.is_zero() {
+        return color;
+    }
+    if floor >= max {
+        return max;
+    }
+    let (c, f, m) = (
+        color.min(max).to_u64().unwrap_or(0),
+        floor.to_u64().unwrap_or(0),
+        max.to_u64().unwrap_or(0),
+    );
+    let code = f + (c * (m - f) + m / 2) / m;
+    <C as NumCast>::from(code).unwrap_or(max)
+}
+
+/// How DAC codes map to perceived brightness on the panel.
+///
+/// Backends keep the logical API "higher = brighter" and apply the
+/// polarity only when driving the hardware.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BrightnessPolarity {
+    /// Higher code = brighter (common-cathode style wiring).
+    #[default]
+    Normal,
+    /// Higher code = dimmer (common-anode style wiring).
+    Inverted,
+}
+
+impl BrightnessPolarity {
+    /// Map a logical color to the hardware code, given the backend's max color.
+    #[inline(always)]
+    pub fn apply<C: PrimInt>(self, color: C, max: C) -> C {
+        match self {
+            BrightnessPolarity::Normal => color,
+            BrightnessPolarity::Inverted => max.saturating_sub(color),
+        }
+    }
+}
+
+/// What a backend does with a `write_pixel` outside its `addr_range()`.
+///
+/// Set with the backend's `with_out_of_range`; drawing through a `Drawer`
+/// clips first, so this only matters for direct writes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutOfRangePolicy {
+    /// Panic, to catch drawing bugs during development.
+    Panic,
+    /// Move the write to the nearest pixel on screen.
+    Clamp,
+    /// Drop the write.
+    #[default]
+    Skip,
+}
+
+impl OutOfRangePolicy {
+    /// Where a write to `(i, j)` lands within `range`, or `None` to drop it.
+    #[inline(always)]
+    pub fn resolve<A: PrimInt>(self, i: A, j: A, range: ((A, A), (A, A))) -> Option<(A, A)> {
+        let ((i0, i1), (j0, j1)) = range;
+        if i >= i0 && i <= i1 && j >= j0 && j <= j1 {
+            return Some((i, j));
+        }
+        match self {
+            OutOfRangePolicy::Panic => panic!("pixel write outside addr_range()"),
+            OutOfRangePolicy::Clamp => Some((i.clamp(i0, i1), j.clamp(j0, j1))),
+            OutOfRangePolicy::Skip => None,
+        }
     }
 }
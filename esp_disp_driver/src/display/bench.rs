@@ -0,0 +1,81 @@
+//! On-target latency measurements for `PixelWriter` backends (`bench`
+//! feature).
+//!
+//! Times come from the CPU cycle counter and cover everything a backend
+//! does per call: address packing, shifting and latching for sipo, the
+//! framebuffer store for bus_dac. Run with the rest of the system quiet
+//! (radio off, scan loop on the other core) for repeatable numbers, and
+//! divide by the CPU clock in MHz for microseconds.
+//!
+//! ```ignore
+//! bench::measure_and_log("sipo", &mut sipo_writer, 0xFF);
+//! bench::measure_and_log("bus_dac", &mut dac_writer, 0x0F);
+//! ```
+//!
+//! The bus_dac scan loops are timed separately, by
+//! `BwPixelWriter8h8v1ch4::measure_scan_latency`.
+
+use crate::display::drawer::Drawer;
+use crate::display::pix_writer::PixelWriter;
+use crate::utils::PrimInt;
+use esp_hal::xtensa_lx::timer::get_cycle_count;
+
+/// Single `write_pixel` calls timed per measurement; the fastest is kept,
+/// so an interrupt landing in one sample does not skew the result.
+pub const WRITE_SAMPLES: usize = 16;
+
+/// Cycle counts from one `measure` run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct BenchReport {
+    /// Fastest of `WRITE_SAMPLES` single `write_pixel` calls.
+    pub write_pixel: u32,
+    /// One `Drawer::fill_screen`.
+    pub fill_screen: u32,
+    /// Pixels covered by `fill_screen`.
+    pub pixels: u32,
+}
+
+impl BenchReport {
+    /// Average `fill_screen` cost per pixel, rounded down.
+    pub fn fill_cycles_per_pixel(&self) -> u32 {
+        self.fill_screen / self.pixels.max(1)
+    }
+}
+
+/// Time `write_pixel` at the top-left corner and a full-screen fill, both
+/// in `color`. Overwrites the whole screen.
+pub fn measure<AddrT: PrimInt, ColorT: PrimInt, PW: PixelWriter<AddrT, ColorT>>(
+    pw: &mut PW,
+    color: ColorT,
+) -> BenchReport {
+    let ((i_min, i_max), (j_min, j_max)) = pw.addr_range();
+    let mut write_pixel = u32::MAX;
+    for _ in 0..WRITE_SAMPLES {
+        let start = get_cycle_count();
+        pw.write_pixel(i_min, j_min, color);
+        write_pixel = write_pixel.min(get_cycle_count().wrapping_sub(start));
+    }
+    let start = get_cycle_count();
+    Drawer::new(pw).fill_screen(color);
+    let fill_screen = get_cycle_count().wrapping_sub(start);
+    let span = |lo: AddrT, hi: AddrT| (hi.to_u64().unwrap() - lo.to_u64().unwrap() + 1) as u32;
+    BenchReport { write_pixel, fill_screen, pixels: span(i_min, i_max) * span(j_min, j_max) }
+}
+
+/// `measure`, then report the result over defmt under `name`.
+pub fn measure_and_log<AddrT: PrimInt, ColorT: PrimInt, PW: PixelWriter<AddrT, ColorT>>(
+    name: &str,
+    pw: &mut PW,
+    color: ColorT,
+) -> BenchReport {
+    let report = measure(pw, color);
+    defmt::info!(
+        "bench {}: write_pixel {} cycles, fill_screen {} cycles ({} px, {}/px)",
+        name,
+        report.write_pixel,
+        report.fill_screen,
+        report.pixels,
+        report.fill_cycles_per_pixel()
+    );
+    report
+}
@@ -0,0 +1,168 @@
+//! Software frame in front of an immediate-mode backend.
+//!
+//! The sipo backend shows every pixel the moment it is written, so a
+//! `fill_screen` or a large redraw is visible while it happens: the old
+//! picture and half of the new one share the screen, and shapes drawn over
+//! each other flicker through their intermediate states. `BufferedWriter`
+//! gives such backends the double-buffered model of bus_dac: drawing only
+//! touches RAM, and `present()` sends the finished frame, skipping every
+//! pixel the panel already shows.
+
+use crate::display::diff::DiffWriter;
+use crate::display::frame_pacer::FramePacer;
+use crate::display::pix_writer::{Capabilities, NativePixelWriter, PixelWriter};
+use crate::utils::PrimInt;
+use num_traits::NumCast;
+
+/// `PixelWriter` adapter that draws into a `W` x `H` frame in RAM and
+/// flushes the changes to `inner` on `present()`.
+///
+/// The frame covers the inner `addr_range()` from its minimum; addresses
+/// outside it are written straight through. `present()` walks the frame
+/// top to bottom inside one batch and forwards it through a `DiffWriter`,
+/// so only pixels that differ from what was last flushed reach the panel.
+/// The first present sends the whole frame.
+///
+/// RAM: the frame (`W * H` colors) plus the `DiffWriter` shadow (`W * H`
+/// `Option<ColorT>`s), 3 bytes per pixel for `u8`, about 91 KB for the
+/// full 201x151 panel. Cover only the region that gets redrawn if that is
+/// too much.
+///
+/// Latency: nothing is visible until `present()`, which then costs one
+/// compare per pixel plus one backend write per changed pixel (a full
+/// shift-and-latch on sipo). A full-screen change takes as long as drawing
+/// it directly did, but shows up as a single top-to-bottom sweep of the
+/// final picture instead of every intermediate step. Use `present_paced`
+/// to flush at a fixed rate.
+pub struct BufferedWriter<PW, ColorT, const W: usize, const H: usize> {
+    out: DiffWriter<PW, ColorT, W, H>,
+    frame: [[ColorT; W]; H],
+}
+
+impl<PW, ColorT: PrimInt, const W: usize, const H: usize> BufferedWriter<PW, ColorT, W, H> {
+    /// Wrap `inner` with every buffered pixel at `background`.
+    pub fn new(inner: PW, background: ColorT) -> Self {
+        Self { out: DiffWriter::new(inner), frame: [[background; W]; H] }
+    }
+
+    /// Send the whole frame on the next `present()`, e.g. after the panel
+    /// lost its contents.
+    pub fn invalidate(&mut self) {
+        self.out.invalidate();
+    }
+
+    pub fn inner(&self) -> &PW {
+        self.out.inner()
+    }
+
+    pub fn into_inner(self) -> PW {
+        self.out.into_inner()
+    }
+
+    /// `present()`, then wait for `pacer`'s next frame slot.
+    pub async fn present_paced<AddrT: PrimInt>(&mut self, pacer: &mut FramePacer)
+    where
+        PW: PixelWriter<AddrT, ColorT>,
+    {
+        PixelWriter::<AddrT, ColorT>::present(self);
+        pacer.pace().await;
+    }
+
+    /// Frame cell for `(i, j)`, if it falls inside the frame.
+    fn cell<AddrT: PrimInt>(&self, i: AddrT, j: AddrT) -> Option<(usize, usize)>
+    where
+        PW: PixelWriter<AddrT, ColorT>,
+    {
+        let ((i_min, _), (j_min, _)) = self.out.addr_range();
+        let r = i.checked_sub(&i_min)?.to_usize()?;
+        let c = j.checked_sub(&j_min)?.to_usize()?;
+        (r < H && c < W).then_some((r, c))
+    }
+}
+
+impl<ColorT, PW, const W: usize, const H: usize> NativePixelWriter for BufferedWriter<PW, ColorT, W, H>
+where
+    ColorT: PrimInt,
+    PW: NativePixelWriter<Color = ColorT>,
+{
+    type Addr = PW::Addr;
+    type Color = ColorT;
+}
+
+impl<AddrT, ColorT, PW, const W: usize, const H: usize> PixelWriter<AddrT, ColorT> for BufferedWriter<PW, ColorT, W, H>
+where
+    AddrT: PrimInt,
+    ColorT: PrimInt,
+    PW: PixelWriter<AddrT, ColorT>,
+{
+    fn write_pixel(&mut self, i: AddrT, j: AddrT, color: ColorT) {
+        match self.cell(i, j) {
+            Some((r, c)) => self.frame[r][c] = color,
+            None => self.out.write_pixel(i, j, color),
+        }
+    }
+
+    fn addr_range(&self) -> ((AddrT, AddrT), (AddrT, AddrT)) {
+        self.out.addr_range()
+    }
+
+    fn color_range(&self) -> (ColorT, ColorT) {
+        self.out.color_range()
+    }
+
+    fn read_pixel(&self, i: AddrT, j: AddrT) -> Option<ColorT> {
+        match self.cell(i, j) {
+            Some((r, c)) => Some(self.frame[r][c]),
+            None => self.out.read_pixel(i, j),
+        }
+    }
+
+    /// Flush the changed pixels, then present the inner writer.
+    fn present(&mut self) {
+        let ((i_min, i_max), (j_min, j_max)) = self.out.addr_range();
+        let (i_min, j_min) = (i_min.to_i64().unwrap(), j_min.to_i64().unwrap());
+        let (i_max, j_max) = (i_max.to_i64().unwrap(), j_max.to_i64().unwrap());
+        self.out.begin_batch();
+        for (r, row) in self.frame.iter().enumerate() {
+            let i = i_min + r as i64;
+            if i > i_max {
+                break;
+            }
+            for (c, &color) in row.iter().enumerate() {
+                let j = j_min + c as i64;
+                if j > j_max {
+                    break;
+                }
+                let cast = |v: i64| <AddrT as NumCast>::from(v).unwrap();
+                self.out.write_pixel(cast(i), cast(j), color);
+            }
+        }
+        self.out.end_batch();
+        self.out.present();
+    }
+
+    /// Row fills in the frame; anything outside it goes to the inner writer.
+    fn fill_rect(&mut self, range: ((AddrT, AddrT), (AddrT, AddrT)), color: ColorT) {
+        let ((i0, i1), (j0, j1)) = range;
+        let (Some((r0, c0)), Some((r1, c1))) = (self.cell(i0, j0), self.cell(i1, j1)) else {
+            // Partly outside the frame: per pixel, each to where it belongs.
+            for (i, j) in crate::display::pix_writer::AddrIter::new(range) {
+                self.write_pixel(i, j, color);
+            }
+            return;
+        };
+        for row in &mut self.frame[r0..=r1] {
+            row[c0..=c1].fill(color);
+        }
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            read_pixel: true,
+            batching: false,
+            presentation: true,
+            fast_fill: true,
+            ..self.out.capabilities()
+        }
+    }
+}
@@ -0,0 +1,167 @@
+//! Packed RGB color formats and down-conversion to the panel's capability.
+//!
+//! Drawing code can author colors as `Rgb565` / `Rgb332` (or plain 8-bit
+//! RGB) and convert them once, here, to whatever the backend accepts:
+//! - the packed value itself (`u16` / `u8`) for RGB backends,
+//! - a luma level for the BW backends (`luma()` for 8-bit DAC codes,
+//!   `luma4()` for the 4-bit bus_dac DAC).
+//!
+//! Widening a channel replicates its high bits into the new low bits, so
+//! full scale maps to full scale and narrowing back is lossless.
+
+/// Expand a `bits`-wide channel value to 8 bits by bit replication.
+#[inline(always)]
+const fn expand(v: u8, bits: u32) -> u8 {
+    let mut out: u16 = 0;
+    let mut shift: i32 = 8 - bits as i32;
+    let v = v as u16;
+    while shift > -(bits as i32) {
+        out |= if shift >= 0 { v << shift } else { v >> -shift };
+        shift -= bits as i32;
+    }
+    out as u8
+}
+
+/// Rec. 601 luma of an 8-bit RGB triple, 0..=255.
+#[inline(always)]
+pub const fn luma_rgb888(r: u8, g: u8, b: u8) -> u8 {
+    ((77 * r as u32 + 150 * g as u32 + 29 * b as u32 + 128) >> 8) as u8
+}
+
+/// 16-bit color: `rrrrr gggggg bbbbb`, red in the high bits.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Rgb565(pub u16);
+
+impl Rgb565 {
+    pub const BLACK: Self = Self(0x0000);
+    pub const WHITE: Self = Self(0xFFFF);
+
+    /// Pack raw channel values (`r`, `b` in 0..=31, `g` in 0..=63; excess bits are dropped).
+    #[inline(always)]
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self((((r & 0x1F) as u16) << 11) | (((g & 0x3F) as u16) << 5) | (b & 0x1F) as u16)
+    }
+
+    /// Truncate an 8-bit RGB triple.
+    #[inline(always)]
+    pub const fn from_rgb888(r: u8, g: u8, b: u8) -> Self {
+        Self::new(r >> 3, g >> 2, b >> 3)
+    }
+
+    /// Raw channel values `(r, g, b)`.
+    #[inline(always)]
+    pub const fn channels(self) -> (u8, u8, u8) {
+        (
+            (self.0 >> 11) as u8 & 0x1F,
+            (self.0 >> 5) as u8 & 0x3F,
+            self.0 as u8 & 0x1F,
+        )
+    }
+
+    /// Widen to an 8-bit RGB triple.
+    #[inline(always)]
+    pub const fn to_rgb888(self) -> (u8, u8, u8) {
+        let (r, g, b) = self.channels();
+        (expand(r, 5), expand(g, 6), expand(b, 5))
+    }
+
+    /// Rec. 601 luma, 0..=255.
+    #[inline(always)]
+    pub const fn luma(self) -> u8 {
+        let (r, g, b) = self.to_rgb888();
+        luma_rgb888(r, g, b)
+    }
+
+    /// Luma as a 4-bit DAC code, 0..=15.
+    #[inline(always)]
+    pub const fn luma4(self) -> u8 {
+        self.luma() >> 4
+    }
+}
+
+/// 8-bit color: `rrr ggg bb`, red in the high bits.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Rgb332(pub u8);
+
+impl Rgb332 {
+    pub const BLACK: Self = Self(0x00);
+    pub const WHITE: Self = Self(0xFF);
+
+    /// Pack raw channel values (`r`, `g` in 0..=7, `b` in 0..=3; excess bits are dropped).
+    #[inline(always)]
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self(((r & 0x07) << 5) | ((g & 0x07) << 2) | (b & 0x03))
+    }
+
+    /// Truncate an 8-bit RGB triple.
+    #[inline(always)]
+    pub const fn from_rgb888(r: u8, g: u8, b: u8) -> Self {
+        Self::new(r >> 5, g >> 5, b >> 6)
+    }
+
+    /// Raw channel values `(r, g, b)`.
+    #[inline(always)]
+    pub const fn channels(self) -> (u8, u8, u8) {
+        ((self.0 >> 5) & 0x07, (self.0 >> 2) & 0x07, self.0 & 0x03)
+    }
+
+    /// Widen to an 8-bit RGB triple.
+    #[inline(always)]
+    pub const fn to_rgb888(self) -> (u8, u8, u8) {
+        let (r, g, b) = self.channels();
+        (expand(r, 3), expand(g, 3), expand(b, 2))
+    }
+
+    /// Rec. 601 luma, 0..=255.
+    #[inline(always)]
+    pub const fn luma(self) -> u8 {
+        let (r, g, b) = self.to_rgb888();
+        luma_rgb888(r, g, b)
+    }
+
+    /// Luma as a 4-bit DAC code, 0..=15.
+    #[inline(always)]
+    pub const fn luma4(self) -> u8 {
+        self.luma() >> 4
+    }
+}
+
+/// Lossless: every `Rgb332` has an exact `Rgb565` counterpart.
+impl From<Rgb332> for Rgb565 {
+    fn from(c: Rgb332) -> Self {
+        let (r, g, b) = c.to_rgb888();
+        Rgb565::from_rgb888(r, g, b)
+    }
+}
+
+/// Lossy: keeps the high bits of each channel.
+impl From<Rgb565> for Rgb332 {
+    fn from(c: Rgb565) -> Self {
+        let (r, g, b) = c.to_rgb888();
+        Rgb332::from_rgb888(r, g, b)
+    }
+}
+
+impl From<Rgb565> for u16 {
+    fn from(c: Rgb565) -> Self {
+        c.0
+    }
+}
+
+impl From<u16> for Rgb565 {
+    fn from(v: u16) -> Self {
+        Self(v)
+    }
+}
+
+impl From<Rgb332> for u8 {
+    fn from(c: Rgb332) -> Self {
+        c.0
+    }
+}
+
+impl From<u8> for Rgb332 {
+    fn from(v: u8) -> Self {
+        Self(v)
+    }
+}
@@ -0,0 +1,159 @@
+//! Write-skipping for backends where every pixel write is expensive.
+//!
+//! `DiffWriter` trades RAM for bus time: it keeps a shadow of the whole
+//! panel, one `Option<ColorT>` per pixel (2 bytes for `u8` colors, so
+//! 60 KB at 200 x 150), to skip the shift-and-latch of every pixel whose
+//! color did not change. Worth it on the sipo backend, which has no
+//! framebuffer of its own; the framebuffer-backed bus_dac writes are cheap
+//! enough that the shadow only costs memory.
+
+use crate::display::pix_writer::{Capabilities, NativePixelWriter, PixelWriter};
+use crate::utils::PrimInt;
+use num_traits::NumCast;
+
+/// `PixelWriter` wrapper that skips writes which would not change the pixel.
+///
+/// Keeps a `W` x `H` shadow of the last color written to each address
+/// (relative to the inner `addr_range()` minimum), and only forwards
+/// `write_pixel` when the color differs. Meant for the sipo backend, where
+/// every write is a full shift-and-latch: redrawing a mostly static frame
+/// then only shifts the pixels that changed.
+///
+/// The shadow costs `W * H` `Option<ColorT>`s. Pixels never written through
+/// the wrapper are unknown and always forwarded, as are addresses outside the
+/// shadow. If the panel contents change behind the wrapper's back (power
+/// cycle, direct writes to the inner writer), call `invalidate()` so the
+/// next redraw goes out in full.
+pub struct DiffWriter<PW, ColorT, const W: usize, const H: usize> {
+    inner: PW,
+    shadow: [[Option<ColorT>; W]; H],
+}
+
+impl<PW, ColorT: PrimInt, const W: usize, const H: usize> DiffWriter<PW, ColorT, W, H> {
+    pub const fn new(inner: PW) -> Self {
+        Self { inner, shadow: [[None; W]; H] }
+    }
+
+    /// Forget every shadowed pixel, forcing the next write to each one out.
+    pub fn invalidate(&mut self) {
+        self.shadow = [[None; W]; H];
+    }
+
+    pub fn inner(&self) -> &PW {
+        &self.inner
+    }
+
+    /// Direct access to the wrapped writer; call `invalidate()` after
+    /// writing through it.
+    pub fn inner_mut(&mut self) -> &mut PW {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> PW {
+        self.inner
+    }
+
+    /// Upload a whole off-screen frame, forwarding only the pixels that
+    /// differ from the shadow, in row-major address order.
+    ///
+    /// `fb[r][c]` lands at the inner `addr_range()` minimum plus `(r, c)`;
+    /// cells past the inner range are skipped. Runs inside one batch.
+    /// Returns the number of pixels forwarded.
+    pub fn write_frame<AddrT: PrimInt>(&mut self, fb: &[[ColorT; W]; H]) -> usize
+    where
+        PW: PixelWriter<AddrT, ColorT>,
+    {
+        let ((i_min, i_max), (j_min, j_max)) = self.inner.addr_range();
+        let mut sent = 0;
+        self.inner.begin_batch();
+        for (r, row) in fb.iter().enumerate() {
+            let Some(i) = <AddrT as NumCast>::from(r).and_then(|r| i_min.checked_add(&r)).filter(|&i| i <= i_max) else {
+                break;
+            };
+            for (c, &color) in row.iter().enumerate() {
+                let Some(j) = <AddrT as NumCast>::from(c).and_then(|c| j_min.checked_add(&c)).filter(|&j| j <= j_max) else {
+                    break;
+                };
+                if self.shadow[r][c] != Some(color) {
+                    self.inner.write_pixel(i, j, color);
+                    self.shadow[r][c] = Some(color);
+                    sent += 1;
+                }
+            }
+        }
+        self.inner.end_batch();
+        sent
+    }
+
+    /// Shadow cell for `(i, j)`, if it falls inside the shadow.
+    fn cell<AddrT: PrimInt>(&self, i: AddrT, j: AddrT) -> Option<(usize, usize)>
+    where
+        PW: PixelWriter<AddrT, ColorT>,
+    {
+        let ((i_min, _), (j_min, _)) = self.inner.addr_range();
+        let r = i.checked_sub(&i_min)?.to_usize()?;
+        let c = j.checked_sub(&j_min)?.to_usize()?;
+        (r < H && c < W).then_some((r, c))
+    }
+}
+
+impl<ColorT, PW, const W: usize, const H: usize> NativePixelWriter for DiffWriter<PW, ColorT, W, H>
+where
+    ColorT: PrimInt,
+    PW: NativePixelWriter<Color = ColorT>,
+{
+    type Addr = PW::Addr;
+    type Color = ColorT;
+}
+
+impl<AddrT, ColorT, PW, const W: usize, const H: usize> PixelWriter<AddrT, ColorT> for DiffWriter<PW, ColorT, W, H>
+where
+    AddrT: PrimInt,
+    ColorT: PrimInt,
+    PW: PixelWriter<AddrT, ColorT>,
+{
+    fn write_pixel(&mut self, i: AddrT, j: AddrT, color: ColorT) {
+        match self.cell(i, j) {
+            Some((r, c)) => {
+                if self.shadow[r][c] != Some(color) {
+                    self.inner.write_pixel(i, j, color);
+                    self.shadow[r][c] = Some(color);
+                }
+            }
+            None => self.inner.write_pixel(i, j, color),
+        }
+    }
+
+    fn addr_range(&self) -> ((AddrT, AddrT), (AddrT, AddrT)) {
+        self.inner.addr_range()
+    }
+
+    fn color_range(&self) -> (ColorT, ColorT) {
+        self.inner.color_range()
+    }
+
+    /// The inner writer's pixel if it can read back, else the shadowed one.
+    fn read_pixel(&self, i: AddrT, j: AddrT) -> Option<ColorT> {
+        self.inner
+            .read_pixel(i, j)
+            .or_else(|| self.cell(i, j).and_then(|(r, c)| self.shadow[r][c]))
+    }
+
+    fn present(&mut self) {
+        self.inner.present();
+    }
+
+    fn begin_batch(&mut self) {
+        self.inner.begin_batch();
+    }
+
+    fn end_batch(&mut self) {
+        self.inner.end_batch();
+    }
+
+    // `fill_rect` keeps the per-pixel default so fills are diffed as well.
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities { fast_fill: false, ..self.inner.capabilities() }
+    }
+}
@@ -1,10 +1,267 @@
-use crate::display::pix_writer::PixelWriter;
-use crate::utils::PrimInt;
+use crate::display::pix_writer::{AddrIter, NativePixelWriter, PixelWriter};
+use crate::display::text::TextLayout;
+use crate::error::{Error, Result};
+use crate::utils::{PrimInt, ScanlineStack, Span};
+use num_traits::NumCast;
+/// Bin count suited to the 4-bit bus_dac DAC, for `Drawer::brightness_histogram`.
+pub const DEFAULT_HISTOGRAM_BINS: usize = 16;
+
+/// `sin(deg)` for 0..=90 degrees in Q14 (`16384` = 1.0), for `blit_rotated`.
+#[rustfmt::skip]
+const SIN_Q14: [i64; 91] = [
+    0, 286, 572, 857, 1143, 1428, 1713, 1997, 2280, 2563,
+    2845, 3126, 3406, 3686, 3964, 4240, 4516, 4790, 5063, 5334,
+    5604, 5872, 6138, 6402, 6664, 6924, 7182, 7438, 7692, 7943,
+    8192, 8438, 8682, 8923, 9162, 9397, 9630, 9860, 10087, 10311,
+    10531, 10749, 10963, 11174, 11381, 11585, 11786, 11982, 12176, 12365,
+    12551, 12733, 12911, 13085, 13255, 13421, 13583, 13741, 13894, 14044,
+    14189, 14330, 14466, 14598, 14726, 14849, 14968, 15082, 15191, 15296,
+    15396, 15491, 15582, 15668, 15749, 15826, 15897, 15964, 16026, 16083,
+    16135, 16182, 16225, 16262, 16294, 16322, 16344, 16362, 16374, 16382,
+    16384,
+];
+
+/// `(sin, cos)` of `deg` (any integer) in Q14.
+fn sin_cos_q14(deg: i32) -> (i64, i64) {
+    let sin = |d: i32| -> i64 {
+        match d {
+            0..=90 => SIN_Q14[d as usize],
+            91..=180 => SIN_Q14[(180 - d) as usize],
+            181..=270 => -SIN_Q14[(d - 180) as usize],
+            _ => -SIN_Q14[(360 - d) as usize],
+        }
+    };
+    let d = deg.rem_euclid(360);
+    (sin(d), sin((d + 90) % 360))
+}
+
+/// `v` wrapped into `min..=max`.
+fn wrap_coord(v: i64, min: i64, max: i64) -> i64 {
+    min + (v - min).rem_euclid(max - min + 1)
+}
+
+/// The inclusive span `[a, b]` wrapped into `min..=max`: one piece, or two
+/// if it crosses the end. A span at least as long as the range covers it.
+fn wrap_span(a: i64, b: i64, min: i64, max: i64) -> [Option<(i64, i64)>; 2] {
+    let len = max - min + 1;
+    if b - a + 1 >= len {
+        return [Some((min, max)), None];
+    }
+    let start = wrap_coord(a, min, max);
+    let end = start + (b - a);
+    if end <= max {
+        [Some((start, end)), None]
+    } else {
+        [Some((start, max)), Some((min, end - len))]
+    }
+}
+
+/// Bresenham steps from `(i0, j0)` to `(i1, j1)`, both inclusive and
+/// unclipped; the stepping behind `Drawer::draw_line` and `line_iter`.
+#[derive(Clone, Debug)]
+pub struct LineSteps {
+    i: i64,
+    j: i64,
+    i1: i64,
+    j1: i64,
+    di: i64,
+    dj: i64,
+    si: i64,
+    sj: i64,
+    err: i64,
+    done: bool,
+}
+
+impl LineSteps {
+    pub fn new(i0: i64, j0: i64, i1: i64, j1: i64) -> Self {
+        let (di, dj) = ((i1 - i0).abs(), -(j1 - j0).abs());
+        let (si, sj) = ((i1 - i0).signum(), (j1 - j0).signum());
+        Self { i: i0, j: j0, i1, j1, di, dj, si, sj, err: di + dj, done: false }
+    }
+}
+
+impl Iterator for LineSteps {
+    type Item = (i64, i64);
+
+    fn next(&mut self) -> Option<(i64, i64)> {
+        if self.done {
+            return None;
+        }
+        let point = (self.i, self.j);
+        if point == (self.i1, self.j1) {
+            self.done = true;
+            return Some(point);
+        }
+        let e2 = 2 * self.err;
+        if e2 >= self.dj {
+            self.err += self.dj;
+            self.i += self.si;
+        }
+        if e2 <= self.di {
+            self.err += self.di;
+            self.j += self.sj;
+        }
+        Some(point)
+    }
+}
+
+/// A drawer's `addr_range()` and edge mode, copied out so pixel iterators
+/// don't borrow it.
+#[derive(Clone, Copy)]
+struct ScreenMap {
+    bounds: ((i64, i64), (i64, i64)),
+    /// `bounds` narrowed to the drawer's clip rect.
+    visible: ((i64, i64), (i64, i64)),
+    edge: EdgeMode,
+}
+
+impl ScreenMap {
+    /// Screen address of a signed point: wrapped in `EdgeMode::Wrap`,
+    /// `None` if it is off-screen or outside the clip rect.
+    fn apply<AddrT: PrimInt>(self, i: i64, j: i64) -> Option<(AddrT, AddrT)> {
+        let ((i_min, i_max), (j_min, j_max)) = self.bounds;
+        let (i, j) = match self.edge {
+            EdgeMode::Clip => (i, j),
+            EdgeMode::Wrap => (wrap_coord(i, i_min, i_max), wrap_coord(j, j_min, j_max)),
+        };
+        let ((i_min, i_max), (j_min, j_max)) = self.visible;
+        if i < i_min || i > i_max || j < j_min || j > j_max {
+            return None;
+        }
+        Some((<AddrT as NumCast>::from(i).unwrap(), <AddrT as NumCast>::from(j).unwrap()))
+    }
+}
+
+/// Marker shape for `Drawer::draw_marker`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MarkerStyle {
+    /// Filled disk of radius `size`.
+    Dot,
+    /// Diagonal cross with arms of `size` pixels.
+    Cross,
+    /// Upright cross with arms of `size` pixels.
+    Plus,
+    /// Square outline `2 * size + 1` pixels wide.
+    Square,
+    /// Circle outline of radius `size`.
+    Circle,
+}
+
+/// What `Drawer` does with coordinates outside `addr_range()`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EdgeMode {
+    /// Drop them.
+    #[default]
+    Clip,
+    /// Wrap them around modulo the screen size (toroidal coordinates), so
+    /// a shape leaving one edge re-enters at the opposite one.
+    Wrap,
+}
+
+/// Dash pattern for `draw_line_dashed` and `draw_rectangle_outline_dashed`:
+/// `on` pixels drawn, then `off` skipped, repeating along the path.
+///
+/// `phase` shifts the pattern forward along the path by that many pixels,
+/// so stepping it by one per frame makes the dashes crawl; see
+/// `MarchingAnts`. An `off` of 0 is a solid line, an `on` of 0 draws nothing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Dash {
+    pub on: u16,
+    pub off: u16,
+    pub phase: u16,
+}
+
+impl Dash {
+    pub const fn new(on: u16, off: u16) -> Self {
+        Self { on, off, phase: 0 }
+    }
+
+    pub const fn with_phase(mut self, phase: u16) -> Self {
+        self.phase = phase;
+        self
+    }
+
+    /// Whether the `k`th pixel along the path is drawn.
+    #[inline]
+    pub fn is_on(&self, k: u32) -> bool {
+        let period = self.on as u32 + self.off as u32;
+        if period == 0 {
+            return false;
+        }
+        let shift = self.phase as u32 % period;
+        (k % period + period - shift) % period < self.on as u32
+    }
+}
+
+/// Selection marquee: a dashed rectangle outline whose dashes crawl
+/// clockwise by one pixel per `advance()`.
+///
+/// Draw it once per frame and call `advance()` after each present:
+///
+/// ```ignore
+/// ants.draw(&mut drawer, i, j, w, h, 255);
+/// drawer.present();
+/// ants.advance();
+/// ```
+///
+/// Only the border is drawn, so erase or redraw what is under it first
+/// on backends that keep their contents.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MarchingAnts {
+    dash: Dash,
+}
+
+impl MarchingAnts {
+    /// Dashes of `on` pixels separated by gaps of `off`.
+    pub const fn new(on: u16, off: u16) -> Self {
+        Self { dash: Dash::new(on, off) }
+    }
+
+    /// Pattern for the current frame.
+    pub fn dash(&self) -> Dash {
+        self.dash
+    }
+
+    /// Move the dashes one pixel further along the border.
+    pub fn advance(&mut self) {
+        // Widened as in `Dash::is_on`; `on + off` can exceed `u16`. A phase
+        // past `u16::MAX` wraps to 0, which no path on a panel is long enough
+        // to show.
+        let period = (self.dash.on as u32 + self.dash.off as u32).max(1);
+        self.dash.phase = ((self.dash.phase as u32 + 1) % period) as u16;
+    }
+
+    /// Draw the `w` x `h` marquee with top-left corner `(i, j)`.
+    pub fn draw<AddrT: PrimInt, ColorT: PrimInt, PW: PixelWriter<AddrT, ColorT>>(
+        &self,
+        drawer: &mut Drawer<'_, AddrT, ColorT, PW>,
+        i: AddrT,
+        j: AddrT,
+        w: AddrT,
+        h: AddrT,
+        color: ColorT,
+    ) {
+        drawer.draw_rectangle_outline_dashed(i, j, w, h, self.dash, color);
+    }
+}
+
+/// `Drawer` over `PW`'s native address and color types, e.g.
+/// `NativeDrawer::new(&mut backend)`.
+pub type NativeDrawer<'a, PW> =
+    Drawer<'a, <PW as NativePixelWriter>::Addr, <PW as NativePixelWriter>::Color, PW>;
+
 pub struct Drawer<'a, AddrT: PrimInt, ColorT: PrimInt, PW>
 where
     PW: PixelWriter<AddrT, ColorT>,
 {
     pixel_writer: &'a mut PW,
+    /// Set by `new_buffered`: frames must be presented to become visible.
+    buffered: bool,
+    edge: EdgeMode,
+    /// Inclusive `((i0, i1), (j0, j1))` drawing is limited to; see `set_clip`.
+    clip: Option<((AddrT, AddrT), (AddrT, AddrT))>,
+    /// Glyph spacing and line height; see `set_char_spacing`.
+    pub(crate) text: TextLayout,
     _marker_color: core::marker::PhantomData<ColorT>,
     _marker_addr: core::marker::PhantomData<AddrT>,
 }
@@ -16,20 +273,120 @@ Drawer<'a, AddrT, ColorT, PW> where
     pub fn new(pixel_writer: &'a mut PW) -> Self {
         Drawer {
             pixel_writer,
+            buffered: false,
+            edge: EdgeMode::Clip,
+            clip: None,
+            text: TextLayout::default(),
             _marker_color: core::marker::PhantomData,
             _marker_addr: core::marker::PhantomData,
         }
     }
 
+    /// Drawer for a double-buffered backend; draw through `frame()` so each
+    /// frame is presented automatically.
+    pub fn new_buffered(pixel_writer: &'a mut PW) -> Self {
+        let mut drawer = Self::new(pixel_writer);
+        drawer.buffered = true;
+        drawer
+    }
+
+    /// Whether this drawer was created with `new_buffered`.
+    pub fn is_buffered(&self) -> bool {
+        self.buffered
+    }
+
+    /// Clip (the default) or wrap off-screen coordinates.
+    ///
+    /// `EdgeMode::Wrap` makes seamless scrolling patterns and tiles a matter
+    /// of drawing at an ever-increasing offset. It applies to
+    /// `write_pixel`, `write_pixel_checked`, `write_pixel_norm` and every
+    /// primitive documented as clipped: lines, spans, outlines, markers,
+    /// text, blits and rectangle fills (split at the edges). Read-back
+    /// operations (`invert_rect`, `write_pixel_blend`, `add_pixel`,
+    /// scrolling) and the unclipped `draw_rectangle` keep their exact
+    /// addresses.
+    pub fn set_edge_mode(&mut self, mode: EdgeMode) {
+        self.edge = mode;
+    }
+
+    pub fn edge_mode(&self) -> EdgeMode {
+        self.edge
+    }
+
+    /// Limit drawing to the inclusive `((i0, i1), (j0, j1))` rectangle, or
+    /// lift the limit with `None` (the default).
+    ///
+    /// `range` uses the same layout as `addr_range()`. Every primitive
+    /// documented as clipped, `in_bounds` and `flood_fill` honor it; in
+    /// `EdgeMode::Wrap` coordinates still wrap around the whole screen and
+    /// only the wrapped pixels inside the clip are drawn.
+    pub fn set_clip(&mut self, range: Option<((AddrT, AddrT), (AddrT, AddrT))>) {
+        self.clip = range;
+    }
+
+    pub fn clip(&self) -> Option<((AddrT, AddrT), (AddrT, AddrT))> {
+        self.clip
+    }
+
+    /// Return every drawing setting to its default (the edge mode, back to
+    /// `EdgeMode::Clip`, no clip rect, and the font's own text spacing), so
+    /// a drawer handed between UI components starts from a known context.
+    /// Whether the drawer is buffered is fixed at construction and kept.
+    pub fn reset_state(&mut self) {
+        self.edge = EdgeMode::default();
+        self.clip = None;
+        self.text = TextLayout::default();
+    }
+
+    /// Start a frame that is presented when the returned guard is dropped.
+    ///
+    /// The guard derefs to the drawer, so `{ let mut f = drawer.frame();
+    /// f.fill_screen(0); }` flips buffers at the closing brace. On a drawer
+    /// from plain `new` the guard presents nothing.
+    pub fn frame(&mut self) -> FrameGuard<'_, 'a, AddrT, ColorT, PW> {
+        FrameGuard { drawer: self }
+    }
+
     pub fn fill_screen(&mut self, color: ColorT) {
-        let ((i_min, i_max), (j_min, j_max)) = self.pixel_writer.addr_range();
-        for i in i_min..=i_max {
-            for j in j_min..=j_max {
-                self.pixel_writer.write_pixel(i, j, color);
+        let range = self.pixel_writer.addr_range();
+        self.pixel_writer.fill_rect(range, color);
+    }
+
+    /// Set every pixel in `addr_range()` to `f(i, j)`, row by row, e.g.
+    /// `noise::noise2d` for procedural test content.
+    pub fn fill_with(&mut self, mut f: impl FnMut(AddrT, AddrT) -> ColorT) {
+        let ((i0, i1), (j0, j1)) = self.pixel_writer.addr_range();
+        self.pixel_writer.begin_batch();
+        for i in i0.to_i64().unwrap()..=i1.to_i64().unwrap() {
+            let i = <AddrT as NumCast>::from(i).unwrap();
+            for j in j0.to_i64().unwrap()..=j1.to_i64().unwrap() {
+                let j = <AddrT as NumCast>::from(j).unwrap();
+                self.pixel_writer.write_pixel(i, j, f(i, j));
             }
         }
+        self.pixel_writer.end_batch();
+    }
+
+    /// Make the frame drawn so far visible (see `PixelWriter::present`).
+    pub fn present(&mut self) {
+        self.pixel_writer.present();
+    }
+
+    /// Present the current frame, then clear the next one to `clear`.
+    ///
+    /// On double-buffered backends the clear runs after the `swap()`, so it
+    /// lands in the buffer that just went off-screen and the next frame starts
+    /// blank. On immediate-mode backends this is just `fill_screen(clear)`.
+    pub fn present_and_clear(&mut self, clear: ColorT) {
+        self.present();
+        self.fill_screen(clear);
     }
 
+    /// Fill a `width` x `height` rectangle with its top-left corner at
+    /// `(i_start, j_start)`.
+    ///
+    /// Not clipped, and `i_start + height - 1` must fit in `AddrT`; prefer
+    /// `draw_rectangle_corners` when either end may fall off-screen.
     pub fn draw_rectangle(
         &mut self,
         i_start: AddrT,
@@ -40,14 +397,1239 @@ Drawer<'a, AddrT, ColorT, PW> where
     ) {
         let i_end = i_start + height - AddrT::one();
         let j_end = j_start + width - AddrT::one();
+        self.pixel_writer.begin_batch();
         for i in i_start..=i_end {
             for j in j_start..=j_end {
                 self.pixel_writer.write_pixel(i, j, color);
             }
         }
+        self.pixel_writer.end_batch();
     }
 
+    /// Fill the rectangle spanned by the opposite corners `(i0, j0)` and
+    /// `(i1, j1)`, both inclusive and in any order, clipped to `addr_range()`.
+    ///
+    /// Unlike `draw_rectangle` there is no size arithmetic, so it cannot
+    /// overflow near the top of `AddrT`.
+    pub fn draw_rectangle_corners(&mut self, i0: AddrT, j0: AddrT, i1: AddrT, j1: AddrT, color: ColorT) {
+        let (i0, i1) = (i0.min(i1), i0.max(i1));
+        let (j0, j1) = (j0.min(j1), j0.max(j1));
+        self.fill_box(
+            i0.to_i64().unwrap(),
+            i1.to_i64().unwrap(),
+            j0.to_i64().unwrap(),
+            j1.to_i64().unwrap(),
+            color,
+        );
+    }
+
+    /// Write one pixel; not clipped, but wrapped in `EdgeMode::Wrap`.
     pub fn write_pixel(&mut self, i: AddrT, j: AddrT, color: ColorT) {
+        match self.edge {
+            EdgeMode::Clip => self.pixel_writer.write_pixel(i, j, color),
+            EdgeMode::Wrap => {
+                self.plot_clipped(i.to_i64().unwrap(), j.to_i64().unwrap(), color);
+            }
+        }
+    }
+
+    /// `write_pixel` with a normalized brightness, for effects computed in
+    /// floating point: `0.0..=1.0` maps linearly onto `color_range()`,
+    /// rounded to nearest. Out-of-range input is clamped and NaN counts as
+    /// `0.0`.
+    pub fn write_pixel_norm(&mut self, i: AddrT, j: AddrT, brightness: f32) {
+        let (min, max) = self.pixel_writer.color_range();
+        let (min, max) = (min.to_i64().unwrap(), max.to_i64().unwrap());
+        // `f32::clamp` would keep NaN; the comparisons below map it to 0.
+        let b = if brightness >= 1.0 {
+            1.0
+        } else if brightness > 0.0 {
+            brightness
+        } else {
+            0.0
+        };
+        // No `f32::round` in core: the value is non-negative, so add 0.5 and
+        // truncate. The min() guards against f32 rounding past the span.
+        let span = max - min;
+        let step = ((b * span as f32 + 0.5) as i64).min(span);
+        let color = <ColorT as NumCast>::from(min + step).unwrap();
+        self.write_pixel(i, j, color);
+    }
+
+    /// Draw the 1-pixel border of the `width` x `height` rectangle with
+    /// top-left corner `(i, j)`, clipped to `addr_range()`.
+    ///
+    /// The `thickness` 1 case of `stroke_rect`.
+    pub fn draw_rectangle_outline(&mut self, i: AddrT, j: AddrT, width: AddrT, height: AddrT, color: ColorT) {
+        self.stroke_rect(i, j, width, height, AddrT::one(), color);
+    }
+
+    /// Draw a border `thickness` pixels wide just inside the `width` x
+    /// `height` rectangle with top-left corner `(i, j)`, clipped to
+    /// `addr_range()`.
+    ///
+    /// The border is four non-overlapping bands, so every pixel is written
+    /// once. If `2 * thickness` reaches the width or height the bands meet
+    /// and the whole rectangle is filled; a `thickness` of 0 draws nothing.
+    pub fn stroke_rect(&mut self, i: AddrT, j: AddrT, width: AddrT, height: AddrT, thickness: AddrT, color: ColorT) {
+        self.stroke_box(
+            i.to_i64().unwrap(),
+            j.to_i64().unwrap(),
+            width.to_i64().unwrap(),
+            height.to_i64().unwrap(),
+            thickness.to_i64().unwrap(),
+            color,
+        );
+    }
+
+    /// `stroke_rect` with the border centered on the rectangle's edge
+    /// instead of inside it: `thickness / 2` pixels fall outside, the rest
+    /// (including the edge itself) inside.
+    pub fn stroke_rect_centered(
+        &mut self,
+        i: AddrT,
+        j: AddrT,
+        width: AddrT,
+        height: AddrT,
+        thickness: AddrT,
+        color: ColorT,
+    ) {
+        let t = thickness.to_i64().unwrap();
+        let out = t / 2;
+        self.stroke_box(
+            i.to_i64().unwrap() - out,
+            j.to_i64().unwrap() - out,
+            width.to_i64().unwrap() + 2 * out,
+            height.to_i64().unwrap() + 2 * out,
+            t,
+            color,
+        );
+    }
+
+    /// `stroke_rect` on `i64` coordinates, so a centered border may start
+    /// off-screen.
+    pub(crate) fn stroke_box(&mut self, i0: i64, j0: i64, w: i64, h: i64, t: i64, color: ColorT) {
+        if w <= 0 || h <= 0 || t <= 0 {
+            return;
+        }
+        let (i1, j1) = (i0 + h - 1, j0 + w - 1);
+        self.pixel_writer.begin_batch();
+        if 2 * t >= w || 2 * t >= h {
+            self.fill_box(i0, i1, j0, j1, color);
+        } else {
+            self.fill_box(i0, i0 + t - 1, j0, j1, color);
+            self.fill_box(i1 - t + 1, i1, j0, j1, color);
+            self.fill_box(i0 + t, i1 - t, j0, j0 + t - 1, color);
+            self.fill_box(i0 + t, i1 - t, j1 - t + 1, j1, color);
+        }
+        self.pixel_writer.end_batch();
+    }
+
+    /// Fill the disc of radius `r` centered on `(ci, cj)`: every pixel at
+    /// most `r` from the center, the same set as a `Dot` marker of size
+    /// `r`. One span per row, clipped to `addr_range()`.
+    pub fn fill_circle(&mut self, ci: AddrT, cj: AddrT, r: AddrT, color: ColorT) {
+        let (ci, cj) = (ci.to_i64().unwrap(), cj.to_i64().unwrap());
+        self.fill_annulus(ci, cj, -1, r.to_i64().unwrap(), color);
+    }
+
+    /// Fill the ring between `inner_r` and `outer_r` around `(ci, cj)`,
+    /// e.g. for gauges and dials.
+    ///
+    /// For `inner_r > 0` the hole is exactly what `fill_circle(ci, cj,
+    /// inner_r, ..)` would fill, so a ring and a disc of the same radius
+    /// tile without gaps or overlap. An `inner_r` of 0 is `fill_circle`;
+    /// `inner_r >= outer_r` draws nothing. Clipped to `addr_range()`.
+    pub fn fill_ring(&mut self, ci: AddrT, cj: AddrT, inner_r: AddrT, outer_r: AddrT, color: ColorT) {
+        if inner_r >= outer_r {
+            return;
+        }
+        let (ci, cj) = (ci.to_i64().unwrap(), cj.to_i64().unwrap());
+        let inner = if inner_r.is_zero() { -1 } else { inner_r.to_i64().unwrap() };
+        self.fill_annulus(ci, cj, inner, outer_r.to_i64().unwrap(), color);
+    }
+
+    /// Fill the pixels more than `inner` and at most `outer` from
+    /// `(ci, cj)`, as one or two spans per row; a negative `inner` leaves
+    /// no hole.
+    pub(crate) fn fill_annulus(&mut self, ci: i64, cj: i64, inner: i64, outer: i64, color: ColorT) {
+        if outer < 0 {
+            return;
+        }
+        self.pixel_writer.begin_batch();
+        for di in -outer..=outer {
+            let i = ci + di;
+            // Half-widths of the outer and inner discs on this row.
+            let wo = (outer * outer - di * di).isqrt();
+            if inner >= 0 && di.abs() <= inner {
+                let wi = (inner * inner - di * di).isqrt();
+                self.fill_box(i, i, cj - wo, cj - wi - 1, color);
+                self.fill_box(i, i, cj + wi + 1, cj + wo, color);
+            } else {
+                self.fill_box(i, i, cj - wo, cj + wo, color);
+            }
+        }
+        self.pixel_writer.end_batch();
+    }
+
+    /// Draw a horizontal progress bar in the `w` x `h` box at `(i, j)`.
+    ///
+    /// A 1-pixel `border` surrounds the inside, whose left
+    /// `fraction / 255` (rounded) is filled with `fg` and the rest with `bg`:
+    /// 0 is empty and 255 full. Boxes thinner than 3 pixels have no inside
+    /// and draw only the border. Clipped to `addr_range()`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_progress_bar(
+        &mut self,
+        i: AddrT,
+        j: AddrT,
+        w: AddrT,
+        h: AddrT,
+        fraction: u8,
+        fg: ColorT,
+        bg: ColorT,
+        border: ColorT,
+    ) {
+        self.draw_rectangle_outline(i, j, w, h, border);
+        let (i0, j0) = (i.to_i64().unwrap() + 1, j.to_i64().unwrap() + 1);
+        let (inner_w, inner_h) = (w.to_i64().unwrap() - 2, h.to_i64().unwrap() - 2);
+        if inner_w <= 0 || inner_h <= 0 {
+            return;
+        }
+        let filled = (inner_w * fraction as i64 + 127) / 255;
+        let i1 = i0 + inner_h - 1;
+        self.fill_box(i0, i1, j0, j0 + filled - 1, fg);
+        self.fill_box(i0, i1, j0 + filled, j0 + inner_w - 1, bg);
+    }
+
+    /// Whether `(i, j)` lies inside `addr_range()` and the clip rect, i.e.
+    /// would be drawn.
+    ///
+    /// Cheap enough to call before per-pixel work, and handy for hit-testing
+    /// touch or cursor input against the screen.
+    #[inline]
+    pub fn in_bounds(&self, i: AddrT, j: AddrT) -> bool {
+        let ((i_min, i_max), (j_min, j_max)) = self.visible_i64();
+        let (i, j) = (i.to_i64().unwrap(), j.to_i64().unwrap());
+        i >= i_min && i <= i_max && j >= j_min && j <= j_max
+    }
+
+    /// Like `write_pixel`, but skip `(i, j)` if it is outside `addr_range()`
+    /// or the clip rect (wrap it in `EdgeMode::Wrap`).
+    ///
+    /// Returns whether the pixel was written.
+    pub fn write_pixel_checked(&mut self, i: AddrT, j: AddrT, color: ColorT) -> bool {
+        if self.edge == EdgeMode::Wrap {
+            return self.plot_clipped(i.to_i64().unwrap(), j.to_i64().unwrap(), color);
+        }
+        if !self.in_bounds(i, j) {
+            return false;
+        }
+        self.pixel_writer.write_pixel(i, j, color);
+        true
+    }
+
+    /// Add `delta` to the pixel at `(i, j)`, saturating at the top of
+    /// `color_range()`, e.g. to accumulate particle trails.
+    ///
+    /// Reads the old value back, so it is skipped, returning `false`, on
+    /// backends without `read_pixel` and outside `addr_range()`. Returns
+    /// whether the pixel was written.
+    pub fn add_pixel(&mut self, i: AddrT, j: AddrT, delta: ColorT) -> bool {
+        self.offset_pixel(i, j, delta.to_i64().unwrap())
+    }
+
+    /// Subtract `delta` from the pixel at `(i, j)`, saturating at the bottom
+    /// of `color_range()`, e.g. to fade trails out; see `add_pixel`.
+    pub fn sub_pixel(&mut self, i: AddrT, j: AddrT, delta: ColorT) -> bool {
+        self.offset_pixel(i, j, -delta.to_i64().unwrap())
+    }
+
+    fn offset_pixel(&mut self, i: AddrT, j: AddrT, delta: i64) -> bool {
+        if !self.in_bounds(i, j) {
+            return false;
+        }
+        let Some(old) = self.pixel_writer.read_pixel(i, j) else {
+            return false;
+        };
+        let (min, max) = self.pixel_writer.color_range();
+        let (min, max) = (min.to_i64().unwrap(), max.to_i64().unwrap());
+        let new = old.to_i64().unwrap().saturating_add(delta).clamp(min, max);
+        self.pixel_writer.write_pixel(i, j, <ColorT as NumCast>::from(new).unwrap());
+        true
+    }
+
+    /// Plot every `(i, j)` from `points` in `color`, skipping off-screen ones.
+    ///
+    /// The building block for scatter plots and particle effects; use
+    /// `draw_marker` instead when single pixels are too small to see.
+    pub fn plot_points(&mut self, points: impl Iterator<Item = (AddrT, AddrT)>, color: ColorT) {
+        self.pixel_writer.begin_batch();
+        for (i, j) in points {
+            self.write_pixel_checked(i, j, color);
+        }
+        self.pixel_writer.end_batch();
+    }
+
+    /// Blend `color` over the pixel at `(i, j)` with opacity `alpha / 255`.
+    ///
+    /// Uses the existing value from `PixelWriter::read_pixel`, rounding the
+    /// integer blend to nearest. On write-only backends there is nothing to
+    /// blend with, so `alpha` is rounded to opaque (>= 128, plain write) or
+    /// transparent (skipped); `255` and `0` behave the same on every backend.
+    pub fn write_pixel_blend(&mut self, i: AddrT, j: AddrT, color: ColorT, alpha: u8) {
+        if alpha == 0 {
+            return;
+        }
+        if alpha == u8::MAX {
+            self.pixel_writer.write_pixel(i, j, color);
+            return;
+        }
+        match self.pixel_writer.read_pixel(i, j) {
+            Some(old) => {
+                let (a, old, new) = (alpha as i64, old.to_i64().unwrap(), color.to_i64().unwrap());
+                let mixed = (new * a + old * (255 - a) + 127).div_euclid(255);
+                let mixed = <ColorT as NumCast>::from(mixed).unwrap();
+                self.pixel_writer.write_pixel(i, j, mixed);
+            }
+            None if alpha >= 128 => self.pixel_writer.write_pixel(i, j, color),
+            None => {}
+        }
+    }
+
+    /// Invert the `w` x `h` rectangle with top-left corner `(i0, j0)`,
+    /// clipped to `addr_range()`, e.g. to highlight a selected menu item.
+    ///
+    /// Each pixel is read back and mirrored within `color_range()`, so
+    /// `value` becomes `max - value` for the usual `min == 0`, and inverting
+    /// twice restores the original. Pixels `read_pixel` cannot return are
+    /// left alone, which makes this a no-op on write-only backends.
+    pub fn invert_rect(&mut self, i0: AddrT, j0: AddrT, w: AddrT, h: AddrT) {
+        let Some(range) = self.rect_bounds(i0, j0, w, h) else {
+            return;
+        };
+        let (min, max) = self.pixel_writer.color_range();
+        let sum = min.to_i64().unwrap() + max.to_i64().unwrap();
+        self.pixel_writer.begin_batch();
+        for (i, j) in AddrIter::new(range) {
+            if let Some(old) = self.pixel_writer.read_pixel(i, j) {
+                let inv = <ColorT as NumCast>::from(sum - old.to_i64().unwrap()).unwrap();
+                self.pixel_writer.write_pixel(i, j, inv);
+            }
+        }
+        self.pixel_writer.end_batch();
+    }
+
+    /// Move the screen contents up by `n` rows, filling the bottom with `fill`.
+    ///
+    /// Works by reading pixels back, so it returns `Error::NotConfigured`,
+    /// without drawing, on backends without `read_pixel`. Rows scrolled off
+    /// the top are lost. The same holds for `scroll_down`, `scroll_left`
+    /// and `scroll_right`.
+    pub fn scroll_up(&mut self, n: AddrT, fill: ColorT) -> Result<()> {
+        self.scroll_by(-n.to_i64().unwrap(), 0, fill)
+    }
+
+    /// Move the screen contents down by `n` rows; see `scroll_up`.
+    pub fn scroll_down(&mut self, n: AddrT, fill: ColorT) -> Result<()> {
+        self.scroll_by(n.to_i64().unwrap(), 0, fill)
+    }
+
+    /// Move the screen contents left by `n` columns; see `scroll_up`.
+    pub fn scroll_left(&mut self, n: AddrT, fill: ColorT) -> Result<()> {
+        self.scroll_by(0, -n.to_i64().unwrap(), fill)
+    }
+
+    /// Move the screen contents right by `n` columns; see `scroll_up`.
+    pub fn scroll_right(&mut self, n: AddrT, fill: ColorT) -> Result<()> {
+        self.scroll_by(0, n.to_i64().unwrap(), fill)
+    }
+
+    /// Draw a line from `(i0, j0)` to `(i1, j1)`, both ends inclusive, clipped.
+    pub fn draw_line(&mut self, i0: AddrT, j0: AddrT, i1: AddrT, j1: AddrT, color: ColorT) {
+        self.draw_line_counted(i0, j0, i1, j1, color);
+    }
+
+    /// `draw_line`, returning how many pixels landed on-screen.
+    ///
+    /// The line is stepped in full and only the plotting is clipped, so the
+    /// visible part is exactly what `draw_line` draws; 0 means it missed
+    /// the screen entirely.
+    pub fn draw_line_counted(&mut self, i0: AddrT, j0: AddrT, i1: AddrT, j1: AddrT, color: ColorT) -> usize {
+        let (i0, j0) = (i0.to_i64().unwrap(), j0.to_i64().unwrap());
+        let (i1, j1) = (i1.to_i64().unwrap(), j1.to_i64().unwrap());
+        self.pixel_writer.begin_batch();
+        let written = self.line_clipped(i0, j0, i1, j1, color);
+        self.pixel_writer.end_batch();
+        written
+    }
+
+    /// `draw_line` with a dash pattern, counted from `(i0, j0)`; returns
+    /// how many pixels landed on-screen.
+    pub fn draw_line_dashed(&mut self, i0: AddrT, j0: AddrT, i1: AddrT, j1: AddrT, dash: Dash, color: ColorT) -> usize {
+        let (i0, j0) = (i0.to_i64().unwrap(), j0.to_i64().unwrap());
+        let (i1, j1) = (i1.to_i64().unwrap(), j1.to_i64().unwrap());
+        self.pixel_writer.begin_batch();
+        let mut written = 0;
+        for (k, (i, j)) in LineSteps::new(i0, j0, i1, j1).enumerate() {
+            if dash.is_on(k as u32) {
+                written += self.plot_clipped(i, j, color) as usize;
+            }
+        }
+        self.pixel_writer.end_batch();
+        written
+    }
+
+    /// `draw_line` `thickness` pixels wide, clipped.
+    ///
+    /// Every Bresenham step draws a span across the major axis: a column of
+    /// `thickness` pixels on mostly horizontal lines, a row on mostly
+    /// vertical ones, with the odd pixel of an even thickness below or to
+    /// the right. The width is measured along that axis, so diagonals come
+    /// out up to ~30% thinner than straight lines. A `thickness` of 0
+    /// draws nothing, 1 is `draw_line`.
+    pub fn draw_line_thick(&mut self, i0: AddrT, j0: AddrT, i1: AddrT, j1: AddrT, thickness: AddrT, color: ColorT) {
+        let (i0, j0) = (i0.to_i64().unwrap(), j0.to_i64().unwrap());
+        let (i1, j1) = (i1.to_i64().unwrap(), j1.to_i64().unwrap());
+        self.line_thick(i0, j0, i1, j1, thickness.to_i64().unwrap(), color);
+    }
+
+    /// Draw a gauge needle: a `thickness`-wide line `length` pixels from
+    /// the center `(ci, cj)` toward `angle_deg`, clipped.
+    ///
+    /// 0 degrees points up and angles run clockwise, as on a dial (and as
+    /// in `blit_rotated`): 90 points right, 180 down. The tip is rounded to
+    /// the nearest pixel from the same 1-degree Q14 table, so multiples of
+    /// 90 degrees land exactly `length` pixels out.
+    pub fn draw_needle(&mut self, ci: AddrT, cj: AddrT, length: AddrT, angle_deg: i32, thickness: AddrT, color: ColorT) {
+        let (ci, cj) = (ci.to_i64().unwrap(), cj.to_i64().unwrap());
+        let len = length.to_i64().unwrap();
+        let (sin, cos) = sin_cos_q14(angle_deg);
+        let round = |v: i64| (v + (1 << 13)).div_euclid(1 << 14);
+        let (ti, tj) = (ci - round(len * cos), cj + round(len * sin));
+        self.line_thick(ci, cj, ti, tj, thickness.to_i64().unwrap(), color);
+    }
+
+    /// Dashed 1-pixel outline of the `w` x `h` box at `(i, j)`.
+    ///
+    /// The pattern runs clockwise from the top-left corner and continues
+    /// around the corners without restarting, so with a `phase` stepping
+    /// by one (see `MarchingAnts`) every dash moves one pixel clockwise.
+    /// Clipped to `addr_range()`.
+    pub fn draw_rectangle_outline_dashed(&mut self, i: AddrT, j: AddrT, w: AddrT, h: AddrT, dash: Dash, color: ColorT) {
+        let (i0, j0) = (i.to_i64().unwrap(), j.to_i64().unwrap());
+        let (w, h) = (w.to_i64().unwrap(), h.to_i64().unwrap());
+        if w <= 0 || h <= 0 {
+            return;
+        }
+        let (i1, j1) = (i0 + h - 1, j0 + w - 1);
+        // Top, right, bottom, left; the later sides skip pixels already
+        // covered when the box is one pixel thin.
+        let top = (j0..=j1).map(|j| (i0, j));
+        let right = (i0 + 1..=i1).map(|i| (i, j1));
+        let bottom = (j0..j1).rev().filter(move |_| i1 > i0).map(|j| (i1, j));
+        let left = (i0 + 1..i1).rev().filter(move |_| j1 > j0).map(|i| (i, j0));
+        self.pixel_writer.begin_batch();
+        for (k, (i, j)) in top.chain(right).chain(bottom).chain(left).enumerate() {
+            if dash.is_on(k as u32) {
+                self.plot_clipped(i, j, color);
+            }
+        }
+        self.pixel_writer.end_batch();
+    }
+
+    /// Draw a horizontal line of `width` pixels starting at `(i, j)`, clipped.
+    pub fn draw_hline(&mut self, i: AddrT, j: AddrT, width: AddrT, color: ColorT) {
+        let (i, j) = (i.to_i64().unwrap(), j.to_i64().unwrap());
+        self.hspan(i, j, j + width.to_i64().unwrap() - 1, color);
+    }
+
+    /// Draw a vertical line of `height` pixels starting at `(i, j)`, clipped.
+    pub fn draw_vline(&mut self, i: AddrT, j: AddrT, height: AddrT, color: ColorT) {
+        let (i, j) = (i.to_i64().unwrap(), j.to_i64().unwrap());
+        self.vspan(i, i + height.to_i64().unwrap() - 1, j, color);
+    }
+
+    /// Fill row `i` across the whole `addr_range()`; nothing if `i` is off-screen.
+    pub fn fill_row(&mut self, i: AddrT, color: ColorT) {
+        let (_, (j_min, j_max)) = self.bounds_i64();
+        self.pixel_writer.begin_batch();
+        self.hspan(i.to_i64().unwrap(), j_min, j_max, color);
+        self.pixel_writer.end_batch();
+    }
+
+    /// Fill column `j` across the whole `addr_range()`; nothing if `j` is off-screen.
+    pub fn fill_col(&mut self, j: AddrT, color: ColorT) {
+        let ((i_min, i_max), _) = self.bounds_i64();
+        self.pixel_writer.begin_batch();
+        self.vspan(i_min, i_max, j.to_i64().unwrap(), color);
+        self.pixel_writer.end_batch();
+    }
+
+    /// Bring-up pattern for the address bus: one dot per address line.
+    ///
+    /// Fills the screen with `bg`, then lights a reference dot at the top-left
+    /// corner of `addr_range()` plus one dot per testable bit: at column
+    /// offsets 1, 2, 4, 8, ... along the top row (H lines) and row offsets
+    /// 1, 2, 4, ... down the left column (V lines). A bit is testable if its
+    /// offset still fits in the range. Returns the number of H and V lines
+    /// tested.
+    ///
+    /// On a good bus every dot sits at its power-of-two offset. A missing dot
+    /// means that line is stuck or open, so the dot landed on the reference
+    /// corner or another dot. Two dots at each other's positions mean those
+    /// lines are swapped, and a whole pattern shifted by a power of two
+    /// means a line is stuck high.
+    pub fn address_line_test(&mut self, fg: ColorT, bg: ColorT) -> (u32, u32) {
+        self.fill_screen(bg);
+        let ((i_min, i_max), (j_min, j_max)) = self.bounds_i64();
+        self.pixel_writer.begin_batch();
+        self.plot_clipped(i_min, j_min, fg);
+        let mut h_lines = 0;
+        while (1 << h_lines) <= j_max - j_min {
+            self.plot_clipped(i_min, j_min + (1 << h_lines), fg);
+            h_lines += 1;
+        }
+        let mut v_lines = 0;
+        while (1 << v_lines) <= i_max - i_min {
+            self.plot_clipped(i_min + (1 << v_lines), j_min, fg);
+            v_lines += 1;
+        }
+        self.pixel_writer.end_batch();
+        (h_lines, v_lines)
+    }
+
+    /// Draw full-screen grid lines through `(origin_i, origin_j)`.
+    ///
+    /// Horizontal lines are `spacing_i` rows apart and vertical lines
+    /// `spacing_j` columns apart, repeating in both directions from the origin
+    /// until the edges of `addr_range()`; a partial last cell is fine. An axis
+    /// whose spacing is 0 gets no lines.
+    pub fn draw_grid(
+        &mut self,
+        origin_i: AddrT,
+        origin_j: AddrT,
+        spacing_i: AddrT,
+        spacing_j: AddrT,
+        color: ColorT,
+    ) {
+        let ((i_min, i_max), (j_min, j_max)) = self.bounds_i64();
+        self.pixel_writer.begin_batch();
+
+        let step = spacing_i.to_i64().unwrap();
+        if step > 0 {
+            let mut i = i_min + (origin_i.to_i64().unwrap() - i_min).rem_euclid(step);
+            while i <= i_max {
+                self.hspan(i, j_min, j_max, color);
+                i += step;
+            }
+        }
+
+        let step = spacing_j.to_i64().unwrap();
+        if step > 0 {
+            let mut j = j_min + (origin_j.to_i64().unwrap() - j_min).rem_euclid(step);
+            while j <= j_max {
+                self.vspan(i_min, i_max, j, color);
+                j += step;
+            }
+        }
+        self.pixel_writer.end_batch();
+    }
+
+    /// Draw a plot marker centered on `(i, j)`, clipped to `addr_range()`.
+    ///
+    /// `size` is the marker's radius in pixels (see `MarkerStyle`); a `size`
+    /// of 0 draws just the center pixel for every style.
+    pub fn draw_marker(&mut self, i: AddrT, j: AddrT, style: MarkerStyle, size: AddrT, color: ColorT) {
+        let (ci, cj) = (i.to_i64().unwrap(), j.to_i64().unwrap());
+        let r = size.to_i64().unwrap();
+        if r == 0 {
+            self.plot_clipped(ci, cj, color);
+            return;
+        }
+        match style {
+            MarkerStyle::Dot => {
+                for di in -r..=r {
+                    for dj in -r..=r {
+                        if di * di + dj * dj <= r * r {
+                            self.plot_clipped(ci + di, cj + dj, color);
+                        }
+                    }
+                }
+            }
+            MarkerStyle::Cross => {
+                self.plot_clipped(ci, cj, color);
+                for d in 1..=r {
+                    self.plot_clipped(ci - d, cj - d, color);
+                    self.plot_clipped(ci - d, cj + d, color);
+                    self.plot_clipped(ci + d, cj - d, color);
+                    self.plot_clipped(ci + d, cj + d, color);
+                }
+            }
+            MarkerStyle::Plus => {
+                self.hspan(ci, cj - r, cj + r, color);
+                self.vspan(ci - r, ci - 1, cj, color);
+                self.vspan(ci + 1, ci + r, cj, color);
+            }
+            MarkerStyle::Square => {
+                self.hspan(ci - r, cj - r, cj + r, color);
+                self.hspan(ci + r, cj - r, cj + r, color);
+                self.vspan(ci - r + 1, ci + r - 1, cj - r, color);
+                self.vspan(ci - r + 1, ci + r - 1, cj + r, color);
+            }
+            MarkerStyle::Circle => {
+                // Pixels whose distance from the center rounds to `r`.
+                let (lo, hi) = ((2 * r - 1) * (2 * r - 1), (2 * r + 1) * (2 * r + 1));
+                for di in -r..=r {
+                    for dj in -r..=r {
+                        let d2 = 4 * (di * di + dj * dj);
+                        if d2 >= lo && d2 < hi {
+                            self.plot_clipped(ci + di, cj + dj, color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Draw a packed 1-bit-per-pixel image with its top-left corner at `(i, j)`.
+    ///
+    /// `data` is row-major, MSB-first within each byte, and every row is
+    /// padded to a whole byte (`(width + 7) / 8` bytes per row). Set bits are
+    /// drawn in `fg`, clear bits in `bg` unless `transparent_bg` is set.
+    /// Pixels outside `addr_range()` are skipped. Returns
+    /// `Error::BufferTooSmall`, without drawing, if `data` is shorter than
+    /// `height` padded rows.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_bitmap_1bpp(
+        &mut self,
+        i: AddrT,
+        j: AddrT,
+        width: AddrT,
+        height: AddrT,
+        data: &[u8],
+        fg: ColorT,
+        bg: ColorT,
+        transparent_bg: bool,
+    ) -> Result<()> {
+        let width = width.to_usize().unwrap();
+        let height = height.to_usize().unwrap();
+        let row_bytes = width.div_ceil(8);
+        if data.len() < row_bytes * height {
+            return Err(Error::BufferTooSmall);
+        }
+        let (i0, j0) = (i.to_i64().unwrap(), j.to_i64().unwrap());
+        for r in 0..height {
+            for c in 0..width {
+                let byte = data[r * row_bytes + c / 8];
+                let set = (byte >> (7 - (c % 8))) & 0x01 != 0;
+                if set {
+                    self.plot_clipped(i0 + r as i64, j0 + c as i64, fg);
+                } else if !transparent_bg {
+                    self.plot_clipped(i0 + r as i64, j0 + c as i64, bg);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Draw a tightly packed, row-major image with its top-left corner at `(i, j)`.
+    ///
+    /// Equivalent to `blit_strided` with `stride == width`.
+    pub fn blit(&mut self, i: AddrT, j: AddrT, width: AddrT, height: AddrT, data: &[ColorT]) -> Result<()> {
+        let stride = width.to_usize().unwrap();
+        self.blit_strided(i, j, width, height, data, stride)
+    }
+
+    /// Draw a `width` x `height` sub-image whose source rows are `stride` pixels apart.
+    ///
+    /// This copies a rectangle out of a larger buffer or sprite atlas without
+    /// repacking it: pass a `data` slice starting at the sub-image's first
+    /// pixel and the full source row length as `stride`. Destination pixels
+    /// outside `addr_range()` are skipped. Returns `Error::BufferTooSmall`,
+    /// without drawing, if `data` ends before the last source pixel.
+    pub fn blit_strided(
+        &mut self,
+        i: AddrT,
+        j: AddrT,
+        width: AddrT,
+        height: AddrT,
+        data: &[ColorT],
+        stride: usize,
+    ) -> Result<()> {
+        let width = width.to_usize().unwrap();
+        let height = height.to_usize().unwrap();
+        if width > 0 && height > 0 && data.len() < (height - 1) * stride + width {
+            return Err(Error::BufferTooSmall);
+        }
+        let (i0, j0) = (i.to_i64().unwrap(), j.to_i64().unwrap());
+        for r in 0..height {
+            for c in 0..width {
+                self.plot_clipped(i0 + r as i64, j0 + c as i64, data[r * stride + c]);
+            }
+        }
+        Ok(())
+    }
+
+    /// Read the `w` x `h` rectangle with top-left corner `(i0, j0)` into
+    /// `out`, packed row-major as `blit` expects.
+    ///
+    /// Saves what a popup or menu is about to cover so `blit` can put it
+    /// back afterwards; also handy for screenshots and undo buffers.
+    /// Entries for pixels outside `addr_range()` are left untouched, and
+    /// `blit` skips the same pixels, so capture-then-blit restores the area
+    /// exactly (with `EdgeMode::Clip`). Returns `Error::NotConfigured` on
+    /// backends without `read_pixel` and `Error::BufferTooSmall` if `out`
+    /// is shorter than `w * h`, both without reading.
+    pub fn capture_region(&self, i0: AddrT, j0: AddrT, w: AddrT, h: AddrT, out: &mut [ColorT]) -> Result<()> {
+        if !self.pixel_writer.capabilities().read_pixel {
+            return Err(Error::NotConfigured);
+        }
+        let (w, h) = (w.to_usize().unwrap(), h.to_usize().unwrap());
+        if out.len() < w * h {
+            return Err(Error::BufferTooSmall);
+        }
+        let (i0, j0) = (i0.to_i64().unwrap(), j0.to_i64().unwrap());
+        let Some(((i_lo, i_hi), (j_lo, j_hi))) = self.clip_box(i0, i0 + h as i64 - 1, j0, j0 + w as i64 - 1) else {
+            return Ok(());
+        };
+        for i in i_lo..=i_hi {
+            let r = (i.to_i64().unwrap() - i0) as usize;
+            for j in j_lo..=j_hi {
+                let c = (j.to_i64().unwrap() - j0) as usize;
+                if let Some(px) = self.pixel_writer.read_pixel(i, j) {
+                    out[r * w + c] = px;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Copy the `w` x `h` rectangle at `(src_i, src_j)` of another writer
+    /// onto this one, with its top-left corner at `(i, j)`.
+    ///
+    /// For rendering off-screen into a RAM writer and uploading the result,
+    /// mirroring one backend onto another, or replaying a golden frame onto
+    /// hardware. Source pixels outside `src.addr_range()`, or that `src`
+    /// cannot read back, are skipped; destination pixels are clipped (or
+    /// wrapped) like any other draw. Returns the number of pixels written,
+    /// or `Error::NotConfigured` if `src` lacks `read_pixel`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn blit_from_pixel_writer<SW: PixelWriter<AddrT, ColorT>>(
+        &mut self,
+        src: &SW,
+        src_i: AddrT,
+        src_j: AddrT,
+        w: AddrT,
+        h: AddrT,
+        i: AddrT,
+        j: AddrT,
+    ) -> Result<usize> {
+        if !src.capabilities().read_pixel {
+            return Err(Error::NotConfigured);
+        }
+        let ((si_min, si_max), (sj_min, sj_max)) = src.addr_range();
+        let (si0, sj0) = (src_i.to_i64().unwrap(), src_j.to_i64().unwrap());
+        let (di0, dj0) = (i.to_i64().unwrap(), j.to_i64().unwrap());
+        let rows = si0.max(si_min.to_i64().unwrap())..=(si0 + h.to_i64().unwrap() - 1).min(si_max.to_i64().unwrap());
+        let cols = sj0.max(sj_min.to_i64().unwrap())..=(sj0 + w.to_i64().unwrap() - 1).min(sj_max.to_i64().unwrap());
+        let cast = |v: i64| <AddrT as NumCast>::from(v).unwrap();
+        let mut written = 0;
+        self.pixel_writer.begin_batch();
+        for si in rows {
+            for sj in cols.clone() {
+                if let Some(px) = src.read_pixel(cast(si), cast(sj)) {
+                    written += self.plot_clipped(di0 + si - si0, dj0 + sj - sj0, px) as usize;
+                }
+            }
+        }
+        self.pixel_writer.end_batch();
+        Ok(written)
+    }
+
+    /// `blit`, skipping source pixels equal to `key` so the background shows through.
+    pub fn blit_transparent(
+        &mut self,
+        i: AddrT,
+        j: AddrT,
+        width: AddrT,
+        height: AddrT,
+        data: &[ColorT],
+        key: ColorT,
+    ) -> Result<()> {
+        let width = width.to_usize().unwrap();
+        let height = height.to_usize().unwrap();
+        if data.len() < width * height {
+            return Err(Error::BufferTooSmall);
+        }
+        let (i0, j0) = (i.to_i64().unwrap(), j.to_i64().unwrap());
+        for r in 0..height {
+            for c in 0..width {
+                let px = data[r * width + c];
+                if px != key {
+                    self.plot_clipped(i0 + r as i64, j0 + c as i64, px);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Draw a packed `width` x `height` sprite rotated clockwise by
+    /// `angle_deg` about its center, skipping pixels equal to `transparent`.
+    ///
+    /// `(i, j)` is where the unrotated sprite's top-left corner would be, so
+    /// 0 degrees draws exactly what `blit_transparent` does. Every
+    /// destination pixel in the rotated footprint (clipped to
+    /// `addr_range()`) is mapped back into the source and takes the nearest
+    /// source pixel, with sine/cosine from a 1-degree Q14 table.
+    ///
+    /// Multiples of 90 degrees are exact. Other angles show the usual
+    /// nearest-neighbor artifacts: stair-stepped edges, single source pixels
+    /// dropped or doubled, and one-pixel lines that may break up; sprites
+    /// with thick features rotate best. Returns `Error::BufferTooSmall`,
+    /// without drawing, if `data` is shorter than `width * height`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn blit_rotated(
+        &mut self,
+        i: AddrT,
+        j: AddrT,
+        width: AddrT,
+        height: AddrT,
+        data: &[ColorT],
+        angle_deg: i32,
+        transparent: ColorT,
+    ) -> Result<()> {
+        let (w, h) = (width.to_i64().unwrap(), height.to_i64().unwrap());
+        if data.len() < (w * h) as usize {
+            return Err(Error::BufferTooSmall);
+        }
+        if w == 0 || h == 0 {
+            return Ok(());
+        }
+        let (sin, cos) = sin_cos_q14(angle_deg);
+        let one = 1i64 << 14;
+        // Offsets from the sprite center are kept doubled (`u = 2 * r - (h - 1)`)
+        // so the center of even-sized sprites stays on the integer grid.
+        // Half extents of the rotated footprint, doubled, rounded up.
+        let ext_i = ((h - 1) * cos.abs() + (w - 1) * sin.abs() + one - 1) / one;
+        let ext_j = ((h - 1) * sin.abs() + (w - 1) * cos.abs() + one - 1) / one;
+        let (i0, j0) = (i.to_i64().unwrap(), j.to_i64().unwrap());
+        let ((i_min, i_max), (j_min, j_max)) = self.bounds_i64();
+        let r_lo = (i_min - i0).max((h - 1 - ext_i).div_euclid(2));
+        let r_hi = (i_max - i0).min((h - 1 + ext_i).div_euclid(2) + 1);
+        let c_lo = (j_min - j0).max((w - 1 - ext_j).div_euclid(2));
+        let c_hi = (j_max - j0).min((w - 1 + ext_j).div_euclid(2) + 1);
+        // Nearest source index from a doubled, Q14-scaled offset.
+        let src = |s: i64, n: i64| (s + (n - 1) * one + one).div_euclid(2 * one);
+        for r in r_lo..=r_hi {
+            let u = 2 * r - (h - 1);
+            for c in c_lo..=c_hi {
+                let v = 2 * c - (w - 1);
+                let sr = src(u * cos - v * sin, h);
+                let sc = src(u * sin + v * cos, w);
+                if !(0..h).contains(&sr) || !(0..w).contains(&sc) {
+                    continue;
+                }
+                let px = data[(sr * w + sc) as usize];
+                if px != transparent {
+                    self.plot_clipped(i0 + r, j0 + c, px);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Clipped `((i0, i1), (j0, j1))` that `draw_rectangle(i, j, width, height, ..)`
+    /// would touch, or `None` if it is empty or entirely off-screen.
+    pub fn rect_bounds(
+        &self,
+        i: AddrT,
+        j: AddrT,
+        width: AddrT,
+        height: AddrT,
+    ) -> Option<((AddrT, AddrT), (AddrT, AddrT))> {
+        let (i, j) = (i.to_i64().unwrap(), j.to_i64().unwrap());
+        let (w, h) = (width.to_i64().unwrap(), height.to_i64().unwrap());
+        self.clip_box(i, i + h - 1, j, j + w - 1)
+    }
+
+    /// Clipped `((i0, i1), (j0, j1))` of a circle of radius `r` centered on
+    /// `(ci, cj)`, e.g. a `Dot` or `Circle` marker of size `r`.
+    pub fn circle_bounds(&self, ci: AddrT, cj: AddrT, r: AddrT) -> Option<((AddrT, AddrT), (AddrT, AddrT))> {
+        let (ci, cj, r) = (ci.to_i64().unwrap(), cj.to_i64().unwrap(), r.to_i64().unwrap());
+        self.clip_box(ci - r, ci + r, cj - r, cj + r)
+    }
+
+    /// Count framebuffer pixels per brightness bin.
+    ///
+    /// `color_range()` is split into `BINS` equal-width bins, lowest first;
+    /// use `DEFAULT_HISTOGRAM_BINS` for one bin per 4-bit DAC level. Pixels
+    /// are read with `PixelWriter::read_pixel`, so on write-only backends
+    /// every bin is 0. Values outside `color_range()` are clamped into the
+    /// first or last bin.
+    pub fn brightness_histogram<const BINS: usize>(&self) -> [u32; BINS] {
+        let mut hist = [0u32; BINS];
+        if BINS == 0 {
+            return hist;
+        }
+        let (c_min, c_max) = self.pixel_writer.color_range();
+        let c_min = c_min.to_i64().unwrap();
+        let span = c_max.to_i64().unwrap() - c_min + 1;
+        for (i, j) in self.pixel_writer.addr_iter() {
+            let Some(color) = self.pixel_writer.read_pixel(i, j) else {
+                continue;
+            };
+            let offset = (color.to_i64().unwrap() - c_min).clamp(0, span - 1);
+            let bin = (offset as i128 * BINS as i128 / span as i128) as usize;
+            hist[bin] += 1;
+        }
+        hist
+    }
+
+    /// Darkest and brightest color in the framebuffer, read back with
+    /// `read_pixel`; `None` if nothing can be read.
+    pub fn used_color_range(&self) -> Option<(ColorT, ColorT)> {
+        let colors = self.pixel_writer.addr_iter().filter_map(|(i, j)| self.pixel_writer.read_pixel(i, j));
+        colors.fold(None, |acc, c| match acc {
+            None => Some((c, c)),
+            Some((lo, hi)) => Some((lo.min(c), hi.max(c))),
+        })
+    }
+
+    /// Stretch the framebuffer linearly so its darkest pixel becomes the
+    /// `color_range()` minimum and its brightest the maximum.
+    ///
+    /// For low-contrast generated content (plasma, noise, plots of a narrow
+    /// signal) that would otherwise use only a few of the DAC's levels.
+    /// Rounds to nearest. Returns whether anything was rewritten: a buffer
+    /// that already spans the full range, or holds a single color, is left
+    /// alone. Two passes over the whole buffer; returns
+    /// `Error::NotConfigured` on backends without `read_pixel`.
+    pub fn auto_contrast(&mut self) -> Result<bool> {
+        if !self.pixel_writer.capabilities().read_pixel {
+            return Err(Error::NotConfigured);
+        }
+        let Some((lo, hi)) = self.used_color_range() else {
+            return Ok(false);
+        };
+        let (c_min, c_max) = self.pixel_writer.color_range();
+        if lo == hi || (lo <= c_min && hi >= c_max) {
+            return Ok(false);
+        }
+        let (lo, hi) = (lo.to_i64().unwrap(), hi.to_i64().unwrap());
+        let (c_min, c_max) = (c_min.to_i64().unwrap(), c_max.to_i64().unwrap());
+        let (src, dst) = (hi - lo, c_max - c_min);
+        self.pixel_writer.begin_batch();
+        for (i, j) in self.pixel_writer.addr_iter() {
+            let Some(color) = self.pixel_writer.read_pixel(i, j) else {
+                continue;
+            };
+            let scaled = c_min + ((color.to_i64().unwrap() - lo) * dst * 2 + src) / (2 * src);
+            self.pixel_writer.write_pixel(i, j, <ColorT as NumCast>::from(scaled.clamp(c_min, c_max)).unwrap());
+        }
+        self.pixel_writer.end_batch();
+        Ok(true)
+    }
+
+    /// Flood-fill the 4-connected region of pixels matching the color at
+    /// `(i, j)` with `color`, e.g. to paint a shape drawn as an outline.
+    ///
+    /// Scanline algorithm: each run is filled with one `fill_rect`, and the
+    /// rows above and below it are queued on `stack` as `Span`s, so memory
+    /// is fixed by `CAP` instead of the region's size. Returns `Ok(true)`
+    /// for a complete fill. If the region needs more than `CAP` pending
+    /// spans the extra work is dropped (see `ScanlineStack`), the fill stops
+    /// short but stays inside the region, and the result is `Ok(false)`.
+    /// A seed outside `addr_range()` or already `color` fills nothing. A
+    /// `color` that reads back as the seed's color once written (e.g. a
+    /// backend that drops bits) would be found again forever, so the fill
+    /// stops after the first run with a warning and `Ok(false)`. Returns
+    /// `Error::NotConfigured` on backends without `read_pixel`.
+    pub fn flood_fill<const CAP: usize>(
+        &mut self,
+        i: AddrT,
+        j: AddrT,
+        color: ColorT,
+        stack: &mut ScanlineStack<CAP>,
+    ) -> Result<bool> {
+        if !self.pixel_writer.capabilities().read_pixel {
+            return Err(Error::NotConfigured);
+        }
+        let ((i_min, i_max), (j_min, j_max)) = self.visible_i64();
+        let (i, j) = (i.to_i64().unwrap(), j.to_i64().unwrap());
+        if !(i_min..=i_max).contains(&i) || !(j_min..=j_max).contains(&j) {
+            return Ok(true);
+        }
+        let cast = |v: i64| <AddrT as NumCast>::from(v).unwrap();
+        let Some(target) = self.pixel_writer.read_pixel(cast(i), cast(j)) else {
+            return Ok(true);
+        };
+        if target == color {
+            return Ok(true);
+        }
+        let matches = |d: &Self, i: i64, j: i64| d.pixel_writer.read_pixel(cast(i), cast(j)) == Some(target);
+        stack.clear();
+        stack.push(Span { i, j0: j, j1: j });
+        let mut aborted = false;
+        self.pixel_writer.begin_batch();
+        'fill: while let Some(span) = stack.pop() {
+            if !(i_min..=i_max).contains(&span.i) {
+                continue;
+            }
+            let mut j = span.j0;
+            while j <= span.j1 {
+                if !matches(self, span.i, j) {
+                    j += 1;
+                    continue;
+                }
+                let (mut lo, mut hi) = (j, j);
+                while lo > j_min && matches(self, span.i, lo - 1) {
+                    lo -= 1;
+                }
+                while hi < j_max && matches(self, span.i, hi + 1) {
+                    hi += 1;
+                }
+                self.fill_box(span.i, span.i, lo, hi, color);
+                if matches(self, span.i, lo) {
+                    aborted = true;
+                    break 'fill;
+                }
+                stack.push(Span { i: span.i - 1, j0: lo, j1: hi });
+                stack.push(Span { i: span.i + 1, j0: lo, j1: hi });
+                j = hi + 2;
+            }
+        }
+        self.pixel_writer.end_batch();
+        if aborted {
+            defmt::warn!("flood_fill: fill color reads back as the target, fill is partial");
+            return Ok(false);
+        }
+        if stack.overflowed() {
+            defmt::warn!("flood_fill: more than {} pending spans, fill is partial", CAP);
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    /// Intersect the inclusive box `[i0, i1] x [j0, j1]` with `addr_range()`
+    /// and the clip rect.
+    fn clip_box(&self, i0: i64, i1: i64, j0: i64, j1: i64) -> Option<((AddrT, AddrT), (AddrT, AddrT))> {
+        let ((i_min, i_max), (j_min, j_max)) = self.visible_i64();
+        let (i0, i1) = (i0.max(i_min), i1.min(i_max));
+        let (j0, j1) = (j0.max(j_min), j1.min(j_max));
+        if i0 > i1 || j0 > j1 {
+            return None;
+        }
+        let cast = |v: i64| <AddrT as NumCast>::from(v).unwrap();
+        Some(((cast(i0), cast(i1)), (cast(j0), cast(j1))))
+    }
+
+    /// Fill the inclusive box `[i0, i1] x [j0, j1]`: clipped, or split into
+    /// up to four wrapped pieces in `EdgeMode::Wrap`.
+    pub(crate) fn fill_box(&mut self, i0: i64, i1: i64, j0: i64, j1: i64, color: ColorT) {
+        if i0 > i1 || j0 > j1 {
+            return;
+        }
+        if self.edge == EdgeMode::Clip {
+            if let Some(range) = self.clip_box(i0, i1, j0, j1) {
+                self.pixel_writer.fill_rect(range, color);
+            }
+            return;
+        }
+        let ((i_min, i_max), (j_min, j_max)) = self.bounds_i64();
+        let rows = wrap_span(i0, i1, i_min, i_max);
+        let cols = wrap_span(j0, j1, j_min, j_max);
+        for (r0, r1) in rows.into_iter().flatten() {
+            for (c0, c1) in cols.into_iter().flatten() {
+                if let Some(range) = self.clip_box(r0, r1, c0, c1) {
+                    self.pixel_writer.fill_rect(range, color);
+                }
+            }
+        }
+    }
+
+    /// `addr_range()` widened to `i64`, so callers can clip without overflow.
+    pub(crate) fn bounds_i64(&self) -> ((i64, i64), (i64, i64)) {
+        let ((i_min, i_max), (j_min, j_max)) = self.pixel_writer.addr_range();
+        (
+            (i_min.to_i64().unwrap(), i_max.to_i64().unwrap()),
+            (j_min.to_i64().unwrap(), j_max.to_i64().unwrap()),
+        )
+    }
+
+    /// `bounds_i64()` narrowed to the clip rect; empty (a minimum above its
+    /// maximum) if the clip lies off-screen.
+    fn visible_i64(&self) -> ((i64, i64), (i64, i64)) {
+        let ((i_min, i_max), (j_min, j_max)) = self.bounds_i64();
+        let Some(((ci0, ci1), (cj0, cj1))) = self.clip else {
+            return ((i_min, i_max), (j_min, j_max));
+        };
+        let v = |a: AddrT| a.to_i64().unwrap();
+        (
+            (i_min.max(v(ci0.min(ci1))), i_max.min(v(ci0.max(ci1)))),
+            (j_min.max(v(cj0.min(cj1))), j_max.min(v(cj0.max(cj1)))),
+        )
+    }
+
+    /// Bresenham line from `(i0, j0)` to `(i1, j1)` (inclusive), clipped;
+    /// returns the number of pixels written.
+    fn line_clipped(&mut self, i0: i64, j0: i64, i1: i64, j1: i64, color: ColorT) -> usize {
+        let mut written = 0;
+        for (i, j) in LineSteps::new(i0, j0, i1, j1) {
+            written += self.plot_clipped(i, j, color) as usize;
+        }
+        written
+    }
+
+    /// Signed-coordinate `draw_line_thick`, batched.
+    fn line_thick(&mut self, i0: i64, j0: i64, i1: i64, j1: i64, thickness: i64, color: ColorT) {
+        if thickness <= 0 {
+            return;
+        }
+        let (lo, hi) = (-(thickness - 1) / 2, thickness / 2);
+        let steep = (i1 - i0).abs() > (j1 - j0).abs();
+        self.pixel_writer.begin_batch();
+        for (i, j) in LineSteps::new(i0, j0, i1, j1) {
+            if steep {
+                self.hspan(i, j + lo, j + hi, color);
+            } else {
+                self.vspan(i + lo, i + hi, j, color);
+            }
+        }
+        self.pixel_writer.end_batch();
+    }
+
+    /// The pixels `draw_line` would write, in drawing order, without
+    /// writing anything.
+    ///
+    /// Clipped (or wrapped) exactly like `draw_line`, so
+    /// `line_iter(..).count()` is what `draw_line_counted` returns. For
+    /// hit-testing a shape or working out what a redraw will dirty. The
+    /// iterator holds a copy of `addr_range()`, the clip rect and the edge
+    /// mode, not a borrow of the drawer.
+    pub fn line_iter(&self, i0: AddrT, j0: AddrT, i1: AddrT, j1: AddrT) -> impl Iterator<Item = (AddrT, AddrT)> + use<AddrT, ColorT, PW> {
+        let map = self.screen_map();
+        let (i0, j0) = (i0.to_i64().unwrap(), j0.to_i64().unwrap());
+        let (i1, j1) = (i1.to_i64().unwrap(), j1.to_i64().unwrap());
+        LineSteps::new(i0, j0, i1, j1).filter_map(move |(i, j)| map.apply(i, j))
+    }
+
+    /// The pixels `draw_rectangle_corners` would fill, row by row, without
+    /// writing anything; see `line_iter`.
+    pub fn rect_iter(&self, i0: AddrT, j0: AddrT, i1: AddrT, j1: AddrT) -> impl Iterator<Item = (AddrT, AddrT)> + use<AddrT, ColorT, PW> {
+        let map = self.screen_map();
+        let (i0, i1) = (i0.min(i1).to_i64().unwrap(), i0.max(i1).to_i64().unwrap());
+        let (j0, j1) = (j0.min(j1).to_i64().unwrap(), j0.max(j1).to_i64().unwrap());
+        let ((i_min, i_max), (j_min, j_max)) = map.bounds;
+        let ((vi_min, vi_max), (vj_min, vj_max)) = map.visible;
+        // Visit each screen pixel at most once: clip the box, or in wrap
+        // mode cut it down to one screen's worth before wrapping.
+        let (i0, i1, j0, j1) = match map.edge {
+            EdgeMode::Clip => (i0.max(vi_min), i1.min(vi_max), j0.max(vj_min), j1.min(vj_max)),
+            EdgeMode::Wrap => (i0, i1.min(i0 + i_max - i_min), j0, j1.min(j0 + j_max - j_min)),
+        };
+        (i0..=i1)
+            .flat_map(move |i| (j0..=j1).map(move |j| (i, j)))
+            .filter_map(move |(i, j)| map.apply(i, j))
+    }
+
+    /// Shift every pixel by `(di, dj)`, filling the uncovered area with `fill`.
+    ///
+    /// Destinations are visited moving away from the source side, so each
+    /// source pixel is read before it is overwritten.
+    fn scroll_by(&mut self, di: i64, dj: i64, fill: ColorT) -> Result<()> {
+        if !self.pixel_writer.capabilities().read_pixel {
+            return Err(Error::NotConfigured);
+        }
+        if di == 0 && dj == 0 {
+            return Ok(());
+        }
+        let ((i_min, i_max), (j_min, j_max)) = self.bounds_i64();
+        let cast = |v: i64| <AddrT as NumCast>::from(v).unwrap();
+        self.pixel_writer.begin_batch();
+        for ii in 0..=(i_max - i_min) {
+            let i = if di > 0 { i_max - ii } else { i_min + ii };
+            for jj in 0..=(j_max - j_min) {
+                let j = if dj > 0 { j_max - jj } else { j_min + jj };
+                let (si, sj) = (i - di, j - dj);
+                let color = if si < i_min || si > i_max || sj < j_min || sj > j_max {
+                    fill
+                } else {
+                    self.pixel_writer.read_pixel(cast(si), cast(sj)).unwrap_or(fill)
+                };
+                self.pixel_writer.write_pixel(cast(i), cast(j), color);
+            }
+        }
+        self.pixel_writer.end_batch();
+        Ok(())
+    }
+
+    /// Draw row `i` from column `j0` to `j1` (inclusive), clipped.
+    fn hspan(&mut self, i: i64, j0: i64, j1: i64, color: ColorT) {
+        for j in j0..=j1 {
+            self.plot_clipped(i, j, color);
+        }
+    }
+
+    /// Draw column `j` from row `i0` to `i1` (inclusive), clipped.
+    fn vspan(&mut self, i0: i64, i1: i64, j: i64, color: ColorT) {
+        for i in i0..=i1 {
+            self.plot_clipped(i, j, color);
+        }
+    }
+
+    /// Write a pixel given in signed coordinates, skipping it if off-screen
+    /// (wrapping it in `EdgeMode::Wrap`).
+    ///
+    /// Returns whether the pixel was written.
+    pub(crate) fn plot_clipped(&mut self, i: i64, j: i64, color: ColorT) -> bool {
+        let Some((i, j)) = self.screen_map().apply(i, j) else {
+            return false;
+        };
         self.pixel_writer.write_pixel(i, j, color);
+        true
+    }
+
+    fn screen_map(&self) -> ScreenMap {
+        ScreenMap { bounds: self.bounds_i64(), visible: self.visible_i64(), edge: self.edge }
+    }
+}
+
+/// RAII frame returned by `Drawer::frame`; presents the frame on drop.
+pub struct FrameGuard<'d, 'a, AddrT: PrimInt, ColorT: PrimInt, PW>
+where
+    PW: PixelWriter<AddrT, ColorT>,
+{
+    drawer: &'d mut Drawer<'a, AddrT, ColorT, PW>,
+}
+
+impl<'a, AddrT: PrimInt, ColorT: PrimInt, PW> core::ops::Deref for FrameGuard<'_, 'a, AddrT, ColorT, PW>
+where
+    PW: PixelWriter<AddrT, ColorT>,
+{
+    type Target = Drawer<'a, AddrT, ColorT, PW>;
+
+    fn deref(&self) -> &Self::Target {
+        self.drawer
+    }
+}
+
+impl<AddrT: PrimInt, ColorT: PrimInt, PW> core::ops::DerefMut for FrameGuard<'_, '_, AddrT, ColorT, PW>
+where
+    PW: PixelWriter<AddrT, ColorT>,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.drawer
+    }
+}
+
+impl<AddrT: PrimInt, ColorT: PrimInt, PW> Drop for FrameGuard<'_, '_, AddrT, ColorT, PW>
+where
+    PW: PixelWriter<AddrT, ColorT>,
+{
+    fn drop(&mut self) {
+        if self.drawer.buffered {
+            self.drawer.present();
+        }
     }
 }
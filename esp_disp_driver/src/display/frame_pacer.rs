@@ -0,0 +1,47 @@
+//! Frame-rate limiter for producer tasks.
+//!
+//! Call `pace().await` once at the end of every frame (typically right after
+//! `swap()` / `present()`); it sleeps just long enough to keep the producer at
+//! the target FPS instead of a fixed delay that ignores drawing time.
+
+use embassy_time::{Duration, Instant, Timer};
+
+pub struct FramePacer {
+    /// Target time between frames; `None` means unlimited.
+    period: Option<Duration>,
+    /// Deadline of the previous frame, set by the first `pace()` call.
+    last: Option<Instant>,
+}
+
+impl FramePacer {
+    /// Create a pacer targeting `fps` frames per second (0 = unlimited).
+    pub fn new(fps: u32) -> Self {
+        let mut pacer = Self { period: None, last: None };
+        pacer.target_fps(fps);
+        pacer
+    }
+
+    /// Change the target rate. Takes effect from the next `pace()` call.
+    pub fn target_fps(&mut self, fps: u32) {
+        self.period = if fps == 0 { None } else { Some(Duration::from_hz(fps as u64)) };
+    }
+
+    /// Wait until the next frame is due.
+    ///
+    /// If the producer is behind schedule this returns immediately and
+    /// restarts the schedule from now, rather than rushing frames to catch up.
+    pub async fn pace(&mut self) {
+        let now = Instant::now();
+        let (Some(period), Some(last)) = (self.period, self.last) else {
+            self.last = Some(now);
+            return;
+        };
+        let deadline = last + period;
+        if now < deadline {
+            Timer::at(deadline).await;
+            self.last = Some(deadline);
+        } else {
+            self.last = Some(now);
+        }
+    }
+}
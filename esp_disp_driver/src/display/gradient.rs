@@ -0,0 +1,109 @@
+//! Multi-stop color gradients, in integer math.
+//!
+//! A `Gradient` maps a position `t` in 0..=255 to a color by interpolating
+//! between its stops. It is the one place gradient math lives: the
+//! `fill_gradient_*` drawers sample it per row or column, and `sample`
+//! turns it into a lookup table, e.g. a palette to cycle through.
+
+use crate::display::drawer::Drawer;
+use crate::display::pix_writer::PixelWriter;
+use crate::error::{Error, Result};
+use crate::utils::{Fixed, PrimInt};
+use num_traits::NumCast;
+
+/// How colors blend between two stops.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, defmt::Format)]
+pub enum Interp {
+    /// Straight line between the stops.
+    #[default]
+    Linear,
+    /// Cubic smoothstep: eases out of one stop and into the next, so
+    /// multi-stop gradients show no visible kink at the stops.
+    Smooth,
+}
+
+/// Color stops `(position, color)` with positions strictly increasing.
+///
+/// Positions before the first stop take its color, positions after the
+/// last take the last one. Interpolated colors are rounded to nearest, so
+/// each stop's own position yields exactly its color.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Gradient<ColorT, const S: usize> {
+    stops: [(u8, ColorT); S],
+    interp: Interp,
+}
+
+impl<ColorT: PrimInt, const S: usize> Gradient<ColorT, S> {
+    /// Linear gradient through `stops`. Returns `Error::OutOfRange` if
+    /// there are none or their positions are not strictly increasing.
+    pub fn new(stops: [(u8, ColorT); S]) -> Result<Self> {
+        if S == 0 || stops.windows(2).any(|w| w[0].0 >= w[1].0) {
+            return Err(Error::OutOfRange);
+        }
+        Ok(Self { stops, interp: Interp::Linear })
+    }
+
+    pub fn with_interp(mut self, interp: Interp) -> Self {
+        self.interp = interp;
+        self
+    }
+
+    pub fn stops(&self) -> &[(u8, ColorT); S] {
+        &self.stops
+    }
+
+    /// Color at position `t`.
+    pub fn color_at(&self, t: u8) -> ColorT {
+        let Some(k) = self.stops.iter().position(|&(p, _)| p >= t) else {
+            return self.stops[S - 1].1;
+        };
+        if k == 0 || self.stops[k].0 == t {
+            return self.stops[k].1;
+        }
+        let ((p0, c0), (p1, c1)) = (self.stops[k - 1], self.stops[k]);
+        let (c0, c1) = (c0.to_i64().unwrap(), c1.to_i64().unwrap());
+        // Blend weight in 0..1.
+        let w = Fixed::from_ratio((t - p0) as i32, (p1 - p0) as i32);
+        let w = match self.interp {
+            Interp::Linear => w,
+            Interp::Smooth => w * w * (Fixed::from_int(3) - w * 2),
+        };
+        <ColorT as NumCast>::from(c0 + w.scale(c1 - c0)).unwrap()
+    }
+
+    /// `K` colors evenly spaced from position 0 to 255, both included.
+    pub fn sample<const K: usize>(&self) -> [ColorT; K] {
+        core::array::from_fn(|k| self.color_at(spread(k, K)))
+    }
+}
+
+/// Position of step `k` of `n` spread over 0..=255.
+fn spread(k: usize, n: usize) -> u8 {
+    if n <= 1 { 0 } else { (k * 255 / (n - 1)) as u8 }
+}
+
+impl<'a, AddrT: PrimInt, ColorT: PrimInt, PW> Drawer<'a, AddrT, ColorT, PW>
+where
+    PW: PixelWriter<AddrT, ColorT>,
+{
+    /// Fill the `w` x `h` box at `(i, j)` with `gradient` running left to
+    /// right: the left column gets position 0, the right one 255.
+    pub fn fill_gradient_h<const S: usize>(&mut self, i: AddrT, j: AddrT, w: AddrT, h: AddrT, gradient: &Gradient<ColorT, S>) {
+        let (i, j) = (i.to_i64().unwrap(), j.to_i64().unwrap());
+        let (w, h) = (w.to_usize().unwrap(), h.to_usize().unwrap());
+        for c in 0..w {
+            let jc = j + c as i64;
+            self.fill_box(i, i + h as i64 - 1, jc, jc, gradient.color_at(spread(c, w)));
+        }
+    }
+
+    /// `fill_gradient_h` running top to bottom.
+    pub fn fill_gradient_v<const S: usize>(&mut self, i: AddrT, j: AddrT, w: AddrT, h: AddrT, gradient: &Gradient<ColorT, S>) {
+        let (i, j) = (i.to_i64().unwrap(), j.to_i64().unwrap());
+        let (w, h) = (w.to_usize().unwrap(), h.to_usize().unwrap());
+        for r in 0..h {
+            let ir = i + r as i64;
+            self.fill_box(ir, ir, j, j + w as i64 - 1, gradient.color_at(spread(r, h)));
+        }
+    }
+}
@@ -0,0 +1,66 @@
+//! Built-in 8x8 icons for status bars and menus.
+//!
+//! Bitmaps use the `draw_bitmap_1bpp` layout (one MSB-first byte per row)
+//! and are drawn with a transparent background, like text glyphs.
+
+use crate::display::drawer::Drawer;
+use crate::display::pix_writer::PixelWriter;
+use crate::utils::PrimInt;
+use num_traits::NumCast;
+
+/// Width and height of every icon in pixels.
+pub const ICON_SIZE: u8 = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum Icon {
+    ArrowUp,
+    ArrowDown,
+    ArrowLeft,
+    ArrowRight,
+    Check,
+    Cross,
+    /// Horizontal cell outline with the terminal on the right; fill in the
+    /// charge level with `fill_rect` inside it.
+    Battery,
+    Wifi,
+}
+
+impl Icon {
+    pub const ALL: [Icon; 8] = [
+        Icon::ArrowUp,
+        Icon::ArrowDown,
+        Icon::ArrowLeft,
+        Icon::ArrowRight,
+        Icon::Check,
+        Icon::Cross,
+        Icon::Battery,
+        Icon::Wifi,
+    ];
+
+    /// Rows of the icon, top first.
+    pub const fn bitmap(self) -> &'static [u8; ICON_SIZE as usize] {
+        match self {
+            Icon::ArrowUp => &[0x18, 0x3C, 0x7E, 0xFF, 0x18, 0x18, 0x18, 0x18],
+            Icon::ArrowDown => &[0x18, 0x18, 0x18, 0x18, 0xFF, 0x7E, 0x3C, 0x18],
+            Icon::ArrowLeft => &[0x10, 0x30, 0x70, 0xFF, 0xFF, 0x70, 0x30, 0x10],
+            Icon::ArrowRight => &[0x08, 0x0C, 0x0E, 0xFF, 0xFF, 0x0E, 0x0C, 0x08],
+            Icon::Check => &[0x00, 0x01, 0x03, 0x86, 0xCC, 0x78, 0x30, 0x00],
+            Icon::Cross => &[0xC3, 0xE7, 0x7E, 0x3C, 0x3C, 0x7E, 0xE7, 0xC3],
+            Icon::Battery => &[0x00, 0xFC, 0x84, 0x87, 0x87, 0x84, 0xFC, 0x00],
+            Icon::Wifi => &[0x7E, 0x81, 0x3C, 0x42, 0x00, 0x18, 0x18, 0x00],
+        }
+    }
+}
+
+impl<'a, AddrT: PrimInt, ColorT: PrimInt, PW> Drawer<'a, AddrT, ColorT, PW>
+where
+    PW: PixelWriter<AddrT, ColorT>,
+{
+    /// Draw `icon` in `color` with its top-left corner at `(i, j)`, leaving
+    /// clear pixels untouched. Clipped to `addr_range()`.
+    pub fn draw_icon(&mut self, i: AddrT, j: AddrT, icon: Icon, color: ColorT) {
+        let size = <AddrT as NumCast>::from(ICON_SIZE).unwrap();
+        // A bitmap always holds `ICON_SIZE` rows, so this cannot be short.
+        let _ = self.draw_bitmap_1bpp(i, j, size, size, icon.bitmap(), color, color, true);
+    }
+}
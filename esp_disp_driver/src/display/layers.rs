@@ -0,0 +1,93 @@
+//! Software layers composited into a single framebuffer.
+//!
+//! Only built with the `layers` feature. A `LayerStack` keeps `L` extra
+//! framebuffers (e.g. background, sprites, HUD) that are drawn into
+//! independently and flattened with `composite` into the buffer the scan
+//! loop shows, so a HUD can change without redrawing the background:
+//!
+//! ```ignore
+//! fb.with_inactive(|out| stack.composite(out));
+//! fb.swap();
+//! ```
+//!
+//! RAM: every layer is a full `W * H` byte frame (30,351 bytes at the
+//! bus_dac's 201x151), on top of the two frames of the `DoubleFb`; see
+//! `utils::framebuffer_bytes`. The stack is usually too large for a task
+//! stack, so keep it in a `static` (e.g. a `StaticCell`).
+
+use crate::display::backend::bus_dac::{FB_HEIGHT, FB_WIDTH};
+
+/// One layer of a `LayerStack`.
+pub struct Layer<const W: usize, const H: usize> {
+    /// Row-major pixels, indexed `frame[i][j]`.
+    pub frame: [[u8; W]; H],
+    /// Hidden layers are skipped by `composite`.
+    pub visible: bool,
+    /// Pixels of this value let lower layers show through.
+    pub color_key: Option<u8>,
+}
+
+/// `L` layers composited bottom (index 0) to top.
+pub struct LayerStack<const L: usize, const W: usize = FB_WIDTH, const H: usize = FB_HEIGHT> {
+    pub layers: [Layer<W, H>; L],
+    /// Output color where no visible layer has an opaque pixel.
+    pub background: u8,
+}
+
+impl<const L: usize, const W: usize, const H: usize> LayerStack<L, W, H> {
+    /// All layers visible, cleared to 0 and without a color key.
+    pub const fn new() -> Self {
+        Self {
+            layers: [const { Layer { frame: [[0; W]; H], visible: true, color_key: None } }; L],
+            background: 0,
+        }
+    }
+
+    /// Pixels of layer `idx`, for drawing.
+    pub fn layer_mut(&mut self, idx: usize) -> &mut [[u8; W]; H] {
+        &mut self.layers[idx].frame
+    }
+
+    pub fn set_visible(&mut self, idx: usize, visible: bool) {
+        self.layers[idx].visible = visible;
+    }
+
+    /// Make `key` transparent on layer `idx`, or the layer opaque with `None`.
+    pub fn set_color_key(&mut self, idx: usize, key: Option<u8>) {
+        self.layers[idx].color_key = key;
+    }
+
+    /// Flatten the visible layers into `out`, bottom to top.
+    ///
+    /// Every pixel of `out` is overwritten: it takes the topmost visible
+    /// layer's value that is not that layer's color key, or `background`.
+    /// Opaque layers are copied row by row, so only the layers from the
+    /// topmost visible opaque one upwards cost per-pixel work.
+    pub fn composite(&self, out: &mut [[u8; W]; H]) {
+        let visible = || self.layers.iter().enumerate().filter(|(_, l)| l.visible);
+        let base = visible()
+            .rev()
+            .find(|(_, l)| l.color_key.is_none())
+            .map(|(idx, _)| idx);
+        match base {
+            Some(idx) => *out = self.layers[idx].frame,
+            None => out.iter_mut().for_each(|row| row.fill(self.background)),
+        }
+        for (_, layer) in visible().filter(|(idx, _)| base.is_none_or(|b| *idx > b)) {
+            let Some(key) = layer.color_key else { continue };
+            for (dst, src) in out.iter_mut().zip(layer.frame.iter()) {
+                for (d, &s) in dst.iter_mut().zip(src.iter()) {
+                    if s != key {
+                        *d = s;
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<const L: usize, const W: usize, const H: usize> Default for LayerStack<L, W, H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
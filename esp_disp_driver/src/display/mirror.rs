@@ -0,0 +1,111 @@
+//! Mirrored output: one drawing surface shown on two panels.
+
+use crate::display::pix_writer::{Capabilities, NativePixelWriter, PixelWriter};
+use crate::utils::PrimInt;
+
+/// `PixelWriter` adapter that forwards every write to two backends, for a
+/// redundant display or an operator panel mirroring the main one.
+///
+/// Both backends get the same calls in the same order, each through its
+/// own writer logic (address packing, latching, framebuffer stores), so
+/// they can even be different kinds, e.g. a sipo panel mirroring a bus_dac
+/// one. The cost is that logic run twice: every pixel is written to the
+/// primary and then the secondary, which halves drawing throughput (a
+/// full sipo fill takes twice as long). The panels' own refresh is
+/// unaffected.
+///
+/// `addr_range()` and `color_range()` are the overlap of the two, so a
+/// `Drawer` on top never addresses a pixel only one panel has.
+/// `read_pixel` reads the primary.
+pub struct MirrorWriter<A, B> {
+    primary: A,
+    secondary: B,
+}
+
+impl<A, B> MirrorWriter<A, B> {
+    pub fn new(primary: A, secondary: B) -> Self {
+        Self { primary, secondary }
+    }
+
+    pub fn primary(&self) -> &A {
+        &self.primary
+    }
+
+    pub fn secondary(&self) -> &B {
+        &self.secondary
+    }
+
+    pub fn into_inner(self) -> (A, B) {
+        (self.primary, self.secondary)
+    }
+}
+
+impl<A, B> NativePixelWriter for MirrorWriter<A, B>
+where
+    A: NativePixelWriter,
+    B: PixelWriter<A::Addr, A::Color>,
+{
+    type Addr = A::Addr;
+    type Color = A::Color;
+}
+
+impl<AddrT, ColorT, A, B> PixelWriter<AddrT, ColorT> for MirrorWriter<A, B>
+where
+    AddrT: PrimInt,
+    ColorT: PrimInt,
+    A: PixelWriter<AddrT, ColorT>,
+    B: PixelWriter<AddrT, ColorT>,
+{
+    fn write_pixel(&mut self, i: AddrT, j: AddrT, color: ColorT) {
+        self.primary.write_pixel(i, j, color);
+        self.secondary.write_pixel(i, j, color);
+    }
+
+    fn addr_range(&self) -> ((AddrT, AddrT), (AddrT, AddrT)) {
+        let ((ai0, ai1), (aj0, aj1)) = self.primary.addr_range();
+        let ((bi0, bi1), (bj0, bj1)) = self.secondary.addr_range();
+        ((ai0.max(bi0), ai1.min(bi1)), (aj0.max(bj0), aj1.min(bj1)))
+    }
+
+    fn color_range(&self) -> (ColorT, ColorT) {
+        let (a_min, a_max) = self.primary.color_range();
+        let (b_min, b_max) = self.secondary.color_range();
+        (a_min.max(b_min), a_max.min(b_max))
+    }
+
+    fn read_pixel(&self, i: AddrT, j: AddrT) -> Option<ColorT> {
+        self.primary.read_pixel(i, j)
+    }
+
+    fn present(&mut self) {
+        self.primary.present();
+        self.secondary.present();
+    }
+
+    fn begin_batch(&mut self) {
+        self.primary.begin_batch();
+        self.secondary.begin_batch();
+    }
+
+    fn end_batch(&mut self) {
+        self.primary.end_batch();
+        self.secondary.end_batch();
+    }
+
+    fn fill_rect(&mut self, range: ((AddrT, AddrT), (AddrT, AddrT)), color: ColorT) {
+        self.primary.fill_rect(range, color);
+        self.secondary.fill_rect(range, color);
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        let (a, b) = (self.primary.capabilities(), self.secondary.capabilities());
+        Capabilities {
+            read_pixel: a.read_pixel,
+            batching: a.batching || b.batching,
+            presentation: a.presentation || b.presentation,
+            fast_fill: a.fast_fill && b.fast_fill,
+            channels: a.channels.min(b.channels),
+            ..a
+        }
+    }
+}
@@ -1,4 +1,26 @@
 pub mod pix_writer;
 pub mod drawer;
 pub mod backend;
-pub mod procedural_vid;
\ No newline at end of file
+pub mod procedural_vid;
+pub mod palette;
+pub mod color;
+pub mod frame_pacer;
+pub mod text;
+pub mod icons;
+pub mod gradient;
+pub mod noise;
+pub mod diff;
+pub mod scroll;
+pub mod pen;
+pub mod viewport;
+pub mod patterns;
+pub mod pwm;
+pub mod shapes;
+pub mod buffered;
+pub mod mirror;
+#[cfg(feature = "sim")]
+pub mod sim;
+#[cfg(feature = "layers")]
+pub mod layers;
+#[cfg(feature = "bench")]
+pub mod bench;
@@ -0,0 +1,43 @@
+//! Integer value noise for procedural fills: clouds, static, plasma.
+//!
+//! No floats and no tables, so it is cheap enough to evaluate per pixel,
+//! through `Drawer::fill_with` or a `scan_loop_proc` callback. The output
+//! depends only on the coordinates and the seed.
+
+/// Lattice spacing of `noise2d` in pixels.
+pub const NOISE_CELL: i32 = 16;
+
+/// Pseudo-random byte for lattice point `(x, y)`.
+#[inline]
+fn lattice(x: i32, y: i32, seed: u32) -> u32 {
+    let mut h = seed ^ (x as u32).wrapping_mul(0x27d4_eb2d) ^ (y as u32).wrapping_mul(0x1656_67b1);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2c1b_3c6d);
+    h ^= h >> 12;
+    h = h.wrapping_mul(0x297a_2d39);
+    h ^= h >> 15;
+    h >> 24
+}
+
+/// Smoothstep of `t / NOISE_CELL`, scaled to `0..=4096`.
+#[inline]
+fn fade(t: u32) -> u32 {
+    t * t * (3 * NOISE_CELL as u32 - 2 * t)
+}
+
+/// Value noise at pixel `(x, y)`, `0..=255`.
+///
+/// Random values on a `NOISE_CELL`-pixel lattice, blended with a
+/// smoothstep in between, so neighbouring pixels differ by at most ~24.
+/// Scale the coordinates for coarser or finer detail, and add a frame
+/// counter to one of them to scroll. Lattice points return their value
+/// exactly. Different seeds give unrelated patterns.
+pub fn noise2d(x: i32, y: i32, seed: u32) -> u8 {
+    let (cx, cy) = (x.div_euclid(NOISE_CELL), y.div_euclid(NOISE_CELL));
+    let (sx, sy) = (fade(x.rem_euclid(NOISE_CELL) as u32), fade(y.rem_euclid(NOISE_CELL) as u32));
+    let blend = |a: u32, b: u32, s: u32| a * (4096 - s) + b * s;
+    let top = blend(lattice(cx, cy, seed), lattice(cx + 1, cy, seed), sx);
+    let bottom = blend(lattice(cx, cy + 1, seed), lattice(cx + 1, cy + 1, seed), sx);
+    // 255 * 4096 * 4096 still fits in a u32.
+    ((top * (4096 - sy) + bottom * sy) >> 24) as u8
+}
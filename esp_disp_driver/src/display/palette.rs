@@ -0,0 +1,148 @@
+//! Fixed 16-entry palettes for the 4-bit bus_dac DAC.
+//!
+//! The DAC is monochrome, so "colors" here are named intensity levels:
+//! - `Gray` names a handful of evenly spread levels,
+//! - `Ega` maps the classic 16 EGA/CGA colors to their luma (Rec. 601),
+//!   which keeps retro UI artwork readable on a gray panel.
+//!
+//! `PaletteWriter` wraps any `PixelWriter<u8, u8>` so drawing code can use
+//! palette indices (0..=15) instead of raw DAC codes.
+
+use crate::display::pix_writer::{Capabilities, NativePixelWriter, PixelWriter};
+
+/// Named intensity levels on the 4-bit DAC.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Gray {
+    Black  = 0,
+    Dark   = 3,
+    Dim    = 5,
+    Mid    = 8,
+    Light  = 10,
+    Bright = 13,
+    White  = 15,
+}
+
+impl Gray {
+    /// DAC code (nibble) for this level.
+    #[inline(always)]
+    pub const fn nibble(self) -> u8 {
+        self as u8
+    }
+
+    /// DAC code for an arbitrary level `n`, clamped to the 4-bit range.
+    #[inline(always)]
+    pub const fn level(n: u8) -> u8 {
+        if n > 15 { 15 } else { n }
+    }
+}
+
+/// The 16 EGA/CGA colors, in their standard index order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Ega {
+    Black = 0,
+    Blue,
+    Green,
+    Cyan,
+    Red,
+    Magenta,
+    Brown,
+    LightGray,
+    DarkGray,
+    LightBlue,
+    LightGreen,
+    LightCyan,
+    LightRed,
+    LightMagenta,
+    Yellow,
+    White,
+}
+
+/// DAC code for each EGA index: luma of the EGA RGB value scaled to 0..=15.
+pub const EGA_GRAY_PALETTE: [u8; 16] = [0, 1, 6, 7, 3, 4, 6, 10, 5, 6, 11, 12, 8, 9, 14, 15];
+
+/// Identity palette: index `n` drives DAC code `n`.
+pub const LINEAR_GRAY_PALETTE: [u8; 16] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+
+impl Ega {
+    /// Palette index of this color (0..=15).
+    #[inline(always)]
+    pub const fn index(self) -> u8 {
+        self as u8
+    }
+
+    /// DAC code (nibble) this color maps to on the gray DAC.
+    #[inline(always)]
+    pub const fn level(self) -> u8 {
+        EGA_GRAY_PALETTE[self as usize]
+    }
+}
+
+/// Pixel writer that translates 4-bit palette indices into DAC codes.
+///
+/// Only the low nibble of the written color is used as the index.
+pub struct PaletteWriter<'w, PW> {
+    inner: &'w mut PW,
+    palette: [u8; 16],
+}
+
+impl<'w, PW: PixelWriter<u8, u8>> PaletteWriter<'w, PW> {
+    pub fn new(inner: &'w mut PW, palette: [u8; 16]) -> Self {
+        Self { inner, palette }
+    }
+
+    /// Preset using `EGA_GRAY_PALETTE`, so `Ega::X.index()` can be drawn directly.
+    pub fn ega(inner: &'w mut PW) -> Self {
+        Self::new(inner, EGA_GRAY_PALETTE)
+    }
+}
+
+impl<PW: PixelWriter<u8, u8>> NativePixelWriter for PaletteWriter<'_, PW> {
+    type Addr = u8;
+    type Color = u8;
+}
+
+impl<PW: PixelWriter<u8, u8>> PixelWriter<u8, u8> for PaletteWriter<'_, PW> {
+    #[inline(always)]
+    fn write_pixel(&mut self, i: u8, j: u8, color: u8) {
+        let code = self.palette[(color & 0x0F) as usize];
+        self.inner.write_pixel(i, j, code);
+    }
+
+    #[inline(always)]
+    fn addr_range(&self) -> ((u8, u8), (u8, u8)) {
+        self.inner.addr_range()
+    }
+
+    #[inline(always)]
+    fn color_range(&self) -> (u8, u8) {
+        (0, 15)
+    }
+
+    fn fill_rect(&mut self, range: ((u8, u8), (u8, u8)), color: u8) {
+        let code = self.palette[(color & 0x0F) as usize];
+        self.inner.fill_rect(range, code);
+    }
+
+    fn present(&mut self) {
+        self.inner.present();
+    }
+
+    fn begin_batch(&mut self) {
+        self.inner.begin_batch();
+    }
+
+    fn end_batch(&mut self) {
+        self.inner.end_batch();
+    }
+
+    /// The inner backend's, minus `read_pixel` (DAC codes cannot be mapped
+    /// back to palette indices).
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            read_pixel: false,
+            ..self.inner.capabilities()
+        }
+    }
+}
@@ -0,0 +1,54 @@
+//! Full-frame test and demo patterns, rendered straight into a framebuffer,
+//! or through a `Drawer` on any backend.
+
+use crate::display::drawer::Drawer;
+use crate::display::pix_writer::PixelWriter;
+use crate::utils::PrimInt;
+
+/// Fill `fb` with a checkerboard of `cell` x `cell` squares, scrolled
+/// diagonally by `offset` pixels.
+///
+/// Square `(row / cell + col / cell)` even gets `color_a`, odd `color_b`;
+/// `offset` is added to both coordinates first, so increasing it by one per
+/// frame moves the board up and to the left. The pattern repeats every
+/// `2 * cell` pixels, so `offset` is reduced modulo that and a free-running
+/// frame counter can be passed without overflowing. A `cell` of 0 is
+/// treated as 1.
+pub fn animated_checkerboard<const W: usize>(fb: &mut [[u8; W]], cell: usize, offset: usize, color_a: u8, color_b: u8) {
+    let cell = cell.max(1);
+    let offset = offset % (2 * cell);
+    for (i, row) in fb.iter_mut().enumerate() {
+        let row_parity = (i + offset) / cell;
+        for (j, px) in row.iter_mut().enumerate() {
+            let even = (row_parity + (j + offset) / cell) & 1 == 0;
+            *px = if even { color_a } else { color_b };
+        }
+    }
+}
+
+impl<'a, AddrT: PrimInt, ColorT: PrimInt, PW> Drawer<'a, AddrT, ColorT, PW>
+where
+    PW: PixelWriter<AddrT, ColorT>,
+{
+    /// Cover `addr_range()` with a checkerboard of `cell` x `cell` squares:
+    /// the square at `(i / cell, j / cell)` gets `color_a` when the sum is
+    /// even, `color_b` otherwise, as `animated_checkerboard` with offset 0.
+    ///
+    /// One `fill_rect` per square, so a quick check that addressing works
+    /// on any backend. A `cell` of 0 is treated as 1.
+    pub fn fill_checkerboard(&mut self, cell: AddrT, color_a: ColorT, color_b: ColorT) {
+        let cell = cell.to_i64().unwrap().max(1);
+        let ((i_min, i_max), (j_min, j_max)) = self.bounds_i64();
+        let (mut i0, j_start) = (i_min - i_min.rem_euclid(cell), j_min - j_min.rem_euclid(cell));
+        while i0 <= i_max {
+            let mut j0 = j_start;
+            while j0 <= j_max {
+                let even = (i0 / cell + j0 / cell) & 1 == 0;
+                let color = if even { color_a } else { color_b };
+                self.fill_box(i0.max(i_min), (i0 + cell - 1).min(i_max), j0.max(j_min), (j0 + cell - 1).min(j_max), color);
+                j0 += cell;
+            }
+            i0 += cell;
+        }
+    }
+}
@@ -0,0 +1,69 @@
+//! Stateful pen for drawing connected paths.
+//!
+//! `Pen` remembers where the last segment ended, so polylines and
+//! turtle-style paths don't repeat every previous point.
+
+use crate::display::drawer::Drawer;
+use crate::display::pix_writer::PixelWriter;
+use crate::utils::PrimInt;
+
+/// A current position on a `Drawer`, plus whether moving it draws.
+///
+/// Segments go through `Drawer::draw_line`, so each one is clipped to
+/// `addr_range()` on its own. The pen starts down.
+pub struct Pen<'d, 'a, AddrT: PrimInt, ColorT: PrimInt, PW>
+where
+    PW: PixelWriter<AddrT, ColorT>,
+{
+    drawer: &'d mut Drawer<'a, AddrT, ColorT, PW>,
+    pos: (AddrT, AddrT),
+    down: bool,
+}
+
+impl<'d, 'a, AddrT: PrimInt, ColorT: PrimInt, PW> Pen<'d, 'a, AddrT, ColorT, PW>
+where
+    PW: PixelWriter<AddrT, ColorT>,
+{
+    /// Put the pen down at `(i, j)` without drawing anything.
+    pub fn new(drawer: &'d mut Drawer<'a, AddrT, ColorT, PW>, i: AddrT, j: AddrT) -> Self {
+        Self { drawer, pos: (i, j), down: true }
+    }
+
+    /// Jump to `(i, j)` without drawing, whether the pen is up or down.
+    pub fn move_to(&mut self, i: AddrT, j: AddrT) {
+        self.pos = (i, j);
+    }
+
+    /// Move to `(i, j)`, drawing a line from the current position if the
+    /// pen is down.
+    pub fn line_to(&mut self, i: AddrT, j: AddrT, color: ColorT) {
+        if self.down {
+            let (i0, j0) = self.pos;
+            self.drawer.draw_line(i0, j0, i, j, color);
+        }
+        self.pos = (i, j);
+    }
+
+    /// Stop drawing: `line_to` only moves until `pen_down`.
+    pub fn pen_up(&mut self) {
+        self.down = false;
+    }
+
+    pub fn pen_down(&mut self) {
+        self.down = true;
+    }
+
+    pub fn is_down(&self) -> bool {
+        self.down
+    }
+
+    /// Current `(i, j)`, where the next segment starts.
+    pub fn position(&self) -> (AddrT, AddrT) {
+        self.pos
+    }
+
+    /// The underlying drawer, for drawing other primitives mid-path.
+    pub fn drawer(&mut self) -> &mut Drawer<'a, AddrT, ColorT, PW> {
+        self.drawer
+    }
+}
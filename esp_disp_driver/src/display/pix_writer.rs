@@ -1,8 +1,156 @@
 use crate::utils::PrimInt;
 
+/// What a `PixelWriter` backend supports beyond plain `write_pixel`.
+///
+/// Returned by `PixelWriter::capabilities`, so generic code can pick a
+/// strategy (e.g. blend only if `read_pixel`) instead of assuming.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Capabilities {
+    /// `read_pixel` returns the current contents.
+    pub read_pixel: bool,
+    /// `begin_batch`/`end_batch` actually defer work.
+    pub batching: bool,
+    /// Writes only show after `present` (double-buffered).
+    pub presentation: bool,
+    /// `fill_rect` is overridden with something faster than per-pixel writes.
+    pub fast_fill: bool,
+    /// Independent color channels (1 for gray panels).
+    pub channels: u8,
+    /// Width of the native address type in bits.
+    pub addr_bits: u8,
+    /// Width of the native color type in bits.
+    pub color_bits: u8,
+}
+
+impl Capabilities {
+    /// Most conservative backend: write-only, unbatched, immediate, one channel.
+    pub const fn write_only<AddrT, ColorT>() -> Self {
+        Self {
+            read_pixel: false,
+            batching: false,
+            presentation: false,
+            fast_fill: false,
+            channels: 1,
+            addr_bits: (8 * core::mem::size_of::<AddrT>()) as u8,
+            color_bits: (8 * core::mem::size_of::<ColorT>()) as u8,
+        }
+    }
+}
+
 pub trait PixelWriter<AddrT : PrimInt, ColorT : PrimInt>{
     fn write_pixel(&mut self, i: AddrT, j: AddrT, color: ColorT);
     fn addr_range(&self) -> ((AddrT, AddrT), (AddrT, AddrT));
     // ((i_min, i_max), (j_min, j_max))
     fn color_range(&self) -> (ColorT, ColorT);
+
+    /// Read back the pixel at `(i, j)`, if the backend keeps a framebuffer.
+    ///
+    /// Write-only backends (e.g. shift-register chains) return `None`, which
+    /// is the default.
+    fn read_pixel(&self, _i: AddrT, _j: AddrT) -> Option<ColorT> {
+        None
+    }
+
+    /// Make everything written so far visible.
+    ///
+    /// Double-buffered backends swap buffers here; immediate-mode backends
+    /// already show each pixel as it is written, so the default does nothing.
+    fn present(&mut self) {}
+
+    /// Start a batch of writes that only need to become visible at `end_batch()`.
+    ///
+    /// Backends that latch per pixel may defer latching until the batch ends.
+    /// Batches do not nest. The default does nothing.
+    fn begin_batch(&mut self) {}
+
+    /// End the current batch, making all of its writes visible. The default does nothing.
+    fn end_batch(&mut self) {}
+
+    /// Fill the inclusive `((i0, i1), (j0, j1))` rectangle with `color`.
+    ///
+    /// `range` uses the same layout as `addr_range()` and must lie within it.
+    /// The default writes every pixel inside one batch; framebuffer backends
+    /// override it with row fills.
+    fn fill_rect(&mut self, range: ((AddrT, AddrT), (AddrT, AddrT)), color: ColorT) {
+        self.begin_batch();
+        for (i, j) in AddrIter::new(range) {
+            self.write_pixel(i, j, color);
+        }
+        self.end_batch();
+    }
+
+    /// Describe the optional features this backend implements.
+    ///
+    /// The default is `Capabilities::write_only`; backends that override any
+    /// of the optional methods should override this too.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::write_only::<AddrT, ColorT>()
+    }
+
+    /// Iterate every address in `addr_range()`, row by row (i outer, j inner).
+    fn addr_iter(&self) -> AddrIter<AddrT> {
+        AddrIter::new(self.addr_range())
+    }
+}
+
+/// A `PixelWriter` with one native address and color type.
+///
+/// `PixelWriter` stays generic over `<AddrT, ColorT>`, so adapters can
+/// accept whatever their inner writer takes and existing code keeps
+/// working. Backends have exactly one pair, and name it here, so generic
+/// code can write `PW::Addr` / `PW::Color` instead of assuming `u8`, and
+/// `NativeDrawer<PW>` picks the matching `Drawer` without spelling the
+/// types out. Adapters implement it whenever their inner writer does.
+pub trait NativePixelWriter: PixelWriter<Self::Addr, Self::Color> {
+    type Addr: PrimInt;
+    type Color: PrimInt;
+}
+
+/// Iterator over an inclusive `((i_min, i_max), (j_min, j_max))` range.
+///
+/// Yields exactly `(i_max - i_min + 1) * (j_max - j_min + 1)` coordinates and
+/// never steps past the maxima, so it is safe at the type's upper bound.
+pub struct AddrIter<AddrT> {
+    i: AddrT,
+    j: AddrT,
+    i_max: AddrT,
+    j_min: AddrT,
+    j_max: AddrT,
+    done: bool,
+}
+
+impl<AddrT: PrimInt> AddrIter<AddrT> {
+    pub fn new(range: ((AddrT, AddrT), (AddrT, AddrT))) -> Self {
+        let ((i_min, i_max), (j_min, j_max)) = range;
+        Self {
+            i: i_min,
+            j: j_min,
+            i_max,
+            j_min,
+            j_max,
+            done: i_min > i_max || j_min > j_max,
+        }
+    }
+}
+
+impl<AddrT: PrimInt> Iterator for AddrIter<AddrT> {
+    type Item = (AddrT, AddrT);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let cur = (self.i, self.j);
+        if self.j == self.j_max {
+            if self.i == self.i_max {
+                self.done = true;
+            } else {
+                self.i = self.i + AddrT::one();
+                self.j = self.j_min;
+            }
+        } else {
+            self.j = self.j + AddrT::one();
+        }
+        Some(cur)
+    }
 }
@@ -0,0 +1,160 @@
+//! Grayscale on on/off panels by temporal dithering (PWM over frames).
+//!
+//! `PwmWriter` only looks steady if `present()` runs at least
+//! `60 * (levels - 1)` times a second, keeping its slowest on/off pattern
+//! above 60 Hz: 180 Hz for 4 levels, 15.3 kHz for all 256. Below that,
+//! intermediate levels shimmer.
+
+use crate::display::pix_writer::{AddrIter, Capabilities, NativePixelWriter, PixelWriter};
+use crate::utils::PrimInt;
+
+/// `PixelWriter` adapter giving a binary (on/off) panel 8-bit grayscale by
+/// temporal dithering.
+///
+/// Colors written through it are kept in a `W` x `H` level buffer (relative
+/// to the inner `addr_range()` minimum). Each `present()` advances a
+/// subframe counter and rewrites every pixel as on (the inner
+/// `color_range()` maximum) or off (its minimum), then presents the inner
+/// writer. Over one period of `levels - 1` subframes a pixel is on for
+/// `color / 255` of them, rounded to the nearest level, with the on
+/// subframes spread as evenly as possible rather than bunched together.
+///
+/// Flicker: a pixel at level `q` of `p = levels - 1` repeats every
+/// `p / gcd(p, q)` subframes, so the slowest pattern takes a full period.
+/// It looks steady once that is above roughly 60 Hz, i.e. `present()` must
+/// run at least `60 * (levels - 1)` times a second: 15.3 kHz for the full
+/// 256 levels, 180 Hz for 4 levels. At the ~60 Hz the scan loops reach
+/// with one present per frame, use few levels (see `with_levels`) or
+/// expect visible shimmer on all but black, white and mid-gray.
+///
+/// Addresses outside the level buffer are written straight through,
+/// thresholded at 128.
+pub struct PwmWriter<PW, const W: usize, const H: usize> {
+    inner: PW,
+    levels: [[u8; W]; H],
+    /// Subframes per period, `levels - 1`.
+    period: u8,
+    /// Current subframe, `0..period`.
+    phase: u8,
+}
+
+impl<PW, const W: usize, const H: usize> PwmWriter<PW, W, H> {
+    /// Wrap `inner` with all pixels at 0 and the full 256 levels.
+    pub const fn new(inner: PW) -> Self {
+        Self { inner, levels: [[0; W]; H], period: 255, phase: 0 }
+    }
+
+    /// Quantize to `levels` gray levels (2..=256, clamped) so a period takes
+    /// only `levels - 1` subframes; see the flicker note on the type.
+    pub fn with_levels(mut self, levels: u16) -> Self {
+        self.period = (levels.clamp(2, 256) - 1) as u8;
+        self.phase = 0;
+        self
+    }
+
+    /// Subframes per dithering period.
+    pub fn period(&self) -> u8 {
+        self.period
+    }
+
+    pub fn inner(&self) -> &PW {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> PW {
+        self.inner
+    }
+
+    /// Whether a pixel at `color` is on in subframe `phase`.
+    ///
+    /// Distributes `q = round(color * period / 255)` on subframes evenly
+    /// over the period, Bresenham style.
+    #[inline]
+    pub fn is_on(color: u8, phase: u8, period: u8) -> bool {
+        let p = period as u32;
+        let q = (color as u32 * p + 127) / 255;
+        let t = phase as u32 % p.max(1);
+        (t + 1) * q / p > t * q / p
+    }
+
+    /// Level buffer cell for `(i, j)`, if it falls inside it.
+    fn cell<AddrT: PrimInt>(&self, i: AddrT, j: AddrT) -> Option<(usize, usize)>
+    where
+        PW: PixelWriter<AddrT, u8>,
+    {
+        let ((i_min, _), (j_min, _)) = self.inner.addr_range();
+        let r = i.checked_sub(&i_min)?.to_usize()?;
+        let c = j.checked_sub(&j_min)?.to_usize()?;
+        (r < H && c < W).then_some((r, c))
+    }
+}
+
+impl<PW, const W: usize, const H: usize> NativePixelWriter for PwmWriter<PW, W, H>
+where
+    PW: NativePixelWriter<Color = u8>,
+{
+    type Addr = PW::Addr;
+    type Color = u8;
+}
+
+impl<AddrT, PW, const W: usize, const H: usize> PixelWriter<AddrT, u8> for PwmWriter<PW, W, H>
+where
+    AddrT: PrimInt,
+    PW: PixelWriter<AddrT, u8>,
+{
+    /// Store the level; it reaches the panel on the next `present()`.
+    fn write_pixel(&mut self, i: AddrT, j: AddrT, color: u8) {
+        match self.cell(i, j) {
+            Some((r, c)) => self.levels[r][c] = color,
+            None => {
+                let (off, on) = self.inner.color_range();
+                self.inner.write_pixel(i, j, if color >= 128 { on } else { off });
+            }
+        }
+    }
+
+    fn addr_range(&self) -> ((AddrT, AddrT), (AddrT, AddrT)) {
+        self.inner.addr_range()
+    }
+
+    fn color_range(&self) -> (u8, u8) {
+        (0, 255)
+    }
+
+    fn read_pixel(&self, i: AddrT, j: AddrT) -> Option<u8> {
+        let (r, c) = self.cell(i, j)?;
+        Some(self.levels[r][c])
+    }
+
+    /// Advance one subframe: write every buffered pixel's on/off state,
+    /// then present the inner writer.
+    fn present(&mut self) {
+        self.phase = (self.phase + 1) % self.period;
+        let (off, on) = self.inner.color_range();
+        let range = self.inner.addr_range();
+        let ((i_min, _), (j_min, _)) = range;
+        self.inner.begin_batch();
+        for (i, j) in AddrIter::new(range) {
+            let (Some(r), Some(c)) = ((i - i_min).to_usize(), (j - j_min).to_usize()) else {
+                continue;
+            };
+            let Some(&level) = self.levels.get(r).and_then(|row| row.get(c)) else {
+                continue;
+            };
+            let px = if Self::is_on(level, self.phase, self.period) { on } else { off };
+            self.inner.write_pixel(i, j, px);
+        }
+        self.inner.end_batch();
+        self.inner.present();
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            read_pixel: true,
+            presentation: true,
+            fast_fill: false,
+            color_bits: 8,
+            ..self.inner.capabilities()
+        }
+    }
+}
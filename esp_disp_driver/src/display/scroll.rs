@@ -0,0 +1,91 @@
+//! Sub-pixel scroll speeds for smooth slow scrolling.
+//!
+//! `Drawer::scroll_*` can only move whole pixels. `SmoothScroller` keeps
+//! the fractional part between frames, so a ticker at half a pixel per
+//! frame moves one pixel every second frame instead of not at all.
+
+use crate::display::drawer::Drawer;
+use crate::display::pix_writer::PixelWriter;
+use crate::error::Result;
+use crate::utils::PrimInt;
+use num_traits::NumCast;
+
+/// Which way the screen contents move; matches the `Drawer::scroll_*` methods.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum ScrollDir {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+/// Accumulates a fractional per-frame scroll amount into whole-pixel steps.
+///
+/// Speeds are in Q8, i.e. 1/256 pixel per frame: `128` is half a pixel per
+/// frame, `256` one pixel, `384` one and a half.
+pub struct SmoothScroller {
+    dir: ScrollDir,
+    speed_q8: u32,
+    /// Movement built up but not yet scrolled, in 1/256 pixel.
+    acc: u32,
+}
+
+impl SmoothScroller {
+    pub const fn new(dir: ScrollDir, speed_q8: u32) -> Self {
+        Self { dir, speed_q8, acc: 0 }
+    }
+
+    /// Change the speed; the accumulated fraction is kept.
+    pub fn set_speed_q8(&mut self, speed_q8: u32) {
+        self.speed_q8 = speed_q8;
+    }
+
+    /// Change the direction, dropping the fraction built up the other way.
+    pub fn set_direction(&mut self, dir: ScrollDir) {
+        if dir != self.dir {
+            self.dir = dir;
+            self.acc = 0;
+        }
+    }
+
+    pub fn direction(&self) -> ScrollDir {
+        self.dir
+    }
+
+    /// Fraction of a pixel carried into the next frame, in 1/256 pixel.
+    pub fn remainder_q8(&self) -> u32 {
+        self.acc
+    }
+
+    /// Advance one frame and return how many whole pixels to scroll now.
+    pub fn advance(&mut self) -> u32 {
+        self.acc = self.acc.saturating_add(self.speed_q8);
+        let step = self.acc >> 8;
+        self.acc &= 0xFF;
+        step
+    }
+
+    /// `advance` one frame and apply the step to `drawer`, filling with `fill`.
+    ///
+    /// Returns the number of pixels scrolled. Errors from the drawer (e.g. a
+    /// write-only backend) are passed through; that frame's step is dropped.
+    pub fn scroll<AddrT, ColorT, PW>(&mut self, drawer: &mut Drawer<'_, AddrT, ColorT, PW>, fill: ColorT) -> Result<u32>
+    where
+        AddrT: PrimInt,
+        ColorT: PrimInt,
+        PW: PixelWriter<AddrT, ColorT>,
+    {
+        let step = self.advance();
+        if step == 0 {
+            return Ok(0);
+        }
+        let n = <AddrT as NumCast>::from(step).unwrap_or(AddrT::max_value());
+        match self.dir {
+            ScrollDir::Up => drawer.scroll_up(n, fill),
+            ScrollDir::Down => drawer.scroll_down(n, fill),
+            ScrollDir::Left => drawer.scroll_left(n, fill),
+            ScrollDir::Right => drawer.scroll_right(n, fill),
+        }?;
+        Ok(step)
+    }
+}
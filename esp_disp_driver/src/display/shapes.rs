@@ -0,0 +1,147 @@
+//! Styled shapes: a geometry plus the colors to draw it with.
+//!
+//! `Styled` bundles a shape with a `PrimitiveStyle` (fill color, stroke
+//! color and stroke width), in the spirit of embedded-graphics, so a style
+//! can be defined once and reused instead of repeating the same arguments
+//! on every `Drawer` call:
+//!
+//! ```ignore
+//! let frame = PrimitiveStyle::stroke(15, 2).with_fill(3);
+//! Rect::new(10, 10, 40, 20).into_styled(frame).draw(&mut drawer);
+//! Circle::new(60, 100, 12).into_styled(frame).draw(&mut drawer);
+//! ```
+//!
+//! The stroke lies inside the shape's outline and the fill covers only
+//! what the stroke leaves, so every pixel is written once.
+
+use crate::display::drawer::Drawer;
+use crate::display::pix_writer::PixelWriter;
+use crate::utils::PrimInt;
+
+/// `width` x `height` rectangle with its top-left corner at `(i, j)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rect<AddrT> {
+    pub i: AddrT,
+    pub j: AddrT,
+    pub width: AddrT,
+    pub height: AddrT,
+}
+
+impl<AddrT> Rect<AddrT> {
+    pub const fn new(i: AddrT, j: AddrT, width: AddrT, height: AddrT) -> Self {
+        Self { i, j, width, height }
+    }
+
+    pub const fn into_styled<ColorT>(self, style: PrimitiveStyle<ColorT>) -> Styled<Self, ColorT> {
+        Styled { shape: self, style }
+    }
+}
+
+/// Circle of radius `r` centered on `(ci, cj)`: the pixels at most `r`
+/// from the center, as `Drawer::fill_circle` draws them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Circle<AddrT> {
+    pub ci: AddrT,
+    pub cj: AddrT,
+    pub r: AddrT,
+}
+
+impl<AddrT> Circle<AddrT> {
+    pub const fn new(ci: AddrT, cj: AddrT, r: AddrT) -> Self {
+        Self { ci, cj, r }
+    }
+
+    pub const fn into_styled<ColorT>(self, style: PrimitiveStyle<ColorT>) -> Styled<Self, ColorT> {
+        Styled { shape: self, style }
+    }
+}
+
+/// How to draw a shape: optional fill and stroke colors, and the stroke
+/// width in pixels. The default draws nothing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PrimitiveStyle<ColorT> {
+    pub fill: Option<ColorT>,
+    pub stroke: Option<ColorT>,
+    /// Ignored without a stroke color; 0 draws no stroke.
+    pub stroke_width: u8,
+}
+
+impl<ColorT> PrimitiveStyle<ColorT> {
+    /// Filled, without a stroke.
+    pub const fn fill(color: ColorT) -> Self {
+        Self { fill: Some(color), stroke: None, stroke_width: 0 }
+    }
+
+    /// Stroked `width` pixels wide, without a fill.
+    pub const fn stroke(color: ColorT, width: u8) -> Self {
+        Self { fill: None, stroke: Some(color), stroke_width: width }
+    }
+
+    pub fn with_fill(mut self, color: ColorT) -> Self {
+        self.fill = Some(color);
+        self
+    }
+
+    pub fn with_stroke(mut self, color: ColorT, width: u8) -> Self {
+        self.stroke = Some(color);
+        self.stroke_width = width;
+        self
+    }
+
+    /// Stroke width actually drawn: 0 without a stroke color.
+    fn stroke_px(&self) -> i64 {
+        if self.stroke.is_some() { self.stroke_width as i64 } else { 0 }
+    }
+}
+
+impl<ColorT> Default for PrimitiveStyle<ColorT> {
+    fn default() -> Self {
+        Self { fill: None, stroke: None, stroke_width: 0 }
+    }
+}
+
+/// A shape and the style to draw it with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Styled<S, ColorT> {
+    pub shape: S,
+    pub style: PrimitiveStyle<ColorT>,
+}
+
+impl<S, ColorT> Styled<S, ColorT> {
+    pub const fn new(shape: S, style: PrimitiveStyle<ColorT>) -> Self {
+        Self { shape, style }
+    }
+}
+
+impl<AddrT: PrimInt, ColorT: PrimInt> Styled<Rect<AddrT>, ColorT> {
+    /// Stroke the border as `Drawer::stroke_rect` does and fill the inside
+    /// it leaves, clipped to `addr_range()`.
+    pub fn draw<PW: PixelWriter<AddrT, ColorT>>(&self, drawer: &mut Drawer<'_, AddrT, ColorT, PW>) {
+        let Rect { i, j, width, height } = self.shape;
+        let (i, j) = (i.to_i64().unwrap(), j.to_i64().unwrap());
+        let (w, h) = (width.to_i64().unwrap(), height.to_i64().unwrap());
+        let t = self.style.stroke_px();
+        if let Some(fill) = self.style.fill {
+            drawer.fill_box(i + t, i + h - 1 - t, j + t, j + w - 1 - t, fill);
+        }
+        if let Some(stroke) = self.style.stroke {
+            drawer.stroke_box(i, j, w, h, t, stroke);
+        }
+    }
+}
+
+impl<AddrT: PrimInt, ColorT: PrimInt> Styled<Circle<AddrT>, ColorT> {
+    /// Stroke the outermost `stroke_width` pixels of the circle and fill
+    /// the disc inside them, clipped to `addr_range()`.
+    pub fn draw<PW: PixelWriter<AddrT, ColorT>>(&self, drawer: &mut Drawer<'_, AddrT, ColorT, PW>) {
+        let Circle { ci, cj, r } = self.shape;
+        let (ci, cj, r) = (ci.to_i64().unwrap(), cj.to_i64().unwrap(), r.to_i64().unwrap());
+        let inner = r - self.style.stroke_px();
+        if let Some(fill) = self.style.fill {
+            drawer.fill_annulus(ci, cj, -1, inner, fill);
+        }
+        if let Some(stroke) = self.style.stroke {
+            drawer.fill_annulus(ci, cj, inner, r, stroke);
+        }
+    }
+}
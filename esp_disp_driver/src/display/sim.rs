@@ -0,0 +1,89 @@
+//! In-memory pixel writer for host-side and golden-image testing.
+//!
+//! Only built with the `sim` feature. `SimPixelWriter` records every pixel
+//! into a plain framebuffer so a `Drawer` sequence can be rendered without
+//! hardware and exported for diffing against a reference image.
+
+use crate::display::pix_writer::{Capabilities, NativePixelWriter, PixelWriter};
+use crate::utils::adler32;
+use alloc::vec::Vec;
+
+/// Framebuffer-backed `PixelWriter` of `W` x `H` 8-bit gray pixels.
+pub struct SimPixelWriter<const W: usize, const H: usize> {
+    /// Row-major pixels, indexed `frame[i][j]`.
+    pub frame: [[u8; W]; H],
+}
+
+impl<const W: usize, const H: usize> SimPixelWriter<W, H> {
+    pub fn new() -> Self {
+        Self { frame: [[0; W]; H] }
+    }
+
+    /// Current pixel value at `(i, j)`.
+    pub fn pixel(&self, i: usize, j: usize) -> u8 {
+        self.frame[i][j]
+    }
+
+    /// Flat row-major copy of the framebuffer (`W * H` bytes).
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.frame.iter().flatten().copied().collect()
+    }
+
+    /// Adler-32 of the framebuffer, row-major; compare against
+    /// `DoubleBuffer::active_checksum` of the same frame on target.
+    pub fn checksum(&self) -> u32 {
+        adler32(self.frame.iter().flatten().copied())
+    }
+
+    /// Export the framebuffer as a binary PGM (`P5`, maxval 255) image.
+    pub fn to_pgm(&self) -> Vec<u8> {
+        let mut out = alloc::format!("P5\n{} {}\n255\n", W, H).into_bytes();
+        out.reserve(W * H);
+        out.extend(self.frame.iter().flatten());
+        out
+    }
+}
+
+impl<const W: usize, const H: usize> Default for SimPixelWriter<W, H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const W: usize, const H: usize> NativePixelWriter for SimPixelWriter<W, H> {
+    type Addr = u8;
+    type Color = u8;
+}
+
+impl<const W: usize, const H: usize> PixelWriter<u8, u8> for SimPixelWriter<W, H> {
+    fn write_pixel(&mut self, i: u8, j: u8, color: u8) {
+        self.frame[i as usize][j as usize] = color;
+    }
+
+    fn addr_range(&self) -> ((u8, u8), (u8, u8)) {
+        ((0, H as u8 - 1), (0, W as u8 - 1))
+    }
+
+    fn color_range(&self) -> (u8, u8) {
+        (0, 255)
+    }
+
+    fn fill_rect(&mut self, range: ((u8, u8), (u8, u8)), color: u8) {
+        let ((i0, i1), (j0, j1)) = range;
+        for row in &mut self.frame[i0 as usize..=i1 as usize] {
+            row[j0 as usize..=j1 as usize].fill(color);
+        }
+    }
+
+    fn read_pixel(&self, i: u8, j: u8) -> Option<u8> {
+        Some(self.frame[i as usize][j as usize])
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            read_pixel: true,
+            fast_fill: true,
+            ..Capabilities::write_only::<u8, u8>()
+        }
+    }
+}
@@ -0,0 +1,236 @@
+//! Bitmap text rendering on top of `Drawer`.
+//!
+//! Glyphs are 1bpp, one MSB-first byte per row (the `draw_bitmap_1bpp`
+//! layout), and drawn with a transparent background unless `draw_str_bg`
+//! fills the cells first. Text is anchored at the top-left corner of its
+//! first glyph and clipped to `addr_range()`.
+
+use crate::display::drawer::Drawer;
+use crate::display::pix_writer::PixelWriter;
+use crate::utils::PrimInt;
+
+/// Fixed-width bitmap font covering a contiguous ASCII range.
+pub struct Font {
+    /// Glyph size in pixels (`width <= 8`).
+    pub width: u8,
+    pub height: u8,
+    /// Distance between the left edges of consecutive glyphs.
+    pub advance: u8,
+    /// Code of the first glyph in `glyphs`.
+    pub first: u8,
+    /// `height` bytes per glyph.
+    pub glyphs: &'static [u8],
+}
+
+impl Font {
+    /// Bitmap rows for `c`, or `None` if the font has no glyph for it.
+    ///
+    /// Lowercase letters fall back to uppercase when only those are present.
+    pub fn glyph(&self, c: char) -> Option<&'static [u8]> {
+        let h = self.height as usize;
+        let count = self.glyphs.len() / h;
+        let idx = |c: char| {
+            let code = c as usize;
+            (code >= self.first as usize && code < self.first as usize + count)
+                .then(|| code - self.first as usize)
+        };
+        let k = idx(c).or_else(|| idx(c.to_ascii_uppercase()))?;
+        Some(&self.glyphs[k * h..(k + 1) * h])
+    }
+
+    /// Width of `s` in pixels: `advance` per character, without the
+    /// spacing after the last glyph.
+    pub fn text_width(&self, s: &str) -> usize {
+        match s.chars().count() {
+            0 => 0,
+            n => (n - 1) * self.advance as usize + self.width as usize,
+        }
+    }
+}
+
+/// 3x5 font for ASCII `' '..='_'`; lowercase is drawn as uppercase.
+pub const FONT_3X5: Font = Font {
+    width: 3,
+    height: 5,
+    advance: 4,
+    first: b' ',
+    glyphs: &GLYPHS_3X5,
+};
+
+#[rustfmt::skip]
+const GLYPHS_3X5: [u8; 64 * 5] = [
+    0x00, 0x00, 0x00, 0x00, 0x00, // ' '
+    0x40, 0x40, 0x40, 0x00, 0x40, // '!'
+    0xA0, 0xA0, 0x00, 0x00, 0x00, // '"'
+    0xA0, 0xE0, 0xA0, 0xE0, 0xA0, // '#'
+    0x60, 0xC0, 0x40, 0x60, 0xC0, // '$'
+    0xA0, 0x20, 0x40, 0x80, 0xA0, // '%'
+    0x40, 0xA0, 0x40, 0xA0, 0x60, // '&'
+    0x40, 0x40, 0x00, 0x00, 0x00, // '\''
+    0x20, 0x40, 0x40, 0x40, 0x20, // '('
+    0x80, 0x40, 0x40, 0x40, 0x80, // ')'
+    0x00, 0xA0, 0x40, 0xA0, 0x00, // '*'
+    0x00, 0x40, 0xE0, 0x40, 0x00, // '+'
+    0x00, 0x00, 0x00, 0x40, 0x80, // ','
+    0x00, 0x00, 0xE0, 0x00, 0x00, // '-'
+    0x00, 0x00, 0x00, 0x00, 0x40, // '.'
+    0x20, 0x20, 0x40, 0x80, 0x80, // '/'
+    0xE0, 0xA0, 0xA0, 0xA0, 0xE0, // '0'
+    0x40, 0xC0, 0x40, 0x40, 0xE0, // '1'
+    0xC0, 0x20, 0x40, 0x80, 0xE0, // '2'
+    0xC0, 0x20, 0x40, 0x20, 0xC0, // '3'
+    0xA0, 0xA0, 0xE0, 0x20, 0x20, // '4'
+    0xE0, 0x80, 0xC0, 0x20, 0xC0, // '5'
+    0x60, 0x80, 0xE0, 0xA0, 0xE0, // '6'
+    0xE0, 0x20, 0x40, 0x40, 0x40, // '7'
+    0xE0, 0xA0, 0xE0, 0xA0, 0xE0, // '8'
+    0xE0, 0xA0, 0xE0, 0x20, 0xC0, // '9'
+    0x00, 0x40, 0x00, 0x40, 0x00, // ':'
+    0x00, 0x40, 0x00, 0x40, 0x80, // ';'
+    0x20, 0x40, 0x80, 0x40, 0x20, // '<'
+    0x00, 0xE0, 0x00, 0xE0, 0x00, // '='
+    0x80, 0x40, 0x20, 0x40, 0x80, // '>'
+    0xC0, 0x20, 0x40, 0x00, 0x40, // '?'
+    0x40, 0xA0, 0xE0, 0x80, 0x60, // '@'
+    0x40, 0xA0, 0xE0, 0xA0, 0xA0, // 'A'
+    0xC0, 0xA0, 0xC0, 0xA0, 0xC0, // 'B'
+    0x60, 0x80, 0x80, 0x80, 0x60, // 'C'
+    0xC0, 0xA0, 0xA0, 0xA0, 0xC0, // 'D'
+    0xE0, 0x80, 0xC0, 0x80, 0xE0, // 'E'
+    0xE0, 0x80, 0xC0, 0x80, 0x80, // 'F'
+    0x60, 0x80, 0xA0, 0xA0, 0x60, // 'G'
+    0xA0, 0xA0, 0xE0, 0xA0, 0xA0, // 'H'
+    0xE0, 0x40, 0x40, 0x40, 0xE0, // 'I'
+    0x20, 0x20, 0x20, 0xA0, 0x40, // 'J'
+    0xA0, 0xA0, 0xC0, 0xA0, 0xA0, // 'K'
+    0x80, 0x80, 0x80, 0x80, 0xE0, // 'L'
+    0xA0, 0xE0, 0xE0, 0xA0, 0xA0, // 'M'
+    0xC0, 0xA0, 0xA0, 0xA0, 0xA0, // 'N'
+    0x40, 0xA0, 0xA0, 0xA0, 0x40, // 'O'
+    0xC0, 0xA0, 0xC0, 0x80, 0x80, // 'P'
+    0x40, 0xA0, 0xA0, 0xC0, 0x60, // 'Q'
+    0xC0, 0xA0, 0xC0, 0xA0, 0xA0, // 'R'
+    0x60, 0x80, 0x40, 0x20, 0xC0, // 'S'
+    0xE0, 0x40, 0x40, 0x40, 0x40, // 'T'
+    0xA0, 0xA0, 0xA0, 0xA0, 0xE0, // 'U'
+    0xA0, 0xA0, 0xA0, 0xA0, 0x40, // 'V'
+    0xA0, 0xA0, 0xE0, 0xE0, 0xA0, // 'W'
+    0xA0, 0xA0, 0x40, 0xA0, 0xA0, // 'X'
+    0xA0, 0xA0, 0x40, 0x40, 0x40, // 'Y'
+    0xE0, 0x20, 0x40, 0x80, 0xE0, // 'Z'
+    0xC0, 0x80, 0x80, 0x80, 0xC0, // '['
+    0x80, 0x80, 0x40, 0x20, 0x20, // '\\'
+    0x60, 0x20, 0x20, 0x20, 0x60, // ']'
+    0x40, 0xA0, 0x00, 0x00, 0x00, // '^'
+    0x00, 0x00, 0x00, 0x00, 0xE0, // '_'
+
+];
+
+/// Glyph spacing and line height overrides kept by a `Drawer`; `None`
+/// means the font's own metrics.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct TextLayout {
+    char_spacing: Option<u8>,
+    line_height: Option<u8>,
+}
+
+impl<'a, AddrT: PrimInt, ColorT: PrimInt, PW> Drawer<'a, AddrT, ColorT, PW>
+where
+    PW: PixelWriter<AddrT, ColorT>,
+{
+    /// Blank columns between consecutive glyphs, replacing the font's own
+    /// `advance - width` (1 for `FONT_3X5`).
+    pub fn set_char_spacing(&mut self, px: u8) {
+        self.text.char_spacing = Some(px);
+    }
+
+    /// Gap between glyphs in use; see `set_char_spacing`.
+    pub fn char_spacing(&self) -> u8 {
+        self.text.char_spacing.unwrap_or(FONT_3X5.advance - FONT_3X5.width)
+    }
+
+    /// Distance between the tops of consecutive lines, i.e. how far a
+    /// `'\n'` moves the pen down. Defaults to the glyph height plus the
+    /// font's native glyph spacing (6 for `FONT_3X5`).
+    pub fn set_line_height(&mut self, px: u8) {
+        self.text.line_height = Some(px);
+    }
+
+    /// Line height in use; see `set_line_height`.
+    pub fn line_height(&self) -> u8 {
+        let native = FONT_3X5.height + FONT_3X5.advance - FONT_3X5.width;
+        self.text.line_height.unwrap_or(native)
+    }
+
+    /// Draw `s` in `FONT_3X5` with its top-left corner at `(i, j)`.
+    ///
+    /// Characters without a glyph are skipped but still advance the pen; a
+    /// `'\n'` starts a new line `line_height()` below, back at column `j`.
+    pub fn draw_str(&mut self, i: AddrT, j: AddrT, s: &str, color: ColorT) {
+        self.draw_str_at(i.to_i64().unwrap(), j.to_i64().unwrap(), s, color, None);
+    }
+
+    /// Draw `s` in `FONT_3X5` centered on `(center_i, center_j)`.
+    ///
+    /// Odd leftovers put the extra pixel below / right of the center. A
+    /// string wider than the screen is left-aligned to the first column
+    /// instead, so its beginning stays readable; the rest is clipped.
+    pub fn draw_str_centered(&mut self, center_i: AddrT, center_j: AddrT, s: &str, color: ColorT) {
+        let font = &FONT_3X5;
+        let ((_, _), (j_min, j_max)) = self.bounds_i64();
+        let width = match s.chars().count() as i64 {
+            0 => 0,
+            n => (n - 1) * self.advance() + font.width as i64,
+        };
+        let i = center_i.to_i64().unwrap() - (font.height as i64 - 1) / 2;
+        let j = if width > j_max - j_min + 1 {
+            j_min
+        } else {
+            center_j.to_i64().unwrap() - (width - 1) / 2
+        };
+        self.draw_str_at(i, j, s, color, None);
+    }
+
+    /// Like `draw_str`, but opaque: each glyph's whole cell, its advance
+    /// by the glyph height, is filled with `bg` before the glyph is drawn
+    /// in `fg`, so the text stays legible over any content.
+    pub fn draw_str_bg(&mut self, i: AddrT, j: AddrT, s: &str, fg: ColorT, bg: ColorT) {
+        self.draw_str_at(i.to_i64().unwrap(), j.to_i64().unwrap(), s, fg, Some(bg));
+    }
+
+    /// Pen advance per character: glyph width plus `char_spacing()`.
+    fn advance(&self) -> i64 {
+        FONT_3X5.width as i64 + self.char_spacing() as i64
+    }
+
+    fn draw_str_at(&mut self, i: i64, j: i64, s: &str, color: ColorT, bg: Option<ColorT>) {
+        let (advance, line_height) = (self.advance(), self.line_height() as i64);
+        let (mut line, mut pen) = (i, j);
+        for c in s.chars() {
+            if c == '\n' {
+                line += line_height;
+                pen = j;
+                continue;
+            }
+            if let Some(bg) = bg {
+                self.fill_box(line, line + FONT_3X5.height as i64 - 1, pen, pen + advance - 1, bg);
+            }
+            self.draw_char(line, pen, c, color);
+            pen += advance;
+        }
+    }
+
+    /// Set pixels of the `FONT_3X5` glyph for `c` at `(i, j)`, clipped;
+    /// nothing for characters without a glyph.
+    fn draw_char(&mut self, i: i64, j: i64, c: char, color: ColorT) {
+        let font = &FONT_3X5;
+        let Some(rows) = font.glyph(c) else { return };
+        for (r, &bits) in rows.iter().enumerate() {
+            for col in 0..font.width as i64 {
+                if (bits << col) & 0x80 != 0 {
+                    self.plot_clipped(i + r as i64, j + col, color);
+                }
+            }
+        }
+    }
+}
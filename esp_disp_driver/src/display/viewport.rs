@@ -0,0 +1,126 @@
+//! Sub-rectangles of one screen, each drawn through its own `Drawer`.
+
+use core::cell::RefCell;
+
+use crate::display::pix_writer::{Capabilities, NativePixelWriter, PixelWriter};
+use crate::error::{Error, Result};
+use crate::utils::PrimInt;
+
+/// `PixelWriter` adapter exposing a `height` x `width` sub-rectangle of a
+/// shared backend, for split screens and dashboards.
+///
+/// Local `(0, 0)` is the viewport's top-left corner; `addr_range()` is
+/// `((0, height - 1), (0, width - 1))`, so a `Drawer` on top clips to the
+/// viewport. Raw `write_pixel` calls outside it are dropped rather than
+/// spilling into the neighbours.
+///
+/// The backend sits in a `RefCell` so several viewports (and their
+/// drawers) can be alive at once; each call borrows it briefly. `present`
+/// is forwarded and shows the whole screen, not just this viewport.
+pub struct Viewport<'w, AddrT, PW> {
+    inner: &'w RefCell<PW>,
+    i0: AddrT,
+    j0: AddrT,
+    height: AddrT,
+    width: AddrT,
+}
+
+impl<'w, AddrT: PrimInt, PW> Viewport<'w, AddrT, PW> {
+    /// The `height` x `width` rectangle of `inner` with top-left corner `(i0, j0)`.
+    ///
+    /// Returns `Error::OutOfRange` if it is empty or does not fit inside the
+    /// backend's `addr_range()`.
+    pub fn new<ColorT: PrimInt>(inner: &'w RefCell<PW>, i0: AddrT, j0: AddrT, height: AddrT, width: AddrT) -> Result<Self>
+    where
+        PW: PixelWriter<AddrT, ColorT>,
+    {
+        let ((i_min, i_max), (j_min, j_max)) = inner.borrow().addr_range();
+        let one = AddrT::one();
+        let last = |start: AddrT, len: AddrT| start.checked_add(&(len.checked_sub(&one)?));
+        let (Some(i1), Some(j1)) = (last(i0, height), last(j0, width)) else {
+            return Err(Error::OutOfRange);
+        };
+        if height < one || width < one || i0 < i_min || j0 < j_min || i1 > i_max || j1 > j_max {
+            return Err(Error::OutOfRange);
+        }
+        Ok(Self { inner, i0, j0, height, width })
+    }
+
+    /// Top-left corner in backend coordinates.
+    pub fn origin(&self) -> (AddrT, AddrT) {
+        (self.i0, self.j0)
+    }
+
+    /// Backend address of local `(i, j)`, if it is inside the viewport.
+    fn to_inner(&self, i: AddrT, j: AddrT) -> Option<(AddrT, AddrT)> {
+        let inside = i >= AddrT::zero() && j >= AddrT::zero() && i < self.height && j < self.width;
+        inside.then(|| (self.i0 + i, self.j0 + j))
+    }
+
+    /// Local span `lo..=hi` clipped to `0..len`, `None` if nothing is left.
+    fn clip_span(lo: AddrT, hi: AddrT, len: AddrT) -> Option<(AddrT, AddrT)> {
+        let (lo, hi) = (lo.max(AddrT::zero()), hi.min(len - AddrT::one()));
+        (lo <= hi).then_some((lo, hi))
+    }
+}
+
+impl<AddrT, PW> NativePixelWriter for Viewport<'_, AddrT, PW>
+where
+    AddrT: PrimInt,
+    PW: NativePixelWriter<Addr = AddrT>,
+{
+    type Addr = AddrT;
+    type Color = PW::Color;
+}
+
+impl<AddrT, ColorT, PW> PixelWriter<AddrT, ColorT> for Viewport<'_, AddrT, PW>
+where
+    AddrT: PrimInt,
+    ColorT: PrimInt,
+    PW: PixelWriter<AddrT, ColorT>,
+{
+    fn write_pixel(&mut self, i: AddrT, j: AddrT, color: ColorT) {
+        if let Some((i, j)) = self.to_inner(i, j) {
+            self.inner.borrow_mut().write_pixel(i, j, color);
+        }
+    }
+
+    fn addr_range(&self) -> ((AddrT, AddrT), (AddrT, AddrT)) {
+        let one = AddrT::one();
+        ((AddrT::zero(), self.height - one), (AddrT::zero(), self.width - one))
+    }
+
+    fn color_range(&self) -> (ColorT, ColorT) {
+        self.inner.borrow().color_range()
+    }
+
+    fn read_pixel(&self, i: AddrT, j: AddrT) -> Option<ColorT> {
+        let (i, j) = self.to_inner(i, j)?;
+        self.inner.borrow().read_pixel(i, j)
+    }
+
+    fn present(&mut self) {
+        self.inner.borrow_mut().present();
+    }
+
+    fn begin_batch(&mut self) {
+        self.inner.borrow_mut().begin_batch();
+    }
+
+    fn end_batch(&mut self) {
+        self.inner.borrow_mut().end_batch();
+    }
+
+    /// Fills the part of the rectangle inside the viewport.
+    fn fill_rect(&mut self, ((i0, i1), (j0, j1)): ((AddrT, AddrT), (AddrT, AddrT)), color: ColorT) {
+        let (Some((i0, i1)), Some((j0, j1))) = (Self::clip_span(i0, i1, self.height), Self::clip_span(j0, j1, self.width)) else {
+            return;
+        };
+        let (di, dj) = (self.i0, self.j0);
+        self.inner.borrow_mut().fill_rect(((di + i0, di + i1), (dj + j0, dj + j1)), color);
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.borrow().capabilities()
+    }
+}
@@ -0,0 +1,26 @@
+//! Crate-wide error type.
+//!
+//! Constructors and setup / batch APIs return `Result<_, Error>`. Per-pixel
+//! and per-sample hot paths stay infallible: they clamp or ignore
+//! out-of-range input instead of panicking, and any configuration they rely
+//! on is checked when the owning object is built.
+
+/// Errors reported by this crate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum Error {
+    /// The bus has more pins than the requested value type can hold.
+    BusWidth,
+    /// An index, frequency or other argument is outside the supported range.
+    OutOfRange,
+    /// A source buffer is shorter than the region it is supposed to cover.
+    BufferTooSmall,
+    /// An optional line (RCLK, SRCLR, readback, ...) the call needs is absent.
+    NotConfigured,
+    /// A readback pin did not respond as expected; check the wiring.
+    NoResponse,
+    /// A frame read back through QH' still differed from the one shifted
+    /// after every retry; see `ParallelBank::with_verify`.
+    VerifyFailed,
+}
+
+pub type Result<T> = core::result::Result<T, Error>;
@@ -1,6 +1,11 @@
 #![no_std]
 #![feature(step_trait)]
+#[cfg(feature = "sim")]
+extern crate alloc;
+pub mod error;
 pub mod sipo;
+pub mod rmt_bank;
 pub mod display;
 pub mod utils;
-pub mod par_data_rw;
\ No newline at end of file
+pub mod par_data_rw;
+pub mod sync_monitor;
\ No newline at end of file
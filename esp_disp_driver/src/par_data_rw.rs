@@ -7,7 +7,13 @@
 //! You can sample the current logic levels and return them as `u8`, `u16`, or `u32`.
 
 use esp_hal::{self as hal, gpio::{InputConfig, OutputConfig, Level}};
-use hal::gpio::{AnyPin, Input, Output};
+use hal::gpio::{AnyPin, Input, Output, Pin};
+use hal::peripherals::GPIO;
+use crate::utils::{delay_cycles, ns_to_cycles, sign_extend};
+use core::marker::PhantomData;
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::StatefulOutputPin;
+use crate::error::{Error, Result};
 
 /// Parallel data reader over a group of GPIO input pins.
 ///
@@ -22,6 +28,31 @@ pub struct ParDataReader<'a, const N: usize> {
     /// Each element is an `Input<'a>` wrapped around an `AnyPin<'a>`.
     /// (The concrete pin type is type-erased by `AnyPin`.)
     pins: [Input<'a>; N],
+    /// GPIO number behind each pin, for `read_atomic_u32`.
+    nums: [u8; N],
+    /// Bit `k` set if a pin lives in GPIO input register `k` (IN / IN1).
+    ports: u8,
+    /// Bits kept by every read; see `with_addr_mask`.
+    mask: u32,
+    /// Bits complemented after sampling; see `with_invert_mask`.
+    invert: u32,
+    /// Index into the `from_pins` array of the pin now at each position;
+    /// see `with_bit_map`.
+    order: [u8; N],
+}
+
+/// Assemble a bus value from raw GPIO input register words.
+///
+/// `ports[0]` holds GPIO 0..=31 (GPIO_IN_REG), `ports[1]` GPIO 32.. (GPIO_IN1_REG);
+/// bit `i` of the result is the level of GPIO `pin_nums[i]`. Only the first
+/// 32 pins are used.
+pub fn gather_port_bits(pin_nums: &[u8], ports: [u32; 2]) -> u32 {
+    let mut value: u32 = 0;
+    for (i, &n) in pin_nums.iter().take(32).enumerate() {
+        let word = ports[(n / 32) as usize];
+        value |= ((word >> (n % 32)) & 1) << i;
+    }
+    value
 }
 
 impl<'a, const N: usize> ParDataReader<'a, N> {
@@ -39,8 +70,89 @@ impl<'a, const N: usize> ParDataReader<'a, N> {
     pub fn from_pins(pins: [AnyPin<'a>; N], input_cfg : InputConfig) -> Self {
         // `Input<'a>` is generic over the concrete pin type, which here is `AnyPin<'a>`.
         // Type inference will set `T = AnyPin<'a>` for each `Input::new`.
+        let nums = pins.each_ref().map(|p| p.number());
+        let ports = nums.iter().fold(0u8, |acc, &n| acc | 1 << (n / 32));
         let pins: [Input<'a>; N] = pins.map(|p: AnyPin<'a>| Input::new(p, input_cfg));
-        Self { pins }
+        Self { pins, nums, ports, mask: u32::MAX, invert: 0, order: core::array::from_fn(|k| k as u8) }
+    }
+
+    /// Clear the bits outside `mask` in every read (all ones by default).
+    ///
+    /// For buses with fewer lines wired than pins, e.g. a 7-bit counter on
+    /// an 8-pin reader: the unconnected pin floats, and masking it keeps its
+    /// noise out of the value. Signed reads sign-extend from the top pin as
+    /// before, so only mask the top bit of unsigned buses.
+    pub fn with_addr_mask(mut self, mask: u32) -> Self {
+        self.mask = mask;
+        self
+    }
+
+    /// Keep only the low `bits` bits of every read, for a counter that
+    /// drives fewer lines than the reader has pins; shorthand for
+    /// `with_addr_mask`. 32 or more keeps everything.
+    pub fn with_used_bits(self, bits: u32) -> Self {
+        self.with_addr_mask(if bits >= 32 { u32::MAX } else { (1 << bits) - 1 })
+    }
+
+    /// The mask applied to reads; see `with_addr_mask`.
+    pub fn addr_mask(&self) -> u32 {
+        self.mask
+    }
+
+    /// Complement the bits set in `mask` after sampling (none by default).
+    ///
+    /// For lines that pass through inverting buffers: the read then
+    /// returns the logical value, not the level at the pin. Applied before
+    /// `with_addr_mask`, so masked-off bits stay 0 either way. Bits past
+    /// the bus width are dropped.
+    pub fn with_invert_mask(mut self, mask: u32) -> Self {
+        self.invert = if N >= 32 { mask } else { mask & ((1 << N) - 1) };
+        self
+    }
+
+    /// The bits complemented on every read; see `with_invert_mask`.
+    pub fn invert_mask(&self) -> u32 {
+        self.invert
+    }
+
+    /// Take pin `k` of the `from_pins` array as logical bit `map[k]`
+    /// instead of bit `k`, for buses wired out of order.
+    ///
+    /// The pins are reordered once here, so every read (`read_u8` through
+    /// `read_atomic_u32`, `read_bits`) returns the reassembled value at no
+    /// extra cost. `with_addr_mask` and `with_invert_mask` refer to the
+    /// logical bits. Each call replaces the previous map. Returns
+    /// `Error::OutOfRange` unless `map` is a permutation of `0..N`. The
+    /// identity by default.
+    pub fn with_bit_map(mut self, map: [u8; N]) -> Result<Self> {
+        // `from_pins` index of each logical bit.
+        let mut src = [u8::MAX; N];
+        for (k, &bit) in map.iter().enumerate() {
+            let slot = src.get_mut(bit as usize).ok_or(Error::OutOfRange)?;
+            if *slot != u8::MAX {
+                return Err(Error::OutOfRange);
+            }
+            *slot = k as u8;
+        }
+        // Current position of each `from_pins` index.
+        let mut pos = [0usize; N];
+        for (p, &k) in self.order.iter().enumerate() {
+            pos[k as usize] = p;
+        }
+        let mut pins = self.pins.map(Some);
+        self.pins = core::array::from_fn(|bit| pins[pos[src[bit] as usize]].take().unwrap());
+        self.nums = core::array::from_fn(|bit| self.nums[pos[src[bit] as usize]]);
+        self.order = src;
+        Ok(self)
+    }
+
+    /// Logical bit of each `from_pins` pin; see `with_bit_map`.
+    pub fn bit_map(&self) -> [u8; N] {
+        let mut map = [0u8; N];
+        for (bit, &k) in self.order.iter().enumerate() {
+            map[k as usize] = bit as u8;
+        }
+        map
     }
 
     /// Read the raw bit values as an array of booleans.
@@ -52,7 +164,8 @@ impl<'a, const N: usize> ParDataReader<'a, N> {
         let mut i: usize = 0;
         while i < N {
             // `Input<'a>` in esp-hal provides `is_high()` / `is_low()` that return bool.
-            out[i] = self.pins[i].is_high();
+            let (keep, flip) = if i < 32 { ((self.mask >> i) & 1 != 0, (self.invert >> i) & 1 != 0) } else { (true, false) };
+            out[i] = keep && (self.pins[i].is_high() != flip);
             i += 1;
         }
         out
@@ -64,25 +177,19 @@ impl<'a, const N: usize> ParDataReader<'a, N> {
     /// - bit 0   ← `pins[0]`  (LSB)
     /// - bit N-1 ← `pins[N-1]` (MSB)
     ///
-    /// # Panics
-    ///
-    /// Panics if `N > 8`, since not all bits can fit into `u8`.
+    /// If `N > 8` only `pins[0..8]` are read; use `try_read_u8()` to reject
+    /// wider buses instead.
     pub fn read_u8(&self) -> u8 {
-        assert!(
-            N <= 8,
-            "ParDataReader::read_u8() called but N > 8; value would not fit into u8"
-        );
-
         let mut value: u8 = 0;
         let mut i: usize = 0;
-        while i < N {
+        while i < N && i < 8 {
             if self.pins[i].is_high() {
                 // pins[i] is bit i, with pins[0] as LSB
                 value |= 1u8 << (i as u8);
             }
             i += 1;
         }
-        value
+        (value ^ self.invert as u8) & self.mask as u8
     }
 
     /// Read the current bus value as `u16`.
@@ -91,24 +198,50 @@ impl<'a, const N: usize> ParDataReader<'a, N> {
     /// - bit 0   ← `pins[0]`  (LSB)
     /// - bit N-1 ← `pins[N-1]` (MSB)
     ///
-    /// # Panics
-    ///
-    /// Panics if `N > 16`, since not all bits can fit into `u16`.
+    /// If `N > 16` only `pins[0..16]` are read; use `try_read_u16()` to
+    /// reject wider buses instead.
     pub fn read_u16(&self) -> u16 {
-        assert!(
-            N <= 16,
-            "ParDataReader::read_u16() called but N > 16; value would not fit into u16"
-        );
-
         let mut value: u16 = 0;
         let mut i: usize = 0;
-        while i < N {
+        while i < N && i < 16 {
             if self.pins[i].is_high() {
                 value |= 1u16 << (i as u16);
             }
             i += 1;
         }
-        value
+        (value ^ self.invert as u16) & self.mask as u16
+    }
+
+    /// Like `read_u8()`, but returns `Error::BusWidth` if `N > 8`.
+    pub fn try_read_u8(&self) -> Result<u8> {
+        if N > 8 {
+            return Err(Error::BusWidth);
+        }
+        Ok(self.read_u8())
+    }
+
+    /// Like `read_u16()`, but returns `Error::BusWidth` if `N > 16`.
+    pub fn try_read_u16(&self) -> Result<u16> {
+        if N > 16 {
+            return Err(Error::BusWidth);
+        }
+        Ok(self.read_u16())
+    }
+
+    /// Read the current bus value as a signed `i8`.
+    ///
+    /// The `N`-bit bus is taken as two's complement with `pins[N-1]` as the
+    /// sign bit, so all-ones reads as -1 for any width. If `N > 8`, only
+    /// `pins[0..8]` are read, with `pins[7]` as the sign bit.
+    pub fn read_i8(&self) -> i8 {
+        sign_extend(self.read_u8() as u32, N.min(8) as u32) as i8
+    }
+
+    /// Read the current bus value as a signed `i16`.
+    ///
+    /// Same sign convention as `read_i8()`, clamped to `pins[0..16]`.
+    pub fn read_i16(&self) -> i16 {
+        sign_extend(self.read_u16() as u32, N.min(16) as u32) as i16
     }
 
     /// Read the current bus value as `u32`.
@@ -129,7 +262,24 @@ impl<'a, const N: usize> ParDataReader<'a, N> {
             }
             i += 1;
         }
-        value
+        (value ^ self.invert) & self.mask
+    }
+
+    /// Read the bus as a `u32` with one register read per GPIO port.
+    ///
+    /// `read_u32()` samples pin by pin, so a bus changing mid-read can
+    /// return a mix of old and new bits. This reads GPIO_IN_REG and/or
+    /// GPIO_IN1_REG once each (only the ports the pins use, grouped at
+    /// construction) and merges them, so all pins on one port are sampled at
+    /// the same instant. A bus split across GPIO 31/32 still has a few
+    /// cycles of skew between its two halves; keep counter buses on one port
+    /// when that matters. Bits beyond 31 are ignored as in `read_u32()`.
+    #[inline]
+    pub fn read_atomic_u32(&self) -> u32 {
+        let regs = GPIO::regs();
+        let in0 = if self.ports & 0b01 != 0 { regs.in_().read().bits() } else { 0 };
+        let in1 = if self.ports & 0b10 != 0 { regs.in1().read().bits() } else { 0 };
+        (gather_port_bits(&self.nums, [in0, in1]) ^ self.invert) & self.mask
     }
 
     /// Convenience alias: read the bus as a `u32`.
@@ -138,10 +288,88 @@ impl<'a, const N: usize> ParDataReader<'a, N> {
         self.read_u32()
     }
 
+    /// Sample the bus `samples` times back to back and return `(min, max, mean)`.
+    ///
+    /// Values are taken with `read_u32()`; the mean is rounded down. Useful for
+    /// characterizing noise on the input lines during bring-up.
+    ///
+    /// Time cost: `samples * N` pin reads with no delay in between, so the
+    /// window covered is roughly `samples` times the cost of one `read_u32()`.
+    /// Returns `(0, 0, 0)` if `samples == 0`.
+    pub fn read_stats(&self, samples: usize) -> (u32, u32, u32) {
+        if samples == 0 {
+            return (0, 0, 0);
+        }
+        let mut min = u32::MAX;
+        let mut max = 0u32;
+        let mut sum: u64 = 0;
+        for _ in 0..samples {
+            let value = self.read_u32();
+            min = min.min(value);
+            max = max.max(value);
+            sum += value as u64;
+        }
+        (min, max, (sum / samples as u64) as u32)
+    }
+
     /// Get a reference to the underlying input pins, e.g., for manual access.
+    ///
+    /// Indexed by logical bit, so after `with_bit_map` the order differs
+    /// from the `from_pins` array.
     pub fn pins(&self) -> &[Input<'a>; N] {
         &self.pins
     }
+
+    /// Mutable access to the input pins, e.g. to change their pull
+    /// configuration; indexed as in `pins`.
+    pub fn pins_mut(&mut self) -> &mut [Input<'a>; N] {
+        &mut self.pins
+    }
+}
+
+/// Split access for a 16-bit bus carrying two 8-bit values.
+///
+/// Matches boards that expose H on the low byte (`pins[0..8]`) and V on the
+/// high byte (`pins[8..16]`) of one counter bus, so a single reader can serve
+/// both address axes.
+impl<'a> ParDataReader<'a, 16> {
+    /// Read the low byte (`pins[0..8]`), e.g. the H address.
+    #[inline]
+    pub fn read_low_u8(&self) -> u8 {
+        self.read_u16() as u8
+    }
+
+    /// Read the high byte (`pins[8..16]`), e.g. the V address.
+    #[inline]
+    pub fn read_high_u8(&self) -> u8 {
+        (self.read_u16() >> 8) as u8
+    }
+
+    /// Read both bytes from one sample of the bus as `(low, high)`.
+    ///
+    /// Prefer this over two separate calls when both halves must be coherent,
+    /// since the counters may advance between two reads.
+    #[inline]
+    pub fn read_split_u8(&self) -> (u8, u8) {
+        let value = self.read_u16();
+        (value as u8, (value >> 8) as u8)
+    }
+}
+
+/// Address bus the bus_dac scan loops sample.
+///
+/// Implemented by `ParDataReader`; the `sim` scan capture implements it
+/// with scripted addresses so the scan mapping runs without hardware.
+pub trait AddrBus {
+    /// Current bus value, low 8 bits.
+    fn read_u8(&self) -> u8;
+}
+
+impl<const N: usize> AddrBus for ParDataReader<'_, N> {
+    #[inline(always)]
+    fn read_u8(&self) -> u8 {
+        ParDataReader::read_u8(self)
+    }
 }
 
 
@@ -149,16 +377,27 @@ impl<'a, const N: usize> ParDataReader<'a, N> {
 
 /// Parallel data writer over a group of GPIO output pins.
 ///
-/// The generic parameter `N` is the number of pins (bus width).
+/// The generic parameter `N` is the number of pins (bus width). Pins are
+/// esp-hal `Output`s by default; any `embedded_hal` `StatefulOutputPin`
+/// works through `new` (pin errors are ignored, as on the sipo lines).
 ///
 /// Bit mapping (mirrors `ParDataReader`):
 /// - `pins[0]`   ← bit 0 (LSB)
 /// - `pins[N-1]` ← bit N-1 (MSB)
-pub struct ParDataWriter<'a, const N: usize> {
+///
+/// An optional strobe pin latches the bus into a sample-and-hold DAC; see
+/// `write_u8_strobed`.
+pub struct ParDataWriter<'a, const N: usize, P = Output<'a>> {
     /// Underlying output pins.
     ///
-    /// Each element is an `Output<'a>` wrapped around an `AnyPin<'a>`.
-    pins: [Output<'a>; N],
+    /// Each element is an `Output<'a>` wrapped around an `AnyPin<'a>` unless
+    /// built from other pins with `new`.
+    pins: [P; N],
+    /// Idle-low strobe pulsed high by `write_u8_strobed`, if attached.
+    strobe: Option<P>,
+    /// Cycles the data is held stable before the strobe rises.
+    strobe_setup_cycles: u32,
+    _pin: PhantomData<&'a ()>,
 }
 
 impl<'a, const N: usize> ParDataWriter<'a, N> {
@@ -182,7 +421,35 @@ impl<'a, const N: usize> ParDataWriter<'a, N> {
     ) -> Self {
         let pins: [Output<'a>; N] =
             pins.map(|p: AnyPin<'a>| Output::new(p, initial_level, output_cfg));
-        Self { pins }
+        Self::new(pins)
+    }
+
+    /// Attach a strobe GPIO, configured with `output_cfg` and idling low.
+    pub fn with_strobe_pin(self, strobe: AnyPin<'a>, output_cfg: OutputConfig) -> Self {
+        self.with_strobe(Output::new(strobe, Level::Low, output_cfg))
+    }
+}
+
+impl<'a, const N: usize, P: StatefulOutputPin> ParDataWriter<'a, N, P> {
+    /// Wrap already configured output pins; see `from_pins` for the mapping.
+    pub fn new(pins: [P; N]) -> Self {
+        Self { pins, strobe: None, strobe_setup_cycles: 0, _pin: PhantomData }
+    }
+
+    /// Attach an idle-low strobe line for `write_u8_strobed`.
+    ///
+    /// Without one, `write_u8_strobed` is a plain `write_u8`.
+    pub fn with_strobe(mut self, mut strobe: P) -> Self {
+        let _ = strobe.set_low();
+        self.strobe = Some(strobe);
+        self
+    }
+
+    /// Hold the data for at least `ns` before the strobe rises, e.g. the
+    /// DAC's data setup time. `cpu_hz` is the current CPU clock.
+    pub fn with_strobe_setup_ns(mut self, ns: u32, cpu_hz: u32) -> Self {
+        self.strobe_setup_cycles = ns_to_cycles(ns, cpu_hz);
+        self
     }
 
     /// Set a single bit line to the given value.
@@ -192,11 +459,30 @@ impl<'a, const N: usize> ParDataWriter<'a, N> {
         if idx >= N {
             return;
         }
-        if bit {
-            self.pins[idx].set_high();
-        } else {
-            self.pins[idx].set_low();
+        let _ = if bit { self.pins[idx].set_high() } else { self.pins[idx].set_low() };
+    }
+
+    /// Invert a single bit line, leaving the others untouched.
+    ///
+    /// - `idx` is the bit index (0-based); out-of-range indices are ignored.
+    pub fn toggle_bit(&mut self, idx: usize) {
+        if idx >= N {
+            return;
+        }
+        let _ = self.pins[idx].toggle();
+    }
+
+    /// Strobe a single bit line: toggle it and toggle it back.
+    ///
+    /// The pulse goes away from the line's current level, so an idle-low
+    /// line gets a high pulse and an idle-high (active-low) line a low one.
+    /// Out-of-range indices are ignored.
+    pub fn pulse_bit(&mut self, idx: usize) {
+        if idx >= N {
+            return;
         }
+        let _ = self.pins[idx].toggle();
+        let _ = self.pins[idx].toggle();
     }
 
     /// Drive all pins from a boolean array.
@@ -217,27 +503,67 @@ impl<'a, const N: usize> ParDataWriter<'a, N> {
     /// - bit 0   → `pins[0]`  (LSB)
     /// - bit N-1 → `pins[N-1]` (MSB)
     ///
-    /// # Panics
-    ///
-    /// Panics if `N > 8`, since higher bits would be inaccessible.
+    /// If `N > 8`, `pins[8..]` are left unchanged.
     pub fn write_u8(&mut self, value: u8) {
         let mut i: usize = 0;
-        while i < N {
+        while i < N && i < 8 {
             let bit = ((value >> i) & 0x01) != 0;
             self.set_bit(i, bit);
             i += 1;
         }
     }
 
-    /// Drive the bus from a `u16` value.
+    /// `write_u8`, then latch it with a high pulse on the strobe line.
     ///
-    /// # Panics
+    /// The strobe rises only after every data pin has been driven and the
+    /// setup time (`with_strobe_setup_ns`) has passed, matching the "data
+    /// stable before latch" requirement of sample-and-hold DACs. Behaves as
+    /// `write_u8` when no strobe is attached.
+    pub fn write_u8_strobed(&mut self, value: u8) {
+        self.write_u8(value);
+        self.latch();
+    }
+
+    /// Pulse the strobe, if attached, after the setup time.
+    fn latch(&mut self) {
+        if let Some(strobe) = &mut self.strobe {
+            delay_cycles(self.strobe_setup_cycles);
+            let _ = strobe.set_high();
+            let _ = strobe.set_low();
+        }
+    }
+
+    /// Step the bus through every code from 0 to the top one, holding each
+    /// for `dwell_us`, to measure the DAC's transfer curve.
+    ///
+    /// Drive the DAC from nothing else while this runs: no scan loop, no
+    /// framebuffer. On a scope the output is a rising staircase of `2^N`
+    /// steps, from 0 V at code 0 to full scale at `2^N - 1` (4 bits: 16
+    /// steps of ~1/15 of full scale each), `dwell_us` wide, after which the
+    /// bus stays at the top code. A linear DAC gives equal step heights;
+    /// resistor mismatch shows as uneven steps, worst at the MSB transition
+    /// (half scale), and a step going down means a non-monotonic DAC or
+    /// swapped data lines. Give a multimeter ~500 ms per step to settle.
+    ///
+    /// Each code is latched with the strobe when one is attached, as in
+    /// `write_u8_strobed`. Blocks for `2^N * dwell_us`; for `N >= 32` the
+    /// sweep stops at `u32::MAX`.
+    pub fn ramp(&mut self, delay: &mut impl DelayNs, dwell_us: u32) {
+        let top = if N >= 32 { u32::MAX } else { (1u32 << N) - 1 };
+        for code in 0..=top {
+            self.write_u32(code);
+            self.latch();
+            delay.delay_us(dwell_us);
+        }
+    }
+
+    /// Drive the bus from a `u16` value.
     ///
-    /// Panics if `N > 16`, since higher bits would be inaccessible.
+    /// If `N > 16`, `pins[16..]` are left unchanged.
     pub fn write_u16(&mut self, value: u16) {
 
         let mut i: usize = 0;
-        while i < N {
+        while i < N && i < 16 {
             let bit = ((value >> i) & 0x0001) != 0;
             self.set_bit(i, bit);
             i += 1;
@@ -263,7 +589,20 @@ impl<'a, const N: usize> ParDataWriter<'a, N> {
     }
 
     /// Get a mutable reference to the underlying output pins, e.g., for manual access.
-    pub fn pins_mut(&mut self) -> &mut [Output<'a>; N] {
+    pub fn pins_mut(&mut self) -> &mut [P; N] {
         &mut self.pins
     }
-}
\ No newline at end of file
+}
+
+/// Data bus the bus_dac scan loops drive; see `AddrBus`.
+pub trait DataBus {
+    /// Output one color code.
+    fn write_u8(&mut self, value: u8);
+}
+
+impl<const N: usize, P: StatefulOutputPin> DataBus for ParDataWriter<'_, N, P> {
+    #[inline(always)]
+    fn write_u8(&mut self, value: u8) {
+        ParDataWriter::write_u8(self, value)
+    }
+}
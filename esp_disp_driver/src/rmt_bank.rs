@@ -0,0 +1,255 @@
+//! Shift-register output through the RMT peripheral.
+//!
+//! `ParallelBank` toggles SRCLK and the SER lanes from the CPU, so bit
+//! timing jitters with interrupts and cache misses. `RmtBank` encodes a
+//! frame into RMT symbols instead, one TX channel playing SRCLK and one per
+//! SER lane, and the hardware plays them back with exact timing. RCLK is
+//! still a GPIO pulsed once the frame is in.
+//!
+//! Channel and memory requirements on the ESP32-S3:
+//!
+//! - `LANES + 1` TX channels out of the 4 (`RMT_TX_CHANNELS`), so at most
+//!   3 SER lanes per bank.
+//! - A frame takes `8 * N + 1` symbols per channel (one per bit plus the
+//!   end marker). A channel's memory block holds `RMT_BLOCK_SYMBOLS` (48),
+//!   so `N <= 5` fits one block. Longer frames, up to `MAX_RMT_FRAME_BYTES`
+//!   (8), either take two blocks per channel (`memsize` 2), which leaves
+//!   fewer channels free, or are refilled as they play: `EspRmtGroup`
+//!   polls every channel until all are done, so none of them runs dry
+//!   while another is being waited on.
+//! - Configure every channel with idle output enabled at the low level, so
+//!   SRCLK falls after its last rising edge and SER rests low.
+//!
+//! The channels are started one after the other, SER lanes first and SRCLK
+//! last. SRCLK rises half a bit into every bit, so the start-up skew
+//! between the first SER channel and SRCLK must stay below half a bit
+//! period. Expect a few hundred ns, so keep half a bit at 1 us or more
+//! (`half_ticks` of 80 at the 80 MHz RMT clock).
+
+use crate::error::{Error, Result};
+use crate::sipo::{LatchLine, ShiftBank};
+use embedded_hal::digital::OutputPin;
+use esp_hal::gpio::Output;
+
+/// TX channels of the ESP32-S3 RMT.
+pub const RMT_TX_CHANNELS: usize = 4;
+/// Symbols in one RMT channel memory block.
+pub const RMT_BLOCK_SYMBOLS: usize = 48;
+/// Longest frame per lane `RmtBank` encodes, in bytes.
+pub const MAX_RMT_FRAME_BYTES: usize = 8;
+
+const MAX_SYMBOLS: usize = 8 * MAX_RMT_FRAME_BYTES + 1;
+/// Longest duration a symbol half can hold, in RMT ticks.
+const MAX_TICKS: u16 = 0x7FFF;
+
+/// One RMT symbol: `level0` for `ticks0` ticks, then `level1` for
+/// `ticks1`, in the hardware word layout. A zero duration ends the
+/// transmission.
+pub const fn symbol(level0: bool, ticks0: u16, level1: bool, ticks1: u16) -> u32 {
+    ((level1 as u32) << 31) | (((ticks1 & MAX_TICKS) as u32) << 16) | ((level0 as u32) << 15) | (ticks0 & MAX_TICKS) as u32
+}
+
+/// Encode `frame` as a SER waveform, MSB of byte 0 first: each bit held
+/// for `2 * half_ticks`. Writes `8 * frame.len() + 1` symbols (with the end
+/// marker) to `out` and returns the count, or `Error::BufferTooSmall`.
+pub fn encode_ser(frame: &[u8], half_ticks: u16, out: &mut [u32]) -> Result<usize> {
+    let len = 8 * frame.len() + 1;
+    if out.len() < len {
+        return Err(Error::BufferTooSmall);
+    }
+    let bits = frame.iter().flat_map(|&byte| (0..8).rev().map(move |bit| (byte >> bit) & 0x01 != 0));
+    for (slot, bit) in out.iter_mut().zip(bits) {
+        *slot = symbol(bit, half_ticks, bit, half_ticks);
+    }
+    out[len - 1] = 0;
+    Ok(len)
+}
+
+/// Encode the SRCLK waveform for `bits` bits: low for the first half of
+/// each bit, rising in the middle, where SER is stable. Writes `bits + 1`
+/// symbols to `out` and returns the count, or `Error::BufferTooSmall`.
+pub fn encode_srclk(bits: usize, half_ticks: u16, out: &mut [u32]) -> Result<usize> {
+    if out.len() < bits + 1 {
+        return Err(Error::BufferTooSmall);
+    }
+    out[..bits].fill(symbol(false, half_ticks, true, half_ticks));
+    out[bits] = 0;
+    Ok(bits + 1)
+}
+
+/// The RMT TX channels behind an `RmtBank`.
+///
+/// Implemented by `EspRmtGroup` for esp-hal channels; tests can play the
+/// symbols into a shift register model instead.
+pub trait RmtTxGroup {
+    /// Start one channel per SER lane playing `ser[k]`, then the SRCLK
+    /// channel playing `srclk`, and block until all of them are done.
+    fn transmit(&mut self, srclk: &[u32], ser: &[&[u32]]) -> Result<()>;
+}
+
+type TxChannel<'d> = esp_hal::rmt::Channel<'d, esp_hal::Blocking, esp_hal::rmt::Tx>;
+
+/// Blocking esp-hal TX channels: `srclk` and one per SER lane.
+pub struct EspRmtGroup<'d, const LANES: usize> {
+    srclk: Option<TxChannel<'d>>,
+    ser: [Option<TxChannel<'d>>; LANES],
+}
+
+impl<'d, const LANES: usize> EspRmtGroup<'d, LANES> {
+    /// Channels already configured with their pins; see the module
+    /// documentation for the idle level and memory size.
+    pub fn new(srclk: TxChannel<'d>, ser: [TxChannel<'d>; LANES]) -> Self {
+        Self { srclk: Some(srclk), ser: ser.map(Some) }
+    }
+}
+
+/// Put the channel of a finished or failed transmission back in `slot`.
+fn reclaim<'d>(
+    slot: &mut Option<TxChannel<'d>>,
+    res: core::result::Result<TxChannel<'d>, (esp_hal::rmt::Error, TxChannel<'d>)>,
+) -> Result<()> {
+    let (channel, res) = match res {
+        Ok(channel) => (channel, Ok(())),
+        Err((_, channel)) => (channel, Err(Error::NoResponse)),
+    };
+    *slot = Some(channel);
+    res
+}
+
+impl<const LANES: usize> RmtTxGroup for EspRmtGroup<'_, LANES> {
+    /// On an error the lanes already started still play out. A channel
+    /// whose transmission fails while playing returns to the group for the
+    /// next frame, but esp-hal consumes a channel that refuses to start: it
+    /// is lost, and later calls return `Error::NotConfigured`.
+    fn transmit(&mut self, srclk: &[u32], ser: &[&[u32]]) -> Result<()> {
+        let mut res = Ok(());
+        let mut lanes = core::array::from_fn::<_, LANES, _>(|_| None);
+        for ((tx, channel), symbols) in lanes.iter_mut().zip(self.ser.iter_mut()).zip(ser) {
+            match channel.take().map(|channel| channel.transmit(symbols)) {
+                Some(Ok(started)) => *tx = Some(started),
+                Some(Err(_)) => res = Err(Error::NoResponse),
+                None => res = Err(Error::NotConfigured),
+            }
+            if res.is_err() {
+                break;
+            }
+        }
+        let mut clock = None;
+        if res.is_ok() {
+            match self.srclk.take().map(|channel| channel.transmit(srclk)) {
+                Some(Ok(started)) => clock = Some(started),
+                Some(Err(_)) => res = Err(Error::NoResponse),
+                None => res = Err(Error::NotConfigured),
+            }
+        }
+        // Poll all of them in turn: a transmission longer than its channel
+        // memory is refilled from `poll`, and must not wait on the others.
+        loop {
+            let mut busy = false;
+            for tx in lanes.iter_mut().flatten().chain(clock.iter_mut()) {
+                busy |= !tx.poll();
+            }
+            if !busy {
+                break;
+            }
+        }
+        if let Some(tx) = clock {
+            res = res.and(reclaim(&mut self.srclk, tx.wait()));
+        }
+        for (tx, channel) in lanes.into_iter().zip(self.ser.iter_mut()) {
+            if let Some(tx) = tx {
+                res = res.and(reclaim(channel, tx.wait()));
+            }
+        }
+        res
+    }
+}
+
+/// `ParallelBank` counterpart whose shifts are played back by the RMT.
+///
+/// `LANES` SER lanes share SRCLK and RCLK as in `ParallelBank`, with the
+/// same frame layout: byte 0 is shifted first, MSB first. Each bit lasts
+/// `2 * half_ticks` RMT ticks.
+pub struct RmtBank<'a, T, const LANES: usize, const N: usize, P = Output<'a>> {
+    pub tx: T,
+    pub latch: Option<LatchLine<'a, P>>,
+    half_ticks: u16,
+    /// Frames of the last shift; see `last_frame`.
+    sent: Option<[[u8; N]; LANES]>,
+}
+
+impl<'a, T: RmtTxGroup, const LANES: usize, const N: usize, P: OutputPin> RmtBank<'a, T, LANES, N, P> {
+    /// Returns `Error::OutOfRange` unless `half_ticks` is 1 to 32767.
+    pub fn new(tx: T, latch: Option<LatchLine<'a, P>>, half_ticks: u16) -> Result<Self> {
+        const { assert!(LANES < RMT_TX_CHANNELS, "RmtBank needs one RMT TX channel per lane plus SRCLK") };
+        const { assert!(N >= 1 && N <= MAX_RMT_FRAME_BYTES, "RmtBank frames must be 1..=MAX_RMT_FRAME_BYTES bytes") };
+        if half_ticks == 0 || half_ticks > MAX_TICKS {
+            return Err(Error::OutOfRange);
+        }
+        Ok(Self { tx, latch, half_ticks, sent: None })
+    }
+
+    /// Half a bit period in RMT ticks.
+    pub fn half_ticks(&self) -> u16 {
+        self.half_ticks
+    }
+
+    /// Shift one frame per lane without latching.
+    pub fn shift_exact(&mut self, frames: [[u8; N]; LANES]) -> Result<()> {
+        let mut clock = [0u32; MAX_SYMBOLS];
+        let mut lanes = [[0u32; MAX_SYMBOLS]; LANES];
+        let len = encode_srclk(8 * N, self.half_ticks, &mut clock)?;
+        for (symbols, frame) in lanes.iter_mut().zip(&frames) {
+            encode_ser(frame, self.half_ticks, symbols)?;
+        }
+        let ser: [&[u32]; LANES] = core::array::from_fn(|lane| &lanes[lane][..len]);
+        self.tx.transmit(&clock[..len], &ser)?;
+        self.sent = Some(frames);
+        Ok(())
+    }
+
+    /// Pulse RCLK; `Error::NotConfigured` without a latch line.
+    pub fn latch(&mut self) -> Result<()> {
+        self.latch.as_mut().ok_or(Error::NotConfigured)?.pulse();
+        Ok(())
+    }
+
+    /// Shift one frame per lane, then latch once.
+    pub fn write_exact(&mut self, frames: [[u8; N]; LANES]) -> Result<()> {
+        self.shift_exact(frames)?;
+        self.latch()
+    }
+
+    /// Shift the same `frame` into every lane, then latch once.
+    pub fn write_same(&mut self, frame: [u8; N]) -> Result<()> {
+        self.write_exact([frame; LANES])
+    }
+
+    /// Frames of the last successful shift, if any.
+    pub fn last_frame(&self) -> Option<&[[u8; N]; LANES]> {
+        self.sent.as_ref()
+    }
+}
+
+impl<'a, T: RmtTxGroup, const LANES: usize, const N: usize, P: OutputPin> ShiftBank<LANES, N> for RmtBank<'a, T, LANES, N, P> {
+    fn shift_exact(&mut self, frames: [[u8; N]; LANES]) -> Result<()> {
+        RmtBank::shift_exact(self, frames)
+    }
+
+    fn latch(&mut self) -> Result<()> {
+        RmtBank::latch(self)
+    }
+
+    fn can_latch(&self) -> bool {
+        self.latch.is_some()
+    }
+
+    /// `Error::NotConfigured`: the RMT bank has no SRCLR line.
+    fn clear_all(&mut self) -> Result<()> {
+        Err(Error::NotConfigured)
+    }
+
+    fn last_frame(&self) -> Option<&[[u8; N]; LANES]> {
+        RmtBank::last_frame(self)
+    }
+}
@@ -1,47 +1,116 @@
-use defmt::warn;
+use crate::utils::{delay_cycles, no_feed, ns_to_cycles, FeedFn};
+use crate::error::{Error, Result};
+use core::marker::PhantomData;
+use embedded_hal::digital::OutputPin;
 use esp_hal::{self as hal, gpio};
-use hal::gpio::{AnyPin, Level, Output, OutputConfig};
+use hal::gpio::{AnyPin, Input, InputConfig, Level, Output, OutputConfig};
 
 /// Common output configuration for 74HC595-style shift registers.
 ///
-/// - Open-drain with pull-up is useful when level-shifting from 3.3 V to a higher rail.
-/// - You can adjust this if your hardware uses different wiring.
+/// - Open-drain, so an external pull-up to a higher rail level-shifts from 3.3 V.
+/// - No internal pull; see `shiftreg_output_cfg_with_pull` for boards without
+///   external resistors.
 fn shiftreg_output_cfg() -> OutputConfig {
+    shiftreg_output_cfg_with_pull(gpio::Pull::None)
+}
+
+/// The shift-register output config with the internal pull set to `pull`.
+///
+/// Open-drain lines need a pull-up somewhere: `Pull::Up` uses the weak
+/// (~45 kOhm) internal one, which saves the external resistor on slow
+/// buses but limits edge rates; keep `Pull::None` when the board has its
+/// own pull-ups, e.g. to 5 V, which the internal one would fight.
+pub fn shiftreg_output_cfg_with_pull(pull: gpio::Pull) -> OutputConfig {
     OutputConfig::default()
         .with_drive_mode(gpio::DriveMode::OpenDrain)
-        .with_pull(gpio::Pull::None)
+        .with_pull(pull)
 }
 
+/// Upper bound on chained registers probed by `detect_chain_len`.
+pub const MAX_CHAIN_REGS: usize = 32;
+
 /* ============================== CONTROL PLANE ============================== */
 
+// The line drivers are generic over `embedded_hal::digital::OutputPin`, so an
+// I2C GPIO expander or a mock pin can stand in for an esp-hal `Output` (the
+// default). Pin errors are ignored on these hot paths; esp-hal pins are
+// infallible.
+
+/// Drive `pin` to `high`, ignoring pin errors.
+#[inline(always)]
+fn drive<P: OutputPin>(pin: &mut P, high: bool) {
+    let _ = if high { pin.set_high() } else { pin.set_low() };
+}
+
 /// Latch line (RCLK).
 ///
 /// A single pulse on this line latches the contents of the internal shift
 /// register to the output register of all chained 74HC595 devices.
-pub struct LatchLine<'a> {
-    rclk: Output<'a>,
+///
+/// 74HC595 latches on the rising edge of RCLK; `active_low` inverts the
+/// idle level and pulse direction for boards where RCLK passes through an
+/// inverting buffer.
+pub struct LatchLine<'a, P = Output<'a>> {
+    rclk: P,
+    active_low: bool,
+    /// Extra cycles to hold the line active during a pulse.
+    high_cycles: u32,
+    _pin: PhantomData<&'a ()>,
+}
+
+impl<'a, P: OutputPin> LatchLine<'a, P> {
+    /// Wrap an already configured output pin and drive it to the idle level.
+    pub fn new(mut rclk: P, active_low: bool) -> Self {
+        drive(&mut rclk, active_low);
+        Self {
+            rclk,
+            active_low,
+            high_cycles: 0,
+            _pin: PhantomData,
+        }
+    }
+
+    /// Emit a single latch pulse according to the configured polarity:
+    /// low -> high -> low, or high -> low -> high if `active_low`.
+    #[inline]
+    pub fn pulse(&mut self) {
+        drive(&mut self.rclk, !self.active_low);
+        delay_cycles(self.high_cycles);
+        drive(&mut self.rclk, self.active_low);
+    }
 }
 
 impl<'a> LatchLine<'a> {
     /// Create a latch line driver from a pin.
-    pub fn from_pin(rclk: AnyPin<'a>) -> Self {
+    ///
+    /// `active_low` should be `false` for RCLK wired directly to a 74HC595.
+    pub fn from_pin(rclk: AnyPin<'a>, active_low: bool) -> Self {
         let cfg = shiftreg_output_cfg();
-        Self {
-            rclk: Output::new(rclk, Level::Low, cfg),
-        }
+        Self::from_pin_w_cfg(rclk, active_low, cfg)
     }
 
-    pub fn from_pin_w_cfg(rclk: AnyPin<'a>, cfg: OutputConfig) -> Self {
-        Self {
-            rclk: Output::new(rclk, Level::Low, cfg),
-        }
+    /// Like `from_pin`, with the internal pull set to `pull`.
+    pub fn from_pin_w_pull(rclk: AnyPin<'a>, active_low: bool, pull: gpio::Pull) -> Self {
+        Self::from_pin_w_cfg(rclk, active_low, shiftreg_output_cfg_with_pull(pull))
     }
 
-    /// Emit a single latch pulse: low -> high -> low.
-    #[inline]
-    pub fn pulse(&mut self) {
-        self.rclk.set_high();
-        self.rclk.set_low();
+    pub fn from_pin_w_cfg(rclk: AnyPin<'a>, active_low: bool, cfg: OutputConfig) -> Self {
+        let init_level = if active_low { Level::High } else { Level::Low };
+        Self::new(Output::new(rclk, init_level, cfg), active_low)
+    }
+
+    /// Create a latch line whose pulse stays active for at least `high_ns`.
+    ///
+    /// `cpu_hz` is the current CPU clock, used to convert `high_ns` to cycles.
+    pub fn with_delay(rclk: AnyPin<'a>, active_low: bool, high_ns: u32, cpu_hz: u32) -> Self {
+        let mut line = Self::from_pin(rclk, active_low);
+        line.high_cycles = ns_to_cycles(high_ns, cpu_hz);
+        line
+    }
+
+    /// Reconfigure the pin at runtime; the driven level is kept.
+    pub fn apply_config(&mut self, cfg: &OutputConfig) {
+        self.rclk.apply_config(cfg);
     }
 }
 
@@ -49,9 +118,33 @@ impl<'a> LatchLine<'a> {
 ///
 /// For 74HC595, this line is active-low: pulling it low clears the shift
 /// register contents.
-pub struct ClearLine<'a> {
-    srclr: Output<'a>,
+pub struct ClearLine<'a, P = Output<'a>> {
+    srclr: P,
     active_low: bool,
+    /// Extra cycles to hold the line active during a pulse.
+    high_cycles: u32,
+    _pin: PhantomData<&'a ()>,
+}
+
+impl<'a, P: OutputPin> ClearLine<'a, P> {
+    /// Wrap an already configured output pin and drive it to the idle level.
+    pub fn new(mut srclr: P, active_low: bool) -> Self {
+        drive(&mut srclr, active_low);
+        Self {
+            srclr,
+            active_low,
+            high_cycles: 0,
+            _pin: PhantomData,
+        }
+    }
+
+    /// Emit a single clear pulse according to the configured polarity.
+    #[inline]
+    pub fn pulse(&mut self) {
+        drive(&mut self.srclr, !self.active_low);
+        delay_cycles(self.high_cycles);
+        drive(&mut self.srclr, self.active_low);
+    }
 }
 
 impl<'a> ClearLine<'a> {
@@ -60,31 +153,22 @@ impl<'a> ClearLine<'a> {
     /// `active_low` should be `true` for 74HC595's \SRCLR.
     pub fn from_pin(srclr: AnyPin<'a>, active_low: bool) -> Self {
         let cfg = shiftreg_output_cfg();
-        let init_level = if active_low { Level::High } else { Level::Low };
-        Self {
-            srclr: Output::new(srclr, init_level, cfg),
-            active_low,
-        }
+        Self::from_pin_w_cfg(srclr, active_low, cfg)
+    }
+
+    /// Like `from_pin`, with the internal pull set to `pull`.
+    pub fn from_pin_w_pull(srclr: AnyPin<'a>, active_low: bool, pull: gpio::Pull) -> Self {
+        Self::from_pin_w_cfg(srclr, active_low, shiftreg_output_cfg_with_pull(pull))
     }
 
     pub fn from_pin_w_cfg(srclr: AnyPin<'a>, active_low: bool, cfg: OutputConfig) -> Self {
         let init_level = if active_low { Level::High } else { Level::Low };
-        Self {
-            srclr: Output::new(srclr, init_level, cfg),
-            active_low,
-        }
+        Self::new(Output::new(srclr, init_level, cfg), active_low)
     }
 
-    /// Emit a single clear pulse according to the configured polarity.
-    #[inline]
-    pub fn pulse(&mut self) {
-        if self.active_low {
-            self.srclr.set_low();
-            self.srclr.set_high();
-        } else {
-            self.srclr.set_high();
-            self.srclr.set_low();
-        }
+    /// Reconfigure the pin at runtime; the driven level is kept.
+    pub fn apply_config(&mut self, cfg: &OutputConfig) {
+        self.srclr.apply_config(cfg);
     }
 }
 
@@ -92,24 +176,97 @@ impl<'a> ClearLine<'a> {
 ///
 /// Every tick on this line shifts the entire daisy chain of 74HC595 devices
 /// by one bit.
-pub struct ShiftClockLine<'a> {
-    srclk: Output<'a>,
+pub struct ShiftClockLine<'a, P = Output<'a>> {
+    srclk: P,
+    /// Extra cycles to hold the line high during a tick.
+    high_cycles: u32,
+    /// Extra cycles to hold the line low after a tick; see `set_duty`.
+    low_cycles: u32,
+    _pin: PhantomData<&'a ()>,
 }
 
-impl<'a> ShiftClockLine<'a> {
-    /// Create a shift clock line driver from a pin.
-    pub fn from_pin(srclk: AnyPin<'a>) -> Self {
-        let cfg = shiftreg_output_cfg();
+impl<'a, P: OutputPin> ShiftClockLine<'a, P> {
+    /// Wrap an already configured output pin and drive it low.
+    pub fn new(mut srclk: P) -> Self {
+        drive(&mut srclk, false);
         Self {
-            srclk: Output::new(srclk, Level::Low, cfg),
+            srclk,
+            high_cycles: 0,
+            low_cycles: 0,
+            _pin: PhantomData,
         }
     }
 
     /// Emit a single shift clock: low -> high -> low.
+    ///
+    /// Uses the hold times from `set_duty`; with the default of 0 / 0 both
+    /// phases are as short as the pin writes allow.
     #[inline]
     pub fn tick(&mut self) {
-        self.srclk.set_high();
-        self.srclk.set_low();
+        self.tick_duty(self.high_cycles, self.low_cycles);
+    }
+
+    /// Emit a single shift clock, holding the line high for `high_cycles`
+    /// and then low for `low_cycles` before returning.
+    ///
+    /// Cycle math: each phase lasts its hold plus one pin write (a few
+    /// cycles through the GPIO peripheral), and `delay_cycles` polls the
+    /// cycle counter, overshooting by up to one poll. The low phase is
+    /// stretched further by whatever the caller does before the next tick:
+    /// setting SER takes one pin write per lane. At `cpu_hz`, a clock of
+    /// `f` Hz with a 50 % duty cycle therefore wants
+    /// `high = low ≈ cpu_hz / (2 * f)` minus that overhead; `ns_to_cycles`
+    /// converts datasheet times. Check the result on a scope for marginal
+    /// signal-integrity cases.
+    #[inline]
+    pub fn tick_duty(&mut self, high_cycles: u32, low_cycles: u32) {
+        drive(&mut self.srclk, true);
+        delay_cycles(high_cycles);
+        drive(&mut self.srclk, false);
+        delay_cycles(low_cycles);
+    }
+
+    /// Make every `tick` hold high for `high_cycles` and low for
+    /// `low_cycles`; see `tick_duty` for the cycle math.
+    pub fn set_duty(&mut self, high_cycles: u32, low_cycles: u32) {
+        self.high_cycles = high_cycles;
+        self.low_cycles = low_cycles;
+    }
+
+    /// The `(high_cycles, low_cycles)` hold times `tick` uses.
+    pub fn duty(&self) -> (u32, u32) {
+        (self.high_cycles, self.low_cycles)
+    }
+}
+
+impl<'a> ShiftClockLine<'a> {
+    /// Create a shift clock line driver from a pin.
+    pub fn from_pin(srclk: AnyPin<'a>) -> Self {
+        let cfg = shiftreg_output_cfg();
+        Self::from_pin_w_cfg(srclk, cfg)
+    }
+
+    /// Like `from_pin`, with the internal pull set to `pull`.
+    pub fn from_pin_w_pull(srclk: AnyPin<'a>, pull: gpio::Pull) -> Self {
+        Self::from_pin_w_cfg(srclk, shiftreg_output_cfg_with_pull(pull))
+    }
+
+    pub fn from_pin_w_cfg(srclk: AnyPin<'a>, cfg: OutputConfig) -> Self {
+        Self::new(Output::new(srclk, Level::Low, cfg))
+    }
+
+    /// Create a shift clock line whose ticks stay high for at least `high_ns`.
+    ///
+    /// Useful with slow level shifters; `cpu_hz` is the current CPU clock.
+    pub fn with_delay(srclk: AnyPin<'a>, high_ns: u32, cpu_hz: u32) -> Self {
+        let mut line = Self::from_pin(srclk);
+        line.high_cycles = ns_to_cycles(high_ns, cpu_hz);
+        line
+    }
+
+    /// Reconfigure the pin at runtime; the driven level is kept.
+    pub fn apply_config(&mut self, cfg: &OutputConfig) {
+        self.srclk.apply_config(cfg);
     }
 }
 
@@ -120,21 +277,66 @@ impl<'a> ShiftClockLine<'a> {
 pub struct ControlPinCfg<'a> {
     /// Shift clock (SRCLK), required.
     pub srclk: AnyPin<'a>,
-    /// Latch clock (RCLK), optional because it can be chained with srclk
+    /// Latch clock (RCLK), optional because it can be tied to srclk; see
+    /// `LatchSource::SharedWithShift`.
     pub rclk: Option<AnyPin<'a>>,
     /// Clear line (\SRCLR), optional, usually active-low.
     pub srclr: Option<AnyPin<'a>>,
     pub clr_active_low: bool,
+    /// Latch through an inverting buffer (high -> low -> high pulse).
+    pub latch_active_low: bool,
+}
+
+impl<'a> ControlPinCfg<'a> {
+    /// SRCLK only, with the default polarities: \SRCLR active low, RCLK
+    /// active high. Fill in the rest with struct update syntax, so new
+    /// options keep their defaults:
+    ///
+    /// ```ignore
+    /// let cfg = ControlPinCfg { rclk: Some(rclk), ..ControlPinCfg::new(srclk) };
+    /// ```
+    pub fn new(srclk: AnyPin<'a>) -> Self {
+        Self { srclk, rclk: None, srclr: None, clr_active_low: true, latch_active_low: false }
+    }
+}
+
+/// Where a `ControlGroup`'s latch pulse comes from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LatchSource {
+    /// Pulse the group's own RCLK line.
+    #[default]
+    Dedicated,
+    /// RCLK is tied to SRCLK on the board (directly or through a small
+    /// delay network), so every shift clock also latches.
+    ///
+    /// A 74HC595 with tied clocks latches the shift register as it was
+    /// *before* each tick, so the outputs trail the shift register by one
+    /// bit. A latch is therefore one extra SRCLK tick after the frame:
+    ///
+    /// 1. the `8 * N` frame ticks (plus gap bits) shift the frame in, the
+    ///    outputs rippling along one bit behind;
+    /// 2. `latch_all` clocks one more tick, which copies the complete frame
+    ///    to the outputs and shifts whatever SER last held one place past
+    ///    it; the next full frame pushes that bit out of the chain again.
+    ///
+    /// Any delay network must keep RCLK from rising after the shift
+    /// register's outputs change (about 10 ns on a 74HC595), or each tick
+    /// latches the post-shift contents and the frame shows one bit off.
+    /// The outputs are never stable mid-write, and `with_latch_per_byte`
+    /// is ignored. The group's `latch` line, if any, is unused.
+    SharedWithShift,
 }
 
 /// Complete control group for a set of shift-register chains.
 ///
 /// Whether this group is "shared" or "exclusive" depends on how many lanes
 /// you pass it to. The type itself does not enforce sharing vs exclusivity.
-pub struct ControlGroup<'a> {
-    pub shift: ShiftClockLine<'a>,
-    pub latch: Option<LatchLine<'a>>,
-    pub clear: Option<ClearLine<'a>>,
+pub struct ControlGroup<'a, P = Output<'a>> {
+    pub shift: ShiftClockLine<'a, P>,
+    pub latch: Option<LatchLine<'a, P>>,
+    pub clear: Option<ClearLine<'a, P>>,
+    /// How `latch_all` latches; `LatchSource::Dedicated` by default.
+    pub latch_source: LatchSource,
 }
 
 impl<'a> ControlGroup<'a> {
@@ -142,41 +344,240 @@ impl<'a> ControlGroup<'a> {
     ///
     /// `active_low` should be `true` for typical 74HC595 wiring where \SRCLR
     /// is active-low.
+    ///
+    /// Uses the default output config and no extra pulse widths; see
+    /// `ControlGroupBuilder` to change those.
     pub fn from_cfg(pins: ControlPinCfg<'a>) -> Self {
-        let shift = ShiftClockLine::from_pin(pins.srclk);
-        let latch = pins.rclk.map(LatchLine::from_pin);
-        let clear = pins.srclr.map(|p| ClearLine::from_pin(p, pins.clr_active_low));
-        Self { shift, latch, clear }
+        ControlGroupBuilder {
+            rclk: pins.rclk,
+            srclr: pins.srclr,
+            ..ControlGroupBuilder::new(pins.srclk)
+        }
+        .with_latch_active_low(pins.latch_active_low)
+        .with_clear_active_low(pins.clr_active_low)
+        .build()
+    }
+
+    /// Switch SRCLK, RCLK and \SRCLR between open-drain and push-pull at
+    /// runtime, e.g. to A/B test signal integrity during bring-up.
+    ///
+    /// The other output options are reset to the shift-register defaults.
+    /// Lines are reconfigured one after another, so for a moment they
+    /// disagree, and a line idling high may briefly float (push-pull ->
+    /// open-drain) or snap high (the reverse); call it between frames, not
+    /// mid-shift. Registers never see a spurious SRCLK/RCLK edge because the
+    /// driven levels are kept.
+    pub fn set_drive_mode(&mut self, mode: gpio::DriveMode) {
+        let cfg = shiftreg_output_cfg().with_drive_mode(mode);
+        self.shift.apply_config(&cfg);
+        if let Some(latch) = &mut self.latch {
+            latch.apply_config(&cfg);
+        }
+        if let Some(clear) = &mut self.clear {
+            clear.apply_config(&cfg);
+        }
+    }
+}
+
+/// Builder for a `ControlGroup` with every control-plane knob in one place.
+///
+/// Defaults match `ControlGroup::from_cfg`: open-drain outputs without
+/// internal pulls, RCLK active high, \SRCLR active low, no extra pulse widths. Pulse widths are given in
+/// nanoseconds and converted with `with_cpu_hz` (240 MHz by default, which
+/// only errs on the long side if the CPU runs slower).
+///
+/// ```ignore
+/// let ctrl = ControlGroupBuilder::new(srclk)
+///     .with_latch(rclk)
+///     .with_drive_mode(gpio::DriveMode::PushPull)
+///     .with_shift_high_ns(50)
+///     .build();
+/// ```
+pub struct ControlGroupBuilder<'a> {
+    srclk: AnyPin<'a>,
+    rclk: Option<AnyPin<'a>>,
+    srclr: Option<AnyPin<'a>>,
+    output_cfg: OutputConfig,
+    latch_active_low: bool,
+    clear_active_low: bool,
+    shift_high_ns: u32,
+    shift_low_ns: u32,
+    latch_high_ns: u32,
+    clear_high_ns: u32,
+    cpu_hz: u32,
+    latch_source: LatchSource,
+}
+
+impl<'a> ControlGroupBuilder<'a> {
+    /// Start a group around the (required) shift clock pin.
+    pub fn new(srclk: AnyPin<'a>) -> Self {
+        Self {
+            srclk,
+            rclk: None,
+            srclr: None,
+            output_cfg: shiftreg_output_cfg(),
+            latch_active_low: false,
+            clear_active_low: true,
+            shift_high_ns: 0,
+            shift_low_ns: 0,
+            latch_high_ns: 0,
+            clear_high_ns: 0,
+            cpu_hz: 240_000_000,
+            latch_source: LatchSource::Dedicated,
+        }
+    }
+
+    /// Add a latch line (RCLK).
+    pub fn with_latch(mut self, rclk: AnyPin<'a>) -> Self {
+        self.rclk = Some(rclk);
+        self
+    }
+
+    /// Add a clear line (\SRCLR).
+    pub fn with_clear(mut self, srclr: AnyPin<'a>) -> Self {
+        self.srclr = Some(srclr);
+        self
+    }
+
+    /// Output config used for all control pins.
+    pub fn with_output_cfg(mut self, cfg: OutputConfig) -> Self {
+        self.output_cfg = cfg;
+        self
+    }
+
+    /// Drive mode of all control pins (open-drain by default).
+    pub fn with_drive_mode(mut self, mode: gpio::DriveMode) -> Self {
+        self.output_cfg = self.output_cfg.with_drive_mode(mode);
+        self
+    }
+
+    /// Internal pull of all control pins (`Pull::None` by default); see
+    /// `shiftreg_output_cfg_with_pull`.
+    pub fn with_pull(mut self, pull: gpio::Pull) -> Self {
+        self.output_cfg = self.output_cfg.with_pull(pull);
+        self
+    }
+
+    /// Latch through an inverting buffer; see `LatchLine`.
+    pub fn with_latch_active_low(mut self, active_low: bool) -> Self {
+        self.latch_active_low = active_low;
+        self
+    }
+
+    pub fn with_clear_active_low(mut self, active_low: bool) -> Self {
+        self.clear_active_low = active_low;
+        self
+    }
+
+    /// Minimum SRCLK high time per tick.
+    pub fn with_shift_high_ns(mut self, ns: u32) -> Self {
+        self.shift_high_ns = ns;
+        self
+    }
+
+    /// Minimum SRCLK low time after each tick; with `with_shift_high_ns`
+    /// sets the duty cycle (see `ShiftClockLine::tick_duty`).
+    pub fn with_shift_low_ns(mut self, ns: u32) -> Self {
+        self.shift_low_ns = ns;
+        self
+    }
+
+    /// Minimum RCLK pulse width.
+    pub fn with_latch_high_ns(mut self, ns: u32) -> Self {
+        self.latch_high_ns = ns;
+        self
+    }
+
+    /// Minimum \SRCLR pulse width.
+    pub fn with_clear_high_ns(mut self, ns: u32) -> Self {
+        self.clear_high_ns = ns;
+        self
+    }
+
+    /// CPU clock used to convert the pulse widths to cycles.
+    pub fn with_cpu_hz(mut self, cpu_hz: u32) -> Self {
+        self.cpu_hz = cpu_hz;
+        self
+    }
+
+    /// Latch through SRCLK on boards with RCLK tied to it; see
+    /// `LatchSource::SharedWithShift`. No `with_latch` pin is needed then.
+    pub fn with_latch_source(mut self, source: LatchSource) -> Self {
+        self.latch_source = source;
+        self
+    }
+
+    pub fn build(self) -> ControlGroup<'a> {
+        let cfg = self.output_cfg;
+        let cycles = |ns| ns_to_cycles(ns, self.cpu_hz);
+
+        let mut shift = ShiftClockLine::from_pin_w_cfg(self.srclk, cfg);
+        shift.set_duty(cycles(self.shift_high_ns), cycles(self.shift_low_ns));
+        let latch = self.rclk.map(|p| {
+            let mut line = LatchLine::from_pin_w_cfg(p, self.latch_active_low, cfg);
+            line.high_cycles = cycles(self.latch_high_ns);
+            line
+        });
+        let clear = self.srclr.map(|p| {
+            let mut line = ClearLine::from_pin_w_cfg(p, self.clear_active_low, cfg);
+            line.high_cycles = cycles(self.clear_high_ns);
+            line
+        });
+        ControlGroup { shift, latch, clear, latch_source: self.latch_source }
+    }
+}
+
+impl<'a, P: OutputPin> ControlGroup<'a, P> {
+    /// Group already built lines, latching through `latch` if given
+    /// (`LatchSource::Dedicated`); see `with_latch_source` for boards with
+    /// RCLK tied to SRCLK.
+    pub fn new(shift: ShiftClockLine<'a, P>, latch: Option<LatchLine<'a, P>>, clear: Option<ClearLine<'a, P>>) -> Self {
+        Self { shift, latch, clear, latch_source: LatchSource::Dedicated }
+    }
+
+    /// Set where `latch_all` takes its pulse from; see `LatchSource`.
+    pub fn with_latch_source(mut self, source: LatchSource) -> Self {
+        self.latch_source = source;
+        self
     }
 
     /// Pulse the latch line for all devices controlled by this group.
     ///
-    /// If no latch line is configured, emit a warning and do nothing.
+    /// With `LatchSource::SharedWithShift` this is one extra SRCLK tick.
+    /// Returns `Error::NotConfigured` if the latch is dedicated but no RCLK
+    /// is configured.
     #[inline]
-    pub fn latch_all(&mut self) {
-        if let Some(l) = &mut self.latch {
-            l.pulse();
-        } else {
-            warn!(
-                "Attempted latch_all() but no RCLK configured; \
-                 configure ControlGroup.latch or call latch externally."
-            );
+    pub fn latch_all(&mut self) -> Result<()> {
+        match self.latch_source {
+            LatchSource::Dedicated => {
+                let l = self.latch.as_mut().ok_or(Error::NotConfigured)?;
+                l.pulse();
+            }
+            LatchSource::SharedWithShift => self.shift.tick(),
         }
+        Ok(())
+    }
+
+    /// Whether `latch_all` can latch: a dedicated RCLK is configured, or
+    /// RCLK is shared with SRCLK.
+    pub fn can_latch(&self) -> bool {
+        self.latch.is_some() || self.latch_source == LatchSource::SharedWithShift
+    }
+
+    /// Whether per-byte latching applies: only with a dedicated RCLK.
+    fn latches_per_byte(&self, per_byte: bool) -> bool {
+        per_byte && self.latch_source == LatchSource::Dedicated
     }
 
     /// Pulse the clear line for all devices controlled by this group.
     ///
-    /// If no clear line is configured, emit a warning and do nothing.
+    /// Returns `Error::NotConfigured` if no SRCLR is configured (tie SRCLR
+    /// inactive in that case).
     #[inline]
-    pub fn clear_all(&mut self) {
-        if let Some(c) = &mut self.clear {
-            c.pulse();
-        } else {
-            warn!(
-                "Attempted clear_all() but no SRCLR configured; \
-                 tie SRCLR high or configure a ClearLine."
-            );
-        }
+    pub fn clear_all(&mut self) -> Result<()> {
+        let c = self.clear.as_mut().ok_or(Error::NotConfigured)?;
+        c.pulse();
+        Ok(())
     }
 }
 
@@ -187,110 +588,826 @@ impl<'a> ControlGroup<'a> {
 /// This type does **not** know anything about clocks or latches.
 /// It is intentionally minimal so that the same lane abstraction can be
 /// used both in a single-chain setup and in a shared-clock multi-lane setup.
-pub struct SerLane<'a> {
-    ser_out: Output<'a>,
+pub struct SerLane<'a, P = Output<'a>> {
+    ser_out: P,
+    /// Optional input wired to QH' of the last register in the chain.
+    readback: Option<Input<'a>>,
 }
 
-impl<'a> SerLane<'a> {
-    /// Create a SIPO data lane from a pin.
-    pub fn from_pin(ser: AnyPin<'a>) -> Self {
-        let cfg = shiftreg_output_cfg();
+impl<'a, P: OutputPin> SerLane<'a, P> {
+    /// Wrap an already configured output pin and drive it low.
+    pub fn new(mut ser: P) -> Self {
+        drive(&mut ser, false);
         Self {
-            ser_out: Output::new(ser, Level::Low, cfg),
+            ser_out: ser,
+            readback: None,
         }
     }
 
-    pub fn from_pin_w_cfg(ser: AnyPin<'a>, cfg: OutputConfig) -> Self {
-        Self {
-            ser_out: Output::new(ser, Level::Low, cfg),
-        }
+    /// Attach a readback input wired to QH' (serial out) of the chain's last register.
+    pub fn with_readback(mut self, qh: AnyPin<'a>, cfg: InputConfig) -> Self {
+        self.readback = Some(Input::new(qh, cfg));
+        self
+    }
+
+    /// Current level of the readback pin, or `None` if no readback is attached.
+    #[inline]
+    pub fn read_back(&self) -> Option<bool> {
+        self.readback.as_ref().map(|r| r.is_high())
     }
 
     /// Drive the SER line to the given bit value.
     #[inline]
     pub fn set_bit(&mut self, bit: bool) {
-        if bit {
-            self.ser_out.set_high();
-        } else {
-            self.ser_out.set_low();
+        drive(&mut self.ser_out, bit);
+    }
+}
+
+impl<'a> SerLane<'a> {
+    /// Create a SIPO data lane from a pin.
+    pub fn from_pin(ser: AnyPin<'a>) -> Self {
+        let cfg = shiftreg_output_cfg();
+        Self::from_pin_w_cfg(ser, cfg)
+    }
+
+    /// Like `from_pin`, with the internal pull set to `pull`.
+    pub fn from_pin_w_pull(ser: AnyPin<'a>, pull: gpio::Pull) -> Self {
+        Self::from_pin_w_cfg(ser, shiftreg_output_cfg_with_pull(pull))
+    }
+
+    pub fn from_pin_w_cfg(ser: AnyPin<'a>, cfg: OutputConfig) -> Self {
+        Self::new(Output::new(ser, Level::Low, cfg))
+    }
+
+    /// Reconfigure the SER pin at runtime; the driven level is kept.
+    pub fn apply_config(&mut self, cfg: &OutputConfig) {
+        self.ser_out.apply_config(cfg);
+    }
+}
+
+
+/// Count how many 8-bit registers sit between `lane`'s SER and its readback.
+///
+/// Flushes the chain with zeros, shifts in a single `1`, then ticks until it
+/// shows up on QH'. Never touches the latch, so with a separate RCLK the
+/// outputs keep their previous contents; with RCLK tied to SRCLK they will
+/// show the marker passing through.
+///
+/// Errors: `NotConfigured` if no readback is attached, `NoResponse` if QH'
+/// is stuck high after flushing, `OutOfRange` if the marker does not appear
+/// within `MAX_CHAIN_REGS` registers.
+fn detect_lane_chain_len<P: OutputPin>(
+    lane: &mut SerLane<'_, P>,
+    shift: &mut ShiftClockLine<'_, P>,
+) -> Result<usize> {
+    if lane.readback.is_none() {
+        return Err(Error::NotConfigured);
+    }
+    let max_bits = 8 * MAX_CHAIN_REGS;
+
+    lane.set_bit(false);
+    for _ in 0..max_bits {
+        shift.tick();
+    }
+    if lane.read_back() == Some(true) {
+        return Err(Error::NoResponse);
+    }
+
+    // Marker enters stage 0; after `m` more ticks it sits at stage `m`, and
+    // QH' shows stage `8 * len - 1`.
+    lane.set_bit(true);
+    shift.tick();
+    lane.set_bit(false);
+    for m in 0..max_bits {
+        if lane.read_back() == Some(true) {
+            // One more tick pushes the marker out, leaving the chain zeroed.
+            shift.tick();
+            return Ok((m + 1) / 8);
         }
+        shift.tick();
     }
+    Err(Error::OutOfRange)
 }
 
+/// QH' levels sampled during a verify pass; see `ParallelBank::with_verify`.
+struct QhSamples<const LANES: usize, const N: usize> {
+    /// Ticks so far in the pass.
+    tick: usize,
+    /// Bit `t` of each lane, MSB-first: its QH' just before tick `t`.
+    bits: [[u8; N]; LANES],
+}
+
+impl<const LANES: usize, const N: usize> QhSamples<LANES, N> {
+    fn new() -> Self {
+        Self { tick: 0, bits: [[0; N]; LANES] }
+    }
+
+    /// Record the readback `levels` (one per lane) before the next tick.
+    /// Only the first `8 * N` ticks are kept.
+    fn record(&mut self, levels: impl Iterator<Item = Option<bool>>) {
+        let t = self.tick;
+        if t < 8 * N {
+            for (row, level) in self.bits.iter_mut().zip(levels) {
+                if level == Some(true) {
+                    row[t / 8] |= 0x80 >> (t % 8);
+                }
+            }
+        }
+        self.tick += 1;
+    }
+
+    /// QH' of `lane` before tick `t`.
+    fn bit(&self, lane: usize, t: usize) -> bool {
+        (self.bits[lane][t / 8] >> (7 - t % 8)) & 0x01 != 0
+    }
+}
 
 /* ======================= PARALLEL BANK (SHARED SRCLK) ======================= */
 
+/// Which end of a frame `ParallelBank` shifts out first.
+///
+/// Reorders whole bytes only; bits within a byte still go out MSB-first.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FrameDirection {
+    /// Byte 0 first, so byte `N - 1` ends up in the register nearest SER.
+    #[default]
+    Forward,
+    /// Byte `N - 1` first, for chains wired end-to-end the other way round.
+    Reverse,
+}
+
+impl FrameDirection {
+    /// Index into the frame of the `k`-th byte shifted out of `n`.
+    #[inline(always)]
+    pub fn byte_index(self, k: usize, n: usize) -> usize {
+        match self {
+            FrameDirection::Forward => k,
+            FrameDirection::Reverse => n - 1 - k,
+        }
+    }
+}
+
 /// A parallel bank of SIPO lanes sharing a single control group.
 ///
 /// - `LANES` is the number of independent chains (lanes).
-/// - `N` is the number of bytes per lane (e.g., N=2 for two 74HC595 devices).
+/// - `N` is the number of bytes per lane (e.g., N=2 for two 74HC595 devices),
+///   or of the longest lane if their lengths differ (see `with_lane_bytes`).
 ///
 /// All lanes are shifted in lockstep using the shared `ControlGroup`:
 /// - `ctrl.shift` provides the SRCLK ticks.
 /// - `ctrl.latch` (optional) provides a shared latch (RCLK).
 /// - `ctrl.clear` (optional) provides a shared clear (SRCLR).
-pub struct ParallelBank<'a, const LANES: usize, const N: usize> {
-    pub lanes: [SerLane<'a>; LANES],
-    pub ctrl:  ControlGroup<'a>,
+pub struct ParallelBank<'a, const LANES: usize, const N: usize, P = Output<'a>, F = FeedFn> {
+    pub lanes: [SerLane<'a, P>; LANES],
+    pub ctrl:  ControlGroup<'a, P>,
+    /// Cycles to busy-wait after each latch so the outputs settle (default 0).
+    pub post_latch_hold_cycles: u32,
+    /// Filler bits clocked before / after every frame (default 0).
+    pub lead_bits: usize,
+    pub trail_bits: usize,
+    /// Called after every shifted byte; see `with_watchdog_feed`.
+    pub feed: F,
+    /// Latch after every byte instead of once per frame; see `with_latch_per_byte`.
+    pub latch_per_byte: bool,
+    /// Byte order across each frame; see `with_frame_direction`.
+    pub direction: FrameDirection,
+    /// Registers per lane, each `1..=N`; see `with_lane_bytes`.
+    lane_bytes: [usize; LANES],
+    /// Lanes that take new frames; see `with_lane_enable`.
+    enable: [bool; LANES],
+    /// Bits clocked per frame, `1..=8 * N`; see `with_used_bits`.
+    used_bits: usize,
+    /// Retries after a readback mismatch, `None` for the unverified fast
+    /// path; see `with_verify`.
+    verify: Option<u8>,
+    /// Retries so far; see `retry_count`.
+    retried: u32,
+    /// Frames of the last shift; see `last_frame`.
+    sent: Option<[[u8; N]; LANES]>,
 }
 
-impl<'a, const LANES: usize, const N: usize> ParallelBank<'a, LANES, N> {
+impl<'a, const LANES: usize, const N: usize, P: OutputPin> ParallelBank<'a, LANES, N, P> {
     /// Create a new parallel bank from an array of lanes and a control group.
     ///
     /// The `ControlGroup` is owned by this bank. If you need to share the same
     /// control lines across multiple banks, you will need to wrap it in some
     /// form of shared ownership (e.g., interior mutability) at a higher layer.
-    pub fn new(lanes: [SerLane<'a>; LANES], ctrl: ControlGroup<'a>) -> Self {
-        Self { lanes, ctrl }
+    pub fn new(lanes: [SerLane<'a, P>; LANES], ctrl: ControlGroup<'a, P>) -> Self {
+        Self {
+            lanes,
+            ctrl,
+            post_latch_hold_cycles: 0,
+            lead_bits: 0,
+            trail_bits: 0,
+            feed: no_feed,
+            latch_per_byte: false,
+            direction: FrameDirection::Forward,
+            lane_bytes: [N; LANES],
+            enable: [true; LANES],
+            used_bits: 8 * N,
+            verify: None,
+            retried: 0,
+            sent: None,
+        }
+    }
+}
+
+impl<'a, const LANES: usize, const N: usize, P: OutputPin, F: FnMut()> ParallelBank<'a, LANES, N, P, F> {
+    /// Hold for `cycles` after every latch before the next shift can start.
+    ///
+    /// Gives slow level shifters / DAC inputs time to settle on the latched
+    /// value; see `ns_to_cycles` to derive it from a datasheet time.
+    pub fn with_post_latch_hold(mut self, cycles: u32) -> Self {
+        self.post_latch_hold_cycles = cycles;
+        self
+    }
+
+    /// Clock `lead` zero bits before and `trail` zero bits after every frame.
+    ///
+    /// For chains with spacer registers: lead bits end up past the far end
+    /// of the frame, trail bits between SER and the frame's last byte, so
+    /// the real data lands on the intended outputs.
+    pub fn with_gap_bits(mut self, lead: usize, trail: usize) -> Self {
+        self.lead_bits = lead;
+        self.trail_bits = trail;
+        self
+    }
+
+    /// Call `feed` after every byte `shift_exact` shifts (every 8 ticks,
+    /// gap bits excluded), so very long chains do not starve a task
+    /// watchdog. No-op by default.
+    ///
+    /// `feed` can be any closure, so it can own or borrow the watchdog.
+    pub fn with_watchdog_feed<G: FnMut()>(self, feed: G) -> ParallelBank<'a, LANES, N, P, G> {
+        ParallelBank {
+            lanes: self.lanes,
+            ctrl: self.ctrl,
+            post_latch_hold_cycles: self.post_latch_hold_cycles,
+            lead_bits: self.lead_bits,
+            trail_bits: self.trail_bits,
+            feed,
+            latch_per_byte: self.latch_per_byte,
+            direction: self.direction,
+            lane_bytes: self.lane_bytes,
+            enable: self.enable,
+            used_bits: self.used_bits,
+            verify: self.verify,
+            retried: self.retried,
+            sent: self.sent,
+        }
+    }
+
+    /// Hold SRCLK high for `high_cycles` and low for `low_cycles` on every
+    /// tick, for chips that need a balanced clock; see
+    /// `ShiftClockLine::tick_duty` for the cycle math. Slows every shift
+    /// by `high_cycles + low_cycles` per bit. 0 / 0 by default.
+    pub fn with_shift_duty(mut self, high_cycles: u32, low_cycles: u32) -> Self {
+        self.ctrl.shift.set_duty(high_cycles, low_cycles);
+        self
+    }
+
+    /// Make `write_exact` / `write_same` pulse the latch after every byte
+    /// (8 ticks) instead of once per frame, one pulse per byte of the
+    /// longest lane.
+    ///
+    /// For cascaded-latch topologies and controllers that expect their
+    /// outputs to update as each register byte arrives. The outputs then
+    /// step through partially shifted states during a write; only the last
+    /// pulse, which comes after the trail gap bits, shows the whole frame.
+    /// Each pulse waits `post_latch_hold_cycles`. `shift_exact` never
+    /// latches, and neither does a group with `LatchSource::SharedWithShift`
+    /// between bytes. Off by default.
+    pub fn with_latch_per_byte(mut self, per_byte: bool) -> Self {
+        self.latch_per_byte = per_byte;
+        self
+    }
+
+    /// Shift every frame starting from byte `N - 1` instead of byte 0.
+    ///
+    /// For chains that are physically reversed end-to-end, so frames can
+    /// keep the documented layout. Applies to every write and shift;
+    /// `last_frame` still reports the frames as passed in.
+    pub fn with_frame_direction(mut self, direction: FrameDirection) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    /// Give each lane its own chain length, for mixed-width banks such as
+    /// 1-byte address chains next to a 2-byte color chain.
+    ///
+    /// Lane `k` then takes the *last* `bytes[k]` bytes of its frame (the
+    /// layout `DefAddrShifter` already produces); its leading bytes are
+    /// ignored. Each shift is only as long as the longest lane, and shorter
+    /// lanes clock zeros first, so every lane's last byte arrives with the
+    /// final tick and all of them latch aligned. With
+    /// `FrameDirection::Reverse` each lane's bytes are reversed within its
+    /// own length. Returns `Error::OutOfRange` unless every length is in
+    /// `1..=N`. All `N` by default.
+    pub fn with_lane_bytes(mut self, bytes: [usize; LANES]) -> Result<Self> {
+        if bytes.iter().any(|&len| len == 0 || len > N) {
+            return Err(Error::OutOfRange);
+        }
+        self.lane_bytes = bytes;
+        Ok(self)
+    }
+
+    /// Per-lane chain lengths in bytes; see `with_lane_bytes`.
+    pub fn lane_bytes(&self) -> [usize; LANES] {
+        self.lane_bytes
+    }
+
+    /// Freeze the lanes whose `enable` entry is false: every shift still
+    /// clocks them (SRCLK is shared), but sends their last frame again
+    /// instead of the one passed in, so their outputs keep showing it
+    /// through the next latch. Holding SER alone would not do: the held
+    /// bit would fill the register. A frozen lane that never shifted
+    /// sends zeros. All enabled by default.
+    ///
+    /// For lanes with static content, e.g. a fixed address byte, that the
+    /// caller would rather not recompute.
+    pub fn with_lane_enable(mut self, enable: [bool; LANES]) -> Self {
+        self.enable = enable;
+        self
+    }
+
+    /// Freeze or unfreeze one lane; see `with_lane_enable`.
+    pub fn set_lane_enabled(&mut self, lane: usize, enabled: bool) {
+        self.enable[lane] = enabled;
     }
+
+    /// Which lanes take new frames; see `with_lane_enable`.
+    pub fn lane_enable(&self) -> [bool; LANES] {
+        self.enable
+    }
+
+    /// Clock only the last `bits` bits of every frame, the low bits of
+    /// its last byte(s), and skip the leading ones.
+    ///
+    /// For address chains narrower than their registers, e.g. 7 address
+    /// lines on a 595: 7 ticks instead of 8 per frame. The skipped bits are
+    /// never clocked, so the top outputs keep whatever the previous shift
+    /// moved there; leave them unwired. SRCLK is shared, so every lane of
+    /// the bank is cut to the same width. Applies after `with_lane_bytes`
+    /// padding; gap bits are clocked as before. Returns `Error::OutOfRange`
+    /// unless `bits` is in `1..=8 * N`. `8 * N` (everything) by default.
+    pub fn with_used_bits(mut self, bits: usize) -> Result<Self> {
+        if bits == 0 || bits > 8 * N {
+            return Err(Error::OutOfRange);
+        }
+        self.used_bits = bits;
+        Ok(self)
+    }
+
+    /// Bits clocked per frame; see `with_used_bits`.
+    pub fn used_bits(&self) -> usize {
+        self.used_bits
+    }
+
+    /// Make `write_exact` check every frame through the QH' readback pins
+    /// and rewrite it up to `retries` times on a mismatch, for displays
+    /// where a flipped bit must not go unnoticed.
+    ///
+    /// Each write shifts and latches the frames, then shifts them again
+    /// while sampling QH', which brings out the copy just latched. If any
+    /// lane with a readback pin differs, the fresh copy is latched and
+    /// checked the same way, so a corrupted frame shows for one shift
+    /// before it is replaced. Costs one extra shift per attempt; latches
+    /// once per copy, whatever `with_latch_per_byte` says. Lanes without a
+    /// readback are shifted but not checked, and each checked chain must be
+    /// exactly its `lane_bytes` registers long (see `detect_chain_len`).
+    ///
+    /// `write_exact` then returns `Error::VerifyFailed` if the last retry
+    /// still differs, leaving that copy on the outputs, and
+    /// `Error::NotConfigured`, before shifting, without a readback lane or
+    /// a dedicated RCLK. Off (`None`) by default.
+    pub fn with_verify(mut self, retries: u8) -> Self {
+        self.verify = Some(retries);
+        self
+    }
+
+    /// Retries set by `with_verify`, `None` if writes are not verified.
+    pub fn verify(&self) -> Option<u8> {
+        self.verify
+    }
+
+    /// Rewrites `with_verify` needed so far, over all writes.
+    pub fn retry_count(&self) -> u32 {
+        self.retried
+    }
+
+    /// Bytes clocked per frame: the longest lane.
+    fn span(&self) -> usize {
+        self.lane_bytes.iter().copied().max().unwrap_or(N)
+    }
+
+    /// Byte lane `lane` shifts in slot `k` of a `span`-byte frame: zero
+    /// padding for a short lane, then its own bytes in `direction` order.
+    #[inline(always)]
+    fn slot_byte(&self, lane: usize, frame: &[u8; N], k: usize, span: usize) -> u8 {
+        let len = self.lane_bytes[lane];
+        let pad = span - len;
+        if k < pad {
+            0
+        } else {
+            frame[N - len + self.direction.byte_index(k - pad, len)]
+        }
+    }
+
+    /// Tick SRCLK once, sampling QH' first during a verify pass.
+    #[inline(always)]
+    fn tick(&mut self, qh: &mut Option<QhSamples<LANES, N>>) {
+        if let Some(qh) = qh {
+            qh.record(self.lanes.iter().map(|lane| lane.read_back()));
+        }
+        self.ctrl.shift.tick();
+    }
+
+    /// Clock `count` zero bits on every lane.
+    fn shift_filler(&mut self, count: usize, qh: &mut Option<QhSamples<LANES, N>>) {
+        if count == 0 {
+            return;
+        }
+        for lane in self.lanes.iter_mut() {
+            lane.set_bit(false);
+        }
+        for _ in 0..count {
+            self.tick(qh);
+        }
+    }
+
+    /// Shift one full frame per lane (plus gap bits) without latching.
     pub fn shift_exact(&mut self, frames: [[u8; N]; LANES]) {
-        let total_bit = 8 * N;
-        for bit_idx in 0..total_bit {
-            let byte_idx = bit_idx / 8;
-            let bit_in_byte = 7 - (bit_idx % 8);
-            for lane_idx in 0..LANES {
-                let byte = frames[lane_idx][byte_idx];
-                let bit = ((byte >> bit_in_byte) & 0x01) != 0;
-                self.lanes[lane_idx].set_bit(bit);
+        self.shift_frames(frames, false, &mut None);
+    }
+
+    /// Shift one frame per lane; with `latch_bytes`, latch after every byte
+    /// but the last, which the caller latches after the trail bits. With
+    /// `qh`, sample the readback pins along the way.
+    fn shift_frames(&mut self, mut frames: [[u8; N]; LANES], latch_bytes: bool, qh: &mut Option<QhSamples<LANES, N>>) {
+        for (lane, frame) in frames.iter_mut().enumerate() {
+            if !self.enable[lane] {
+                *frame = self.sent.map_or([0; N], |sent| sent[lane]);
+            }
+        }
+        self.sent = Some(frames);
+        self.shift_filler(self.lead_bits, qh);
+        let span = self.span();
+        // Leading bits dropped by `with_used_bits`.
+        let skip = (8 * span).saturating_sub(self.used_bits);
+        for k in 0..span {
+            let bytes: [u8; LANES] = core::array::from_fn(|lane| self.slot_byte(lane, &frames[lane], k, span));
+            for bit_in_byte in (0..8).rev().filter(|&bit| 8 * k + 7 - bit >= skip) {
+                for (lane, byte) in self.lanes.iter_mut().zip(bytes) {
+                    lane.set_bit(((byte >> bit_in_byte) & 0x01) != 0);
+                }
+                self.tick(qh);
+            }
+            if latch_bytes && k + 1 < span {
+                // A missing latch is reported by the final one.
+                let _ = self.latch();
             }
-            self.ctrl.shift.tick();
+            (self.feed)();
         }
+        self.shift_filler(self.trail_bits, qh);
+    }
+
+    /// Whether every readback lane's QH' showed its own bits of `frames`
+    /// during a verify pass.
+    ///
+    /// Before tick `t` of the pass, QH' shows bit `t` of the lane's own
+    /// bytes (gap and padding bits cancel out). Bits `with_used_bits` does
+    /// not clock, and any beyond the end of the pass, are not checked.
+    fn readback_matches(&self, frames: &[[u8; N]; LANES], qh: &QhSamples<LANES, N>) -> bool {
+        let clocked = self.used_bits.min(8 * self.span());
+        let ticks = self.lead_bits + clocked + self.trail_bits;
+        (0..LANES).filter(|&lane| self.lanes[lane].read_back().is_some()).all(|lane| {
+            let len = self.lane_bytes[lane];
+            ((8 * len).saturating_sub(clocked)..(8 * len).min(ticks)).all(|t| {
+                let byte = frames[lane][N - len + self.direction.byte_index(t / 8, len)];
+                ((byte >> (7 - t % 8)) & 0x01 != 0) == qh.bit(lane, t)
+            })
+        })
+    }
+
+    /// `write_exact` with `with_verify(retries)`.
+    fn write_verified(&mut self, frames: [[u8; N]; LANES], retries: u8) -> Result<()> {
+        if self.lanes.iter().all(|lane| lane.read_back().is_none()) || self.ctrl.latch_source != LatchSource::Dedicated {
+            return Err(Error::NotConfigured);
+        }
+        self.shift_frames(frames, false, &mut None);
+        self.latch()?;
+        // Disabled lanes resolved, so every pass shifts the same bits.
+        let frames = self.sent.unwrap_or(frames);
+        for attempt in 0..=retries {
+            let mut qh = Some(QhSamples::new());
+            self.shift_frames(frames, false, &mut qh);
+            if qh.is_some_and(|qh| self.readback_matches(&frames, &qh)) {
+                return Ok(());
+            }
+            if attempt < retries {
+                self.retried += 1;
+                self.latch()?;
+            }
+        }
+        Err(Error::VerifyFailed)
+    }
+
+    /// Shift the same `frame` into every lane, then latch once.
+    ///
+    /// Shorthand for `write_exact([frame; LANES])`, e.g. to clear or
+    /// initialize all chains at once. Returns `Error::NotConfigured` (after
+    /// shifting) if there is no latch.
+    pub fn write_same(&mut self, frame: [u8; N]) -> Result<()> {
+        self.write_exact([frame; LANES])
     }
 
     /// Shift one full frame per lane and then latch once via the control group.
     ///
     /// - Uses the bank's `ctrl.shift` as the shared SRCLK.
-    /// - Uses `ctrl.latch` if available; otherwise emits a warning.
-    pub fn write_exact(&mut self, frames: [[u8; N]; LANES]) {
-        self.shift_exact(frames);
-        self.ctrl.latch_all();
+    /// - Uses `ctrl.latch`; returns `Error::NotConfigured` (after shifting)
+    ///   if there is none.
+    /// - With `with_latch_per_byte`, latches after every byte instead.
+    /// - With `with_verify`, checks the latched frames through QH' and
+    ///   rewrites them on a mismatch.
+    pub fn write_exact(&mut self, frames: [[u8; N]; LANES]) -> Result<()> {
+        if let Some(retries) = self.verify {
+            return self.write_verified(frames, retries);
+        }
+        self.shift_frames(frames, self.ctrl.latches_per_byte(self.latch_per_byte), &mut None);
+        self.latch()
+    }
+
+    /// Latch via the control group, then wait `post_latch_hold_cycles`.
+    pub fn latch(&mut self) -> Result<()> {
+        self.ctrl.latch_all()?;
+        delay_cycles(self.post_latch_hold_cycles);
+        Ok(())
+    }
+
+    /// Bring every chain to a defined power-on state: clear the shift
+    /// registers (when an SRCLR line is configured), then shift in and latch
+    /// an all-zero frame so the outputs stop showing whatever they powered
+    /// up with. Returns `Error::NotConfigured` if there is no latch.
+    pub fn initialize(&mut self) -> Result<()> {
+        // Optional: without SRCLR the zero frame overwrites everything anyway.
+        let _ = self.clear_all();
+        self.write_same([0; N])
+    }
+
+    /// Clear all outputs via the control group's clear line.
+    pub fn clear_all(&mut self) -> Result<()> {
+        self.ctrl.clear_all()?;
+        self.sent = None;
+        Ok(())
+    }
+
+    /// The frames most recently shifted into each lane, gap bits excluded.
+    ///
+    /// A copy kept by `shift_exact` / `write_exact` / `write_same`, for
+    /// checking what was sent without a readback pin, e.g. in tests of the
+    /// pixel-to-frame mapping. Says nothing about whether it was latched.
+    /// `None` before the first shift and after a successful `clear_all`.
+    /// Costs `LANES * N` bytes.
+    pub fn last_frame(&self) -> Option<&[[u8; N]; LANES]> {
+        self.sent.as_ref()
+    }
+
+    /// Detect the number of registers chained on `lane_idx` via its readback pin.
+    ///
+    /// One-time setup call; see `detect_lane_chain_len` for the procedure and
+    /// errors. All other lanes shift zeros while probing. Returns
+    /// `Error::OutOfRange` if `lane_idx >= LANES`.
+    pub fn detect_chain_len(&mut self, lane_idx: usize) -> Result<usize> {
+        if lane_idx >= LANES {
+            return Err(Error::OutOfRange);
+        }
+        for lane in self.lanes.iter_mut() {
+            lane.set_bit(false);
+        }
+        detect_lane_chain_len(&mut self.lanes[lane_idx], &mut self.ctrl.shift)
+    }
+}
+
+impl<'a, const LANES: usize, const N: usize, F> ParallelBank<'a, LANES, N, Output<'a>, F> {
+    /// Switch every control line and SER lane to `mode` at runtime; see
+    /// `ControlGroup::set_drive_mode` for the glitch caveats.
+    pub fn set_drive_mode(&mut self, mode: gpio::DriveMode) {
+        self.ctrl.set_drive_mode(mode);
+        let cfg = shiftreg_output_cfg().with_drive_mode(mode);
+        for lane in self.lanes.iter_mut() {
+            lane.apply_config(&cfg);
+        }
+    }
+}
+
+/// A bank of `LANES` SER lanes of `N` bytes each sharing SRCLK and RCLK,
+/// whatever clocks the bits out.
+///
+/// Implemented by the CPU-driven `ParallelBank` and by
+/// `rmt_bank::RmtBank`, so writers such as `PixelWriterSipo` take either.
+/// Frames follow the `ParallelBank::shift_exact` layout.
+pub trait ShiftBank<const LANES: usize, const N: usize> {
+    /// Shift one frame per lane without latching.
+    fn shift_exact(&mut self, frames: [[u8; N]; LANES]) -> Result<()>;
+
+    /// Pulse RCLK; `Error::NotConfigured` if the bank cannot latch.
+    fn latch(&mut self) -> Result<()>;
+
+    /// Whether `latch` can succeed.
+    fn can_latch(&self) -> bool;
+
+    /// Shift one frame per lane, then latch once.
+    fn write_exact(&mut self, frames: [[u8; N]; LANES]) -> Result<()> {
+        self.shift_exact(frames)?;
+        self.latch()
+    }
+
+    /// Clear every register without shifting; `Error::NotConfigured`
+    /// without an SRCLR line.
+    fn clear_all(&mut self) -> Result<()>;
+
+    /// Clear the registers if possible, then shift and latch all zeros.
+    fn initialize(&mut self) -> Result<()> {
+        let _ = self.clear_all();
+        self.write_exact([[0; N]; LANES])
+    }
+
+    /// Frames of the last shift, if any.
+    fn last_frame(&self) -> Option<&[[u8; N]; LANES]>;
+
+    /// Bits clocked per frame.
+    fn used_bits(&self) -> usize {
+        8 * N
+    }
+}
+
+impl<const LANES: usize, const N: usize, P: OutputPin, F: FnMut()> ShiftBank<LANES, N> for ParallelBank<'_, LANES, N, P, F> {
+    fn shift_exact(&mut self, frames: [[u8; N]; LANES]) -> Result<()> {
+        ParallelBank::shift_exact(self, frames);
+        Ok(())
+    }
+
+    fn latch(&mut self) -> Result<()> {
+        ParallelBank::latch(self)
+    }
+
+    fn can_latch(&self) -> bool {
+        self.ctrl.can_latch()
+    }
+
+    fn write_exact(&mut self, frames: [[u8; N]; LANES]) -> Result<()> {
+        ParallelBank::write_exact(self, frames)
+    }
+
+    fn clear_all(&mut self) -> Result<()> {
+        ParallelBank::clear_all(self)
+    }
+
+    fn initialize(&mut self) -> Result<()> {
+        ParallelBank::initialize(self)
+    }
+
+    fn last_frame(&self) -> Option<&[[u8; N]; LANES]> {
+        ParallelBank::last_frame(self)
     }
 
-    /// Clear all outputs via the control group, if a clear line is configured.
-    pub fn clear_all(&mut self) {
-        self.ctrl.clear_all();
+    fn used_bits(&self) -> usize {
+        ParallelBank::used_bits(self)
     }
 }
 
 
 /* =========================== SINGLE-CHAIN WRAPPER =========================== */
 
-pub struct SipoSingle<'a, const N: usize> {
-    pub lane: SerLane<'a>,
-    pub ctrl: ControlGroup<'a>,
+pub struct SipoSingle<'a, const N: usize, P = Output<'a>, F = FeedFn> {
+    pub lane: SerLane<'a, P>,
+    pub ctrl: ControlGroup<'a, P>,
+    /// Cycles to busy-wait after each latch so the outputs settle (default 0).
+    pub post_latch_hold_cycles: u32,
+    /// Filler bits clocked before / after every frame (default 0).
+    pub lead_bits: usize,
+    pub trail_bits: usize,
+    /// Called after every shifted byte; see `with_watchdog_feed`.
+    pub feed: F,
+    /// Latch after every byte; see `ParallelBank::with_latch_per_byte`.
+    pub latch_per_byte: bool,
+    /// Retries after a readback mismatch; see `with_verify`.
+    verify: Option<u8>,
+    /// Retries so far; see `retry_count`.
+    retried: u32,
+    /// Frame of the last shift; see `last_frame`.
+    sent: Option<[u8; N]>,
+}
+
+impl<'a, const N: usize, P: OutputPin> SipoSingle<'a, N, P> {
+    pub fn new(lane: SerLane<'a, P>, ctrl: ControlGroup<'a, P>) -> Self {
+        Self {
+            lane,
+            ctrl,
+            post_latch_hold_cycles: 0,
+            lead_bits: 0,
+            trail_bits: 0,
+            feed: no_feed,
+            latch_per_byte: false,
+            verify: None,
+            retried: 0,
+            sent: None,
+        }
+    }
 }
 
-impl<'a, const N: usize> SipoSingle<'a, N> {
-    pub fn new(lane: SerLane<'a>, ctrl: ControlGroup<'a>) -> Self {
-        Self { lane, ctrl }
+impl<'a, const N: usize, P: OutputPin, F: FnMut()> SipoSingle<'a, N, P, F> {
+    /// Hold for `cycles` after every latch; see `ParallelBank::with_post_latch_hold`.
+    pub fn with_post_latch_hold(mut self, cycles: u32) -> Self {
+        self.post_latch_hold_cycles = cycles;
+        self
+    }
+
+    /// Clock filler bits around every frame; see `ParallelBank::with_gap_bits`.
+    pub fn with_gap_bits(mut self, lead: usize, trail: usize) -> Self {
+        self.lead_bits = lead;
+        self.trail_bits = trail;
+        self
+    }
+
+    /// Call `feed` after every shifted byte; see `ParallelBank::with_watchdog_feed`.
+    pub fn with_watchdog_feed<G: FnMut()>(self, feed: G) -> SipoSingle<'a, N, P, G> {
+        SipoSingle {
+            lane: self.lane,
+            ctrl: self.ctrl,
+            post_latch_hold_cycles: self.post_latch_hold_cycles,
+            lead_bits: self.lead_bits,
+            trail_bits: self.trail_bits,
+            feed,
+            latch_per_byte: self.latch_per_byte,
+            verify: self.verify,
+            retried: self.retried,
+            sent: self.sent,
+        }
+    }
+
+    /// Set the SRCLK duty cycle; see `ParallelBank::with_shift_duty`.
+    pub fn with_shift_duty(mut self, high_cycles: u32, low_cycles: u32) -> Self {
+        self.ctrl.shift.set_duty(high_cycles, low_cycles);
+        self
+    }
+
+    /// Latch after every byte in `write_exact`; see
+    /// `ParallelBank::with_latch_per_byte`.
+    pub fn with_latch_per_byte(mut self, per_byte: bool) -> Self {
+        self.latch_per_byte = per_byte;
+        self
+    }
+
+    /// Check every frame through the readback pin and rewrite it up to
+    /// `retries` times; see `ParallelBank::with_verify`.
+    pub fn with_verify(mut self, retries: u8) -> Self {
+        self.verify = Some(retries);
+        self
+    }
+
+    /// Retries set by `with_verify`, `None` if writes are not verified.
+    pub fn verify(&self) -> Option<u8> {
+        self.verify
+    }
+
+    /// Rewrites `with_verify` needed so far, over all writes.
+    pub fn retry_count(&self) -> u32 {
+        self.retried
     }
 
+    /// Tick SRCLK once, sampling QH' first during a verify pass.
+    #[inline(always)]
+    fn tick(&mut self, qh: &mut Option<QhSamples<1, N>>) {
+        if let Some(qh) = qh {
+            qh.record(core::iter::once(self.lane.read_back()));
+        }
+        self.ctrl.shift.tick();
+    }
+
+    /// Clock `count` zero bits.
+    fn shift_filler(&mut self, count: usize, qh: &mut Option<QhSamples<1, N>>) {
+        if count == 0 {
+            return;
+        }
+        self.lane.set_bit(false);
+        for _ in 0..count {
+            self.tick(qh);
+        }
+    }
 
     /// Shift one full frame (N bytes) without latching.
     ///
     /// The caller may later call `self.ctrl.latch_all()` if it wants to latch
     /// separately. For convenience, `write_exact` does both.
     pub fn shift_exact(&mut self, frame: &[u8; N]) {
+        self.shift_frame(frame, false, &mut None);
+    }
+
+    /// Shift one frame; see `ParallelBank::shift_frames`.
+    fn shift_frame(&mut self, frame: &[u8; N], latch_bytes: bool, qh: &mut Option<QhSamples<1, N>>) {
+        self.sent = Some(*frame);
+        self.shift_filler(self.lead_bits, qh);
         // For a single lane, we treat it as LANES = 1.
         for bit in 0..(8 * N) {
             let byte_idx = bit / 8;
@@ -298,18 +1415,84 @@ impl<'a, const N: usize> SipoSingle<'a, N> {
             let byte = frame[byte_idx];
             let bit_val = ((byte >> bit_in_byte) & 0x01) != 0;
             self.lane.set_bit(bit_val);
-            self.ctrl.shift.tick();
+            self.tick(qh);
+            if bit_in_byte == 0 {
+                if latch_bytes && byte_idx + 1 < N {
+                    let _ = self.latch();
+                }
+                (self.feed)();
+            }
         }
+        self.shift_filler(self.trail_bits, qh);
     }
 
-    /// Shift one full frame and then latch once.
-    pub fn write_exact(&mut self, frame: &[u8; N]) {
-        self.shift_exact(frame);
-        self.ctrl.latch_all();
+    /// `write_exact` with `with_verify(retries)`; see
+    /// `ParallelBank::write_verified`.
+    fn write_verified(&mut self, frame: &[u8; N], retries: u8) -> Result<()> {
+        if self.lane.read_back().is_none() || self.ctrl.latch_source != LatchSource::Dedicated {
+            return Err(Error::NotConfigured);
+        }
+        self.shift_frame(frame, false, &mut None);
+        self.latch()?;
+        for attempt in 0..=retries {
+            let mut qh = Some(QhSamples::new());
+            self.shift_frame(frame, false, &mut qh);
+            if qh.is_some_and(|qh| (0..8 * N).all(|t| ((frame[t / 8] >> (7 - t % 8)) & 0x01 != 0) == qh.bit(0, t))) {
+                return Ok(());
+            }
+            if attempt < retries {
+                self.retried += 1;
+                self.latch()?;
+            }
+        }
+        Err(Error::VerifyFailed)
     }
 
-    /// Clear the chain using the control group's clear line, if present.
-    pub fn clear(&mut self) {
-        self.ctrl.clear_all();
+    /// Shift one full frame and then latch once (every byte with
+    /// `with_latch_per_byte`). With `with_verify`, checks it through the
+    /// readback pin and rewrites it on a mismatch.
+    pub fn write_exact(&mut self, frame: &[u8; N]) -> Result<()> {
+        if let Some(retries) = self.verify {
+            return self.write_verified(frame, retries);
+        }
+        self.shift_frame(frame, self.ctrl.latches_per_byte(self.latch_per_byte), &mut None);
+        self.latch()
+    }
+
+    /// Latch via the control group, then wait `post_latch_hold_cycles`.
+    pub fn latch(&mut self) -> Result<()> {
+        self.ctrl.latch_all()?;
+        delay_cycles(self.post_latch_hold_cycles);
+        Ok(())
+    }
+
+    /// Clear the chain using the control group's clear line.
+    pub fn clear(&mut self) -> Result<()> {
+        self.ctrl.clear_all()?;
+        self.sent = None;
+        Ok(())
+    }
+
+    /// The frame most recently shifted; see `ParallelBank::last_frame`.
+    /// `None` before the first shift and after a successful `clear`.
+    pub fn last_frame(&self) -> Option<&[u8; N]> {
+        self.sent.as_ref()
+    }
+
+    /// Detect how many registers are chained, using the lane's readback pin.
+    ///
+    /// One-time setup call, e.g. to check `N` at boot for modular displays.
+    /// See `detect_lane_chain_len` for the errors.
+    pub fn detect_chain_len(&mut self) -> Result<usize> {
+        detect_lane_chain_len(&mut self.lane, &mut self.ctrl.shift)
+    }
+}
+
+impl<'a, const N: usize, F> SipoSingle<'a, N, Output<'a>, F> {
+    /// Switch the control lines and SER to `mode`; see
+    /// `ParallelBank::set_drive_mode`.
+    pub fn set_drive_mode(&mut self, mode: gpio::DriveMode) {
+        self.ctrl.set_drive_mode(mode);
+        self.lane.apply_config(&shiftreg_output_cfg().with_drive_mode(mode));
     }
 }
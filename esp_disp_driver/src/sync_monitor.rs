@@ -0,0 +1,83 @@
+//! HSYNC / VSYNC frequency monitor for bring-up.
+//!
+//! Samples the two sync inputs in a busy loop, counts rising edges over a
+//! measurement window timed by the CPU cycle counter, and reports the
+//! measured horizontal and vertical frequencies.
+
+use esp_hal::{self as hal, gpio::InputConfig};
+use hal::gpio::{AnyPin, Input};
+use hal::time::Duration;
+use hal::xtensa_lx::timer::get_cycle_count;
+
+/// Edge counter over the HSYNC and VSYNC lines.
+///
+/// Only rising edges are counted, so the reported frequencies are correct
+/// regardless of the sync pulse polarity.
+pub struct SyncMonitor<'a> {
+    hsync: Input<'a>,
+    vsync: Input<'a>,
+    /// CPU clock frequency in Hz, used to convert cycle counts into time.
+    cpu_hz: u32,
+}
+
+impl<'a> SyncMonitor<'a> {
+    /// Construct a sync monitor from the HSYNC and VSYNC pins.
+    ///
+    /// # Parameters
+    ///
+    /// - `hsync`, `vsync`: sync input pins.
+    /// - `input_cfg`: input configuration (pull-up, pull-down, floating).
+    /// - `cpu_hz`: current CPU clock frequency in Hz (e.g. `240_000_000`).
+    pub fn from_pins(
+        hsync: AnyPin<'a>,
+        vsync: AnyPin<'a>,
+        input_cfg: InputConfig,
+        cpu_hz: u32,
+    ) -> Self {
+        Self {
+            hsync: Input::new(hsync, input_cfg),
+            vsync: Input::new(vsync, input_cfg),
+            cpu_hz,
+        }
+    }
+
+    /// Count sync edges for `duration` and return `(h_hz, v_hz)`.
+    ///
+    /// This blocks the calling core for the whole window. The polling loop
+    /// must run faster than the HSYNC rate (~31.5 kHz for 640x480@60), which
+    /// is comfortably the case at the S3's CPU clocks.
+    pub fn measure(&self, duration: Duration) -> (u32, u32) {
+        let window_us = duration.as_micros().max(1);
+        let window_cycles = window_us * (self.cpu_hz / 1_000_000) as u64;
+
+        let mut h_prev = self.hsync.is_high();
+        let mut v_prev = self.vsync.is_high();
+        let mut h_edges: u64 = 0;
+        let mut v_edges: u64 = 0;
+
+        // The cycle counter is 32 bits and wraps every ~18 s at 240 MHz, so
+        // accumulate deltas instead of comparing against an absolute deadline.
+        let mut elapsed: u64 = 0;
+        let mut last = get_cycle_count();
+        while elapsed < window_cycles {
+            let h = self.hsync.is_high();
+            let v = self.vsync.is_high();
+            if h && !h_prev {
+                h_edges += 1;
+            }
+            if v && !v_prev {
+                v_edges += 1;
+            }
+            h_prev = h;
+            v_prev = v;
+
+            let now = get_cycle_count();
+            elapsed += now.wrapping_sub(last) as u64;
+            last = now;
+        }
+
+        let h_hz = (h_edges * 1_000_000 / window_us) as u32;
+        let v_hz = (v_edges * 1_000_000 / window_us) as u32;
+        (h_hz, v_hz)
+    }
+}
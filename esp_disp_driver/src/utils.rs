@@ -1,4 +1,5 @@
 use core::iter::Step;
+use esp_hal::xtensa_lx::timer::get_cycle_count;
 pub trait PrimInt:
     num_traits::PrimInt + Step
 {
@@ -25,4 +26,420 @@ macro_rules! anypins_from_peri {
             ),+
         ]
     }};
-}
\ No newline at end of file
+}
+
+/// `anypins_from_peri!` checked against the expected bus width `N`:
+/// expands to a `[AnyPin; N]`, or fails to build with a message naming the
+/// mismatch, catching the pin left out of (or added to) a long list.
+///
+/// ```ignore
+/// let data = anypins_from_peri_n!(peripherals; 4; 4, 5, 6, 7);
+/// ```
+///
+/// `N` must be a constant expression. Listing three pins for a four-pin
+/// bus, `anypins_from_peri_n!(peripherals; 4; 4, 5, 6)`, fails to build
+/// with "number of pins listed does not match N"; CI checks this by
+/// building `examples/pin_count_mismatch.rs`, which must fail.
+#[macro_export]
+macro_rules! anypins_from_peri_n {
+    ($perip:expr; $n:expr; $($pin:literal),+ $(,)?) => {{
+        const _: () = assert!(
+            [$(stringify!($pin)),+].len() == $n,
+            "anypins_from_peri_n!: number of pins listed does not match N"
+        );
+        let pins: [::esp_hal::gpio::AnyPin<'_>; $n] = $crate::anypins_from_peri!($perip; $($pin),+);
+        pins
+    }};
+}
+
+/// Build bus_dac's `VgaHwResources` from the GPIO numbers of each bus,
+/// lowest bit first:
+///
+/// ```ignore
+/// let res = vga_hw_resources!(peripherals;
+///     haddr: [21, 47, 48, 45, 0, 35, 36, 37],
+///     vaddr: [14, 13, 12, 11, 10, 9, 46, 3],
+///     data: [4, 5, 6, 7],
+/// );
+/// ```
+///
+/// Each list becomes an `anypins_from_peri!` array, so a count that does
+/// not match the bus width is a type error and a pin listed twice is a
+/// use-after-move.
+#[macro_export]
+macro_rules! vga_hw_resources {
+    ($perip:expr;
+        haddr: [$($h:literal),+ $(,)?],
+        vaddr: [$($v:literal),+ $(,)?],
+        data: [$($d:literal),+ $(,)?] $(,)?
+    ) => {
+        $crate::display::backend::bus_dac::VgaHwResources {
+            haddr_pins: $crate::anypins_from_peri!($perip; $($h),+),
+            vaddr_pins: $crate::anypins_from_peri!($perip; $($v),+),
+            data_pins: $crate::anypins_from_peri!($perip; $($d),+),
+        }
+    };
+}
+
+/// First GPIO number that appears twice across `groups` of `(role, pins)`,
+/// with the roles of its first and second use (the same role if it is
+/// listed twice within one group).
+pub fn find_shared_pin<'r>(groups: &[(&'r str, &[u8])]) -> Option<(u8, &'r str, &'r str)> {
+    for (g, &(role, pins)) in groups.iter().enumerate() {
+        for (k, &pin) in pins.iter().enumerate() {
+            let earlier = groups[..g].iter().find(|(_, p)| p.contains(&pin)).map(|&(r, _)| r);
+            let earlier = earlier.or_else(|| pins[..k].contains(&pin).then_some(role));
+            if let Some(first) = earlier {
+                return Some((pin, first, role));
+            }
+        }
+    }
+    None
+}
+
+/// Panic, naming the pin and both roles, if any GPIO appears twice across
+/// `groups`; see `find_shared_pin`.
+///
+/// Called by the backend constructors so a pin wired to two roles (say an
+/// address and a data line) fails at bring-up instead of showing up as a
+/// garbled picture. Does nothing without `debug_assertions`.
+#[track_caller]
+pub fn debug_assert_pins_disjoint(groups: &[(&str, &[u8])]) {
+    if cfg!(debug_assertions) && let Some((pin, first, second)) = find_shared_pin(groups) {
+        panic!("GPIO{} assigned to both {} and {}", pin, first, second);
+    }
+}
+
+/// Hook called periodically from long-running driver loops, e.g. to feed a
+/// task watchdog. Each loop documents how often it calls it.
+///
+/// This is the default hook type; the `with_watchdog_feed` builders take
+/// any `FnMut()`, so a closure can own or borrow the watchdog it feeds.
+pub type FeedFn = fn();
+
+/// Default `FeedFn`: does nothing.
+#[inline(always)]
+pub fn no_feed() {}
+
+/// Convert a duration in nanoseconds into CPU cycles at `cpu_hz`.
+///
+/// Rounds up, so a non-zero `ns` never yields a shorter delay than asked for.
+/// Saturates at `u32::MAX`.
+pub const fn ns_to_cycles(ns: u32, cpu_hz: u32) -> u32 {
+    let cycles = (ns as u64 * cpu_hz as u64).div_ceil(1_000_000_000);
+    if cycles > u32::MAX as u64 {
+        u32::MAX
+    } else {
+        cycles as u32
+    }
+}
+
+/// Bytes needed for a `w` x `h` framebuffer at `bpp` bits per pixel.
+///
+/// Each row is padded to a whole byte, matching `[[u8; W]; H]` at 8 bpp and
+/// the `draw_bitmap_1bpp` layout at 1 bpp. Usable in const context, e.g. to
+/// size `esp_alloc::heap_allocator!` or check a static buffer fits.
+pub const fn framebuffer_bytes(w: usize, h: usize, bpp: usize) -> usize {
+    (w * bpp).div_ceil(8) * h
+}
+
+/// Row stride for a `width`-pixel row padded up to a multiple of `align`.
+///
+/// Use as the `STRIDE` of bus_dac's `FrameBuf` so every row starts on a word
+/// (`align = 4`) or cache-line (`align = 32`) boundary for DMA; `align` of 0
+/// or 1 means no padding.
+pub const fn row_stride(width: usize, align: usize) -> usize {
+    if align <= 1 { width } else { width.next_multiple_of(align) }
+}
+
+/// Adler-32 checksum (as in zlib) of `data`.
+///
+/// Two running sums modulo 65521, the largest prime below 2^16: `a` starts
+/// at 1 and adds every byte, `b` adds `a` after each byte; the result is
+/// `b << 16 | a`. A Fletcher-style checksum, not a CRC: cheap (two adds
+/// per byte, one modulo per 5552 bytes) and sensitive to byte order, but
+/// weaker than a CRC on very short inputs. Matches `zlib.adler32` in
+/// Python, so host tools can compute the expected value.
+pub fn adler32(data: impl IntoIterator<Item = u8>) -> u32 {
+    const MOD: u32 = 65521;
+    // Most bytes that can be summed before `b` could overflow a u32.
+    const NMAX: usize = 5552;
+    let (mut a, mut b, mut n) = (1u32, 0u32, 0usize);
+    for byte in data {
+        a += byte as u32;
+        b += a;
+        n += 1;
+        if n == NMAX {
+            a %= MOD;
+            b %= MOD;
+            n = 0;
+        }
+    }
+    ((b % MOD) << 16) | (a % MOD)
+}
+
+/// Bytes for the two frames of a `DoubleBuffer` of such framebuffers.
+///
+/// Excludes the buffer's few bytes of bookkeeping; use
+/// `size_of::<DoubleBuffer<_>>()` when the exact type is at hand.
+pub const fn double_buffer_bytes(w: usize, h: usize, bpp: usize) -> usize {
+    2 * framebuffer_bytes(w, h, bpp)
+}
+
+/// Interpret the low `bits` bits of `value` as a two's complement number.
+///
+/// Bits above `bits` are ignored; `bits == 0` yields 0 and `bits >= 32`
+/// reinterprets the whole word.
+pub const fn sign_extend(value: u32, bits: u32) -> i32 {
+    if bits == 0 {
+        return 0;
+    }
+    if bits >= 32 {
+        return value as i32;
+    }
+    let shift = 32 - bits;
+    ((value << shift) as i32) >> shift
+}
+
+/// Busy-wait for `cycles` CPU cycles using the cycle counter.
+#[inline]
+pub fn delay_cycles(cycles: u32) {
+    if cycles == 0 {
+        return;
+    }
+    let start = get_cycle_count();
+    while get_cycle_count().wrapping_sub(start) < cycles {}
+}
+
+/// Signed 16.16 fixed-point number, for float-free sub-pixel math.
+///
+/// The interpolation primitive for sub-pixel and blending math, e.g. the
+/// blend weights of `display::gradient::Gradient`. Range is about +-32768
+/// with a resolution of 1/65536. Overflow behaves like the underlying
+/// `i32` (panics in debug builds, wraps in release).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, defmt::Format)]
+pub struct Fixed(i32);
+
+impl Fixed {
+    pub const FRAC_BITS: u32 = 16;
+    pub const ZERO: Self = Self(0);
+    pub const ONE: Self = Self(1 << Self::FRAC_BITS);
+    pub const HALF: Self = Self(1 << (Self::FRAC_BITS - 1));
+    pub const MIN: Self = Self(i32::MIN);
+    pub const MAX: Self = Self(i32::MAX);
+
+    /// Wrap raw 16.16 bits.
+    pub const fn from_bits(bits: i32) -> Self {
+        Self(bits)
+    }
+
+    pub const fn to_bits(self) -> i32 {
+        self.0
+    }
+
+    /// `v` as a fixed-point number; `v` must fit in the 16 integer bits.
+    pub const fn from_int(v: i32) -> Self {
+        Self(v << Self::FRAC_BITS)
+    }
+
+    /// `num / den`, truncated toward zero. Panics if `den` is 0.
+    pub const fn from_ratio(num: i32, den: i32) -> Self {
+        Self((((num as i64) << Self::FRAC_BITS) / den as i64) as i32)
+    }
+
+    /// Convert any `PrimInt`, or `None` if it is outside the integer range.
+    pub fn from_prim<T: PrimInt>(v: T) -> Option<Self> {
+        let v = v.to_i64()?;
+        (-(1 << 15)..(1 << 15)).contains(&v).then(|| Self::from_int(v as i32))
+    }
+
+    /// `floor()` as a `PrimInt`, or `None` if it does not fit in `T`.
+    pub fn to_prim<T: PrimInt>(self) -> Option<T> {
+        <T as num_traits::NumCast>::from(self.floor())
+    }
+
+    /// Largest integer `<= self`.
+    pub const fn floor(self) -> i32 {
+        self.0 >> Self::FRAC_BITS
+    }
+
+    /// Smallest integer `>= self`.
+    pub const fn ceil(self) -> i32 {
+        ((self.0 as i64 + Self::ONE.0 as i64 - 1) >> Self::FRAC_BITS) as i32
+    }
+
+    /// Nearest integer, halves rounding up (towards +infinity).
+    pub const fn round(self) -> i32 {
+        ((self.0 as i64 + Self::HALF.0 as i64) >> Self::FRAC_BITS) as i32
+    }
+
+    /// Fractional part, `self - floor(self)`, always in `0..1`.
+    pub const fn frac(self) -> Self {
+        Self(self.0 & (Self::ONE.0 - 1))
+    }
+
+    pub const fn abs(self) -> Self {
+        Self(self.0.abs())
+    }
+
+    /// `a + (b - a) * t`: `t = 0` gives `a`, `t = 1` gives `b`.
+    pub fn lerp(a: Self, b: Self, t: Self) -> Self {
+        a + (b - a) * t
+    }
+
+    /// `v * self` rounded to nearest, halves away from zero, for integers
+    /// past the 16-bit integer range, e.g. the difference of two colors.
+    pub const fn scale(self, v: i64) -> i64 {
+        let p = v * self.0 as i64;
+        let half = Self::HALF.0 as i64;
+        if p >= 0 { (p + half) >> Self::FRAC_BITS } else { -((half - p) >> Self::FRAC_BITS) }
+    }
+}
+
+impl core::ops::Add for Fixed {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self(self.0 + rhs.0)
+    }
+}
+
+impl core::ops::Sub for Fixed {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self(self.0 - rhs.0)
+    }
+}
+
+impl core::ops::Neg for Fixed {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self(-self.0)
+    }
+}
+
+/// Rounds to nearest, halves up.
+impl core::ops::Mul for Fixed {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        let p = self.0 as i64 * rhs.0 as i64;
+        Self(((p + Self::HALF.0 as i64) >> Self::FRAC_BITS) as i32)
+    }
+}
+
+/// Truncates toward zero. Panics if `rhs` is zero.
+impl core::ops::Div for Fixed {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self {
+        Self((((self.0 as i64) << Self::FRAC_BITS) / rhs.0 as i64) as i32)
+    }
+}
+
+/// Exact scaling by an integer.
+impl core::ops::Mul<i32> for Fixed {
+    type Output = Self;
+
+    fn mul(self, rhs: i32) -> Self {
+        Self(self.0 * rhs)
+    }
+}
+
+/// Division by an integer, truncating toward zero.
+impl core::ops::Div<i32> for Fixed {
+    type Output = Self;
+
+    fn div(self, rhs: i32) -> Self {
+        Self(self.0 / rhs)
+    }
+}
+
+impl core::ops::AddAssign for Fixed {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl core::ops::SubAssign for Fixed {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+/// Horizontal run of pixels `j0..=j1` on row `i`, the unit of work of the
+/// span-based fills.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, defmt::Format)]
+pub struct Span {
+    pub i: i64,
+    pub j0: i64,
+    pub j1: i64,
+}
+
+/// Fixed-capacity LIFO of `Span`s, the pending-work storage for scanline
+/// fills so they never touch the heap.
+///
+/// Overflow policy: `push` on a full stack drops the span, returns `false`
+/// and latches `overflowed()`. A fill keeps draining what it already
+/// queued, so the result is a partial fill that never writes outside the
+/// region, and the fill reports it (and logs a warning) instead of
+/// corrupting memory. Each entry is 24 bytes; a few dozen cover most
+/// convex and gently concave regions, mazes and spirals need more.
+pub struct ScanlineStack<const CAP: usize> {
+    items: [Span; CAP],
+    len: usize,
+    overflowed: bool,
+}
+
+impl<const CAP: usize> ScanlineStack<CAP> {
+    pub const fn new() -> Self {
+        Self { items: [Span { i: 0, j0: 0, j1: 0 }; CAP], len: 0, overflowed: false }
+    }
+
+    /// Queue `span`; `false` (and the overflow flag) if the stack is full.
+    pub fn push(&mut self, span: Span) -> bool {
+        if self.len == CAP {
+            self.overflowed = true;
+            return false;
+        }
+        self.items[self.len] = span;
+        self.len += 1;
+        true
+    }
+
+    pub fn pop(&mut self) -> Option<Span> {
+        self.len = self.len.checked_sub(1)?;
+        Some(self.items[self.len])
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub const fn capacity(&self) -> usize {
+        CAP
+    }
+
+    /// Whether a `push` has been dropped since the last `clear`.
+    pub fn overflowed(&self) -> bool {
+        self.overflowed
+    }
+
+    /// Empty the stack and reset the overflow flag, ready for the next fill.
+    pub fn clear(&mut self) {
+        self.len = 0;
+        self.overflowed = false;
+    }
+}
+
+impl<const CAP: usize> Default for ScanlineStack<CAP> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,55 @@
+//! On-target tests for the packed color formats in `display::color`.
+
+#![no_std]
+#![no_main]
+
+esp_bootloader_esp_idf::esp_app_desc!();
+
+#[cfg(test)]
+#[embedded_test::tests(executor = esp_rtos::embassy::Executor::new())]
+mod tests {
+    use defmt::assert_eq;
+    use esp_disp_driver::display::color::{Rgb332, Rgb565};
+
+    #[init]
+    fn init() {
+        let peripherals = esp_hal::init(esp_hal::Config::default());
+
+        let timg1 = esp_hal::timer::timg::TimerGroup::new(peripherals.TIMG1);
+        esp_rtos::start(timg1.timer0);
+
+        rtt_target::rtt_init_defmt!();
+    }
+
+    #[test]
+    fn rgb565_round_trips_through_rgb888() {
+        for v in 0..=u16::MAX {
+            let c = Rgb565(v);
+            let (r, g, b) = c.to_rgb888();
+            assert_eq!(Rgb565::from_rgb888(r, g, b), c);
+        }
+    }
+
+    #[test]
+    fn rgb332_round_trips_through_rgb565() {
+        for v in 0..=u8::MAX {
+            let c = Rgb332(v);
+            let (r, g, b) = c.to_rgb888();
+            assert_eq!(Rgb332::from_rgb888(r, g, b), c);
+            assert_eq!(Rgb332::from(Rgb565::from(c)), c);
+        }
+    }
+
+    #[test]
+    fn luma_extremes_and_primaries() {
+        assert_eq!(Rgb565::WHITE.to_rgb888(), (255, 255, 255));
+        assert_eq!(Rgb565::WHITE.luma(), 255);
+        assert_eq!(Rgb565::BLACK.luma(), 0);
+        assert_eq!(Rgb332::WHITE.luma4(), 15);
+        assert_eq!(Rgb332::BLACK.luma4(), 0);
+        // Green dominates Rec. 601 luma, blue contributes least.
+        assert_eq!(Rgb565::new(0, 63, 0).luma(), 149);
+        assert_eq!(Rgb565::new(31, 0, 0).luma(), 77);
+        assert_eq!(Rgb565::new(0, 0, 31).luma(), 29);
+    }
+}
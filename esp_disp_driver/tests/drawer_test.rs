@@ -0,0 +1,1354 @@
+//! On-target tests for `Drawer`, run against an in-memory `PixelWriter`.
+
+#![no_std]
+#![no_main]
+
+esp_bootloader_esp_idf::esp_app_desc!();
+
+#[cfg(test)]
+#[embedded_test::tests(executor = esp_rtos::embassy::Executor::new())]
+mod tests {
+    use defmt::{assert, assert_eq};
+    use esp_disp_driver::display::buffered::BufferedWriter;
+    use esp_disp_driver::display::diff::DiffWriter;
+    use esp_disp_driver::display::gradient::{Gradient, Interp};
+    use esp_disp_driver::display::noise::noise2d;
+    use esp_disp_driver::display::icons::{Icon, ICON_SIZE};
+    use esp_disp_driver::display::mirror::MirrorWriter;
+    use esp_disp_driver::display::drawer::{Dash, Drawer, EdgeMode, MarchingAnts, MarkerStyle, NativeDrawer, DEFAULT_HISTOGRAM_BINS};
+    use esp_disp_driver::display::palette::PaletteWriter;
+    use esp_disp_driver::display::pix_writer::{Capabilities, NativePixelWriter, PixelWriter};
+    use esp_disp_driver::display::pen::Pen;
+    use esp_disp_driver::display::pwm::PwmWriter;
+    use esp_disp_driver::display::scroll::{ScrollDir, SmoothScroller};
+    use esp_disp_driver::display::shapes::{Circle, PrimitiveStyle, Rect};
+    use esp_disp_driver::display::text::FONT_3X5;
+    use esp_disp_driver::display::viewport::Viewport;
+    use esp_disp_driver::error::Error;
+    use esp_disp_driver::utils::ScanlineStack;
+
+    const W: usize = 16;
+    const H: usize = 12;
+
+    /// Framebuffer-backed writer that records every pixel it is given.
+    struct MockWriter {
+        px: [[u8; W]; H],
+        writes: usize,
+    }
+
+    impl MockWriter {
+        fn new() -> Self {
+            Self { px: [[0; W]; H], writes: 0 }
+        }
+    }
+
+    impl PixelWriter<u8, u8> for MockWriter {
+        fn write_pixel(&mut self, i: u8, j: u8, color: u8) {
+            self.px[i as usize][j as usize] = color;
+            self.writes += 1;
+        }
+
+        fn addr_range(&self) -> ((u8, u8), (u8, u8)) {
+            ((0, H as u8 - 1), (0, W as u8 - 1))
+        }
+
+        fn color_range(&self) -> (u8, u8) {
+            (0, 255)
+        }
+
+        fn read_pixel(&self, i: u8, j: u8) -> Option<u8> {
+            Some(self.px[i as usize][j as usize])
+        }
+
+        fn capabilities(&self) -> Capabilities {
+            Capabilities { read_pixel: true, ..Capabilities::write_only::<u8, u8>() }
+        }
+    }
+
+    impl NativePixelWriter for MockWriter {
+        type Addr = u8;
+        type Color = u8;
+    }
+
+    /// Writer that discards pixels and counts calls, for call-count checks.
+    struct NullWriter {
+        pixel_calls: usize,
+        rect_calls: usize,
+        presents: usize,
+    }
+
+    impl NullWriter {
+        fn new() -> Self {
+            Self { pixel_calls: 0, rect_calls: 0, presents: 0 }
+        }
+    }
+
+    impl PixelWriter<u8, u8> for NullWriter {
+        fn write_pixel(&mut self, _i: u8, _j: u8, _color: u8) {
+            self.pixel_calls += 1;
+        }
+
+        fn addr_range(&self) -> ((u8, u8), (u8, u8)) {
+            ((0, 150), (0, 200))
+        }
+
+        fn color_range(&self) -> (u8, u8) {
+            (0, 255)
+        }
+
+        fn fill_rect(&mut self, _range: ((u8, u8), (u8, u8)), _color: u8) {
+            self.rect_calls += 1;
+        }
+
+        fn present(&mut self) {
+            self.presents += 1;
+        }
+    }
+
+    /// Bounding box of all non-zero pixels, as `((i0, i1), (j0, j1))`.
+    fn written_bounds(w: &MockWriter) -> Option<((u8, u8), (u8, u8))> {
+        let mut bounds: Option<((u8, u8), (u8, u8))> = None;
+        for (i, row) in w.px.iter().enumerate() {
+            for (j, &px) in row.iter().enumerate() {
+                if px == 0 {
+                    continue;
+                }
+                let (i, j) = (i as u8, j as u8);
+                bounds = Some(match bounds {
+                    None => ((i, i), (j, j)),
+                    Some(((i0, i1), (j0, j1))) => ((i0.min(i), i1.max(i)), (j0.min(j), j1.max(j))),
+                });
+            }
+        }
+        bounds
+    }
+
+    #[init]
+    fn init() {
+        let peripherals = esp_hal::init(esp_hal::Config::default());
+
+        let timg1 = esp_hal::timer::timg::TimerGroup::new(peripherals.TIMG1);
+        esp_rtos::start(timg1.timer0);
+
+        rtt_target::rtt_init_defmt!();
+    }
+
+    #[test]
+    fn bitmap_1bpp_glyph() {
+        // 3x5 "1", MSB-first, one padded byte per row.
+        const GLYPH: [u8; 5] = [0b0100_0000, 0b1100_0000, 0b0100_0000, 0b0100_0000, 0b1110_0000];
+        let mut w = MockWriter::new();
+        let mut d = Drawer::new(&mut w);
+        d.draw_bitmap_1bpp(2, 3, 3, 5, &GLYPH, 9, 1, false).unwrap();
+        assert_eq!(w.px[2][3..6], [1, 9, 1]);
+        assert_eq!(w.px[3][3..6], [9, 9, 1]);
+        assert_eq!(w.px[6][3..6], [9, 9, 9]);
+        assert_eq!(w.writes, 15);
+
+        // Transparent background only touches set bits, and clips at the edge.
+        let mut w = MockWriter::new();
+        let mut d = Drawer::new(&mut w);
+        d.draw_bitmap_1bpp(10, 14, 3, 5, &GLYPH, 9, 1, true).unwrap();
+        assert_eq!(w.px[10][14..16], [0, 9]);
+        assert_eq!(w.px[11][14..16], [9, 9]);
+        assert_eq!(w.writes, 3);
+    }
+
+    #[test]
+    fn addr_iter_covers_range_once() {
+        let w = MockWriter::new();
+        let mut n = 0;
+        let mut last = (0, 0);
+        for (i, j) in w.addr_iter() {
+            if n > 0 {
+                assert!((i, j) > last);
+            }
+            last = (i, j);
+            n += 1;
+        }
+        assert_eq!(n, W * H);
+        assert_eq!(last, (H as u8 - 1, W as u8 - 1));
+    }
+
+    #[test]
+    fn blit_strided_sub_region() {
+        // 16-wide source where each pixel encodes its own (row, col).
+        let mut src = [0u8; 16 * 4];
+        for (k, px) in src.iter_mut().enumerate() {
+            *px = ((k / 16) * 16 + k % 16) as u8;
+        }
+        let mut w = MockWriter::new();
+        let mut d = Drawer::new(&mut w);
+        // 3x2 block starting at source (1, 5), drawn at (4, 14): last column clips.
+        d.blit_strided(4, 14, 3, 2, &src[16 + 5..], 16).unwrap();
+        assert_eq!(w.px[4][14..16], [21, 22]);
+        assert_eq!(w.px[5][14..16], [37, 38]);
+        assert_eq!(w.writes, 4);
+
+        // The last row only needs `width` pixels, not a whole stride.
+        let mut w = MockWriter::new();
+        let mut d = Drawer::new(&mut w);
+        assert_eq!(d.blit_strided(0, 0, 3, 2, &src[..16 + 3], 16), Ok(()));
+        assert_eq!(d.blit_strided(0, 0, 3, 2, &src[..16 + 2], 16), Err(Error::BufferTooSmall));
+        assert_eq!(w.writes, 6);
+    }
+
+    #[test]
+    fn marker_footprints() {
+        // 5x5 window around the center (5, 5), `#` for each pixel drawn.
+        let styles = [
+            (MarkerStyle::Dot, [".....", "..#..", ".###.", "..#..", "....."], ["..#..", ".###.", "#####", ".###.", "..#.."]),
+            (MarkerStyle::Cross, [".....", ".#.#.", "..#..", ".#.#.", "....."], ["#...#", ".#.#.", "..#..", ".#.#.", "#...#"]),
+            (MarkerStyle::Plus, [".....", "..#..", ".###.", "..#..", "....."], ["..#..", "..#..", "#####", "..#..", "..#.."]),
+            (MarkerStyle::Square, [".....", ".###.", ".#.#.", ".###.", "....."], ["#####", "#...#", "#...#", "#...#", "#####"]),
+            (MarkerStyle::Circle, [".....", ".###.", ".#.#.", ".###.", "....."], [".###.", "#...#", "#...#", "#...#", ".###."]),
+        ];
+        for (style, mask1, mask2) in styles {
+            let mut w = MockWriter::new();
+            Drawer::new(&mut w).draw_marker(5, 5, style, 0, 7);
+            assert_eq!(w.writes, 1);
+            assert_eq!(w.px[5][5], 7);
+
+            for (size, mask) in [(1, mask1), (2, mask2)] {
+                let mut w = MockWriter::new();
+                Drawer::new(&mut w).draw_marker(5, 5, style, size, 7);
+                let mut drawn = 0;
+                for (i, row) in w.px.iter().enumerate() {
+                    for (j, &px) in row.iter().enumerate() {
+                        let set = (3..8).contains(&i) && (3..8).contains(&j) && mask[i - 3].as_bytes()[j - 3] == b'#';
+                        assert_eq!(px, if set { 7 } else { 0 });
+                        drawn += set as usize;
+                    }
+                }
+                // Each pixel written once.
+                assert_eq!(w.writes, drawn);
+            }
+        }
+
+        // Clipped at the corner: only the in-range quarter of the plus remains.
+        let mut w = MockWriter::new();
+        let mut d = Drawer::new(&mut w);
+        d.draw_marker(0, 0, MarkerStyle::Plus, 2, 7);
+        assert_eq!(w.writes, 5);
+    }
+
+    #[test]
+    fn plot_points_skips_off_screen() {
+        let mut w = MockWriter::new();
+        let mut d = Drawer::new(&mut w);
+        let (hi, wi) = (H as u8, W as u8);
+        let points = [(0, 0), (3, 4), (hi, 0), (0, wi), (hi - 1, wi - 1), (255, 255)];
+        assert!(d.in_bounds(0, 0) && d.in_bounds(hi - 1, wi - 1));
+        assert!(!d.in_bounds(hi, 0) && !d.in_bounds(0, wi));
+        assert!(d.write_pixel_checked(2, 2, 5));
+        assert!(!d.write_pixel_checked(hi, 2, 5));
+        d.plot_points(points.iter().copied(), 9);
+        assert_eq!(w.writes, 4);
+        assert_eq!((w.px[0][0], w.px[3][4], w.px[H - 1][W - 1]), (9, 9, 9));
+
+        // The clip rect narrows the visible area further.
+        let mut w = MockWriter::new();
+        let mut d = Drawer::new(&mut w);
+        d.set_clip(Some(((2, 4), (3, 5))));
+        assert!(d.in_bounds(2, 3) && d.in_bounds(4, 5));
+        assert!(!d.in_bounds(0, 0) && !d.in_bounds(5, 5) && !d.in_bounds(3, 2));
+        assert!(!d.write_pixel_checked(0, 0, 5));
+        d.plot_points(points.iter().copied(), 9);
+        d.draw_rectangle_corners(0, 0, 3, 255, 1);
+        assert_eq!(w.writes, 1 + 2 * 3);
+        assert_eq!((w.px[3][4], w.px[4][5], w.px[2][3], w.px[3][6]), (1, 0, 1, 0));
+    }
+
+    #[test]
+    fn write_pixel_norm_clamps_and_rounds() {
+        let mut w = MockWriter::new();
+        let mut d = Drawer::new(&mut w);
+        d.write_pixel_norm(0, 0, 0.0);
+        d.write_pixel_norm(0, 1, 1.0);
+        d.write_pixel_norm(0, 2, -3.0);
+        d.write_pixel_norm(0, 3, 7.5);
+        d.write_pixel_norm(0, 4, f32::NAN);
+        d.write_pixel_norm(0, 5, 0.5);
+        assert_eq!(&w.px[0][..6], &[0, 255, 0, 255, 0, 128]);
+    }
+
+    #[test]
+    fn wrap_mode_wraps_past_edges() {
+        let mut w = MockWriter::new();
+        let mut d = Drawer::new(&mut w);
+        assert_eq!(d.edge_mode(), EdgeMode::Clip);
+        assert!(!d.write_pixel_checked(0, W as u8, 1));
+        d.set_edge_mode(EdgeMode::Wrap);
+        d.write_pixel(3, W as u8, 2);
+        d.draw_line(5, W as u8 - 2, 5, W as u8 + 1, 3);
+        d.draw_rectangle_corners(H as u8 - 1, W as u8 - 1, H as u8, W as u8, 4);
+        assert_eq!(w.px[3][0], 2);
+        assert_eq!(&w.px[5][..2], &[3, 3]);
+        assert_eq!(&w.px[5][W - 2..], &[3, 3]);
+        assert_eq!((w.px[0][0], w.px[0][W - 1], w.px[H - 1][0], w.px[H - 1][W - 1]), (4, 4, 4, 4));
+        assert_eq!(w.px[1][0], 0);
+    }
+
+    #[test]
+    fn reset_state_restores_clipping() {
+        let mut w = MockWriter::new();
+        let mut d = Drawer::new(&mut w);
+        d.set_edge_mode(EdgeMode::Wrap);
+        d.set_clip(Some(((0, 0), (0, 0))));
+        d.reset_state();
+        assert_eq!(d.edge_mode(), EdgeMode::Clip);
+        assert_eq!(d.clip(), None);
+        assert!(!d.write_pixel_checked(0, W as u8, 1));
+        d.fill_screen(5);
+        d.draw_rectangle_corners(0, 0, 255, 255, 6);
+        assert!(w.px.iter().flatten().all(|&px| px == 6));
+        assert_eq!(w.writes, 2 * W * H);
+    }
+
+    /// `MockWriter` storage behind a 4-bit `color_range()`.
+    struct FourBitWriter(MockWriter);
+
+    impl PixelWriter<u8, u8> for FourBitWriter {
+        fn write_pixel(&mut self, i: u8, j: u8, color: u8) {
+            self.0.write_pixel(i, j, color);
+        }
+
+        fn addr_range(&self) -> ((u8, u8), (u8, u8)) {
+            self.0.addr_range()
+        }
+
+        fn color_range(&self) -> (u8, u8) {
+            (0, 15)
+        }
+
+        fn read_pixel(&self, i: u8, j: u8) -> Option<u8> {
+            self.0.read_pixel(i, j)
+        }
+    }
+
+    #[test]
+    fn add_and_sub_pixel_saturate_at_color_range() {
+        let mut w = FourBitWriter(MockWriter::new());
+        let mut d = Drawer::new(&mut w);
+        assert!(d.add_pixel(1, 1, 10));
+        assert!(d.add_pixel(1, 1, 10));
+        assert!(d.add_pixel(1, 2, 15));
+        assert!(d.sub_pixel(1, 3, 4));
+        assert!(!d.add_pixel(H as u8, 0, 1));
+        assert_eq!(&w.0.px[1][1..4], &[15, 15, 0]);
+
+        let mut d = Drawer::new(&mut w);
+        assert!(d.sub_pixel(1, 1, 6));
+        assert!(d.sub_pixel(1, 2, 200));
+        assert_eq!(&w.0.px[1][1..3], &[9, 0]);
+
+        // Full 8-bit range on the plain mock; no read_pixel, no write.
+        let mut m = MockWriter::new();
+        let mut d = Drawer::new(&mut m);
+        d.add_pixel(0, 0, 250);
+        d.add_pixel(0, 0, 250);
+        assert_eq!(m.px[0][0], 255);
+        let mut p = PaletteWriter::ega(&mut m);
+        assert!(!Drawer::new(&mut p).add_pixel(0, 1, 1));
+    }
+
+    #[test]
+    fn viewports_offset_and_clip() {
+        let screen = core::cell::RefCell::new(MockWriter::new());
+        let mut left = Viewport::new(&screen, 2, 0, 8, 8).unwrap();
+        let mut right = Viewport::new(&screen, 2, 8, 8, 8).unwrap();
+        assert_eq!(PixelWriter::<u8, u8>::addr_range(&left), ((0, 7), (0, 7)));
+        assert!(Viewport::<u8, _>::new(&screen, 5, 8, 8, 9).is_err());
+
+        let mut dl = Drawer::new(&mut left);
+        let mut dr = Drawer::new(&mut right);
+        dl.write_pixel(0, 0, 1);
+        dr.write_pixel(0, 0, 2);
+        // Clipped to the viewport, not spilled into the right one.
+        dl.draw_hline(7, 4, 10, 3);
+        dl.write_pixel(0, 8, 9);
+        dr.draw_rectangle_corners(7, 7, 10, 10, 4);
+
+        let px = screen.borrow().px;
+        assert_eq!((px[2][0], px[2][8]), (1, 2));
+        assert_eq!(&px[9][3..10], &[0, 3, 3, 3, 3, 0, 0]);
+        assert_eq!((px[9][15], px[2][8 + 8 - 1]), (4, 0));
+        assert_eq!(screen.borrow().writes, 2 + 4 + 1);
+
+        // A rectangle hanging off the viewport fills the overlap only.
+        left.fill_rect(((6, 12), (5, 20)), 5);
+        let px = screen.borrow().px;
+        assert!(px[8..10].iter().all(|row| row[5..8] == [5; 3]));
+        assert_eq!((px[7][5], px[8][4], px[8][8], px[10][5]), (0, 0, 0, 0));
+        left.fill_rect(((8, 12), (0, 3)), 6);
+        assert!(screen.borrow().px.iter().flatten().all(|&p| p != 6));
+    }
+
+    #[test]
+    fn diff_writer_skips_unchanged_pixels() {
+        let mut dw: DiffWriter<MockWriter, u8, W, H> = DiffWriter::new(MockWriter::new());
+        let mut d = Drawer::new(&mut dw);
+        d.fill_screen(3);
+        d.draw_rectangle(2, 2, 4, 4, 3);
+        d.write_pixel(0, 0, 9);
+        assert_eq!(dw.inner().writes, W * H + 1);
+
+        // A forced full redraw after invalidating goes out in full again.
+        dw.invalidate();
+        Drawer::new(&mut dw).fill_screen(3);
+        assert_eq!(dw.inner().writes, 2 * W * H + 1);
+        assert_eq!(dw.inner().px[0][0], 3);
+    }
+
+    #[test]
+    fn buffered_writer_flushes_only_changes_on_present() {
+        let mut bw: BufferedWriter<MockWriter, u8, W, H> = BufferedWriter::new(MockWriter::new(), 0);
+        let mut d = Drawer::new(&mut bw);
+        d.fill_screen(5);
+        d.draw_rectangle(2, 2, 4, 4, 3);
+        assert_eq!(bw.read_pixel(3u8, 3u8), Some(3));
+        assert_eq!(bw.inner().writes, 0);
+
+        bw.present();
+        assert_eq!(bw.inner().writes, W * H);
+        assert_eq!((bw.inner().px[0][0], bw.inner().px[3][3]), (5, 3));
+
+        // Redrawing the same picture sends nothing; a change sends only itself.
+        let mut d = Drawer::new(&mut bw);
+        d.fill_screen(5);
+        d.draw_rectangle(2, 2, 4, 4, 3);
+        d.write_pixel(0, 0, 9);
+        bw.present();
+        assert_eq!(bw.inner().writes, W * H + 1);
+        assert_eq!(bw.inner().px[0][0], 9);
+    }
+
+    #[test]
+    fn blit_from_pixel_writer_round_trips() {
+        let mut src = MockWriter::new();
+        for (n, px) in src.px.iter_mut().flatten().enumerate() {
+            *px = n as u8;
+        }
+        let mut dst = MockWriter::new();
+        let mut d = Drawer::new(&mut dst);
+        assert_eq!(d.blit_from_pixel_writer(&src, 0, 0, W as u8, H as u8, 0, 0), Ok(W * H));
+        assert_eq!(dst.px, src.px);
+
+        // Source clipped to its range, destination to its own.
+        let mut dst = MockWriter::new();
+        let mut d = Drawer::new(&mut dst);
+        assert_eq!(d.blit_from_pixel_writer(&src, H as u8 - 2, 1, 3, 3, 10, W as u8 - 2), Ok(2 * 2));
+        assert_eq!((dst.px[10][W - 2], dst.px[11][W - 1]), (src.px[H - 2][1], src.px[H - 1][2]));
+        assert_eq!(dst.writes, 4);
+
+        let blind = NullWriter::new();
+        let mut d = Drawer::new(&mut dst);
+        assert_eq!(d.blit_from_pixel_writer(&blind, 0, 0, 2, 2, 0, 0), Err(Error::NotConfigured));
+    }
+
+    #[test]
+    fn pwm_on_fraction_matches_brightness() {
+        let mut pw: PwmWriter<MockWriter, W, H> = PwmWriter::new(MockWriter::new());
+        let colors = [0u8, 1, 64, 128, 200, 255];
+        for (j, &c) in colors.iter().enumerate() {
+            pw.write_pixel(0u8, j as u8, c);
+        }
+        assert_eq!(pw.read_pixel(0u8, 2u8), Some(64));
+        let mut on = [0usize; 6];
+        for _ in 0..pw.period() {
+            pw.present();
+            for (n, &px) in on.iter_mut().zip(&pw.inner().px[0]) {
+                *n += (px == 255) as usize;
+            }
+        }
+        assert_eq!(on, [0, 1, 64, 128, 200, 255]);
+
+        // 4 levels: a 3-subframe period, 128 rounds to 2 of 3.
+        let mut pw: PwmWriter<MockWriter, W, H> = PwmWriter::new(MockWriter::new()).with_levels(4);
+        pw.write_pixel(1u8, 1u8, 128);
+        let mut on = 0;
+        for _ in 0..3 * pw.period() {
+            pw.present();
+            on += (pw.inner().px[1][1] == 255) as usize;
+        }
+        assert_eq!((pw.period(), on), (3, 6));
+    }
+
+    #[test]
+    fn scroll_moves_contents_and_fills() {
+        let mut w = MockWriter::new();
+        let mut d = Drawer::new(&mut w);
+        d.write_pixel(1, 2, 7);
+        d.scroll_down(3, 1).unwrap();
+        d.scroll_left(2, 1).unwrap();
+        assert_eq!(w.px[4][0], 7);
+        assert_eq!((w.px[1][2], w.px[0][5], w.px[5][W - 1]), (1, 1, 1));
+
+        let mut null = NullWriter::new();
+        assert_eq!(Drawer::new(&mut null).scroll_up(1, 0), Err(Error::NotConfigured));
+    }
+
+    #[test]
+    fn smooth_scroller_accumulates_fractions() {
+        let steps = |speed_q8: u32| {
+            let mut s = SmoothScroller::new(ScrollDir::Left, speed_q8);
+            let mut out = [0u32; 6];
+            out.iter_mut().for_each(|step| *step = s.advance());
+            out
+        };
+        assert_eq!(steps(128), [0, 1, 0, 1, 0, 1]);
+        assert_eq!(steps(384), [1, 2, 1, 2, 1, 2]);
+        assert_eq!(steps(86), [0, 0, 1, 0, 0, 1]);
+        assert_eq!(steps(256), [1; 6]);
+
+        let mut w = MockWriter::new();
+        let mut d = Drawer::new(&mut w);
+        d.write_pixel(0, 5, 7);
+        let mut s = SmoothScroller::new(ScrollDir::Right, 128);
+        for _ in 0..4 {
+            s.scroll(&mut d, 0).unwrap();
+        }
+        assert_eq!(w.px[0][7], 7);
+    }
+
+    #[test]
+    fn address_line_test_lights_powers_of_two() {
+        let mut w = MockWriter::new();
+        let mut d = Drawer::new(&mut w);
+        d.fill_screen(5);
+        // 16 columns (offsets up to 15) and 12 rows (up to 11).
+        assert_eq!(d.address_line_test(9, 0), (4, 4));
+        for j in 0..W {
+            let lit = j == 0 || j.is_power_of_two();
+            assert_eq!(w.px[0][j], if lit { 9 } else { 0 });
+        }
+        for i in 1..H {
+            assert_eq!(w.px[i][0], if i.is_power_of_two() { 9 } else { 0 });
+        }
+        assert_eq!(w.px.iter().flatten().filter(|&&c| c == 9).count(), 9);
+    }
+
+    #[test]
+    fn progress_bar_fill_matches_fraction() {
+        // 12 wide: 10 inner columns, 1..=10.
+        for (fraction, filled) in [(0u8, 0usize), (51, 2), (128, 5), (254, 10), (255, 10)] {
+            let mut w = MockWriter::new();
+            let mut d = Drawer::new(&mut w);
+            d.draw_progress_bar(2, 0, 12, 4, fraction, 9, 1, 5);
+            let inner = &w.px[3][1..11];
+            assert_eq!(inner.iter().filter(|&&c| c == 9).count(), filled);
+            assert_eq!(inner.iter().filter(|&&c| c == 1).count(), 10 - filled);
+            assert!(inner[..filled].iter().all(|&c| c == 9));
+            assert_eq!((w.px[2][0], w.px[5][11], w.px[3][0], w.px[3][11]), (5, 5, 5, 5));
+            assert_eq!(w.writes, 12 * 4);
+        }
+
+        // Off the right edge: only the visible part is drawn.
+        let mut w = MockWriter::new();
+        let mut d = Drawer::new(&mut w);
+        d.draw_progress_bar(0, 10, 12, 3, 255, 9, 1, 5);
+        assert_eq!(&w.px[1][10..], &[5, 9, 9, 9, 9, 9]);
+        assert_eq!(w.writes, 3 * 6);
+    }
+
+    #[test]
+    fn stroke_rect_at_half_thickness_fills_once() {
+        // 8 wide, 6 high: thickness 3 is half the height, so the bands meet.
+        let mut w = MockWriter::new();
+        let mut d = Drawer::new(&mut w);
+        d.stroke_rect(1, 2, 8, 6, 3, 7);
+        assert_eq!(w.px.iter().flatten().filter(|&&c| c == 7).count(), 8 * 6);
+        assert!(w.px[1..7].iter().all(|row| row[2..10].iter().all(|&c| c == 7)));
+        assert_eq!(w.writes, 8 * 6);
+
+        // One thinner leaves a 4x2 hole, still writing each pixel once.
+        let mut w = MockWriter::new();
+        let mut d = Drawer::new(&mut w);
+        d.stroke_rect(1, 2, 8, 6, 2, 7);
+        assert_eq!(w.writes, 8 * 6 - 4 * 2);
+        assert!(w.px[3..5].iter().all(|row| row[4..8].iter().all(|&c| c == 0)));
+        assert_eq!((w.px[2][3], w.px[5][8], w.px[3][3], w.px[4][8]), (7, 7, 7, 7));
+
+        // Centered on the screen edge: the outer pixel is clipped away.
+        let mut w = MockWriter::new();
+        let mut d = Drawer::new(&mut w);
+        d.stroke_rect_centered(0, 0, 10, 6, 3, 7);
+        assert_eq!(&w.px[0][..12], &[7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 0]);
+        assert_eq!(&w.px[2][..12], &[7, 7, 0, 0, 0, 0, 0, 0, 7, 7, 7, 0]);
+        assert_eq!((w.px[6][0], w.px[7][0]), (7, 0));
+    }
+
+    #[test]
+    fn fill_ring_leaves_the_inner_disc_empty() {
+        let (mut ring, mut hole, mut disc) = (MockWriter::new(), MockWriter::new(), MockWriter::new());
+        Drawer::new(&mut ring).fill_ring(5, 7, 2, 4, 1);
+        Drawer::new(&mut hole).fill_circle(5, 7, 2, 1);
+        Drawer::new(&mut disc).fill_circle(5, 7, 4, 1);
+        for ((r, h), d) in ring.px.iter().flatten().zip(hole.px.iter().flatten()).zip(disc.px.iter().flatten()) {
+            assert!(!(*r == 1 && *h == 1));
+            assert_eq!(*r | *h, *d);
+        }
+        assert_eq!((ring.px[5][7], ring.px[5][9], ring.px[5][10], ring.px[5][11]), (0, 0, 1, 1));
+
+        let mut w = MockWriter::new();
+        Drawer::new(&mut w).fill_ring(5, 7, 4, 4, 1);
+        assert_eq!(w.writes, 0);
+        Drawer::new(&mut w).fill_ring(5, 7, 0, 3, 1);
+        Drawer::new(&mut disc).fill_circle(5, 7, 4, 0);
+        Drawer::new(&mut disc).fill_circle(5, 7, 3, 1);
+        assert_eq!(w.px, disc.px);
+    }
+
+    #[test]
+    fn native_types_name_the_backend() {
+        // Generic code that never says `u8`.
+        fn far_corner<PW: NativePixelWriter>(pw: &PW) -> (PW::Addr, PW::Addr) {
+            let ((_, i_max), (_, j_max)) = pw.addr_range();
+            (i_max, j_max)
+        }
+
+        let mut w = MockWriter::new();
+        let (i, j) = far_corner(&w);
+        NativeDrawer::new(&mut w).write_pixel(i, j, 6);
+        assert_eq!(w.px[H - 1][W - 1], 6);
+
+        // Adapters pass the inner writer's types through.
+        let mut dw: DiffWriter<MockWriter, u8, W, H> = DiffWriter::new(MockWriter::new());
+        assert_eq!(far_corner(&dw), (H as u8 - 1, W as u8 - 1));
+        NativeDrawer::new(&mut dw).fill_screen(2);
+        assert_eq!(dw.inner().writes, W * H);
+    }
+
+    #[test]
+    fn styled_shapes_draw_stroke_and_fill() {
+        let style = PrimitiveStyle::stroke(7, 2).with_fill(3);
+
+        let mut w = MockWriter::new();
+        let mut d = Drawer::new(&mut w);
+        Rect::new(1, 2, 8, 6).into_styled(style).draw(&mut d);
+        assert_eq!(&w.px[1][1..11], &[0, 7, 7, 7, 7, 7, 7, 7, 7, 0]);
+        assert_eq!(&w.px[3][1..11], &[0, 7, 7, 3, 3, 3, 3, 7, 7, 0]);
+        assert_eq!(&w.px[4][2..10], &[7, 7, 3, 3, 3, 3, 7, 7]);
+        assert_eq!(w.px.iter().flatten().filter(|&&c| c == 3).count(), 4 * 2);
+        assert_eq!(w.px.iter().flatten().filter(|&&c| c == 7).count(), 8 * 6 - 4 * 2);
+        // Stroke and fill never overlap.
+        assert_eq!(w.writes, 8 * 6);
+
+        // Radius 4, stroke 2: the fill is the radius-2 disc.
+        let mut w = MockWriter::new();
+        let mut d = Drawer::new(&mut w);
+        Circle::new(5, 5, 4).into_styled(style).draw(&mut d);
+        assert_eq!(&w.px[5][..11], &[0, 7, 7, 3, 3, 3, 3, 3, 7, 7, 0]);
+        assert_eq!(&w.px[1][4..7], &[0, 7, 0]);
+        assert_eq!(&w.px[2][2..9], &[0, 7, 7, 7, 7, 7, 0]);
+        assert_eq!(w.px.iter().flatten().filter(|&&c| c == 3).count(), 13);
+        assert_eq!(w.px.iter().flatten().filter(|&&c| c != 0).count(), 49);
+        assert_eq!(w.writes, 49);
+
+        let mut w = MockWriter::new();
+        let mut d = Drawer::new(&mut w);
+        Circle::new(5, 5, 4).into_styled(PrimitiveStyle::default()).draw(&mut d);
+        assert_eq!(w.writes, 0);
+    }
+
+    #[test]
+    fn pen_draws_connected_paths() {
+        let mut w = MockWriter::new();
+        let mut d = Drawer::new(&mut w);
+        let mut pen = Pen::new(&mut d, 1, 1);
+        // Closed 4x4 square: 12 distinct border pixels.
+        pen.line_to(1, 4, 3);
+        pen.line_to(4, 4, 3);
+        pen.line_to(4, 1, 3);
+        pen.line_to(1, 1, 3);
+        assert_eq!(pen.position(), (1, 1));
+
+        // Pen up: moving draws nothing, then a segment off the bottom clips.
+        pen.pen_up();
+        pen.line_to(8, 8, 5);
+        assert!(!pen.is_down());
+        pen.pen_down();
+        pen.line_to(200, 8, 5);
+        pen.move_to(0, 0);
+        assert_eq!(pen.position(), (0, 0));
+
+        assert_eq!(w.px.iter().flatten().filter(|&&c| c == 3).count(), 12);
+        assert_eq!((w.px[1][1], w.px[4][4], w.px[2][2]), (3, 3, 0));
+        assert_eq!(w.px.iter().flatten().filter(|&&c| c == 5).count(), H - 8);
+        assert_eq!((w.px[8][8], w.px[H - 1][8]), (5, 5));
+    }
+
+    #[test]
+    fn primitive_bounds_match_pixels() {
+        let mut w = MockWriter::new();
+        let mut d = Drawer::new(&mut w);
+        let rect = d.rect_bounds(9, 12, 6, 5);
+        d.draw_bitmap_1bpp(9, 12, 6, 5, &[0xFC; 5], 1, 0, true).unwrap();
+        assert_eq!(rect, Some(((9, 11), (12, 15))));
+        assert_eq!(written_bounds(&w), rect);
+
+        let mut w = MockWriter::new();
+        let mut d = Drawer::new(&mut w);
+        let circle = d.circle_bounds(1, 4, 3);
+        d.draw_marker(1, 4, MarkerStyle::Circle, 3, 1);
+        assert_eq!(circle, Some(((0, 4), (1, 7))));
+        assert_eq!(written_bounds(&w), circle);
+
+        let d = Drawer::new(&mut w);
+        assert_eq!(d.rect_bounds(0, 20, 4, 4), None);
+        assert_eq!(d.rect_bounds(0, 0, 0, 4), None);
+    }
+
+    #[test]
+    fn histogram_of_gradient() {
+        // Every column j holds 16 * j: one column per 4-bit bin, H pixels each.
+        let mut w = MockWriter::new();
+        let mut d = Drawer::new(&mut w);
+        for i in 0..H as u8 {
+            for j in 0..W as u8 {
+                d.write_pixel(i, j, j * 16);
+            }
+        }
+        let hist: [u32; DEFAULT_HISTOGRAM_BINS] = d.brightness_histogram();
+        assert_eq!(hist, [H as u32; 16]);
+
+        let hist: [u32; 4] = d.brightness_histogram();
+        assert_eq!(hist, [4 * H as u32; 4]);
+        assert_eq!(hist.iter().sum::<u32>(), (W * H) as u32);
+    }
+
+    #[test]
+    fn auto_contrast_stretches_a_narrow_image() {
+        let mut w = MockWriter::new();
+        let mut d = Drawer::new(&mut w);
+        d.fill_screen(115);
+        d.write_pixel(0, 0, 100);
+        d.write_pixel(H as u8 - 1, W as u8 - 1, 130);
+        assert_eq!(d.used_color_range(), Some((100, 130)));
+        assert_eq!(d.auto_contrast(), Ok(true));
+        assert_eq!(d.used_color_range(), Some((0, 255)));
+        assert_eq!((w.px[0][0], w.px[1][1], w.px[H - 1][W - 1]), (0, 128, 255));
+
+        // Already full range: nothing is rewritten.
+        let writes = w.writes;
+        assert_eq!(Drawer::new(&mut w).auto_contrast(), Ok(false));
+        assert_eq!(w.writes, writes);
+        assert_eq!(Drawer::new(&mut NullWriter::new()).auto_contrast(), Err(Error::NotConfigured));
+    }
+
+    #[test]
+    fn marching_ants_shift_one_pixel_per_advance() {
+        let mut w = MockWriter::new();
+        assert_eq!(Drawer::new(&mut w).draw_line_dashed(0, 0, 0, 9, Dash::new(3, 2), 1), 6);
+        assert_eq!(&w.px[0][..10], &[1, 1, 1, 0, 0, 1, 1, 1, 0, 0]);
+
+        let mut ants = MarchingAnts::new(2, 2);
+        let mut frames = [[0u8; 4]; 3];
+        for frame in &mut frames {
+            let mut w = MockWriter::new();
+            ants.draw(&mut Drawer::new(&mut w), 1, 1, 4, 4, 1);
+            // Clockwise from the top-left: top row, then down the right side.
+            *frame = [w.px[1][1], w.px[1][2], w.px[1][3], w.px[1][4]];
+            assert_eq!(w.px.iter().flatten().filter(|&&p| p == 1).count(), 6);
+            ants.advance();
+        }
+        assert_eq!(frames, [[1, 1, 0, 0], [0, 1, 1, 0], [0, 0, 1, 1]]);
+
+        // A period past `u16::MAX` must not overflow.
+        let mut ants = MarchingAnts::new(u16::MAX, u16::MAX);
+        ants.advance();
+        assert_eq!(ants.dash().phase, 1);
+    }
+
+    #[test]
+    fn fill_screen_uses_fill_rect() {
+        let mut w = NullWriter::new();
+        Drawer::new(&mut w).fill_screen(3);
+        assert_eq!((w.pixel_calls, w.rect_calls), (0, 1));
+
+        // Without an override the default falls back to one write per pixel.
+        let mut w = MockWriter::new();
+        Drawer::new(&mut w).fill_screen(3);
+        assert_eq!(w.writes, W * H);
+        assert!(w.px.iter().flatten().all(|&px| px == 3));
+    }
+
+    #[test]
+    fn rectangle_corners_any_order() {
+        let mut a = MockWriter::new();
+        Drawer::new(&mut a).draw_rectangle_corners(2, 3, 5, 7, 4);
+        let mut b = MockWriter::new();
+        Drawer::new(&mut b).draw_rectangle_corners(5, 7, 2, 3, 4);
+        let mut c = MockWriter::new();
+        Drawer::new(&mut c).draw_rectangle_corners(5, 3, 2, 7, 4);
+        assert_eq!(written_bounds(&a), Some(((2, 5), (3, 7))));
+        assert_eq!(a.px, b.px);
+        assert_eq!(a.px, c.px);
+        assert_eq!(a.writes, 4 * 5);
+
+        // Equal corners draw a single pixel.
+        let mut w = MockWriter::new();
+        Drawer::new(&mut w).draw_rectangle_corners(6, 6, 6, 6, 4);
+        assert_eq!(w.writes, 1);
+
+        // Clipped at the far edge without overflowing.
+        let mut w = MockWriter::new();
+        Drawer::new(&mut w).draw_rectangle_corners(10, 14, 255, 255, 4);
+        assert_eq!(written_bounds(&w), Some(((10, H as u8 - 1), (14, W as u8 - 1))));
+    }
+
+    #[test]
+    fn blend_math_at_alpha_levels() {
+        let mut w = MockWriter::new();
+        let mut d = Drawer::new(&mut w);
+        for (j, alpha) in [0u8, 64, 128, 191, 255].into_iter().enumerate() {
+            d.write_pixel(0, j as u8, 40);
+            d.write_pixel_blend(0, j as u8, 240, alpha);
+            d.write_pixel(1, j as u8, 200);
+            d.write_pixel_blend(1, j as u8, 0, alpha);
+        }
+        // 40 + (240 - 40) * a / 255, and 200 * (255 - a) / 255, rounded.
+        assert_eq!(w.px[0][..5], [40, 90, 140, 190, 240]);
+        assert_eq!(w.px[1][..5], [200, 150, 100, 50, 0]);
+
+        // Write-only backends round alpha to opaque or transparent.
+        let mut w = NullWriter::new();
+        let mut d = Drawer::new(&mut w);
+        for alpha in [0, 127, 128, 255] {
+            d.write_pixel_blend(0, 0, 9, alpha);
+        }
+        assert_eq!(w.pixel_calls, 2);
+    }
+
+    #[test]
+    fn frame_guard_presents_on_drop() {
+        let mut w = NullWriter::new();
+        let mut d = Drawer::new_buffered(&mut w);
+        {
+            let mut f = d.frame();
+            f.fill_screen(0);
+        }
+        {
+            let mut f = d.frame();
+            f.write_pixel(1, 1, 5);
+        }
+        assert!(d.is_buffered());
+        assert_eq!((w.presents, w.rect_calls, w.pixel_calls), (2, 1, 1));
+
+        // Immediate-mode drawers never present from the guard.
+        let mut w = NullWriter::new();
+        let mut d = Drawer::new(&mut w);
+        d.frame().fill_screen(0);
+        assert_eq!(w.presents, 0);
+    }
+
+    #[test]
+    fn capabilities_per_backend() {
+        // bus_dac and sipo are checked where their writers are built, in
+        // utils_test and sipo_test.
+        let mut null = NullWriter::new();
+        assert_eq!(
+            null.capabilities(),
+            Capabilities {
+                read_pixel: false,
+                batching: false,
+                presentation: false,
+                fast_fill: false,
+                channels: 1,
+                addr_bits: 8,
+                color_bits: 8,
+            }
+        );
+
+        let mut mock = MockWriter::new();
+        assert!(mock.capabilities().read_pixel);
+        let palette = PaletteWriter::ega(&mut mock);
+        assert!(!palette.capabilities().read_pixel);
+        let palette = PaletteWriter::ega(&mut null);
+        assert_eq!(palette.capabilities(), Capabilities::write_only::<u8, u8>());
+
+        let wide = Capabilities::write_only::<u16, u32>();
+        assert_eq!((wide.addr_bits, wide.color_bits), (16, 32));
+    }
+
+    #[cfg(feature = "bench")]
+    #[test]
+    fn bench_covers_the_whole_screen() {
+        use esp_disp_driver::display::bench::{measure, WRITE_SAMPLES};
+        let mut w = MockWriter::new();
+        let report = measure(&mut w, 9);
+        assert_eq!(report.pixels, (W * H) as u32);
+        assert!(report.fill_cycles_per_pixel() <= report.fill_screen);
+        assert!(w.px.iter().flatten().all(|&p| p == 9));
+        assert_eq!(w.writes, WRITE_SAMPLES + W * H);
+    }
+
+    #[cfg(feature = "sim")]
+    #[test]
+    fn sim_capabilities() {
+        use esp_disp_driver::display::sim::SimPixelWriter;
+        let caps = SimPixelWriter::<4, 4>::new().capabilities();
+        assert!(caps.read_pixel && caps.fast_fill);
+        assert!(!caps.presentation && !caps.batching);
+    }
+
+    #[test]
+    fn needle_tip_follows_the_dial_angle() {
+        // Center (6, 8), 5 px long: 0 up, 90 right, 180 down, 270 left.
+        for (angle, bounds) in [
+            (0, ((1, 6), (8, 8))),
+            (90, ((6, 6), (8, 13))),
+            (180, ((6, 11), (8, 8))),
+            (270, ((6, 6), (3, 8))),
+            // 5 * sin(45) = 3.54, rounded to 4.
+            (45, ((2, 6), (8, 12))),
+            (-90, ((6, 6), (3, 8))),
+        ] {
+            let mut w = MockWriter::new();
+            Drawer::new(&mut w).draw_needle(6, 8, 5, angle, 1, 1);
+            assert_eq!(written_bounds(&w), Some(bounds));
+        }
+
+        // Three wide: a horizontal needle covers three rows.
+        let mut w = MockWriter::new();
+        Drawer::new(&mut w).draw_needle(6, 8, 5, 90, 3, 1);
+        assert_eq!(written_bounds(&w), Some(((5, 7), (8, 13))));
+        assert_eq!(w.writes, 18);
+
+        // Off the top edge: clipped.
+        let mut w = MockWriter::new();
+        Drawer::new(&mut w).draw_needle(6, 8, 20, 0, 2, 1);
+        assert_eq!(written_bounds(&w), Some(((0, 6), (8, 9))));
+    }
+
+    #[test]
+    fn str_centered_and_left_aligned_when_too_wide() {
+        assert_eq!(FONT_3X5.text_width(""), 0);
+        assert_eq!(FONT_3X5.text_width("HI"), 7);
+        assert_eq!(FONT_3X5.glyph('a'), FONT_3X5.glyph('A'));
+        assert_eq!(FONT_3X5.glyph('~'), None);
+
+        let mut w = MockWriter::new();
+        Drawer::new(&mut w).draw_str_centered(6, 8, "HI", 1);
+        assert_eq!(written_bounds(&w), Some(((4, 8), (5, 11))));
+
+        // Off the top edge: clipped, not shifted.
+        let mut w = MockWriter::new();
+        Drawer::new(&mut w).draw_str_centered(0, 8, "I", 1);
+        assert_eq!(written_bounds(&w), Some(((0, 2), (7, 9))));
+
+        // 19 px on a 16 px wide screen.
+        let mut w = MockWriter::new();
+        Drawer::new(&mut w).draw_str_centered(6, 8, "ABCDE", 1);
+        assert_eq!(written_bounds(&w), Some(((4, 8), (0, 14))));
+        assert_eq!(w.px[4][1], 1);
+    }
+
+    #[test]
+    fn char_spacing_and_line_height_move_the_pen() {
+        // The top row of 'I' is 0xE0: three pixels from the pen column.
+        let mut w = MockWriter::new();
+        let mut d = Drawer::new(&mut w);
+        assert_eq!((d.char_spacing(), d.line_height()), (1, 6));
+        d.draw_str(0, 1, "II", 1);
+        assert_eq!(&w.px[0][1..9], &[1, 1, 1, 0, 1, 1, 1, 0]);
+
+        let mut w = MockWriter::new();
+        let mut d = Drawer::new(&mut w);
+        d.set_char_spacing(3);
+        d.set_line_height(7);
+        d.draw_str(0, 1, "II\nI", 1);
+        assert_eq!(&w.px[0][1..11], &[1, 1, 1, 0, 0, 0, 1, 1, 1, 0]);
+        assert_eq!(&w.px[7][1..5], &[1, 1, 1, 0]);
+        assert_eq!(w.px[6][1..5], [0; 4]);
+
+        let mut d = Drawer::new(&mut w);
+        d.set_char_spacing(0);
+        d.reset_state();
+        assert_eq!(d.char_spacing(), 1);
+    }
+
+    #[test]
+    fn str_bg_fills_the_whole_advance_box() {
+        let mut w = MockWriter::new();
+        for row in w.px.iter_mut() {
+            row.fill(7);
+        }
+        // Second cell runs off the right edge at column 15.
+        Drawer::new(&mut w).draw_str_bg(2, 10, "I.", 1, 0);
+        for i in 0..H {
+            for j in 0..W {
+                let in_cell = (2..7).contains(&i) && j >= 10;
+                assert_eq!(w.px[i][j] == 7, !in_cell);
+            }
+        }
+        // 'I' is 0xE0, 0x40, ...: the gap column and the glyph's side
+        // columns are background.
+        assert_eq!(&w.px[2][10..14], &[1, 1, 1, 0]);
+        assert_eq!(&w.px[3][10..14], &[0, 1, 0, 0]);
+    }
+
+    #[test]
+    fn invert_rect_twice_restores() {
+        let mut w = MockWriter::new();
+        for (i, row) in w.px.iter_mut().enumerate() {
+            for (j, px) in row.iter_mut().enumerate() {
+                *px = (i * W + j) as u8;
+            }
+        }
+        let orig = w.px;
+        let mut d = Drawer::new(&mut w);
+        // Hangs off the bottom-right corner.
+        d.invert_rect(8, 10, 10, 10);
+        d.invert_rect(8, 10, 10, 10);
+        assert_eq!(w.px, orig);
+
+        let mut d = Drawer::new(&mut w);
+        d.invert_rect(1, 2, 3, 2);
+        assert_eq!(w.px[1][2], 255 - orig[1][2]);
+        assert_eq!(w.px[2][4], 255 - orig[2][4]);
+        assert_eq!(w.px[3][4], orig[3][4]);
+        assert_eq!(w.px[1][5], orig[1][5]);
+        assert_eq!(w.writes, 2 * 4 * 6 + 6);
+
+        let mut n = NullWriter::new();
+        Drawer::new(&mut n).invert_rect(0, 0, 4, 4);
+        assert_eq!(n.pixel_calls, 0);
+    }
+
+    #[test]
+    fn capture_then_blit_restores_region() {
+        let mut w = MockWriter::new();
+        for (i, row) in w.px.iter_mut().enumerate() {
+            for (j, px) in row.iter_mut().enumerate() {
+                *px = (i * W + j) as u8;
+            }
+        }
+        let orig = w.px;
+        let mut d = Drawer::new(&mut w);
+        // Hangs off the bottom-right corner.
+        let mut saved = [0xEEu8; 5 * 4];
+        d.capture_region(9, 13, 5, 4, &mut saved).unwrap();
+        assert_eq!(&saved[..3], &orig[9][13..]);
+        assert_eq!(saved[3], 0xEE);
+        d.fill_screen(0);
+        d.blit(9, 13, 5, 4, &saved).unwrap();
+        assert_eq!(&w.px[9][13..], &orig[9][13..]);
+        assert_eq!(&w.px[11][13..], &orig[11][13..]);
+        assert_eq!(w.px[8][13], 0);
+
+        let d = Drawer::new(&mut w);
+        let mut short = [0u8; 19];
+        assert!(matches!(d.capture_region(0, 0, 5, 4, &mut short), Err(Error::BufferTooSmall)));
+        let mut n = NullWriter::new();
+        assert!(matches!(Drawer::new(&mut n).capture_region(0, 0, 1, 1, &mut short), Err(Error::NotConfigured)));
+    }
+
+    #[test]
+    fn line_count_matches_clipped_length() {
+        let mut w = MockWriter::new();
+        let mut d = Drawer::new(&mut w);
+        // Fully visible: max(|di|, |dj|) + 1 pixels, in either direction.
+        assert_eq!(d.draw_line_counted(1, 2, 5, 11, 1), 10);
+        assert_eq!(d.draw_line_counted(5, 11, 1, 2, 1), 10);
+        assert_eq!(d.draw_line_counted(3, 3, 3, 3, 1), 1);
+        // Diagonal from (0, 0) leaves the 12-row screen after row 11.
+        assert_eq!(d.draw_line_counted(0, 0, 20, 20, 1), 12);
+        // Horizontal line clipped on both ends to the 16 columns.
+        assert_eq!(d.draw_line_counted(4, 0, 4, 255, 1), 16);
+        assert_eq!(w.writes, 10 + 10 + 1 + 12 + 16);
+
+        let mut w = MockWriter::new();
+        Drawer::new(&mut w).draw_line(0, 0, 11, 3, 1);
+        assert_eq!(written_bounds(&w), Some(((0, 11), (0, 3))));
+        assert_eq!(w.writes, 12);
+    }
+
+    #[test]
+    fn pixel_iters_match_drawn_pixels() {
+        let mut w = MockWriter::new();
+        let mut d = Drawer::new(&mut w);
+        let lines = [(1, 2, 5, 11), (0, 0, 20, 20), (4, 0, 4, 255), (11, 15, 0, 0)];
+        let mut total = 0;
+        for (i0, j0, i1, j1) in lines {
+            let n = d.line_iter(i0, j0, i1, j1).count();
+            assert_eq!(d.draw_line_counted(i0, j0, i1, j1, 1), n);
+            total += n;
+        }
+        let mut it = d.line_iter(0, 0, 2, 2);
+        assert_eq!((it.next(), it.next(), it.next(), it.next()), (Some((0, 0)), Some((1, 1)), Some((2, 2)), None));
+
+        // Hangs off the bottom-right corner: 3 rows of 4 visible columns.
+        let n = d.rect_iter(9, 12, 14, 30).count();
+        assert_eq!(n, 3 * 4);
+        d.draw_rectangle_corners(9, 12, 14, 30, 2);
+        total += n;
+
+        // Wrapped, larger than the screen: every pixel exactly once.
+        d.set_edge_mode(EdgeMode::Wrap);
+        assert_eq!(d.rect_iter(3, 3, 40, 40).count(), W * H);
+        let n = d.line_iter(5, 14, 5, 17).count();
+        assert_eq!(d.line_iter(5, 14, 5, 17).last(), Some((5, 1)));
+        d.draw_line(5, 14, 5, 17, 3);
+        total += n;
+        assert_eq!(w.writes, total);
+    }
+
+    #[test]
+    fn blit_rotated_quarter_turns() {
+        // 3 wide, 2 tall; 0 is the transparent key.
+        #[rustfmt::skip]
+        let sprite: [u8; 6] = [
+            1, 2, 3,
+            4, 0, 6,
+        ];
+        let mut a = MockWriter::new();
+        let mut b = MockWriter::new();
+        Drawer::new(&mut a).blit_rotated(3, 4, 3, 2, &sprite, 0, 0).unwrap();
+        Drawer::new(&mut b).blit_transparent(3, 4, 3, 2, &sprite, 0).unwrap();
+        assert_eq!(a.px, b.px);
+        assert_eq!(a.writes, 5);
+
+        // 360 is 0 again.
+        let mut c = MockWriter::new();
+        Drawer::new(&mut c).blit_rotated(3, 4, 3, 2, &sprite, 360, 0).unwrap();
+        assert_eq!(c.px, b.px);
+
+        // Clockwise quarter turn: the left column becomes the top row.
+        let mut w = MockWriter::new();
+        Drawer::new(&mut w).blit_rotated(3, 4, 3, 2, &sprite, 90, 0).unwrap();
+        let ((i0, _), (j0, _)) = written_bounds(&w).unwrap();
+        let at = |r: usize, c: usize| w.px[i0 as usize + r][j0 as usize + c];
+        assert_eq!([at(0, 0), at(0, 1)], [4, 1]);
+        assert_eq!([at(1, 0), at(1, 1)], [0, 2]);
+        assert_eq!([at(2, 0), at(2, 1)], [6, 3]);
+        assert_eq!(w.writes, 5);
+
+        // Half turn of a sprite partly off the top-left corner is clipped.
+        let mut w = MockWriter::new();
+        Drawer::new(&mut w).blit_rotated(0, 0, 3, 2, &sprite, 180, 0).unwrap();
+        assert_eq!(w.px[0][..3], [6, 0, 4]);
+        assert_eq!(w.px[1][..3], [3, 2, 1]);
+
+        let mut w = MockWriter::new();
+        assert_eq!(
+            Drawer::new(&mut w).blit_rotated(0, 0, 3, 3, &sprite, 45, 0),
+            Err(Error::BufferTooSmall)
+        );
+    }
+
+    #[test]
+    fn flood_fill_degrades_to_a_partial_fill_on_overflow() {
+        let count = |w: &MockWriter, c: u8| w.px.iter().flatten().filter(|&&p| p == c).count();
+        let inside = |i: usize, j: usize| (3..=6).contains(&i) && (3..=8).contains(&j);
+        let mut w = MockWriter::new();
+        Drawer::new(&mut w).draw_rectangle_outline(2, 2, 8, 6, 1);
+
+        // One slot: the first span's neighbours no longer fit.
+        let mut stack = ScanlineStack::<1>::new();
+        assert_eq!(Drawer::new(&mut w).flood_fill(4, 4, 2, &mut stack), Ok(false));
+        assert!(stack.overflowed());
+        let partial = count(&w, 2);
+        assert!(partial > 0 && partial < 24);
+        for (i, row) in w.px.iter().enumerate() {
+            for (j, &p) in row.iter().enumerate() {
+                assert!(p != 2 || inside(i, j));
+            }
+        }
+        assert_eq!(count(&w, 1), 24);
+
+        // Enough room finishes the job; the stack is reusable.
+        let mut stack = ScanlineStack::<8>::new();
+        let mut w = MockWriter::new();
+        Drawer::new(&mut w).draw_rectangle_outline(2, 2, 8, 6, 1);
+        for _ in 0..2 {
+            assert_eq!(Drawer::new(&mut w).flood_fill(4, 4, 3, &mut stack), Ok(true));
+            assert!(!stack.overflowed() && stack.is_empty());
+            assert_eq!(count(&w, 3), 24);
+            assert_eq!(count(&w, 0), 16 * 12 - 48);
+        }
+
+        let mut null = NullWriter::new();
+        assert_eq!(Drawer::new(&mut null).flood_fill(0, 0, 1, &mut stack), Err(Error::NotConfigured));
+
+        // A fill color that reads back as the target stops after one run,
+        // reported as a partial fill. Rows above `.1` keep the low nibble.
+        struct NibbleWriter(MockWriter, u8);
+
+        impl PixelWriter<u8, u8> for NibbleWriter {
+            fn write_pixel(&mut self, i: u8, j: u8, color: u8) {
+                self.0.write_pixel(i, j, if i < self.1 { color & 0x0F } else { color });
+            }
+
+            fn addr_range(&self) -> ((u8, u8), (u8, u8)) {
+                self.0.addr_range()
+            }
+
+            fn color_range(&self) -> (u8, u8) {
+                (0, 15)
+            }
+
+            fn read_pixel(&self, i: u8, j: u8) -> Option<u8> {
+                self.0.read_pixel(i, j)
+            }
+
+            fn capabilities(&self) -> Capabilities {
+                self.0.capabilities()
+            }
+        }
+
+        let mut w = NibbleWriter(MockWriter::new(), H as u8);
+        assert_eq!(Drawer::new(&mut w).flood_fill(4, 4, 0x10, &mut stack), Ok(false));
+        assert!(!stack.overflowed());
+        assert_eq!(w.0.writes, W);
+
+        // An abort after an overflow keeps the overflow flag: row 4 fills,
+        // row 5 is dropped from the one-slot stack, and row 3 aborts.
+        let mut stack = ScanlineStack::<1>::new();
+        let mut w = NibbleWriter(MockWriter::new(), 4);
+        assert_eq!(Drawer::new(&mut w).flood_fill(4, 4, 0x10, &mut stack), Ok(false));
+        assert!(stack.overflowed());
+        assert_eq!(w.0.writes, 2 * W);
+        assert!(w.0.px[4].iter().all(|&p| p == 0x10));
+    }
+
+    #[test]
+    fn each_icon_blits_its_set_bits() {
+        let expected = [28, 28, 28, 28, 16, 40, 24, 18];
+        for (icon, want) in Icon::ALL.into_iter().zip(expected) {
+            let mut w = MockWriter::new();
+            Drawer::new(&mut w).draw_icon(2, 3, icon, 5);
+            assert_eq!(w.writes, want);
+            assert_eq!(w.px.iter().flatten().filter(|&&p| p == 5).count(), want);
+            let ((i0, i1), (j0, j1)) = written_bounds(&w).unwrap();
+            assert!(i0 >= 2 && i1 < 2 + ICON_SIZE && j0 >= 3 && j1 < 3 + ICON_SIZE);
+        }
+
+        // Clipped at the bottom-right corner: only the top-left 2x2 lands.
+        let mut w = MockWriter::new();
+        Drawer::new(&mut w).draw_icon(10, 14, Icon::Cross, 5);
+        assert_eq!(w.writes, 4);
+    }
+
+    #[test]
+    fn mirror_writes_reach_both_panels() {
+        let mut mirror = MirrorWriter::new(MockWriter::new(), MockWriter::new());
+        let mut d = Drawer::new(&mut mirror);
+        d.write_pixel(3, 4, 7);
+        d.fill_circle(6, 8, 2, 5);
+        assert_eq!(mirror.read_pixel(3, 4), Some(7));
+        assert_eq!(mirror.primary().px, mirror.secondary().px);
+        let (a, b) = mirror.into_inner();
+        assert_eq!((a.px[3][4], b.px[3][4], b.px[6][8]), (7, 7, 5));
+        assert_eq!(a.writes, b.writes);
+
+        // Only the overlap is addressable.
+        let screen = core::cell::RefCell::new(MockWriter::new());
+        let mirror = MirrorWriter::new(MockWriter::new(), Viewport::new(&screen, 0, 0, 5, 9).unwrap());
+        assert_eq!(mirror.addr_range(), ((0, 4), (0, 8)));
+    }
+
+    #[test]
+    fn diff_write_frame_sends_changed_cells_in_address_order() {
+        /// Records the addresses it is written at, in order.
+        struct LogWriter {
+            log: [(u8, u8); 16],
+            len: usize,
+        }
+        impl PixelWriter<u8, u8> for LogWriter {
+            fn write_pixel(&mut self, i: u8, j: u8, _color: u8) {
+                self.log[self.len] = (i, j);
+                self.len += 1;
+            }
+            fn addr_range(&self) -> ((u8, u8), (u8, u8)) {
+                ((1, 3), (2, 5))
+            }
+            fn color_range(&self) -> (u8, u8) {
+                (0, 255)
+            }
+        }
+
+        let mut w: DiffWriter<_, u8, 4, 3> = DiffWriter::new(LogWriter { log: [(0, 0); 16], len: 0 });
+        let mut fb = [[1u8; 4]; 3];
+        assert_eq!(w.write_frame(&fb), 12);
+        let expected: [(u8, u8); 12] = core::array::from_fn(|k| (1 + k as u8 / 4, 2 + k as u8 % 4));
+        assert_eq!(w.inner().log[..12], expected);
+
+        fb[2][1] = 5;
+        assert_eq!(w.write_frame(&fb), 1);
+        assert_eq!((w.inner().len, w.inner().log[12]), (13, (3, 3)));
+    }
+
+    #[test]
+    fn checkerboard_cells_alternate() {
+        let mut w = MockWriter::new();
+        Drawer::new(&mut w).fill_checkerboard(3, 1, 2);
+        assert_eq!(w.px[0][0], 1);
+        assert_eq!(w.px[2][2], 1);
+        assert_eq!(w.px[0][3], 2);
+        assert_eq!(w.px[3][0], 2);
+        assert_eq!(w.px[4][4], 1);
+        // Column 15 starts a clipped cell: square (3, 5), even.
+        assert_eq!(w.px[H - 1][W - 1], 1);
+        assert_eq!(w.px[H - 1][W - 2], 2);
+        assert!(w.px.iter().flatten().all(|&px| px == 1 || px == 2));
+
+        let mut w = MockWriter::new();
+        Drawer::new(&mut w).fill_checkerboard(0, 1, 2);
+        assert_eq!(&w.px[0][..4], &[1, 2, 1, 2]);
+        assert_eq!(w.px[1][0], 2);
+    }
+
+    #[test]
+    fn noise_is_deterministic_smooth_and_spread() {
+        let (mut lo, mut hi) = (u8::MAX, u8::MIN);
+        let mut differs = false;
+        for y in -64..64 {
+            for x in -64..64 {
+                let v = noise2d(x, y, 7);
+                assert_eq!(v, noise2d(x, y, 7));
+                assert!(v.abs_diff(noise2d(x + 1, y, 7)) <= 24);
+                differs |= v != noise2d(x, y, 8);
+                lo = lo.min(v);
+                hi = hi.max(v);
+            }
+        }
+        assert!(differs);
+        assert!(lo < 48 && hi > 208, "range {}..={}", lo, hi);
+
+        let mut w = MockWriter::new();
+        Drawer::new(&mut w).fill_with(|i, j| noise2d(j as i32, i as i32, 7));
+        assert_eq!(w.px[3][5], noise2d(5, 3, 7));
+        assert_eq!(w.px[H - 1][W - 1], noise2d(W as i32 - 1, H as i32 - 1, 7));
+    }
+
+    #[test]
+    fn gradient_hits_its_stops_exactly() {
+        let stops = [(0, 10u8), (100, 200), (255, 0)];
+        for interp in [Interp::Linear, Interp::Smooth] {
+            let g = Gradient::new(stops).unwrap().with_interp(interp);
+            for (p, c) in stops {
+                assert_eq!(g.color_at(p), c);
+            }
+        }
+        let linear = Gradient::new(stops).unwrap();
+        assert_eq!(linear.color_at(50), 105);
+        assert_eq!(linear.color_at(99), 198);
+        // Smooth eases in: near a stop it stays closer to it than linear.
+        let smooth = linear.with_interp(Interp::Smooth);
+        assert_eq!(smooth.color_at(50), 105);
+        assert!(smooth.color_at(10) < linear.color_at(10));
+        assert_eq!(linear.sample::<3>(), [10, 165, 0]);
+
+        // Clamped outside the stops; unsorted stops are rejected.
+        let inner = Gradient::new([(64, 1u8), (192, 9)]).unwrap();
+        assert_eq!((inner.color_at(0), inner.color_at(255)), (1, 9));
+        assert_eq!(Gradient::new([(5, 1u8), (5, 2)]), Err(Error::OutOfRange));
+
+        let mut w = MockWriter::new();
+        Drawer::new(&mut w).fill_gradient_h(2, 0, 16, 3, &Gradient::new([(0, 0u8), (255, 15)]).unwrap());
+        assert_eq!(w.px[2], core::array::from_fn::<u8, 16, _>(|j| j as u8));
+        assert_eq!(w.px[4], w.px[2]);
+        assert_eq!(w.px[5], [0; 16]);
+    }
+}
@@ -0,0 +1,254 @@
+//! On-target tests for `par_data_rw`: `ParDataWriter` driven through mock
+//! pins, `ParDataReader` on unconnected GPIOs set by their pulls.
+
+#![no_std]
+#![no_main]
+
+esp_bootloader_esp_idf::esp_app_desc!();
+
+#[cfg(test)]
+#[embedded_test::tests(executor = esp_rtos::embassy::Executor::new())]
+mod tests {
+    use core::cell::Cell;
+    use core::convert::Infallible;
+    use defmt::{assert, assert_eq};
+    use embedded_hal::delay::DelayNs;
+    use embedded_hal::digital::{ErrorType, OutputPin, StatefulOutputPin};
+    use esp_disp_driver::par_data_rw::{gather_port_bits, ParDataReader, ParDataWriter};
+    use esp_hal::gpio::{AnyPin, InputConfig, Pull};
+
+    /// Sample-and-hold DAC model: the strobe's rising edge latches the bus.
+    #[derive(Default)]
+    struct Dac {
+        bus: Cell<u8>,
+        held: Cell<u8>,
+        strobes: Cell<u32>,
+    }
+
+    /// Data pin `Some(bit)` of the DAC's bus, or its strobe input (`None`).
+    struct MockPin<'d> {
+        dac: &'d Dac,
+        bit: Option<u8>,
+        high: bool,
+    }
+
+    impl<'d> MockPin<'d> {
+        fn data(dac: &'d Dac, bit: u8) -> Self {
+            Self { dac, bit: Some(bit), high: false }
+        }
+
+        fn strobe(dac: &'d Dac) -> Self {
+            Self { dac, bit: None, high: false }
+        }
+
+        fn set(&mut self, high: bool) {
+            let rising = high && !self.high;
+            self.high = high;
+            let d = self.dac;
+            match self.bit {
+                Some(bit) => d.bus.set((d.bus.get() & !(1 << bit)) | ((high as u8) << bit)),
+                None if rising => {
+                    d.held.set(d.bus.get());
+                    d.strobes.set(d.strobes.get() + 1);
+                }
+                None => {}
+            }
+        }
+    }
+
+    impl ErrorType for MockPin<'_> {
+        type Error = Infallible;
+    }
+
+    impl OutputPin for MockPin<'_> {
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            self.set(false);
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            self.set(true);
+            Ok(())
+        }
+    }
+
+    impl StatefulOutputPin for MockPin<'_> {
+        fn is_set_high(&mut self) -> Result<bool, Infallible> {
+            Ok(self.high)
+        }
+
+        fn is_set_low(&mut self) -> Result<bool, Infallible> {
+            Ok(!self.high)
+        }
+    }
+
+    #[init]
+    fn init() {
+        let peripherals = esp_hal::init(esp_hal::Config::default());
+
+        let timg1 = esp_hal::timer::timg::TimerGroup::new(peripherals.TIMG1);
+        esp_rtos::start(timg1.timer0);
+
+        rtt_target::rtt_init_defmt!();
+    }
+
+    /// Eight GPIOs the demo boards leave unconnected: reader inputs on them
+    /// only see their own pulls, and outputs drive nothing.
+    fn free_inputs() -> [AnyPin<'static>; 8] {
+        [1, 2, 8, 38, 39, 40, 41, 42].map(|n| unsafe { AnyPin::steal(n) })
+    }
+
+    fn writer(dac: &Dac) -> ParDataWriter<'static, 4, MockPin<'_>> {
+        ParDataWriter::new(core::array::from_fn(|bit| MockPin::data(dac, bit as u8)))
+    }
+
+    #[test]
+    fn strobe_latches_settled_data() {
+        let dac = Dac::default();
+        let mut w = writer(&dac).with_strobe(MockPin::strobe(&dac));
+
+        w.write_u8_strobed(0xA);
+        assert_eq!((dac.held.get(), dac.strobes.get()), (0xA, 1));
+
+        // Plain writes change the bus but not the held sample.
+        w.write_u8(0x5);
+        assert_eq!((dac.bus.get(), dac.held.get()), (0x5, 0xA));
+
+        // Every bit flips here, so a strobe before the last pin would be seen.
+        w.write_u8_strobed(0x5 ^ 0xF);
+        assert_eq!((dac.held.get(), dac.strobes.get()), (0xA, 2));
+        w.write_u8_strobed(0x3);
+        assert_eq!((dac.held.get(), dac.strobes.get()), (0x3, 3));
+    }
+
+    #[test]
+    fn no_strobe_is_a_plain_write() {
+        let dac = Dac::default();
+        let mut w = writer(&dac);
+        w.write_u8_strobed(0x9);
+        assert_eq!((dac.bus.get(), dac.held.get(), dac.strobes.get()), (0x9, 0, 0));
+    }
+
+    #[test]
+    fn ramp_is_a_staircase_of_every_code() {
+        /// Records the latched DAC sample at every dwell.
+        struct Probe<'d> {
+            dac: &'d Dac,
+            steps: [u8; 16],
+            n: usize,
+            ns: u32,
+        }
+
+        impl DelayNs for Probe<'_> {
+            fn delay_ns(&mut self, ns: u32) {
+                self.steps[self.n] = self.dac.held.get();
+                self.n += 1;
+                self.ns += ns;
+            }
+        }
+
+        let dac = Dac::default();
+        let mut w = writer(&dac).with_strobe(MockPin::strobe(&dac));
+        let mut probe = Probe { dac: &dac, steps: [0; 16], n: 0, ns: 0 };
+        w.ramp(&mut probe, 3);
+        assert_eq!(probe.n, 16);
+        assert_eq!(probe.steps, core::array::from_fn(|k| k as u8));
+        assert_eq!(probe.ns, 16 * 3_000);
+        assert_eq!((dac.strobes.get(), dac.bus.get()), (16, 0xF));
+    }
+
+    #[test]
+    fn addr_mask_ignores_unwired_bits() {
+        // Unconnected inputs with pull-ups all read high.
+        let pins = free_inputs();
+        let cfg = InputConfig::default().with_pull(Pull::Up);
+        let reader = ParDataReader::from_pins(pins, cfg);
+        assert_eq!(reader.addr_mask(), u32::MAX);
+        assert_eq!(reader.read_u8(), 0xFF);
+
+        let reader = reader.with_addr_mask(0x7F);
+        assert_eq!(reader.read_u8(), 0x7F);
+        assert_eq!(reader.read_u16(), 0x7F);
+        assert_eq!(reader.read_u32(), 0x7F);
+        assert_eq!(reader.read_bits(), [true, true, true, true, true, true, true, false]);
+
+        let pins = free_inputs();
+        let reader = ParDataReader::from_pins(pins, cfg).with_used_bits(7);
+        assert_eq!(reader.addr_mask(), 0x7F);
+        assert_eq!(reader.read_u8(), 0x7F);
+    }
+
+    #[test]
+    fn split_reads_return_both_halves_of_a_16_bit_bus() {
+        // Each free pin serves twice; pulled up, the inversion sets the pattern.
+        let mut pins = free_inputs().into_iter().chain(free_inputs());
+        let pins: [AnyPin<'static>; 16] = core::array::from_fn(|_| pins.next().unwrap());
+        let cfg = InputConfig::default().with_pull(Pull::Up);
+        let reader = ParDataReader::from_pins(pins, cfg).with_invert_mask(!0xA53Cu32);
+        assert_eq!(reader.read_u16(), 0xA53C);
+        assert_eq!(reader.read_low_u8(), 0x3C);
+        assert_eq!(reader.read_high_u8(), 0xA5);
+        assert_eq!(reader.read_split_u8(), (0x3C, 0xA5));
+    }
+
+    #[test]
+    fn invert_mask_complements_buffered_lines() {
+        // Pulled up: every pin reads high, so inverted lines read 0.
+        let pins = free_inputs();
+        let cfg = InputConfig::default().with_pull(Pull::Up);
+        let reader = ParDataReader::from_pins(pins, cfg).with_invert_mask(0x104);
+        assert_eq!(reader.invert_mask(), 0x04);
+        assert_eq!(reader.read_u8(), 0xFB);
+        assert_eq!(reader.read_u32(), 0xFB);
+        assert!(!reader.read_bits()[2]);
+
+        // Inverted, then masked.
+        let reader = reader.with_addr_mask(0x0F);
+        assert_eq!(reader.read_u8(), 0x0B);
+    }
+
+    #[test]
+    fn bit_map_reassembles_scattered_lines() {
+        use esp_disp_driver::error::Error;
+
+        // Pulled up except the first two pins: 0xFC in wiring order.
+        let pins = free_inputs();
+        let mut reader = ParDataReader::from_pins(pins, InputConfig::default().with_pull(Pull::Up));
+        for pin in &mut reader.pins_mut()[..2] {
+            pin.apply_config(&InputConfig::default().with_pull(Pull::Down));
+        }
+        assert_eq!(reader.bit_map(), [0, 1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(reader.read_u8(), 0xFC);
+
+        // Reversed: the two low pins are the top bits.
+        let reader = reader.with_bit_map([7, 6, 5, 4, 3, 2, 1, 0]).unwrap();
+        assert_eq!(reader.read_u8(), 0x3F);
+        assert_eq!(reader.read_u32(), 0x3F);
+        assert!(!reader.read_bits()[7]);
+
+        // A new map still refers to the wiring order.
+        let reader = reader.with_bit_map([1, 2, 3, 4, 5, 6, 7, 0]).unwrap();
+        assert_eq!(reader.bit_map(), [1, 2, 3, 4, 5, 6, 7, 0]);
+        assert_eq!(reader.read_u8(), 0xF9);
+        assert_eq!(reader.with_invert_mask(0x02).read_u8(), 0xFB);
+
+        let pins = free_inputs();
+        let reader = ParDataReader::from_pins(pins, InputConfig::default());
+        assert!(matches!(reader.with_bit_map([0, 1, 2, 3, 4, 5, 6, 6]), Err(Error::OutOfRange)));
+    }
+
+    #[test]
+    fn port_bits_merge_across_the_gpio31_boundary() {
+        // Bus straddling IN / IN1: bits 0..=1 on GPIO 30, 31, bits 2..=3 on 32, 33.
+        let nums = [30, 31, 32, 33];
+        assert_eq!(gather_port_bits(&nums, [1 << 30 | 1 << 31, 0b01]), 0b0111);
+        assert_eq!(gather_port_bits(&nums, [1 << 31, 0b10]), 0b1010);
+        // Other pins on either port do not leak in.
+        assert_eq!(gather_port_bits(&nums, [!(3 << 30), !0b11]), 0);
+        assert_eq!(gather_port_bits(&nums, [u32::MAX, u32::MAX]), 0b1111);
+
+        // Pin order, not GPIO order, sets the bit position.
+        assert_eq!(gather_port_bits(&[40, 2], [1 << 2, 0]), 0b10);
+        assert_eq!(gather_port_bits(&[40, 2], [0, 1 << 8]), 0b01);
+    }
+}
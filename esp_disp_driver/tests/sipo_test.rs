@@ -0,0 +1,778 @@
+//! On-target tests for the shift-register logic in `esp_disp_driver::sipo`,
+//! driven through mock pins instead of GPIOs.
+
+#![no_std]
+#![no_main]
+
+esp_bootloader_esp_idf::esp_app_desc!();
+
+#[cfg(test)]
+#[embedded_test::tests(executor = esp_rtos::embassy::Executor::new())]
+mod tests {
+    use core::cell::Cell;
+    use core::convert::Infallible;
+    use defmt::assert_eq;
+    use embedded_hal::digital::{ErrorType, OutputPin};
+    use esp_disp_driver::display::backend::sipo::{DefAddrShifter, PixelWriterSipo};
+    use esp_disp_driver::error::Error;
+    use esp_disp_driver::rmt_bank::{symbol, RmtBank, RmtTxGroup};
+    use esp_disp_driver::sipo::{
+        ClearLine, ControlGroup, FrameDirection, LatchLine, LatchSource, ParallelBank, SerLane, ShiftClockLine, SipoSingle,
+    };
+
+    /// Minimal 74HC595 model: SRCLK rising edges shift SER in, RCLK rising
+    /// edges copy the shift register to the outputs.
+    #[derive(Default)]
+    struct Chip {
+        ser: Cell<bool>,
+        shift: Cell<u32>,
+        out: Cell<u32>,
+        ticks: Cell<u32>,
+        latches: Cell<u32>,
+        /// Outputs after each of the first 16 latches.
+        latched: Cell<[u32; 16]>,
+    }
+
+    #[derive(Clone, Copy)]
+    enum Role {
+        Ser,
+        Srclk,
+        Rclk,
+        Srclr,
+        /// SRCLK and RCLK tied together: latch the old contents, then shift.
+        TiedClk,
+    }
+
+    /// Pin wired to one chip, or for clocks shared across lanes, several.
+    struct MockPin<'c> {
+        chips: &'c [Chip],
+        role: Role,
+        high: bool,
+    }
+
+    impl<'c> MockPin<'c> {
+        fn new(chip: &'c Chip, role: Role) -> Self {
+            Self::shared(core::slice::from_ref(chip), role)
+        }
+
+        fn shared(chips: &'c [Chip], role: Role) -> Self {
+            Self { chips, role, high: false }
+        }
+
+        fn set(&mut self, high: bool) {
+            let rising = high && !self.high;
+            self.high = high;
+            for c in self.chips {
+                match self.role {
+                    Role::Ser => c.ser.set(high),
+                    Role::Srclk if rising => {
+                        c.shift.set((c.shift.get() << 1) | c.ser.get() as u32);
+                        c.ticks.set(c.ticks.get() + 1);
+                    }
+                    Role::Rclk if rising => {
+                        c.out.set(c.shift.get());
+                        let mut latched = c.latched.get();
+                        if let Some(slot) = latched.get_mut(c.latches.get() as usize) {
+                            *slot = c.shift.get();
+                        }
+                        c.latched.set(latched);
+                        c.latches.set(c.latches.get() + 1);
+                    }
+                    Role::Srclr if rising => c.shift.set(0),
+                    Role::TiedClk if rising => {
+                        c.out.set(c.shift.get());
+                        c.shift.set((c.shift.get() << 1) | c.ser.get() as u32);
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    impl ErrorType for MockPin<'_> {
+        type Error = Infallible;
+    }
+
+    impl OutputPin for MockPin<'_> {
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            self.set(false);
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            self.set(true);
+            Ok(())
+        }
+    }
+
+    /// One level written to a `TracePin`.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+    enum Edge {
+        /// SER of the given lane.
+        Ser(u8, bool),
+        Srclk(bool),
+        Rclk(bool),
+    }
+
+    /// Ordered log of every level written to the `TracePin`s sharing it.
+    struct Trace {
+        log: Cell<[Option<Edge>; 64]>,
+        len: Cell<usize>,
+    }
+
+    impl Trace {
+        fn new() -> Self {
+            Self { log: Cell::new([None; 64]), len: Cell::new(0) }
+        }
+
+        fn push(&self, edge: Edge) {
+            let mut log = self.log.get();
+            log[self.len.get()] = Some(edge);
+            self.log.set(log);
+            self.len.set(self.len.get() + 1);
+        }
+
+        fn clear(&self) {
+            self.len.set(0);
+        }
+
+        /// Assert the log is exactly `expected`.
+        fn assert_eq(&self, expected: &[Edge]) {
+            assert_eq!(self.len.get(), expected.len());
+            for (got, want) in self.log.get().iter().zip(expected) {
+                assert_eq!(*got, Some(*want));
+            }
+        }
+    }
+
+    /// Pin that logs every write, even ones that keep the level, so the
+    /// exact toggle sequence can be checked.
+    struct TracePin<'t> {
+        trace: &'t Trace,
+        edge: fn(bool) -> Edge,
+    }
+
+    impl ErrorType for TracePin<'_> {
+        type Error = Infallible;
+    }
+
+    impl OutputPin for TracePin<'_> {
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            self.trace.push((self.edge)(false));
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            self.trace.push((self.edge)(true));
+            Ok(())
+        }
+    }
+
+    #[init]
+    fn init() {
+        let peripherals = esp_hal::init(esp_hal::Config::default());
+
+        let timg1 = esp_hal::timer::timg::TimerGroup::new(peripherals.TIMG1);
+        esp_rtos::start(timg1.timer0);
+
+        rtt_target::rtt_init_defmt!();
+    }
+
+    fn ctrl(chip: &Chip, latch: bool) -> ControlGroup<'static, MockPin<'_>> {
+        ctrl_shared(core::slice::from_ref(chip), latch)
+    }
+
+    /// Control lines fanned out to every chip in `chips`, one per lane.
+    fn ctrl_shared(chips: &[Chip], latch: bool) -> ControlGroup<'static, MockPin<'_>> {
+        ControlGroup::new(
+            ShiftClockLine::new(MockPin::shared(chips, Role::Srclk)),
+            latch.then(|| LatchLine::new(MockPin::shared(chips, Role::Rclk), false)),
+            None,
+        )
+    }
+
+    /// `BwPixelWriter8h8v1ch8`'s layout on mock chips: data, then the `i`
+    /// and `j` address registers.
+    type MockWriter<'c> = PixelWriterSipo<'static, 1, 3, 2, 1, DefAddrShifter, u8, ParallelBank<'static, 3, 1, MockPin<'c>>>;
+
+    /// `MockWriter` with one chip per lane, RCLK and SRCLR wired to all three.
+    fn mock_writer(chips: &[Chip; 3]) -> MockWriter<'_> {
+        let lanes = core::array::from_fn(|idx| SerLane::new(MockPin::new(&chips[idx], Role::Ser)));
+        let mut group = ctrl_shared(chips, true);
+        group.clear = Some(ClearLine::new(MockPin::shared(chips, Role::Srclr), false));
+        PixelWriterSipo::new(ParallelBank::new(lanes, group), DefAddrShifter).unwrap()
+    }
+
+    #[test]
+    fn byte_produces_exact_ser_srclk_sequence() {
+        let trace = Trace::new();
+        let pin = |edge| TracePin { trace: &trace, edge };
+        let group = ControlGroup::new(ShiftClockLine::new(pin(Edge::Srclk)), Some(LatchLine::new(pin(Edge::Rclk), false)), None);
+        let mut sipo: SipoSingle<'_, 1, _> = SipoSingle::new(SerLane::new(pin(|b| Edge::Ser(0, b))), group);
+        // Construction parks every line low.
+        trace.assert_eq(&[Edge::Srclk(false), Edge::Rclk(false), Edge::Ser(0, false)]);
+        trace.clear();
+
+        // 0xA5 MSB first: SER set up before each rising SRCLK, one RCLK pulse at the end.
+        sipo.write_exact(&[0xA5]).unwrap();
+        let mut want = [Edge::Srclk(false); 26];
+        for (k, bit) in [1, 0, 1, 0, 0, 1, 0, 1].into_iter().enumerate() {
+            want[3 * k] = Edge::Ser(0, bit == 1);
+            want[3 * k + 1] = Edge::Srclk(true);
+        }
+        want[24] = Edge::Rclk(true);
+        want[25] = Edge::Rclk(false);
+        trace.assert_eq(&want);
+
+        // Two lanes: both SER lines settle, then one shared clock per bit.
+        let group = ControlGroup::new(ShiftClockLine::new(pin(Edge::Srclk)), None, None);
+        let lanes = [SerLane::new(pin(|b| Edge::Ser(0, b))), SerLane::new(pin(|b| Edge::Ser(1, b)))];
+        let mut bank: ParallelBank<'_, 2, 1, _> = ParallelBank::new(lanes, group);
+        trace.clear();
+        bank.shift_exact([[0x80], [0x01]]);
+        let mut want = [Edge::Srclk(false); 32];
+        for k in 0..8 {
+            want[4 * k] = Edge::Ser(0, k == 0);
+            want[4 * k + 1] = Edge::Ser(1, k == 7);
+            want[4 * k + 2] = Edge::Srclk(true);
+        }
+        trace.assert_eq(&want);
+    }
+
+    #[test]
+    fn single_chain_shifts_msb_first_then_latches() {
+        let chip = Chip::default();
+        let lane = SerLane::new(MockPin::new(&chip, Role::Ser));
+        let mut sipo: SipoSingle<'_, 2, _> = SipoSingle::new(lane, ctrl(&chip, true));
+
+        sipo.shift_exact(&[0xA5, 0x3C]);
+        assert_eq!(chip.ticks.get(), 16);
+        assert_eq!(chip.latches.get(), 0);
+        assert_eq!(chip.out.get(), 0);
+
+        sipo.write_exact(&[0x12, 0x34]).unwrap();
+        assert_eq!(chip.ticks.get(), 32);
+        assert_eq!(chip.latches.get(), 1);
+        assert_eq!(chip.out.get() & 0xFFFF, 0x1234);
+    }
+
+    #[test]
+    fn missing_latch_is_reported_after_shifting() {
+        let chip = Chip::default();
+        let lane = SerLane::new(MockPin::new(&chip, Role::Ser));
+        let mut bank: ParallelBank<'_, 1, 1, _> = ParallelBank::new([lane], ctrl(&chip, false));
+
+        assert_eq!(bank.write_exact([[0x81]]), Err(Error::NotConfigured));
+        assert_eq!(chip.ticks.get(), 8);
+        assert_eq!(chip.shift.get() & 0xFF, 0x81);
+    }
+
+    #[test]
+    fn gap_bits_pad_the_frame() {
+        let chip = Chip::default();
+        let lane = SerLane::new(MockPin::new(&chip, Role::Ser));
+        let mut sipo: SipoSingle<'_, 2, _> = SipoSingle::new(lane, ctrl(&chip, true)).with_gap_bits(3, 5);
+
+        sipo.write_exact(&[0xFF, 0x01]).unwrap();
+        assert_eq!(chip.ticks.get(), 8 * 2 + 3 + 5);
+        assert_eq!(chip.out.get(), 0xFF01 << 5);
+
+        let chip = Chip::default();
+        let lane = SerLane::new(MockPin::new(&chip, Role::Ser));
+        let mut bank: ParallelBank<'_, 1, 1, _> =
+            ParallelBank::new([lane], ctrl(&chip, true)).with_gap_bits(4, 0);
+        bank.write_exact([[0xAA]]).unwrap();
+        assert_eq!(chip.ticks.get(), 8 + 4);
+        assert_eq!(chip.out.get(), 0xAA);
+    }
+
+    #[test]
+    fn watchdog_feed_can_capture_state() {
+        let chip = Chip::default();
+        let fed = Cell::new(0);
+        let lane = SerLane::new(MockPin::new(&chip, Role::Ser));
+        let mut bank: ParallelBank<'_, 1, 2, _, _> =
+            ParallelBank::new([lane], ctrl(&chip, true)).with_gap_bits(3, 0).with_watchdog_feed(|| fed.set(fed.get() + 1));
+        bank.write_exact([[0x12, 0x34]]).unwrap();
+        assert_eq!(fed.get(), 2);
+        assert_eq!(chip.out.get() & 0xFFFF, 0x1234);
+
+        let chip = Chip::default();
+        let lane = SerLane::new(MockPin::new(&chip, Role::Ser));
+        let mut sipo: SipoSingle<'_, 3, _, _> =
+            SipoSingle::new(lane, ctrl(&chip, true)).with_watchdog_feed(|| fed.set(fed.get() + 1));
+        sipo.write_exact(&[1, 2, 3]).unwrap();
+        assert_eq!(fed.get(), 5);
+    }
+
+    #[test]
+    fn write_same_broadcasts_to_every_lane() {
+        let chips: [Chip; 3] = Default::default();
+        let lanes = core::array::from_fn(|idx| SerLane::new(MockPin::new(&chips[idx], Role::Ser)));
+        let mut bank: ParallelBank<'_, 3, 2, _> = ParallelBank::new(lanes, ctrl_shared(&chips, true));
+
+        bank.write_same([0xC3, 0x5A]).unwrap();
+        for chip in &chips {
+            assert_eq!(chip.ticks.get(), 16);
+            assert_eq!(chip.latches.get(), 1);
+            assert_eq!(chip.out.get(), 0xC35A);
+        }
+    }
+
+    /// Level an RMT channel playing `symbols` outputs `t` ticks after it
+    /// starts; low (idle) once it has finished.
+    fn rmt_level(symbols: &[u32], mut t: u32) -> bool {
+        for &sym in symbols {
+            for half in [sym & 0xFFFF, sym >> 16] {
+                let ticks = half & 0x7FFF;
+                if ticks == 0 {
+                    return false;
+                }
+                if t < ticks {
+                    return half & 0x8000 != 0;
+                }
+                t -= ticks;
+            }
+        }
+        false
+    }
+
+    /// RMT channels playing into `Chip` models, SRCLK starting `skew`
+    /// ticks after the SER lanes.
+    struct RmtPlayback<'c> {
+        chips: &'c [Chip],
+        skew: u32,
+    }
+
+    impl RmtTxGroup for RmtPlayback<'_> {
+        fn transmit(&mut self, srclk: &[u32], ser: &[&[u32]]) -> Result<(), Error> {
+            let end: u32 = srclk.iter().map(|&sym| (sym & 0x7FFF) + ((sym >> 16) & 0x7FFF)).sum();
+            for t in 0..end {
+                if rmt_level(srclk, t + 1) && !rmt_level(srclk, t) {
+                    for (chip, lane) in self.chips.iter().zip(ser) {
+                        let bit = rmt_level(lane, t + 1 + self.skew) as u32;
+                        chip.shift.set((chip.shift.get() << 1) | bit);
+                        chip.ticks.set(chip.ticks.get() + 1);
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn rmt_bank_shifts_frames_despite_start_skew() {
+        assert_eq!(symbol(true, 3, false, 5), 0x0005_8003);
+        for skew in [0, 3] {
+            let chips: [Chip; 2] = Default::default();
+            let latch = LatchLine::new(MockPin::shared(&chips, Role::Rclk), false);
+            let tx = RmtPlayback { chips: &chips, skew };
+            let mut bank: RmtBank<'_, _, 2, 2, _> = RmtBank::new(tx, Some(latch), 4).unwrap();
+            bank.write_exact([[0xA5, 0x0F], [0x3C, 0x81]]).unwrap();
+            assert_eq!(chips[0].out.get(), 0xA50F);
+            assert_eq!(chips[1].out.get(), 0x3C81);
+            assert_eq!(chips[0].ticks.get(), 16);
+            assert_eq!(chips[0].latches.get(), 1);
+            assert_eq!(bank.last_frame(), Some(&[[0xA5, 0x0F], [0x3C, 0x81]]));
+        }
+
+        let chips: [Chip; 1] = Default::default();
+        let tx = RmtPlayback { chips: &chips, skew: 0 };
+        assert!(matches!(RmtBank::<'_, _, 1, 1, MockPin<'_>>::new(tx, None, 0), Err(Error::OutOfRange)));
+        let tx = RmtPlayback { chips: &chips, skew: 0 };
+        let mut bank: RmtBank<'_, _, 1, 1, MockPin<'_>> = RmtBank::new(tx, None, 2).unwrap();
+        assert_eq!(bank.write_same([0x42]), Err(Error::NotConfigured));
+        assert_eq!(chips[0].shift.get(), 0x42);
+    }
+
+    #[test]
+    fn sipo_writer_drives_an_rmt_bank() {
+        use esp_disp_driver::display::pix_writer::PixelWriter;
+
+        let chips: [Chip; 3] = Default::default();
+        let latch = LatchLine::new(MockPin::shared(&chips, Role::Rclk), false);
+        let tx = RmtPlayback { chips: &chips, skew: 0 };
+        let bank: RmtBank<'_, _, 3, 1, _> = RmtBank::new(tx, Some(latch), 4).unwrap();
+        let mut w: PixelWriterSipo<'_, 1, 3, 2, 1, DefAddrShifter, u8, _> = PixelWriterSipo::new(bank, DefAddrShifter).unwrap();
+        w.write_pixel(5, 7, 0x42);
+        assert_eq!(chips.each_ref().map(|chip| chip.out.get() & 0xFF), [0x42, 5, 7]);
+        assert_eq!(chips[0].latches.get(), 1);
+        assert_eq!(w.p_sipo_bank.last_frame(), Some(&[[0x42], [5], [7]]));
+        // No SRCLR on the RMT bank.
+        assert_eq!(w.blank(), Err(Error::NotConfigured));
+
+        let tx = RmtPlayback { chips: &chips, skew: 0 };
+        let bank: RmtBank<'_, _, 3, 1, MockPin<'_>> = RmtBank::new(tx, None, 4).unwrap();
+        let w = PixelWriterSipo::<'_, 1, 3, 2, 1, DefAddrShifter, u8, _>::new(bank, DefAddrShifter);
+        assert!(matches!(w, Err(Error::NotConfigured)));
+    }
+
+    #[test]
+    fn disabled_lane_keeps_its_output() {
+        let chips: [Chip; 2] = Default::default();
+        let lanes = core::array::from_fn(|idx| SerLane::new(MockPin::new(&chips[idx], Role::Ser)));
+        let mut bank: ParallelBank<'_, 2, 2, _> = ParallelBank::new(lanes, ctrl_shared(&chips, true));
+        assert_eq!(bank.lane_enable(), [true, true]);
+
+        bank.write_exact([[0x12, 0x34], [0x56, 0x78]]).unwrap();
+        bank.set_lane_enabled(1, false);
+        bank.write_exact([[0x9A, 0xBC], [0xDE, 0xF0]]).unwrap();
+        // The model keeps every shifted bit; the low 16 are the two registers.
+        assert_eq!(chips[0].out.get() & 0xFFFF, 0x9ABC);
+        assert_eq!(chips[1].out.get() & 0xFFFF, 0x5678);
+        // Still clocked along with the enabled lane.
+        assert_eq!(chips[1].ticks.get(), 32);
+        assert_eq!(bank.last_frame(), Some(&[[0x9A, 0xBC], [0x56, 0x78]]));
+
+        bank.set_lane_enabled(1, true);
+        bank.write_exact([[0, 0], [0xDE, 0xF0]]).unwrap();
+        assert_eq!(chips[1].out.get() & 0xFFFF, 0xDEF0);
+    }
+
+    #[test]
+    fn latch_per_byte_pulses_once_per_byte() {
+        let chips: [Chip; 2] = Default::default();
+        let lanes = core::array::from_fn(|idx| SerLane::new(MockPin::new(&chips[idx], Role::Ser)));
+        let mut bank: ParallelBank<'_, 2, 3, _> =
+            ParallelBank::new(lanes, ctrl_shared(&chips, true)).with_latch_per_byte(true);
+
+        bank.write_exact([[0x11, 0x22, 0x33], [0x44, 0x55, 0x66]]).unwrap();
+        assert_eq!((chips[0].latches.get(), chips[1].latches.get()), (3, 3));
+        assert_eq!((chips[0].out.get(), chips[1].out.get()), (0x112233, 0x445566));
+
+        bank.write_same([0x01, 0x02, 0x03]).unwrap();
+        assert_eq!(chips[0].latches.get(), 6);
+
+        // shift_exact still never latches.
+        bank.shift_exact([[0; 3]; 2]);
+        assert_eq!(chips[0].latches.get(), 6);
+
+        let chip = Chip::default();
+        let lane = SerLane::new(MockPin::new(&chip, Role::Ser));
+        let mut sipo: SipoSingle<'_, 2, _> = SipoSingle::new(lane, ctrl(&chip, true)).with_latch_per_byte(true);
+        sipo.write_exact(&[0xAB, 0xCD]).unwrap();
+        assert_eq!(chip.latches.get(), 2);
+        assert_eq!(chip.out.get() & 0xFFFF, 0xABCD);
+    }
+
+    #[test]
+    fn shared_latch_is_one_extra_shift_tick() {
+        let chip = Chip::default();
+        let lane = SerLane::new(MockPin::new(&chip, Role::Ser));
+        let group = ControlGroup::new(ShiftClockLine::new(MockPin::new(&chip, Role::TiedClk)), None, None)
+            .with_latch_source(LatchSource::SharedWithShift);
+        assert!(group.can_latch());
+        let mut sipo: SipoSingle<'_, 2, _> = SipoSingle::new(lane, group).with_latch_per_byte(true);
+
+        sipo.write_exact(&[0xA5, 0x3C]).unwrap();
+        // Outputs trail by one bit until the latch tick catches them up.
+        assert_eq!(chip.out.get() & 0xFFFF, 0xA53C);
+        sipo.write_exact(&[0x12, 0x34]).unwrap();
+        assert_eq!(chip.out.get() & 0xFFFF, 0x1234);
+
+        let mut dedicated = ctrl(&chip, false);
+        assert!(!dedicated.can_latch());
+        assert!(matches!(dedicated.latch_all(), Err(Error::NotConfigured)));
+    }
+
+    #[test]
+    fn mixed_width_lanes_latch_aligned() {
+        let chips: [Chip; 3] = Default::default();
+        let lanes = core::array::from_fn(|idx| SerLane::new(MockPin::new(&chips[idx], Role::Ser)));
+        let bank: ParallelBank<'_, 3, 2, _> = ParallelBank::new(lanes, ctrl_shared(&chips, true));
+        assert!(matches!(bank.with_lane_bytes([2, 0, 1]), Err(Error::OutOfRange)));
+
+        let lanes = core::array::from_fn(|idx| SerLane::new(MockPin::new(&chips[idx], Role::Ser)));
+        let mut bank: ParallelBank<'_, 3, 2, _> =
+            ParallelBank::new(lanes, ctrl_shared(&chips, true)).with_lane_bytes([2, 1, 1]).unwrap();
+        // Leading bytes of the 1-byte lanes are ignored; zeros go out instead.
+        bank.write_exact([[0xA5, 0x3C], [0xFF, 0x12], [0xFF, 0x34]]).unwrap();
+        assert_eq!(chips[0].ticks.get(), 16);
+        assert_eq!(chips[0].out.get() & 0xFFFF, 0xA53C);
+        assert_eq!((chips[1].out.get() & 0xFFFF, chips[2].out.get() & 0xFFFF), (0x0012, 0x0034));
+
+        // Short lanes only: the frame shrinks to the longest of them.
+        let lanes = core::array::from_fn(|idx| SerLane::new(MockPin::new(&chips[idx], Role::Ser)));
+        let mut bank: ParallelBank<'_, 3, 2, _> = ParallelBank::new(lanes, ctrl_shared(&chips, true))
+            .with_lane_bytes([1, 1, 1])
+            .unwrap()
+            .with_frame_direction(FrameDirection::Reverse);
+        bank.write_same([0xEE, 0x56]).unwrap();
+        assert_eq!(chips[0].ticks.get(), 16 + 8);
+        assert_eq!(chips[2].out.get() & 0xFF, 0x56);
+    }
+
+    #[test]
+    fn used_bits_shorten_the_shift() {
+        let chips: [Chip; 2] = Default::default();
+        let lanes = core::array::from_fn(|idx| SerLane::new(MockPin::new(&chips[idx], Role::Ser)));
+        let bank: ParallelBank<'_, 2, 1, _> = ParallelBank::new(lanes, ctrl_shared(&chips, true));
+        assert!(matches!(bank.with_used_bits(9), Err(Error::OutOfRange)));
+
+        let lanes = core::array::from_fn(|idx| SerLane::new(MockPin::new(&chips[idx], Role::Ser)));
+        let mut bank: ParallelBank<'_, 2, 1, _> =
+            ParallelBank::new(lanes, ctrl_shared(&chips, true)).with_used_bits(7).unwrap();
+        assert_eq!(bank.used_bits(), 7);
+        // The top bit of each frame is never clocked.
+        bank.write_exact([[0xD5], [0x2A]]).unwrap();
+        assert_eq!(chips[0].ticks.get(), 7);
+        assert_eq!((chips[0].out.get() & 0x7F, chips[1].out.get() & 0x7F), (0x55, 0x2A));
+
+        // Two-byte frames keep their low bits.
+        let chip = Chip::default();
+        let lane = SerLane::new(MockPin::new(&chip, Role::Ser));
+        let mut bank: ParallelBank<'_, 1, 2, _> =
+            ParallelBank::new([lane], ctrl(&chip, true)).with_used_bits(10).unwrap();
+        bank.write_exact([[0xFF, 0x5A]]).unwrap();
+        assert_eq!(chip.ticks.get(), 10);
+        assert_eq!(chip.out.get() & 0x3FF, 0x35A);
+    }
+
+    #[test]
+    fn reverse_direction_shifts_last_byte_first() {
+        let chips: [Chip; 2] = Default::default();
+        let lanes = core::array::from_fn(|idx| SerLane::new(MockPin::new(&chips[idx], Role::Ser)));
+        let mut bank: ParallelBank<'_, 2, 3, _> =
+            ParallelBank::new(lanes, ctrl_shared(&chips, true)).with_frame_direction(FrameDirection::Reverse);
+
+        bank.write_exact([[0x11, 0x22, 0x33], [0x44, 0x55, 0x66]]).unwrap();
+        assert_eq!((chips[0].out.get(), chips[1].out.get()), (0x332211, 0x665544));
+        assert_eq!(bank.last_frame(), Some(&[[0x11, 0x22, 0x33], [0x44, 0x55, 0x66]]));
+
+        bank.write_same([0x01, 0x02, 0x03]).unwrap();
+        assert_eq!(chips[1].out.get() & 0xFF_FFFF, 0x030201);
+
+        bank.direction = FrameDirection::Forward;
+        bank.write_same([0x01, 0x02, 0x03]).unwrap();
+        assert_eq!(chips[1].out.get() & 0xFF_FFFF, 0x010203);
+    }
+
+    #[test]
+    fn initialize_latches_a_blank_frame() {
+        let chips: [Chip; 2] = Default::default();
+        for chip in &chips {
+            chip.shift.set(0xDEAD);
+            chip.out.set(0xBEEF);
+        }
+        let lanes = core::array::from_fn(|idx| SerLane::new(MockPin::new(&chips[idx], Role::Ser)));
+        let mut group = ctrl_shared(&chips, true);
+        group.clear = Some(ClearLine::new(MockPin::shared(&chips, Role::Srclr), false));
+        let mut bank: ParallelBank<'_, 2, 2, _> = ParallelBank::new(lanes, group);
+
+        bank.initialize().unwrap();
+        for chip in &chips {
+            assert_eq!((chip.shift.get() & 0xFFFF, chip.out.get() & 0xFFFF), (0, 0));
+            assert_eq!(chip.latches.get(), 1);
+        }
+        assert_eq!(bank.last_frame(), Some(&[[0, 0], [0, 0]]));
+
+        // No latch line: shifted, but reported.
+        let lanes = core::array::from_fn(|idx| SerLane::new(MockPin::new(&chips[idx], Role::Ser)));
+        let mut bank: ParallelBank<'_, 2, 2, _> = ParallelBank::new(lanes, ctrl_shared(&chips, false));
+        assert_eq!(bank.initialize(), Err(Error::NotConfigured));
+    }
+
+    #[test]
+    fn last_frame_tracks_shifts_until_clear() {
+        let chips: [Chip; 2] = Default::default();
+        let lanes = core::array::from_fn(|idx| SerLane::new(MockPin::new(&chips[idx], Role::Ser)));
+        let mut group = ctrl_shared(&chips, true);
+        group.clear = Some(ClearLine::new(MockPin::shared(&chips, Role::Srclr), false));
+        let mut bank: ParallelBank<'_, 2, 2, _> = ParallelBank::new(lanes, group);
+
+        assert_eq!(bank.last_frame(), None);
+        bank.shift_exact([[1, 2], [3, 4]]);
+        assert_eq!(bank.last_frame(), Some(&[[1, 2], [3, 4]]));
+        bank.write_same([7, 8]).unwrap();
+        assert_eq!(bank.last_frame(), Some(&[[7, 8], [7, 8]]));
+        bank.clear_all().unwrap();
+        assert_eq!(bank.last_frame(), None);
+
+        let chip = Chip::default();
+        let lane = SerLane::new(MockPin::new(&chip, Role::Ser));
+        let mut sipo: SipoSingle<'_, 2, _> = SipoSingle::new(lane, ctrl(&chip, true));
+        sipo.write_exact(&[0x12, 0x34]).unwrap();
+        assert_eq!(sipo.last_frame(), Some(&[0x12, 0x34]));
+        // No clear line: the cache survives the failed clear.
+        assert_eq!(sipo.clear(), Err(Error::NotConfigured));
+        assert_eq!(sipo.last_frame(), Some(&[0x12, 0x34]));
+    }
+
+    #[test]
+    fn verify_retries_a_mismatched_readback() {
+        use esp_hal::gpio::{AnyPin, InputConfig, Pull};
+
+        // Nothing drives GPIO41, so its pull stands in for a QH' stuck at
+        // one level: every bit that should read the other way mismatches.
+        let readback = |pull| (unsafe { AnyPin::steal(41) }, InputConfig::default().with_pull(pull));
+
+        let chips: [Chip; 1] = Default::default();
+        let (qh, cfg) = readback(Pull::Up);
+        let lanes = [SerLane::new(MockPin::new(&chips[0], Role::Ser)).with_readback(qh, cfg)];
+        let mut bank: ParallelBank<'_, 1, 1, _> = ParallelBank::new(lanes, ctrl_shared(&chips, true)).with_verify(2);
+        assert_eq!(bank.write_exact([[0xA5]]), Err(Error::VerifyFailed));
+        // One write, then a checking shift per attempt, latching every copy.
+        assert_eq!(chips[0].ticks.get(), 8 + 3 * 8);
+        assert_eq!(chips[0].latches.get(), 3);
+        assert_eq!(bank.retry_count(), 2);
+
+        // A frame matching the stuck level passes on the first check.
+        bank.write_exact([[0xFF]]).unwrap();
+        assert_eq!(chips[0].latches.get(), 3 + 1);
+        assert_eq!(chips[0].out.get() & 0xFF, 0xFF);
+        assert_eq!(bank.retry_count(), 2);
+
+        // Without a readback lane there is nothing to check against.
+        let chip = Chip::default();
+        let lane = SerLane::new(MockPin::new(&chip, Role::Ser));
+        let mut sipo: SipoSingle<'_, 1, _> = SipoSingle::new(lane, ctrl(&chip, true)).with_verify(1);
+        assert_eq!(sipo.write_exact(&[0x00]), Err(Error::NotConfigured));
+        assert_eq!(chip.ticks.get(), 0);
+
+        let (qh, cfg) = readback(Pull::Down);
+        let lane = SerLane::new(MockPin::new(&chip, Role::Ser)).with_readback(qh, cfg);
+        let mut sipo: SipoSingle<'_, 1, _> = SipoSingle::new(lane, ctrl(&chip, true)).with_verify(1);
+        sipo.write_exact(&[0x00]).unwrap();
+        assert_eq!(sipo.retry_count(), 0);
+        assert_eq!(sipo.write_exact(&[0x01]), Err(Error::VerifyFailed));
+        assert_eq!(sipo.retry_count(), 1);
+    }
+
+    #[test]
+    fn verify_passes_once_a_retry_reads_back() {
+        use esp_hal::gpio::{AnyPin, Input, InputConfig, Pull};
+
+        // GPIO41 floats as in `verify_retries_a_mismatched_readback`. The
+        // feed runs after every shifted byte, so after the first checking
+        // shift it re-pulls the pin to match, as if a glitch had cleared.
+        let readback = |pull| (unsafe { AnyPin::steal(41) }, InputConfig::default().with_pull(pull));
+        let bytes = Cell::new(0u32);
+        let feed = || {
+            bytes.set(bytes.get() + 1);
+            if bytes.get() == 2 {
+                // Dropping an `Input` leaves the pin configured.
+                let (pin, cfg) = readback(Pull::Down);
+                Input::new(pin, cfg);
+            }
+        };
+
+        let chips: [Chip; 1] = Default::default();
+        let (qh, cfg) = readback(Pull::Up);
+        let lanes = [SerLane::new(MockPin::new(&chips[0], Role::Ser)).with_readback(qh, cfg)];
+        let mut bank: ParallelBank<'_, 1, 1, _, _> = ParallelBank::new(lanes, ctrl_shared(&chips, true))
+            .with_verify(2)
+            .with_watchdog_feed(feed);
+        assert_eq!(bank.verify(), Some(2));
+        bank.write_exact([[0x00]]).unwrap();
+        // The write and two checking shifts: one retry, then a match.
+        assert_eq!(bytes.get(), 3);
+        assert_eq!(chips[0].ticks.get(), 8 + 2 * 8);
+        assert_eq!(chips[0].latches.get(), 2);
+        assert_eq!(chips[0].out.get() & 0xFF, 0x00);
+        assert_eq!(bank.retry_count(), 1);
+    }
+
+    #[test]
+    fn lane_map_moves_fields_to_their_registers() {
+        use esp_disp_driver::display::backend::sipo::LaneMap;
+        use esp_disp_driver::display::pix_writer::PixelWriter;
+
+        let chips: [Chip; 3] = Default::default();
+        let mut plain = mock_writer(&chips);
+        plain.write_pixel(5, 7, 0x42);
+        assert_eq!(plain.p_sipo_bank.last_frame(), Some(&[[0x42], [5], [7]]));
+        assert!(plain.lane_map().is_identity());
+
+        // Address registers first in the chain order, data last.
+        let map = LaneMap::from_lane_order([2, 0, 1]);
+        let mut permuted = mock_writer(&chips).with_lane_map(map).unwrap();
+        permuted.write_pixel(5, 7, 0x42);
+        assert_eq!(permuted.p_sipo_bank.last_frame(), Some(&[[5], [7], [0x42]]));
+        assert_eq!(chips.each_ref().map(|chip| chip.out.get() & 0xFF), [5, 7, 0x42]);
+        assert_eq!(permuted.lane_map().slot(0, 0), (2, 0));
+
+        // Two fields on one register, or one past the bank, is rejected.
+        let clash = LaneMap::from_lane_order([0, 0, 1]);
+        assert!(!clash.is_permutation());
+        assert!(matches!(mock_writer(&chips).with_lane_map(clash), Err(Error::OutOfRange)));
+        assert!(!LaneMap::<3, 1>::identity().with_byte(2, 0, (3, 0)).is_permutation());
+    }
+
+    #[test]
+    fn sipo_write_frame_uploads_every_cell() {
+        use esp_disp_driver::display::backend::utils::Resolution;
+
+        // (data, i, j) on the three chips after each latch.
+        let latched = |chips: &[Chip; 3], k: usize| {
+            let [data, i, j] = chips.each_ref().map(|chip| (chip.latched.get()[k] & 0xFF) as u8);
+            (data, i, j)
+        };
+
+        let chips: [Chip; 3] = Default::default();
+        let mut w = mock_writer(&chips);
+        let fb: [[u8; 4]; 3] = core::array::from_fn(|i| core::array::from_fn(|j| (10 * i + j) as u8));
+        assert_eq!(w.write_frame(&fb), 12);
+        // One latched frame per cell, in row-major address order.
+        assert_eq!(chips[0].latches.get(), 12);
+        for k in 0..12 {
+            let (i, j) = (k as u8 / 4, k as u8 % 4);
+            assert_eq!(latched(&chips, k), (fb[i as usize][j as usize], i, j));
+        }
+        assert_eq!(w.p_sipo_bank.last_frame(), Some(&[[23], [2], [3]]));
+
+        // Clipped to the configured panel.
+        let chips: [Chip; 3] = Default::default();
+        let mut w = mock_writer(&chips).with_resolution(Resolution::new(2, 2));
+        assert_eq!(w.write_frame(&fb), 4);
+        assert_eq!(chips[0].latches.get(), 4);
+        for (k, want) in [(0, 0, 0), (1, 0, 1), (10, 1, 0), (11, 1, 1)].into_iter().enumerate() {
+            assert_eq!(latched(&chips, k), want);
+        }
+    }
+
+    #[test]
+    fn unblank_restores_the_prior_content() {
+        use esp_disp_driver::display::pix_writer::PixelWriter;
+
+        let chips: [Chip; 3] = Default::default();
+        let mut sipo = mock_writer(&chips);
+        sipo.write_pixel(5, 7, 0x42);
+        sipo.blank().unwrap();
+        assert!(sipo.is_blanked());
+        assert_eq!(sipo.p_sipo_bank.last_frame(), None);
+        assert_eq!(chips.each_ref().map(|chip| chip.out.get() & 0xFF), [0, 0, 0]);
+        sipo.write_pixel(1, 1, 9);
+        assert_eq!(sipo.p_sipo_bank.last_frame(), None);
+        sipo.unblank().unwrap();
+        assert!(!sipo.is_blanked());
+        assert_eq!(sipo.p_sipo_bank.last_frame(), Some(&[[0x42], [5], [7]]));
+        assert_eq!(chips.each_ref().map(|chip| chip.out.get() & 0xFF), [0x42, 5, 7]);
+    }
+
+    #[test]
+    fn sipo_capabilities_follow_the_batched_latch() {
+        use esp_disp_driver::display::pix_writer::{Capabilities, PixelWriter};
+
+        let chips: [Chip; 3] = Default::default();
+        let mut w = mock_writer(&chips);
+        // Write-only: nothing to read back, nothing to present.
+        assert_eq!(w.capabilities(), Capabilities::write_only::<u8, u8>());
+        assert_eq!(w.read_pixel(0, 0), None);
+        // Without the batched latch, every pixel in a batch still latches.
+        w.begin_batch();
+        w.write_pixel(0, 0, 1);
+        w.write_pixel(0, 1, 2);
+        w.end_batch();
+        assert_eq!(chips[0].latches.get(), 2);
+
+        let mut w = w.with_batched_latch(true);
+        assert!(w.capabilities().batching);
+        w.begin_batch();
+        w.write_pixel(3, 4, 5);
+        w.write_pixel(3, 5, 6);
+        assert_eq!(chips[0].latches.get(), 2);
+        w.end_batch();
+        assert_eq!(chips[0].latches.get(), 3);
+        assert_eq!(chips.each_ref().map(|chip| chip.out.get() & 0xFF), [6, 3, 5]);
+    }
+}
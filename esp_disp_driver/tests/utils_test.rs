@@ -0,0 +1,1339 @@
+//! On-target tests for the pure helpers in `esp_disp_driver::utils`.
+
+#![no_std]
+#![no_main]
+
+esp_bootloader_esp_idf::esp_app_desc!();
+
+#[cfg(test)]
+#[embedded_test::tests(executor = esp_rtos::embassy::Executor::new())]
+mod tests {
+    use defmt::{assert, assert_eq};
+    use esp_disp_driver::display::backend::utils::{apply_brightness_floor, blend_frames, BrightnessPolarity, BufferSel, DoubleBuffer, FrameStore, OutOfRangePolicy, PresentStats, RefreshWatchdog, Resolution, RingBuffer, ScanControl, ScanCoordinator, ScanOrientation, ScanSync, ScanWindow, VsyncEvent, VsyncTracker};
+    use esp_disp_driver::display::backend::bus_dac::{BwPixelWriter8h8v1ch4, DoubleFb, FrameBuf, VgaHwResources, FB_HEIGHT, FB_WIDTH};
+    use esp_disp_driver::display::drawer::Drawer;
+    use esp_hal::gpio::AnyPin;
+    use esp_disp_driver::utils::{adler32, double_buffer_bytes, find_shared_pin, framebuffer_bytes, Fixed, ns_to_cycles, row_stride, sign_extend};
+
+    #[init]
+    fn init() {
+        let peripherals = esp_hal::init(esp_hal::Config::default());
+
+        let timg1 = esp_hal::timer::timg::TimerGroup::new(peripherals.TIMG1);
+        esp_rtos::start(timg1.timer0);
+
+        rtt_target::rtt_init_defmt!();
+    }
+
+    #[test]
+    fn ns_to_cycles_at_common_clocks() {
+        assert_eq!(ns_to_cycles(0, 240_000_000), 0);
+        assert_eq!(ns_to_cycles(1_000, 240_000_000), 240);
+        assert_eq!(ns_to_cycles(1_000, 80_000_000), 80);
+        assert_eq!(ns_to_cycles(100, 160_000_000), 16);
+        // 1 ns at 80 MHz is 0.08 cycles and must round up.
+        assert_eq!(ns_to_cycles(1, 80_000_000), 1);
+        assert_eq!(ns_to_cycles(u32::MAX, 240_000_000), 1_030_792_151);
+        assert_eq!(ns_to_cycles(u32::MAX, u32::MAX), u32::MAX);
+    }
+
+    #[test]
+    fn shared_pin_names_both_roles() {
+        let haddr = [21, 47, 48, 45, 0, 35, 36, 37];
+        let data = [4, 5, 6, 7];
+        assert_eq!(find_shared_pin(&[("haddr", &haddr), ("vaddr", &[14, 13, 12, 11]), ("data", &data)]), None);
+        assert_eq!(find_shared_pin(&[("haddr", &haddr), ("vaddr", &[14, 36]), ("data", &data)]), Some((36, "haddr", "vaddr")));
+        assert_eq!(find_shared_pin(&[("haddr", &haddr), ("data", &[4, 5, 4])]), Some((4, "data", "data")));
+    }
+
+    #[test]
+    fn brightness_polarity_maps_extremes() {
+        assert_eq!(BrightnessPolarity::Normal.apply(0, 15), 0);
+        assert_eq!(BrightnessPolarity::Normal.apply(15, 15), 15);
+        assert_eq!(BrightnessPolarity::Inverted.apply(0, 15), 15);
+        assert_eq!(BrightnessPolarity::Inverted.apply(15, 15), 0);
+        assert_eq!(BrightnessPolarity::Inverted.apply(0, 255), 255);
+        assert_eq!(BrightnessPolarity::Inverted.apply(255, 255), 0);
+    }
+
+    #[test]
+    fn brightness_floor_lifts_logical_black() {
+        assert_eq!(apply_brightness_floor(0u8, 3, 15), 3);
+        assert_eq!(apply_brightness_floor(15u8, 3, 15), 15);
+        assert_eq!(apply_brightness_floor(0u8, 0, 15), 0);
+        assert_eq!(apply_brightness_floor(7u8, 0, 15), 7);
+        // Mid-grey lands roughly halfway between floor and max.
+        assert_eq!(apply_brightness_floor(128u8, 40, 255), 148);
+        for c in 1..=255u8 {
+            assert!(apply_brightness_floor(c, 40, 255) >= apply_brightness_floor(c - 1, 40, 255));
+        }
+        // Composes with polarity: an inverted panel's black moves down from max.
+        assert_eq!(BrightnessPolarity::Inverted.apply(apply_brightness_floor(0u8, 3, 15), 15), 12);
+    }
+
+    #[test]
+    fn try_swap_flips_once_per_call() {
+        let db = DoubleBuffer::new(0u8);
+        db.with_inactive(|b| *b = 1);
+        assert_eq!(db.front_index(), 0);
+        assert!(db.try_swap(db.front_index()));
+        assert_eq!(db.front_index(), 1);
+        assert_eq!(db.with_active(|b| *b), 1);
+
+        // Second producer finishes the next frame and swaps back.
+        db.with_inactive(|b| *b = 2);
+        assert!(db.try_swap(db.front_index()));
+        assert_eq!(db.with_active(|b| *b), 2);
+
+        // Mixing with the single-producer swap keeps the same index protocol.
+        db.swap();
+        assert_eq!(db.with_active(|b| *b), 1);
+    }
+
+    #[test]
+    fn concurrent_try_swaps_let_exactly_one_win() {
+        let db = DoubleBuffer::new(0u8);
+        // Two producers both draw against the same front buffer, then race
+        // to publish: whichever swaps second must lose, not flip it back.
+        let (front_a, front_b) = (db.front_index(), db.front_index());
+        db.with_inactive(|b| *b = 1);
+        assert!(db.try_swap(front_a));
+        assert!(!db.try_swap(front_b));
+        assert_eq!(db.with_active(|b| *b), 1);
+        assert_eq!(db.front_index(), front_a ^ 1);
+
+        // The loser retries against the new front.
+        let front_b = db.front_index();
+        db.with_inactive(|b| *b = 2);
+        assert!(db.try_swap(front_b));
+        assert_eq!(db.with_active(|b| *b), 2);
+    }
+
+    #[test]
+    fn double_buffer_inits_without_clone() {
+        // No `Clone`: each buffer keeps exactly the value it was given.
+        struct Frame([u8; 4]);
+        let db = DoubleBuffer::new_with(Frame([1; 4]), Frame([2; 4]));
+        assert_eq!(db.with_active(|f| f.0), [1; 4]);
+        assert_eq!(db.with_inactive(|f| f.0), [2; 4]);
+        db.swap();
+        assert_eq!(db.with_active(|f| f.0), [2; 4]);
+
+        // Usable in a static initialiser.
+        static DB: DoubleBuffer<[[u8; 3]; 2]> = DoubleBuffer::new_with([[5; 3]; 2], [[0; 3]; 2]);
+        assert_eq!(DB.with_active(|f| f[1][2]), 5);
+
+        let db: DoubleBuffer<[u16; 4]> = DoubleBuffer::new_default();
+        assert_eq!((db.with_active(|f| *f), db.with_inactive(|f| *f)), ([0; 4], [0; 4]));
+    }
+
+    #[test]
+    fn present_from_copies_into_the_scanned_buffer() {
+        let db = DoubleBuffer::new([[0u8; 4]; 3]);
+        let mut mine = [[0u8; 4]; 3];
+        mine[1][2] = 7;
+        mine[2][3] = 9;
+        db.present_from(&mine);
+        assert_eq!(db.with_active(|f| *f), mine);
+        // The old front buffer is now the one drawn into, untouched.
+        assert_eq!(db.with_inactive(|f| *f), [[0u8; 4]; 3]);
+
+        mine[0][0] = 1;
+        db.present_from(&mine);
+        assert_eq!(db.with_active(|f| f[0][0]), 1);
+    }
+
+    #[test]
+    fn double_buffer_as_frame_store() {
+        // Drawn through the trait only, as a generic backend would.
+        fn draw_and_show<S: FrameStore<Frame = [[u8; 4]; 3]>>(store: &S, color: u8) {
+            store.with_inactive(|f| f[2][3] = color);
+            store.swap();
+        }
+
+        let db = DoubleBuffer::new([[0u8; 4]; 3]);
+        assert_eq!(FrameStore::resolution(&db), Resolution::new(4, 3));
+        draw_and_show(&db, 5);
+        assert_eq!(FrameStore::with_buffer(&db, BufferSel::Active, |f| f[2][3]), 5);
+        assert_eq!(FrameStore::with_buffer(&db, BufferSel::Inactive, |f| f[2][3]), 0);
+        draw_and_show(&db, 6);
+        assert_eq!(db.with_active(|f| f[2][3]), 6);
+    }
+
+    #[test]
+    fn inactive_guard_lands_a_burst_before_the_swap() {
+        let db = DoubleBuffer::new([[0u8; 4]; 3]);
+        let mut guard = db.lock_inactive().unwrap();
+        assert!(db.lock_inactive().is_none());
+        {
+            let mut d = Drawer::new(&mut guard);
+            d.fill_screen(2);
+            d.write_pixel(1, 3, 7);
+        }
+        guard[0][0] = 9;
+        // Still drawing: the scan side sees nothing and cannot be swapped to it.
+        assert!(!db.try_swap(db.front_index()));
+        assert_eq!(db.with_active(|f| f[1][3]), 0);
+
+        guard.swap();
+        assert_eq!(db.with_active(|f| (f[0][0], f[1][3], f[2][2])), (9, 7, 2));
+        // Dropping a guard without swapping releases it too.
+        drop(db.lock_inactive().unwrap());
+        assert!(db.try_swap(db.front_index()));
+
+        // `with_inactive` takes the same lock, so neither a guard nor a swap
+        // can get at the buffer while it runs.
+        db.with_inactive(|f| {
+            f[0][1] = 4;
+            assert!(db.lock_inactive().is_none());
+            assert!(!db.try_swap(db.front_index()));
+        });
+        assert!(db.lock_inactive().is_some());
+    }
+
+    #[test]
+    fn cache_ranges_follow_the_swap() {
+        let db = DoubleBuffer::new([[0u8; 5]; 3]);
+        let (active, len) = db.active_cache_range();
+        let (inactive, _) = db.inactive_cache_range();
+        assert_eq!(len, 15);
+        assert!(active + len <= inactive || inactive + len <= active);
+        assert_eq!(db.active_ptr() as usize, active);
+
+        db.swap();
+        assert_eq!(db.active_cache_range(), (inactive, len));
+        assert_eq!(db.inactive_cache_range(), (active, len));
+    }
+
+    #[test]
+    fn debug_diff_counts_changed_pixels() {
+        let db = DoubleBuffer::new([[0u8; 5]; 4]);
+        assert_eq!(db.debug_diff(), 0);
+        db.with_inactive(|f| {
+            f[0][0] = 1;
+            f[3][4] = 2;
+            f[2][1] = 3;
+        });
+        assert_eq!(db.debug_diff(), 3);
+        db.swap();
+        assert_eq!(db.debug_diff(), 3);
+        db.with_inactive(|f| *f = [[9; 5]; 4]);
+        assert_eq!(db.debug_diff(), 20);
+    }
+
+    #[test]
+    fn on_present_runs_after_each_swap() {
+        use core::sync::atomic::{AtomicU32, Ordering};
+        static PRESENTS: AtomicU32 = AtomicU32::new(0);
+        fn count() {
+            PRESENTS.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let db = DoubleBuffer::new(0u8).with_on_present(count);
+        db.swap();
+        assert!(db.try_swap(db.front_index()));
+        assert_eq!(PRESENTS.load(Ordering::Relaxed), 2);
+
+        // No hook, no calls.
+        let plain = DoubleBuffer::new(0u8);
+        plain.swap();
+        assert_eq!(PRESENTS.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn scan_coordinator_double_buffering_waits_for_the_scanner() {
+        let coord = ScanCoordinator::<2>::new();
+        assert_eq!(coord.acquire(), Some(1));
+        coord.publish(1);
+        // Both buffers busy until the next frame start.
+        assert_eq!(coord.acquire(), None);
+        assert_eq!(coord.frame_boundary(), 1);
+        assert_eq!((coord.displayed(), coord.pending()), (1, None));
+        assert_eq!(coord.acquire(), Some(0));
+        // Nothing new: the same buffer stays on screen.
+        assert_eq!(coord.frame_boundary(), 1);
+        assert_eq!(coord.dropped(), 0);
+    }
+
+    #[test]
+    fn ring_buffer_simulation_never_tears() {
+        // Producer and scan loop interleaved step by step on a pseudo-random
+        // schedule. The producer writes frame `n` one row per step and
+        // swaps; the scanner starts a frame, then reads one row per step.
+        // A torn frame would show rows from different frames.
+        let fb = RingBuffer::<[[u32; 1]; 4], 3>::new([[0; 1]; 4]);
+        let mut seed = 0x2545_f491u32;
+        let (mut drawn, mut produced) = (0, 1);
+        let (mut row, mut shown, mut last_shown, mut frames) = (4, 0, 0, 0);
+        for _ in 0..4000 {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            if seed & 1 == 0 {
+                fb.with_inactive(|frame| frame[drawn][0] = produced);
+                drawn += 1;
+                if drawn == 4 {
+                    fb.swap();
+                    drawn = 0;
+                    produced += 1;
+                }
+            } else if row == 4 {
+                fb.mark_scan_started();
+                row = 0;
+            } else {
+                let v = fb.with_active(|frame| frame[row][0]);
+                if row == 0 {
+                    shown = v;
+                    // Frames appear in order, possibly skipping some.
+                    assert!(shown >= last_shown);
+                    frames += (shown != last_shown) as u32;
+                    last_shown = shown;
+                }
+                assert_eq!(v, shown);
+                row += 1;
+            }
+        }
+        assert!(frames > 100);
+        // The producer outran the scanner at times, dropping frames.
+        assert!(fb.coordinator().dropped() > 0);
+    }
+
+    #[test]
+    fn blend_mixes_frames_by_alpha() {
+        use static_cell::StaticCell;
+
+        static DB: StaticCell<DoubleBuffer<[[u8; 3]; 1]>> = StaticCell::new();
+        let db = DB.init(DoubleBuffer::new([[0, 100, 255]]));
+        let other = [[255, 200, 15]];
+        let mut out = [[0; 3]; 1];
+        db.blend_into(&other, &mut out, 0);
+        assert_eq!(out, [[0, 100, 255]]);
+        db.blend_into(&other, &mut out, 255);
+        assert_eq!(out, other);
+        db.blend_into(&other, &mut out, 128);
+        assert_eq!(out, [[128, 150, 135]]);
+        // Results stay between the inputs, so 4-bit codes stay 4-bit.
+        blend_frames(&[[0, 15, 7]], &[[15, 15, 8]], &mut out, 200);
+        assert_eq!(out, [[12, 15, 8]]);
+    }
+
+    #[test]
+    fn sync_frame_waits_for_the_scanner_to_pick_up_the_swap() {
+        use core::sync::atomic::{AtomicPtr, AtomicU32, Ordering};
+        use static_cell::StaticCell;
+        static DB: StaticCell<DoubleBuffer<u8>> = StaticCell::new();
+        static SCANNER: AtomicPtr<DoubleBuffer<u8>> = AtomicPtr::new(core::ptr::null_mut());
+        static SPINS: AtomicU32 = AtomicU32::new(0);
+        // Stands in for the scan loop on the other core: it reaches the
+        // next frame start on the producer's third spin.
+        fn scanner_step() {
+            if SPINS.fetch_add(1, Ordering::Relaxed) == 2 {
+                unsafe { &*SCANNER.load(Ordering::Relaxed) }.mark_scan_started();
+            }
+        }
+
+        let db: &DoubleBuffer<u8> = DB.init(DoubleBuffer::new(0));
+        SCANNER.store(db as *const _ as *mut _, Ordering::Relaxed);
+        assert!(db.is_frame_synced());
+
+        db.swap();
+        assert!(!db.is_frame_synced());
+        db.sync_frame_with(scanner_step);
+        assert_eq!(SPINS.load(Ordering::Relaxed), 3);
+        assert!(db.is_frame_synced());
+
+        // Racing ahead: a frame start between two swaps only covers the first.
+        assert!(db.try_swap(db.front_index()));
+        db.mark_scan_started();
+        db.swap();
+        assert!(!db.is_frame_synced());
+        db.mark_scan_started();
+        db.sync_frame();
+        assert_eq!(SPINS.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn refresh_watchdog_flags_a_starved_scan() {
+        let sync = ScanSync::new();
+        let ctl = ScanControl::new();
+        let frames = |n: u32| {
+            for _ in 0..n {
+                sync.enter_vblank();
+                sync.leave_vblank();
+            }
+        };
+        let mut dog = RefreshWatchdog::new(&sync, 50).with_control(&ctl);
+        assert_eq!(dog.check(0), None);
+        frames(60);
+        assert_eq!(dog.check(1_000_000), Some(60));
+        frames(20);
+        assert_eq!(dog.check(2_000_000), Some(20));
+        assert_eq!(dog.violations(), 1);
+
+        // Paused: skipped, then the next check only restarts the window.
+        ctl.pause();
+        assert_eq!(dog.check(3_000_000), None);
+        ctl.resume();
+        assert_eq!(dog.check(4_000_000), None);
+        frames(30);
+        assert_eq!(dog.check(4_500_000), Some(60));
+        assert_eq!(dog.violations(), 1);
+    }
+
+    #[test]
+    fn vsync_tracker_sees_each_leading_edge_once() {
+        let mut vsync = VsyncTracker::new(true);
+        assert_eq!(vsync.sample(true, false), VsyncEvent::None);
+        // A level held across lines is a single edge.
+        assert_eq!(vsync.sample(false, true), VsyncEvent::InSync);
+        assert_eq!(vsync.sample(false, false), VsyncEvent::None);
+        assert_eq!(vsync.sample(true, false), VsyncEvent::None);
+        assert_eq!(vsync.sample(false, false), VsyncEvent::Resync);
+        assert_eq!((vsync.frames(), vsync.resyncs()), (2, 1));
+    }
+
+    #[cfg(feature = "sim")]
+    #[test]
+    fn vsync_tracker_recovers_after_a_glitch() {
+        use esp_disp_driver::display::backend::bus_dac::VSYNC_SLACK_LINES;
+        use static_cell::StaticCell;
+
+        // One pixel per line, row `v` drawn as code `v + 1`, so the codes
+        // show which line of the frame went out on each line.
+        const V_TOTAL: usize = 10;
+        let frame = core::array::from_fn(|i| {
+            let mut row = [0u8; FB_WIDTH];
+            row[0] = (17 * (i % 15 + 1)) as u8;
+            row
+        });
+        static FB: StaticCell<DoubleFb> = StaticCell::new();
+        let dac = bus_dac_writer(FB.init(DoubleBuffer::new(frame)));
+        let rows: [u8; V_TOTAL] = core::array::from_fn(|v| v as u8 + 1);
+        let assert_frames = |codes: &[u8], lengths: &[usize]| {
+            let mut at = 0;
+            for &n in lengths {
+                assert_eq!(codes[at..at + n], rows[..n]);
+                at += n;
+            }
+            assert_eq!(at, codes.len());
+        };
+        // External VSYNC, active low and two lines wide, every `V_TOTAL`
+        // lines from line `phase`.
+        let vsync = |phase: usize| move |t: usize| (t + V_TOTAL - phase) % V_TOTAL >= 2;
+
+        // Edges within the slack after the frame start are in sync.
+        let (codes, tracker) = dac.capture_generated_synced(1, V_TOTAL, 3, true, vsync(VSYNC_SLACK_LINES));
+        assert_frames(&codes, &[V_TOTAL; 3]);
+        assert_eq!((tracker.frames(), tracker.resyncs()), (3, 0));
+        let (_, tracker) = dac.capture_generated_synced(1, V_TOTAL, 3, true, vsync(VSYNC_SLACK_LINES + 1));
+        assert_eq!(tracker.resyncs(), 1);
+
+        // Glitch: from line 24 the external timing runs three lines late.
+        // The third frame is cut at the edge and the next starts with it.
+        let glitched = |t: usize| if t < 24 { vsync(1)(t) } else { vsync(8)(t) };
+        let (codes, tracker) = dac.capture_generated_synced(1, V_TOTAL, 5, true, glitched);
+        assert_frames(&codes, &[10, 10, 8, 10, 10]);
+        // One correction, then every later edge lands on the frame start.
+        assert_eq!((tracker.frames(), tracker.resyncs()), (5, 1));
+    }
+
+    #[test]
+    fn scan_control_pause_and_park() {
+        let ctl = ScanControl::new();
+        assert!(!ctl.is_paused() && !ctl.is_parked());
+        ctl.pause();
+        // Requested, but the scan loop has not reached its check yet.
+        assert!(ctl.is_paused() && !ctl.is_parked());
+        ctl.set_parked(true);
+        assert!(ctl.is_parked());
+        ctl.resume();
+        assert!(!ctl.is_paused());
+        ctl.set_parked(false);
+        assert!(!ctl.is_parked());
+    }
+
+    #[test]
+    fn sign_extend_boundaries() {
+        // All ones is -1 at every width.
+        assert_eq!(sign_extend(0xFF, 8), -1);
+        assert_eq!(sign_extend(0x0F, 4), -1);
+        assert_eq!(sign_extend(0xFFFF, 16), -1);
+        assert_eq!(sign_extend(0x1, 1), -1);
+        // Most negative and most positive values.
+        assert_eq!(sign_extend(0x80, 8), i8::MIN as i32);
+        assert_eq!(sign_extend(0x7F, 8), i8::MAX as i32);
+        assert_eq!(sign_extend(0x8000, 16), i16::MIN as i32);
+        assert_eq!(sign_extend(0x7FFF, 16), i16::MAX as i32);
+        assert_eq!(sign_extend(0x20, 6), -32);
+        // Bits above the width are ignored.
+        assert_eq!(sign_extend(0xF07, 4), 7);
+        assert_eq!(sign_extend(0x5, 0), 0);
+    }
+
+    #[test]
+    fn scan_window_blanks_outside() {
+        const BLANK: u8 = 3;
+        let w = ScanWindow::new(40, 60, 10, 20);
+        assert_eq!(w, ScanWindow { i0: 10, j0: 20, i1: 40, j1: 60 });
+        // Inside, including the edges.
+        assert_eq!(w.pick(10, 20, 9, BLANK), 9);
+        assert_eq!(w.pick(40, 60, 9, BLANK), 9);
+        assert_eq!(w.pick(25, 30, 9, BLANK), 9);
+        // One past each edge.
+        assert_eq!(w.pick(9, 30, 9, BLANK), BLANK);
+        assert_eq!(w.pick(41, 30, 9, BLANK), BLANK);
+        assert_eq!(w.pick(25, 19, 9, BLANK), BLANK);
+        assert_eq!(w.pick(25, 61, 9, BLANK), BLANK);
+        assert_eq!(w.pick(200, 200, 9, BLANK), BLANK);
+    }
+
+    #[test]
+    fn framebuffer_sizes() {
+        assert_eq!(framebuffer_bytes(201, 151, 8), 201 * 151);
+        assert_eq!(framebuffer_bytes(201, 151, 1), 26 * 151);
+        assert_eq!(framebuffer_bytes(201, 151, 4), 101 * 151);
+        assert_eq!(framebuffer_bytes(640, 480, 16), 614_400);
+        assert_eq!(double_buffer_bytes(100, 75, 8), 15_000);
+
+        let frames = double_buffer_bytes(FB_WIDTH, FB_HEIGHT, 8);
+        assert!(frames <= core::mem::size_of::<DoubleFb>());
+        assert!(core::mem::size_of::<DoubleFb>() - frames < 64);
+    }
+
+    #[test]
+    fn fixed_conversions_and_rounding() {
+        assert_eq!(Fixed::from_int(3).to_bits(), 3 << 16);
+        assert_eq!(Fixed::from_int(-2).floor(), -2);
+        assert_eq!(Fixed::ONE.to_bits(), 65536);
+        assert_eq!(Fixed::HALF + Fixed::HALF, Fixed::ONE);
+
+        let x = Fixed::from_ratio(7, 2); // 3.5
+        assert_eq!((x.floor(), x.ceil(), x.round()), (3, 4, 4));
+        assert_eq!(x.frac(), Fixed::HALF);
+        let y = -x; // -3.5
+        assert_eq!((y.floor(), y.ceil(), y.round()), (-4, -3, -3));
+        assert_eq!(y.frac(), Fixed::HALF);
+        assert_eq!(y.abs(), x);
+        let z = Fixed::from_int(5);
+        assert_eq!((z.floor(), z.ceil(), z.round()), (5, 5, 5));
+        assert_eq!(z.frac(), Fixed::ZERO);
+
+        assert_eq!(Fixed::from_prim(200u8), Some(Fixed::from_int(200)));
+        assert_eq!(Fixed::from_prim(-32768i32), Some(Fixed::from_int(-32768)));
+        assert_eq!(Fixed::from_prim(32768u16), None);
+        assert_eq!(Fixed::from_ratio(599, 2).to_prim::<u8>(), None);
+        assert_eq!(Fixed::from_ratio(401, 2).to_prim::<u8>(), Some(200));
+        assert_eq!(Fixed::from_ratio(-1, 2).to_prim::<u8>(), None);
+        assert_eq!(Fixed::from_ratio(-1, 2).to_prim::<i16>(), Some(-1));
+
+        // Scaling wide integers rounds halves away from zero.
+        assert_eq!(Fixed::HALF.scale(3), 2);
+        assert_eq!(Fixed::HALF.scale(-3), -2);
+        assert_eq!(Fixed::from_ratio(1, 4).scale(0xFF_FFFF), 0x40_0000);
+        assert_eq!(Fixed::ONE.scale(i32::MAX as i64 * 4), i32::MAX as i64 * 4);
+    }
+
+    #[test]
+    fn fixed_arithmetic() {
+        let (a, b) = (Fixed::from_ratio(5, 2), Fixed::from_ratio(-3, 4)); // 2.5, -0.75
+        assert_eq!(a + b, Fixed::from_ratio(7, 4));
+        assert_eq!(a - b, Fixed::from_ratio(13, 4));
+        assert_eq!(a * b, Fixed::from_ratio(-15, 8));
+        assert_eq!(a / b, Fixed::from_ratio(-10, 3));
+        assert_eq!(a * 4, Fixed::from_int(10));
+        assert_eq!(a / 2, Fixed::from_ratio(5, 4));
+
+        let mut acc = Fixed::ZERO;
+        acc += a;
+        acc -= Fixed::ONE;
+        assert_eq!(acc, Fixed::from_ratio(3, 2));
+
+        // Products round to nearest: 1/65536 * 1/2 rounds up to 1/65536.
+        let eps = Fixed::from_bits(1);
+        assert_eq!(eps * Fixed::HALF, eps);
+        assert_eq!(eps * Fixed::from_ratio(1, 4), Fixed::ZERO);
+        // Quotients truncate toward zero.
+        assert_eq!(Fixed::ONE / Fixed::from_int(3), Fixed::from_bits(21845));
+        assert_eq!(-Fixed::ONE / Fixed::from_int(3), Fixed::from_bits(-21845));
+        // Large but in-range intermediates go through i64.
+        assert_eq!(Fixed::from_int(181) * Fixed::from_int(181), Fixed::from_int(32761));
+        assert_eq!(Fixed::from_int(30000) / Fixed::from_int(100), Fixed::from_int(300));
+    }
+
+    #[test]
+    fn fixed_lerp_endpoints_and_midpoint() {
+        let (a, b) = (Fixed::from_int(10), Fixed::from_int(-6));
+        assert_eq!(Fixed::lerp(a, b, Fixed::ZERO), a);
+        assert_eq!(Fixed::lerp(a, b, Fixed::ONE), b);
+        assert_eq!(Fixed::lerp(a, b, Fixed::HALF), Fixed::from_int(2));
+        assert_eq!(Fixed::lerp(a, b, Fixed::from_ratio(1, 4)).round(), 6);
+        // Walking t in 1/16 steps hits every integer between 0 and 16 exactly.
+        for k in 0..=16 {
+            let t = Fixed::from_ratio(k, 16);
+            assert_eq!(Fixed::lerp(Fixed::ZERO, Fixed::from_int(16), t), Fixed::from_int(k));
+        }
+    }
+
+    #[test]
+    fn row_stride_padding() {
+        assert_eq!(row_stride(FB_WIDTH, 0), FB_WIDTH);
+        assert_eq!(row_stride(FB_WIDTH, 1), FB_WIDTH);
+        assert_eq!(row_stride(FB_WIDTH, 4), 204);
+        assert_eq!(row_stride(FB_WIDTH, 32), 224);
+        assert_eq!(row_stride(224, 32), 224);
+
+        const STRIDE: usize = row_stride(FB_WIDTH, 32);
+        let padding = (STRIDE - FB_WIDTH) * FB_HEIGHT;
+        assert_eq!(padding, 3473);
+        assert_eq!(core::mem::size_of::<FrameBuf<STRIDE>>(), core::mem::size_of::<FrameBuf>() + padding);
+    }
+
+    #[test]
+    fn scan_orientation_maps_addresses() {
+        assert_eq!(ScanOrientation::default(), ScanOrientation::RowMajor);
+        // (line, sample) -> (row, col): V is the row either way.
+        assert_eq!(ScanOrientation::RowMajor.to_frame(3, 200), (3, 200));
+        assert_eq!(ScanOrientation::ColumnMajor.to_frame(200, 3), (3, 200));
+
+        let frame = [[0u8, 1, 2], [10, 11, 12]];
+        let at = |o: ScanOrientation, line: usize, sample: usize| {
+            let (r, c) = o.to_frame(line, sample);
+            frame[r][c]
+        };
+        assert_eq!(at(ScanOrientation::RowMajor, 1, 2), 12);
+        assert_eq!(at(ScanOrientation::ColumnMajor, 2, 1), 12);
+        // Also maps (rows, columns) limits to (line, sample) ones.
+        assert_eq!(ScanOrientation::ColumnMajor.to_frame(FB_HEIGHT, FB_WIDTH), (FB_WIDTH, FB_HEIGHT));
+    }
+
+    #[cfg(feature = "layers")]
+    #[test]
+    fn layers_composite_with_color_keys() {
+        use esp_disp_driver::display::layers::LayerStack;
+
+        let mut stack: LayerStack<3, 4, 2> = LayerStack::new();
+        stack.background = 9;
+        let mut out = [[0u8; 4]; 2];
+        // All layers opaque: the top one wins.
+        stack.layer_mut(2)[0][0] = 7;
+        stack.composite(&mut out);
+        assert_eq!(out, [[7, 0, 0, 0], [0, 0, 0, 0]]);
+
+        *stack.layer_mut(0) = [[1; 4]; 2];
+        *stack.layer_mut(1) = [[2, 0, 2, 0], [0; 4]];
+        *stack.layer_mut(2) = [[0, 0, 0, 3], [3, 0, 0, 0]];
+        stack.set_color_key(1, Some(0));
+        stack.set_color_key(2, Some(0));
+        stack.composite(&mut out);
+        assert_eq!(out, [[2, 1, 2, 3], [3, 1, 1, 1]]);
+
+        stack.set_visible(2, false);
+        stack.composite(&mut out);
+        assert_eq!(out, [[2, 1, 2, 1], [1, 1, 1, 1]]);
+
+        // Nothing opaque left: keyed pixels fall through to the background.
+        stack.set_visible(0, false);
+        stack.composite(&mut out);
+        assert_eq!(out, [[2, 9, 2, 9], [9, 9, 9, 9]]);
+    }
+
+    #[cfg(feature = "packed-fb")]
+    #[test]
+    fn blit_4bpp_at_even_and_odd_columns() {
+        use esp_disp_driver::display::backend::bus_dac::{PackedFrameBuf, PACKED_FB_STRIDE};
+        use esp_disp_driver::error::Error;
+
+        // 3x2 sprite, rows padded to 2 bytes: [1 2 3] / [4 5 6].
+        let sprite = [0x21, 0x03, 0x54, 0x06];
+        let row = |fb: &PackedFrameBuf, i: usize, j0: usize| -> [u8; 5] { core::array::from_fn(|k| fb.pixel(i, j0 + k)) };
+
+        let mut fb = PackedFrameBuf::new();
+        fb.rows = [[0xFF; PACKED_FB_STRIDE]; FB_HEIGHT];
+        fb.blit_4bpp(2, 4, 3, 2, &sprite).unwrap();
+        assert_eq!(row(&fb, 2, 3), [15, 1, 2, 3, 15]);
+        assert_eq!(row(&fb, 3, 3), [15, 4, 5, 6, 15]);
+        assert_eq!(fb.rows[2][2], 0x21);
+
+        fb.blit_4bpp(6, 7, 3, 2, &sprite).unwrap();
+        assert_eq!(row(&fb, 6, 6), [15, 1, 2, 3, 15]);
+        assert_eq!(row(&fb, 7, 6), [15, 4, 5, 6, 15]);
+        assert_eq!(fb.rows[6][4], 0x32);
+
+        // Clipped on the top and right edges.
+        fb.blit_4bpp(-1, FB_WIDTH as i16 - 2, 3, 2, &sprite).unwrap();
+        assert_eq!((fb.pixel(0, FB_WIDTH - 2), fb.pixel(0, FB_WIDTH - 1)), (4, 5));
+        assert_eq!(fb.pixel(1, FB_WIDTH - 2), 15);
+
+        assert_eq!(fb.blit_4bpp(0, 0, 3, 2, &sprite[..3]), Err(Error::BufferTooSmall));
+
+        let mut frame: FrameBuf = [[0; FB_WIDTH]; FB_HEIGHT];
+        fb.unpack_into(&mut frame);
+        assert_eq!(&frame[6][6..11], &[255, 17, 34, 51, 255]);
+    }
+
+    #[test]
+    fn dac_scaling_maps_full_brightness_to_max_code() {
+        use esp_disp_driver::display::backend::bus_dac::scale_to_dac;
+
+        for bits in 1..=8 {
+            let max_code = ((1u16 << bits) - 1) as u8;
+            assert_eq!(scale_to_dac(255, bits), max_code, "{bits} bits");
+            assert_eq!(scale_to_dac(0, bits), 0, "{bits} bits");
+        }
+        // 4-bit levels stretched by 17 come back unchanged.
+        assert!((0..16u8).all(|l| scale_to_dac(l * 17, 4) == l));
+        assert_eq!(scale_to_dac(128, 6), 32);
+    }
+
+    #[test]
+    fn present_stats_intervals_and_drops() {
+        let mut stats = PresentStats::new();
+        assert_eq!((stats.min_interval_us(), stats.avg_interval_us()), (0, 0));
+
+        // (frame, time): the second and fourth swaps land in the same
+        // frame as the one before them.
+        for (frame, t) in [(1, 0), (1, 10_000), (2, 26_000), (2, 30_000), (4, 60_000)] {
+            stats.record_swap(Some(frame), Some(t));
+        }
+        assert_eq!((stats.swaps(), stats.dropped()), (5, 2));
+        assert_eq!((stats.min_interval_us(), stats.max_interval_us()), (4_000, 30_000));
+        assert_eq!(stats.avg_interval_us(), 15_000);
+
+        // No frame counter, no timestamps: only swaps are counted.
+        stats.reset();
+        stats.record_swap(None, None);
+        stats.record_swap(None, None);
+        assert_eq!((stats.swaps(), stats.dropped(), stats.max_interval_us()), (2, 0, 0));
+    }
+
+    #[test]
+    fn anypins_from_peri_n_takes_the_listed_pins() {
+        use esp_disp_driver::anypins_from_peri_n;
+        use esp_hal::gpio::Pin;
+
+        // Unconnected pins; only their numbers are looked at.
+        let p = unsafe { esp_hal::peripherals::Peripherals::steal() };
+        let pins = anypins_from_peri_n!(p; 4; 15, 16, 18, 17);
+        assert_eq!(pins.each_ref().map(|pin| pin.number()), [15, 16, 18, 17]);
+    }
+
+    #[test]
+    fn vga_hw_resources_takes_each_bus_in_order() {
+        use esp_disp_driver::vga_hw_resources;
+        use esp_hal::gpio::Pin;
+
+        // Only their numbers are looked at; the pins are never driven.
+        let p = unsafe { esp_hal::peripherals::Peripherals::steal() };
+        let res: VgaHwResources<'_, 8, 8, 4> = vga_hw_resources!(p;
+            haddr: [38, 39, 40, 41, 42, 43, 44, 45],
+            vaddr: [8, 9, 10, 11, 12, 13, 14, 15],
+            data: [7, 6, 5, 4],
+        );
+        assert_eq!((res.haddr_pins[0].number(), res.haddr_pins[7].number()), (38, 45));
+        assert_eq!(res.vaddr_pins.each_ref().map(|pin| pin.number()), [8, 9, 10, 11, 12, 13, 14, 15]);
+        assert_eq!(res.data_pins.each_ref().map(|pin| pin.number()), [7, 6, 5, 4]);
+    }
+
+    #[test]
+    fn backends_agree_on_resolution() {
+        use esp_disp_driver::display::backend::sipo::BwPixelWriter8h8v1ch8;
+        use esp_disp_driver::display::pix_writer::PixelWriter;
+        use static_cell::StaticCell;
+
+        static FB: StaticCell<DoubleFb> = StaticCell::new();
+        let dac = bus_dac_writer(FB.init(DoubleBuffer::new([[0u8; FB_WIDTH]; FB_HEIGHT])));
+
+        let res = sipo_resources();
+        let shifted = BwPixelWriter8h8v1ch8::from_resources(res).unwrap();
+
+        let panel = Resolution::new(FB_WIDTH as u16, FB_HEIGHT as u16);
+        assert_eq!(dac.resolution(), panel);
+        assert_eq!(shifted.resolution(), panel);
+        assert_eq!(dac.addr_range(), shifted.addr_range());
+        assert_eq!(dac.addr_range(), ((0, 150), (0, 200)));
+
+        let small = shifted.with_resolution(Resolution::new(64, 48));
+        assert_eq!(PixelWriter::<u8, u8>::addr_range(&small), ((0, 47), (0, 63)));
+        assert_eq!(Resolution::new(300, 1).addr_range::<u8>(), ((0, 0), (0, 255)));
+    }
+
+    #[test]
+    fn lane_layout_check_rejects_mismatched_banks() {
+        use esp_disp_driver::display::backend::sipo::{lane_layout_error, BwPixelWriter8h8v1ch8};
+
+        // The stock writer: one data lane and two address lanes, one byte each.
+        let () = BwPixelWriter8h8v1ch8::LANE_LAYOUT;
+        assert_eq!(lane_layout_error(1, 2, 3, 1), None);
+        assert_eq!(lane_layout_error(3, 2, 5, 2), None);
+        // RGB plus two address lanes needs a 5-lane bank, not 3.
+        assert!(lane_layout_error(3, 2, 3, 1).is_some());
+        assert!(lane_layout_error(0, 2, 2, 1).is_some());
+        assert!(lane_layout_error(1, 2, 3, 0).is_some());
+    }
+
+    #[test]
+    fn gray_traversal_halves_address_toggles() {
+        use esp_disp_driver::display::backend::sipo::Traversal;
+
+        // Address lines that change between consecutive pixels, and whether
+        // every pixel of the range was visited exactly once.
+        let walk = |order: Traversal, range: ((u8, u8), (u8, u8))| {
+            let ((i0, i1), (j0, j1)) = range;
+            let mut seen = [[0u8; 16]; 16];
+            let (mut toggles, mut prev) = (0, None::<(u8, u8)>);
+            for (i, j) in order.iter(range) {
+                seen[i as usize][j as usize] += 1;
+                if let Some((pi, pj)) = prev {
+                    toggles += ((i ^ pi).count_ones() + (j ^ pj).count_ones()) as usize;
+                }
+                prev = Some((i, j));
+            }
+            let once = seen.iter().enumerate().all(|(i, row)| {
+                row.iter().enumerate().all(|(j, &n)| n == ((i0..=i1).contains(&(i as u8)) && (j0..=j1).contains(&(j as u8))) as u8)
+            });
+            (toggles, once)
+        };
+
+        assert_eq!(walk(Traversal::RowMajor, ((0, 7), (0, 7))), (120, true));
+        assert_eq!(walk(Traversal::Gray, ((8, 15), (0, 7))), (63, true));
+
+        // Unaligned ranges are still covered exactly once, with fewer toggles.
+        let (row_major, _) = walk(Traversal::RowMajor, ((3, 12), (5, 10)));
+        let (gray, once) = walk(Traversal::Gray, ((3, 12), (5, 10)));
+        assert!(once && gray < row_major);
+        let mut single = Traversal::Gray.iter(((4, 4), (9, 9)));
+        assert_eq!((single.next(), single.next()), (Some((4, 9)), None));
+        assert_eq!(Traversal::Gray.iter(((5, 4), (0, 0))).next(), None);
+    }
+
+    #[test]
+    fn bus_dac_blank_pauses_the_scan() {
+        use esp_disp_driver::display::pix_writer::PixelWriter;
+        use esp_disp_driver::error::Error;
+        use static_cell::StaticCell;
+
+        static FB: StaticCell<DoubleFb> = StaticCell::new();
+        static CTL: ScanControl = ScanControl::new();
+        let mut dac = bus_dac_writer(FB.init(DoubleBuffer::new([[0u8; FB_WIDTH]; FB_HEIGHT])));
+        assert_eq!(dac.blank(), Err(Error::NotConfigured));
+        let mut dac = dac.with_scan_control(&CTL);
+        dac.write_pixel(2, 3, 4);
+        dac.blank().unwrap();
+        assert!(dac.is_blanked() && CTL.is_paused());
+        // Drawing while blanked still reaches the framebuffer.
+        dac.write_pixel(4, 5, 6);
+        dac.unblank().unwrap();
+        assert!(!dac.is_blanked() && !CTL.is_paused());
+        assert_eq!((dac.read_pixel(2, 3), dac.read_pixel(4, 5)), (Some(4), Some(6)));
+    }
+
+    #[test]
+    fn bus_dac_capabilities_match_its_behavior() {
+        use esp_disp_driver::display::pix_writer::{Capabilities, PixelWriter};
+        use static_cell::StaticCell;
+
+        static FB: StaticCell<DoubleFb> = StaticCell::new();
+        let fb: &'static DoubleFb = FB.init(DoubleBuffer::new([[0u8; FB_WIDTH]; FB_HEIGHT]));
+        let mut dac = bus_dac_writer(fb);
+        assert_eq!(
+            dac.capabilities(),
+            Capabilities {
+                read_pixel: true,
+                batching: false,
+                presentation: true,
+                fast_fill: true,
+                channels: 1,
+                addr_bits: 8,
+                color_bits: 8,
+            }
+        );
+        // Drawn pixels read back before they are shown...
+        dac.write_pixel(2, 3, 9);
+        assert_eq!(dac.read_pixel(2, 3), Some(9));
+        assert_eq!(fb.with_active(|f| f[2][3]), 0);
+        // ...and `present` shows them.
+        dac.present();
+        assert_eq!(fb.with_active(|f| f[2][3]), 9);
+    }
+
+    /// bus_dac pins for tests that never scan the real buses.
+    ///
+    /// The address buses are inputs on the board's counter lines, wired as
+    /// in bus_dac_demo and never driven; the data bus, the only one driven,
+    /// goes to the unconnected GPIO15..=18 rather than the DAC.
+    fn bus_dac_resources() -> VgaHwResources<'static, 8, 8, 4> {
+        fn pins<const K: usize>(nums: [u8; K]) -> [AnyPin<'static>; K] {
+            nums.map(|n| unsafe { AnyPin::steal(n) })
+        }
+        VgaHwResources {
+            haddr_pins: pins([21, 47, 48, 45, 0, 35, 36, 37]),
+            vaddr_pins: pins([14, 13, 12, 11, 10, 9, 46, 3]),
+            data_pins: pins([15, 16, 17, 18]),
+        }
+    }
+
+    /// sipo pins (one data lane) for tests that only check the frames the
+    /// bank records with `last_frame`, never what reaches the chips.
+    fn sipo_resources() -> esp_disp_driver::display::backend::sipo::VgaHwResources<'static> {
+        let pin = |n: u8| unsafe { AnyPin::steal(n) };
+        esp_disp_driver::display::backend::sipo::VgaHwResources {
+            rclk : pin(35),
+            srclk : pin(36),
+            srclr_al : pin(37),
+            data_ser : [pin(38)],
+            i_addr_ser : pin(39),
+            j_addr_ser : pin(40),
+        }
+    }
+
+    /// bus_dac writer on `bus_dac_resources` around `fb`.
+    fn bus_dac_writer(fb: &'static DoubleFb) -> BwPixelWriter8h8v1ch4<'static> {
+        BwPixelWriter8h8v1ch4::with_hw_resources(bus_dac_resources(), fb, None, None, None)
+    }
+
+    #[cfg(feature = "sim")]
+    #[test]
+    fn scan_capture_replays_the_framebuffer() {
+        use esp_disp_driver::display::backend::bus_dac::ScanScript;
+        use static_cell::StaticCell;
+
+        // 17 * k scales to DAC code k on the 4-bit bus.
+        let mut frame = [[0u8; FB_WIDTH]; FB_HEIGHT];
+        frame[0][..3].copy_from_slice(&[17, 34, 51]);
+        frame[1][..3].copy_from_slice(&[68, 85, 102]);
+        static FB: StaticCell<DoubleFb> = StaticCell::new();
+        let fb: &'static DoubleFb = FB.init(DoubleBuffer::new(frame));
+        let mut dac = bus_dac_writer(fb);
+
+        // Two lines, blanking, then the next frame; (1, 250) is H blanking.
+        let v_blank = FB_HEIGHT as u8;
+        let samples = [(0, 0), (0, 1), (0, 2), (1, 250), (1, 0), (1, 1), (1, 2), (v_blank, 0), (v_blank, 1), (0, 0), (0, 1)];
+        let codes = dac.capture_scan(&ScanScript::new(&samples));
+        assert_eq!(codes, [(0, 0, 1), (0, 1, 2), (0, 2, 3), (1, 0, 4), (1, 1, 5), (1, 2, 6), (0, 0, 1), (0, 1, 2)]);
+
+        // Outside the scan window the blank color goes out instead.
+        dac.set_scan_window(0, 0, 1, 1);
+        let dac = dac.with_blank_color(255);
+        let codes = dac.capture_scan(&ScanScript::new(&samples[..7]));
+        assert_eq!(codes, [(0, 0, 1), (0, 1, 2), (0, 2, 15), (1, 0, 4), (1, 1, 5), (1, 2, 15)]);
+    }
+
+    #[cfg(feature = "sim")]
+    #[test]
+    fn bus_dac_scans_single_and_triple_buffers() {
+        use esp_disp_driver::display::backend::bus_dac::ScanScript;
+        use esp_disp_driver::display::backend::utils::{SingleBuffer, TripleBuffer};
+        use esp_disp_driver::display::pix_writer::PixelWriter;
+        use static_cell::StaticCell;
+
+        let samples = [(0, 0), (0, 1)];
+
+        // One buffer: drawing is on screen at once, swap or not.
+        static SINGLE: StaticCell<SingleBuffer<FrameBuf>> = StaticCell::new();
+        let single: &'static SingleBuffer<FrameBuf> = SINGLE.init(SingleBuffer::new([[0; FB_WIDTH]; FB_HEIGHT]));
+        let mut dac = BwPixelWriter8h8v1ch4::with_hw_resources(bus_dac_resources(), single, None, None, None);
+        dac.fill_rect(((0, 0), (0, 1)), 17);
+        assert_eq!(dac.capture_scan(&ScanScript::new(&samples)), [(0, 0, 1), (0, 1, 1)]);
+        dac.present_frame();
+        assert_eq!(single.resolution(), Resolution::new(FB_WIDTH as u16, FB_HEIGHT as u16));
+
+        // Three buffers: the drawn frame shows once presented and the scan
+        // starts a frame.
+        static TRIPLE: StaticCell<TripleBuffer<FrameBuf>> = StaticCell::new();
+        let triple: &'static TripleBuffer<FrameBuf> = TRIPLE.init(TripleBuffer::new([[0; FB_WIDTH]; FB_HEIGHT]));
+        let mut dac = BwPixelWriter8h8v1ch4::with_hw_resources(bus_dac_resources(), triple, None, None, None);
+        dac.fill_rect(((0, 0), (0, 1)), 34);
+        assert_eq!(dac.capture_scan(&ScanScript::new(&samples)), [(0, 0, 0), (0, 1, 0)]);
+        dac.present_frame();
+        triple.mark_scan_started();
+        assert_eq!(dac.capture_scan(&ScanScript::new(&samples)), [(0, 0, 2), (0, 1, 2)]);
+    }
+
+    #[cfg(feature = "sim")]
+    #[test]
+    fn step_and_generated_scans_replay_the_framebuffer() {
+        use esp_disp_driver::display::backend::bus_dac::ScanScript;
+        use static_cell::StaticCell;
+
+        let mut frame = [[0u8; FB_WIDTH]; FB_HEIGHT];
+        frame[0][..3].copy_from_slice(&[17, 34, 51]);
+        frame[1][..3].copy_from_slice(&[68, 85, 102]);
+        static FB: StaticCell<DoubleFb> = StaticCell::new();
+        let fb: &'static DoubleFb = FB.init(DoubleBuffer::new(frame));
+        let mut dac = bus_dac_writer(fb);
+
+        // Every sample outside blanking is drawn, line changes included.
+        let v_blank = FB_HEIGHT as u8;
+        let samples = [(0, 0), (0, 1), (0, 2), (1, 250), (1, 0), (1, 1), (v_blank, 0), (0, 0)];
+        let codes = dac.capture_scan_step(&ScanScript::new(&samples));
+        assert_eq!(codes, [(0, 0, 1), (0, 1, 2), (0, 2, 3), (1, 0, 4), (1, 1, 5), (0, 0, 1)]);
+
+        // One generated frame: the visible area, then 0 through blanking.
+        let (h_total, v_total) = (FB_WIDTH + 2, FB_HEIGHT + 1);
+        let codes = dac.capture_generated(h_total, v_total);
+        assert_eq!(codes.len(), h_total * v_total);
+        assert_eq!(codes[..4], [1, 2, 3, 0]);
+        assert_eq!(codes[h_total..h_total + 3], [4, 5, 6]);
+        assert_eq!(codes[FB_WIDTH..h_total], [0, 0]);
+        assert!(codes[FB_HEIGHT * h_total..].iter().all(|&code| code == 0));
+
+        dac.set_scan_window(0, 0, 1, 1);
+        let dac = dac.with_blank_color(255);
+        assert_eq!(dac.capture_scan_step(&ScanScript::new(&samples[..3])), [(0, 0, 1), (0, 1, 2), (0, 2, 15)]);
+        assert_eq!(dac.capture_generated(h_total, v_total)[..4], [1, 2, 15, 15]);
+    }
+
+    #[cfg(feature = "sim")]
+    #[test]
+    fn brightness_floor_is_a_hardware_code() {
+        use esp_disp_driver::display::backend::bus_dac::ScanScript;
+        use esp_disp_driver::display::backend::sipo::BwPixelWriter8h8v1ch8;
+        use esp_disp_driver::display::pix_writer::PixelWriter;
+        use static_cell::StaticCell;
+
+        // On the 4-bit DAC, floor 3 is code 3: black goes out as 3, white
+        // as 15 and mid-grey (code 8) lands at 3 + 8 * 12 / 15.
+        let mut frame = [[0u8; FB_WIDTH]; FB_HEIGHT];
+        frame[0][..3].copy_from_slice(&[0, 255, 136]);
+        static FB: StaticCell<DoubleFb> = StaticCell::new();
+        let dac = bus_dac_writer(FB.init(DoubleBuffer::new(frame))).with_brightness_floor(3);
+        let samples = [(0, 0), (0, 1), (0, 2)];
+        assert_eq!(dac.capture_scan(&ScanScript::new(&samples)), [(0, 0, 3), (0, 1, 15), (0, 2, 9)]);
+        let dac = dac.with_polarity(BrightnessPolarity::Inverted);
+        assert_eq!(dac.capture_scan(&ScanScript::new(&samples)), [(0, 0, 12), (0, 1, 0), (0, 2, 6)]);
+
+        // The sipo chain holds 8-bit codes, so there the floor is out of 255.
+        let mut sipo = BwPixelWriter8h8v1ch8::from_resources(sipo_resources()).unwrap().with_brightness_floor(40);
+        sipo.write_pixel(5, 7, 0);
+        assert_eq!(sipo.p_sipo_bank.last_frame(), Some(&[[40], [5], [7]]));
+        sipo.write_pixel(5, 7, 255);
+        assert_eq!(sipo.p_sipo_bank.last_frame(), Some(&[[255], [5], [7]]));
+    }
+
+    #[cfg(feature = "sim")]
+    #[test]
+    fn pipelined_scan_prefetches_the_next_color() {
+        use esp_disp_driver::display::backend::bus_dac::ScanScript;
+        use static_cell::StaticCell;
+
+        let mut frame = [[0u8; FB_WIDTH]; FB_HEIGHT];
+        frame[0][..4].copy_from_slice(&[17, 34, 51, 68]);
+        frame[1][..3].copy_from_slice(&[85, 102, 119]);
+        static FB: StaticCell<DoubleFb> = StaticCell::new();
+        let fb: &'static DoubleFb = FB.init(DoubleBuffer::new(frame));
+        let dac = bus_dac_writer(fb);
+
+        // A repeated H is the same pixel, a skipped one is looked up on the
+        // spot, and a wrapping H ends the line; held twice, as the counters
+        // would, it also starts the next one.
+        let v_blank = FB_HEIGHT as u8;
+        let samples = [
+            (0, 0), (0, 1), (0, 1), (0, 3), (0, 250), (1, 0), (1, 0), (1, 1), (1, 2),
+            (v_blank, 0), (v_blank, 1), (0, 0), (0, 0), (0, 1),
+        ];
+        let codes = dac.capture_scan_pipelined(&ScanScript::new(&samples));
+        assert_eq!(codes, [(0, 0, 1), (0, 1, 2), (0, 3, 4), (1, 0, 5), (1, 1, 6), (1, 2, 7), (0, 0, 1), (0, 1, 2)]);
+
+        // Same picture as the per-pixel loop on a steadily counting scan.
+        let samples = [(0, 0), (0, 1), (0, 2), (0, 3), (1, 0), (1, 0), (1, 1), (1, 2), (2, 0)];
+        let plain = dac.capture_scan(&ScanScript::new(&samples));
+        assert_eq!(dac.capture_scan_pipelined(&ScanScript::new(&samples)), plain);
+    }
+
+    #[cfg(feature = "bench")]
+    #[test]
+    fn pipelined_scan_cuts_the_address_to_color_latency() {
+        use static_cell::StaticCell;
+
+        static FB: StaticCell<DoubleFb> = StaticCell::new();
+        let dac = bus_dac_writer(FB.init(DoubleBuffer::new([[0u8; FB_WIDTH]; FB_HEIGHT])));
+        let latency = dac.measure_scan_latency();
+        assert!(latency.pipelined <= latency.plain);
+    }
+
+    #[cfg(feature = "sim")]
+    #[test]
+    fn column_major_scan_reaches_the_right_columns() {
+        use esp_disp_driver::display::backend::bus_dac::ScanScript;
+        use static_cell::StaticCell;
+
+        let mut frame = [[0u8; FB_WIDTH]; FB_HEIGHT];
+        frame[0][160] = 17;
+        frame[1][160] = 34;
+        frame[FB_HEIGHT - 1][160] = 51;
+        frame[1][161] = 68;
+        frame[0][0] = 85;
+        frame[1][0] = 102;
+        static FB: StaticCell<DoubleFb> = StaticCell::new();
+        let fb: &'static DoubleFb = FB.init(DoubleBuffer::new(frame));
+        let dac = bus_dac_writer(fb).with_orientation(ScanOrientation::ColumnMajor);
+
+        // H is the line address: columns past FB_HEIGHT are still drawn,
+        // V past the last row blanks one pixel and H past the last column
+        // is the end of the frame.
+        let (last_row, past_row, past_col) = (FB_HEIGHT as u8 - 1, FB_HEIGHT as u8, FB_WIDTH as u8);
+        let samples = [(0, 160), (1, 160), (last_row, 160), (past_row, 160), (0, 161), (1, 161), (0, past_col), (0, 0), (1, 0)];
+        let codes = dac.capture_scan(&ScanScript::new(&samples));
+        assert_eq!(codes, [(0, 160, 1), (1, 160, 2), (last_row, 160, 3), (1, 161, 4), (0, 0, 5), (1, 0, 6)]);
+    }
+
+    #[test]
+    fn out_of_range_policy_skips_or_clamps() {
+        use esp_disp_driver::display::backend::sipo::BwPixelWriter8h8v1ch8;
+        use esp_disp_driver::display::pix_writer::PixelWriter;
+        use static_cell::StaticCell;
+
+        let range = ((0u8, 9), (0u8, 9));
+        assert_eq!(OutOfRangePolicy::Skip.resolve(3, 4, range), Some((3, 4)));
+        assert_eq!(OutOfRangePolicy::Skip.resolve(10, 4, range), None);
+        assert_eq!(OutOfRangePolicy::Clamp.resolve(10, 255, range), Some((9, 9)));
+
+        static FB: StaticCell<DoubleFb> = StaticCell::new();
+        let fb: &'static DoubleFb = FB.init(DoubleBuffer::new([[0u8; FB_WIDTH]; FB_HEIGHT]));
+        let mut dac = bus_dac_writer(fb);
+        assert_eq!(dac.out_of_range, OutOfRangePolicy::Skip);
+        dac.write_pixel(FB_HEIGHT as u8, 3, 9);
+        assert!(fb.with_inactive(|f| f.iter().flatten().all(|&px| px == 0)));
+        let mut dac = dac.with_out_of_range(OutOfRangePolicy::Clamp);
+        dac.write_pixel(FB_HEIGHT as u8, 3, 9);
+        assert_eq!(fb.with_inactive(|f| f[FB_HEIGHT - 1][3]), 9);
+
+        let small = Resolution::new(16, 8);
+        let mut sipo = BwPixelWriter8h8v1ch8::from_resources(sipo_resources()).unwrap().with_resolution(small);
+        sipo.write_pixel(8, 3, 0x42);
+        assert_eq!(sipo.p_sipo_bank.last_frame(), None);
+        let mut sipo = sipo.with_out_of_range(OutOfRangePolicy::Clamp);
+        sipo.write_pixel(8, 20, 0x42);
+        assert_eq!(sipo.p_sipo_bank.last_frame(), Some(&[[0x42], [7], [15]]));
+    }
+
+    #[test]
+    #[should_panic]
+    fn out_of_range_policy_panics() {
+        OutOfRangePolicy::Panic.resolve(10u8, 0, ((0, 9), (0, 9)));
+    }
+
+    #[test]
+    fn addr_offset_shifts_sampled_position() {
+        use static_cell::StaticCell;
+
+        static FB: StaticCell<DoubleFb> = StaticCell::new();
+        let dac = bus_dac_writer(FB.init(DoubleBuffer::new([[0u8; FB_WIDTH]; FB_HEIGHT])));
+        assert_eq!(dac.addr_offset, (0, 0));
+        assert_eq!(dac.frame_position(7, 3), Some((3, 7)));
+
+        // Counters reading (h, v) = (40, 2) at the first visible pixel.
+        let dac = dac.with_addr_offset((-2, -40));
+        assert_eq!(dac.frame_position(40, 2), Some((0, 0)));
+        assert_eq!(dac.frame_position(47, 5), Some((3, 7)));
+        assert_eq!(dac.frame_position(39, 2), None);
+        assert_eq!(dac.frame_position(40, 1), None);
+        assert_eq!(dac.frame_position(40 + FB_WIDTH as u8, 2), None);
+
+        // Column-major counters show the same picture: V still picks the row.
+        let dac = dac.with_orientation(ScanOrientation::ColumnMajor).with_addr_offset((1, 0));
+        assert_eq!(dac.frame_position(3, 7), Some((8, 3)));
+        assert_eq!(dac.frame_position(160, 7), Some((8, 160)));
+    }
+
+    #[cfg(feature = "sim")]
+    #[test]
+    fn addr_offset_shifts_the_captured_scan() {
+        use esp_disp_driver::display::backend::bus_dac::ScanScript;
+        use static_cell::StaticCell;
+
+        let mut frame = [[0u8; FB_WIDTH]; FB_HEIGHT];
+        frame[0][..3].copy_from_slice(&[17, 34, 51]);
+        frame[1][..3].copy_from_slice(&[68, 85, 102]);
+        static FB: StaticCell<DoubleFb> = StaticCell::new();
+        let dac = bus_dac_writer(FB.init(DoubleBuffer::new(frame))).with_addr_offset((-2, -40));
+
+        // `scan_capture_replays_the_framebuffer`'s script, two lines down
+        // and 40 pixels right: the same codes at the shifted addresses.
+        let v_blank = FB_HEIGHT as u8 + 2;
+        let samples = [(2, 40), (2, 41), (2, 42), (3, 0), (3, 40), (3, 41), (3, 42), (v_blank, 40), (v_blank, 41), (2, 40), (2, 41)];
+        let codes = dac.capture_scan(&ScanScript::new(&samples));
+        assert_eq!(codes, [(2, 40, 1), (2, 41, 2), (2, 42, 3), (3, 40, 4), (3, 41, 5), (3, 42, 6), (2, 40, 1), (2, 41, 2)]);
+
+        // The unshifted addresses now fall before the first visible pixel.
+        let codes = dac.capture_scan(&ScanScript::new(&[(2, 0), (2, 39), (1, 40), (2, 40)]));
+        assert_eq!(codes, [(2, 40, 1)]);
+    }
+
+    #[test]
+    fn initialize_blanks_every_frame() {
+        use esp_disp_driver::display::backend::utils::{FrameStore, TripleBuffer};
+        use esp_disp_driver::display::pix_writer::PixelWriter;
+        use static_cell::StaticCell;
+
+        static FB: StaticCell<DoubleFb> = StaticCell::new();
+        let fb = FB.init(DoubleBuffer::new([[0x5A; FB_WIDTH]; FB_HEIGHT]));
+        fb.with_inactive(|f| f[3][4] = 0xFF);
+        let dac = bus_dac_writer(fb).initialized();
+        assert_eq!(dac.read_pixel(3, 4), Some(0));
+        assert_eq!(fb.with_active(|f| (f[0][0], f[3][4], f[FB_HEIGHT - 1][FB_WIDTH - 1])), (0, 0, 0));
+        assert_eq!(fb.debug_diff(), 0);
+
+        // Every buffer of a ring is cleared, the one on screen included,
+        // and nothing is published doing it.
+        static TRIPLE: StaticCell<TripleBuffer<FrameBuf>> = StaticCell::new();
+        let triple: &'static TripleBuffer<FrameBuf> = TRIPLE.init(TripleBuffer::new([[0x5A; FB_WIDTH]; FB_HEIGHT]));
+        let _dac = BwPixelWriter8h8v1ch4::with_hw_resources(bus_dac_resources(), triple, None, None, None).initialized();
+        assert!(triple.with_active(|f| f.iter().flatten().all(|&px| px == 0)));
+        assert_eq!(triple.coordinator().pending(), None);
+        for _ in 0..3 {
+            triple.with_inactive(|f| assert!(f.iter().flatten().all(|&px| px == 0)));
+            triple.swap();
+            triple.mark_scan_started();
+        }
+        assert_eq!(triple.coordinator().dropped(), 0);
+    }
+
+    #[test]
+    fn transposed_source_lands_row_major() {
+        use esp_disp_driver::display::backend::utils::SourceLayout;
+        use esp_disp_driver::error::Error;
+        use static_cell::StaticCell;
+
+        static FB: StaticCell<DoubleFb> = StaticCell::new();
+        static SRC: StaticCell<[u8; FB_WIDTH * FB_HEIGHT]> = StaticCell::new();
+        let fb = FB.init(DoubleBuffer::new([[0u8; FB_WIDTH]; FB_HEIGHT]));
+        let mut dac = bus_dac_writer(fb);
+
+        // Column-major: column j is contiguous, pixel (i, j) holds i + 2j.
+        let src = SRC.init([0; FB_WIDTH * FB_HEIGHT]);
+        for (k, px) in src.iter_mut().enumerate() {
+            let (j, i) = (k / FB_HEIGHT, k % FB_HEIGHT);
+            *px = (i + 2 * j) as u8;
+        }
+        dac.present_slice_from(src, SourceLayout::ColumnMajor).unwrap();
+        fb.with_active(|f| {
+            assert_eq!((f[0][0], f[1][0], f[0][1]), (0, 1, 2));
+            assert_eq!(f[FB_HEIGHT - 1][FB_WIDTH - 1], (FB_HEIGHT - 1 + 2 * (FB_WIDTH - 1)) as u8);
+        });
+
+        // Row-major reads the same buffer as rows instead.
+        dac.present_slice_from(src, SourceLayout::RowMajor).unwrap();
+        fb.with_active(|f| assert_eq!((f[0][1], f[1][0]), (src[1], src[FB_WIDTH])));
+        assert_eq!(dac.present_slice_from(&src[1..], SourceLayout::RowMajor), Err(Error::BufferTooSmall));
+    }
+
+    #[test]
+    fn drawer_from_resources_keeps_the_writer() {
+        use esp_disp_driver::display::backend::sipo::BwPixelWriter8h8v1ch8;
+        use static_cell::StaticCell;
+
+        let res = sipo_resources();
+        let mut slot: Option<BwPixelWriter8h8v1ch8> = None;
+        let mut d = Drawer::from_sipo(&mut slot, res).unwrap();
+        d.write_pixel(5, 7, 0x42);
+        // The writer outlives the drawer, with the pixel shifted.
+        assert_eq!(slot.unwrap().p_sipo_bank.last_frame(), Some(&[[0x42], [5], [7]]));
+
+        static FB: StaticCell<DoubleFb> = StaticCell::new();
+        let fb: &'static DoubleFb = FB.init(DoubleBuffer::new([[0u8; FB_WIDTH]; FB_HEIGHT]));
+        let res = bus_dac_resources();
+        let mut slot: Option<BwPixelWriter8h8v1ch4> = None;
+        Drawer::from_bus_dac(&mut slot, res, fb).write_pixel(3, 4, 9);
+        assert_eq!(fb.with_inactive(|f| f[3][4]), 9);
+        assert!(slot.is_some());
+    }
+
+    #[test]
+    fn present_dirty_reports_drawn_bounds() {
+        use esp_disp_driver::display::drawer::Drawer;
+        use static_cell::StaticCell;
+
+        static FB: StaticCell<DoubleFb> = StaticCell::new();
+        let mut dac = bus_dac_writer(FB.init(DoubleBuffer::new([[0u8; FB_WIDTH]; FB_HEIGHT])));
+        let mut seen = None;
+
+        // Untracked: always the whole frame.
+        dac.present_dirty(|rows, cols| seen = Some((rows, cols)));
+        assert_eq!(seen, Some(((0, FB_HEIGHT as u8 - 1), (0, FB_WIDTH as u8 - 1))));
+
+        let mut dac = dac.with_dirty_tracking(true);
+        let mut d = Drawer::new(&mut dac);
+        d.write_pixel(10, 20, 7);
+        d.draw_rectangle(12, 15, 3, 2, 7);
+        dac.present_dirty(|rows, cols| seen = Some((rows, cols)));
+        assert_eq!(seen, Some(((10, 13), (15, 20))));
+
+        // Nothing drawn since: presented, but no region to report.
+        seen = None;
+        dac.present_dirty(|rows, cols| seen = Some((rows, cols)));
+        assert_eq!(seen, None);
+
+        // Any other present clears the area too.
+        Drawer::new(&mut dac).write_pixel(3, 4, 7);
+        dac.present_frame();
+        dac.present_dirty(|rows, cols| seen = Some((rows, cols)));
+        assert_eq!(seen, None);
+        Drawer::new(&mut dac).write_pixel(3, 4, 7);
+        dac.present_frame_from(&[[0u8; FB_WIDTH]; FB_HEIGHT]).unwrap();
+        dac.present_dirty(|rows, cols| seen = Some((rows, cols)));
+        assert_eq!(seen, None);
+    }
+
+    #[test]
+    fn changes_are_bounded_against_last_presented() {
+        use esp_disp_driver::display::pix_writer::PixelWriter;
+        use static_cell::StaticCell;
+
+        static FB: StaticCell<DoubleFb> = StaticCell::new();
+        let mut dac = bus_dac_writer(FB.init(DoubleBuffer::new([[0u8; FB_WIDTH]; FB_HEIGHT])));
+        assert_eq!(dac.changed_since_present(), None);
+
+        dac.write_pixel(3, 10, 7);
+        dac.write_pixel(5, 4, 7);
+        assert_eq!(dac.changed_since_present(), Some(((3, 5), (4, 10))));
+        assert_eq!(dac.with_last_presented(|f| f[3][10]), Some(0));
+
+        dac.present();
+        assert_eq!(dac.with_last_presented(|f| (f[3][10], f[5][4])), Some((7, 7)));
+        // The drawn buffer is the old frame again: both pixels differ.
+        assert_eq!(dac.changed_since_present(), Some(((3, 5), (4, 10))));
+        dac.write_pixel(3, 10, 7);
+        dac.write_pixel(5, 4, 7);
+        assert_eq!(dac.changed_since_present(), None);
+    }
+
+    #[test]
+    fn checkerboard_parity_at_offset() {
+        use esp_disp_driver::display::patterns::animated_checkerboard;
+
+        let mut fb = [[0u8; 8]; 6];
+        animated_checkerboard(&mut fb, 2, 0, 1, 2);
+        assert_eq!(fb[0], [1, 1, 2, 2, 1, 1, 2, 2]);
+        assert_eq!(fb[2], [2, 2, 1, 1, 2, 2, 1, 1]);
+
+        animated_checkerboard(&mut fb, 2, 1, 1, 2);
+        assert_eq!(fb[0], [1, 2, 2, 1, 1, 2, 2, 1]);
+        assert_eq!(fb[1], [2, 1, 1, 2, 2, 1, 1, 2]);
+
+        // One period later, and far along a free-running counter, nothing moves.
+        let shifted = fb;
+        animated_checkerboard(&mut fb, 2, 5, 1, 2);
+        assert_eq!(fb, shifted);
+        animated_checkerboard(&mut fb, 2, usize::MAX - 2, 1, 2);
+        assert_eq!(fb, shifted);
+
+        animated_checkerboard(&mut fb, 0, 0, 1, 2);
+        assert_eq!(fb[0], [1, 2, 1, 2, 1, 2, 1, 2]);
+    }
+
+    #[test]
+    fn adler32_checksums_the_active_buffer() {
+        assert_eq!(adler32(*b"Wikipedia"), 0x11E6_0398);
+        assert_eq!(adler32([]), 1);
+        // Long enough to exercise the deferred modulo.
+        assert_eq!(adler32([0xFF; 6000]), 0xA497_59EA);
+
+        let db = DoubleBuffer::new([[0u8; 3]; 2]);
+        assert_eq!(db.active_checksum(), adler32([0; 6]));
+        db.with_inactive(|f| *f = [*b"Wik", *b"ipe"]);
+        assert_eq!(db.active_checksum(), adler32([0; 6]));
+        db.swap();
+        assert_eq!(db.active_checksum(), adler32(*b"Wikipe"));
+    }
+}